@@ -19,8 +19,10 @@ extern crate kiss3d;
 extern crate nalgebra as na;
 extern crate pbr;
 
+use k::collision::{AllowedCollisionMatrix, CollisionChecker, Obstacle};
+use k::link::{Collision, Geometry, LinkBuilder};
 use k::prelude::*;
-use k::{JacobianIKSolver, JointBuilder, JointType};
+use k::{CollisionAwareIKSolver, JacobianIKSolver, JointBuilder, JointType};
 use kiss3d::camera::ArcBall;
 use kiss3d::event::{Action, Key, WindowEvent};
 use kiss3d::light::Light;
@@ -67,6 +69,24 @@ fn create_cubes(window: &mut Window) -> Vec<SceneNode> {
     vec![c_fixed, c0, c1, c2, c3]
 }
 
+/// Set the link with the same box as the cube
+fn set_cube_link(node: &k::Node<f32>, name: &str) {
+    node.set_link(Some(
+        LinkBuilder::new()
+            .name(name)
+            .add_collision(Collision::new(
+                name.to_owned(),
+                Isometry3::identity(),
+                Geometry::Box {
+                    depth: 0.1,
+                    width: 0.1,
+                    height: 0.1,
+                },
+            ))
+            .finalize(),
+    ));
+}
+
 fn main() {
     let fixed: k::Node<f32> = JointBuilder::new()
         .name("fixed")
@@ -107,6 +127,10 @@ fn main() {
         .finalize()
         .into();
     connect![fixed => l0 => l1 => l2 => l3];
+    set_cube_link(&l0, "torso");
+    set_cube_link(&l1, "upper_arm");
+    set_cube_link(&l2, "fore_arm");
+    set_cube_link(&l3, "hand");
 
     let arm = k::SerialChain::new_unchecked(k::Chain::from_root(fixed));
 
@@ -135,7 +159,17 @@ fn main() {
     let at = Point3::new(0.0f32, 0.0, 0.0);
     let mut arc_ball = ArcBall::new(eye, at);
 
-    let solver = JacobianIKSolver::default();
+    // reject the solutions in the arm itself or in the ground (y = 0, 0.2 below the base),
+    // the torso slides on the base and is allowed to touch the ground
+    let mut matrix = AllowedCollisionMatrix::from_adjacent_links(&arm);
+    matrix.allow_obstacle("torso", 0);
+    let checker = CollisionChecker::new()
+        .allowed_collision_matrix(matrix)
+        .obstacle(Obstacle::Plane {
+            normal: Vector3::y_axis(),
+            offset: 0.0,
+        });
+    let solver = CollisionAwareIKSolver::new(JacobianIKSolver::default(), checker);
     let _ = create_ground(&mut window);
 
     while window.render_with_camera(&mut arc_ball) {
@@ -13,10 +13,52 @@ use std::io::Write;
 use rayon::prelude::*;
 
 use rand::{thread_rng, Rng};
+use k::collision::{AllowedCollisionMatrix, CollisionChecker, Obstacle};
+use k::link::{Collision, Geometry, LinkBuilder};
 use k::prelude::*;
 use k::{JacobianIKSolver, JointBuilder, JointType};
 use na::{Isometry3, Translation3, UnitQuaternion, Vector3};
 
+/// Run with `--drop-colliding` to drop the colliding samples instead of tagging them
+fn drop_colliding() -> bool {
+    std::env::args().any(|arg| arg == "--drop-colliding")
+}
+
+/// Set the link with a box from the joint to the next joint
+fn set_box_link(node: &k::Node<f32>, name: &str, length: f32) {
+    node.set_link(Some(
+        LinkBuilder::new()
+            .name(name)
+            .add_collision(Collision::new(
+                name.to_owned(),
+                Isometry3::translation(length / 2.0, 0.0, 0.0),
+                Geometry::Box {
+                    depth: length,
+                    width: 0.05,
+                    height: 0.05,
+                },
+            ))
+            .finalize(),
+    ));
+}
+
+/// Height of the ground in the world (Y is up), the base of the arm is 0.2 above it
+const GROUND_HEIGHT: f32 = 0.0;
+
+/// Self collisions and the ground below the base
+///
+/// The torso slides on the base, so it is allowed to touch the ground.
+fn collision_checker(arm: &k::Chain<f32>) -> CollisionChecker<f32> {
+    let mut matrix = AllowedCollisionMatrix::from_adjacent_links(arm);
+    matrix.allow_obstacle("torso", 0);
+    CollisionChecker::new()
+        .allowed_collision_matrix(matrix)
+        .obstacle(Obstacle::Plane {
+            normal: Vector3::y_axis(),
+            offset: GROUND_HEIGHT,
+        })
+}
+
 macro_rules! init {
     (
         $fixed: ident, $l0: ident, $l1: ident, $l2: ident, $l3: ident,
@@ -62,6 +104,10 @@ macro_rules! init {
                 .finalize()
                 .into();
             connect![$fixed => $l0 => $l1 => $l2 => $l3];
+            set_box_link(&$l0, "torso", 0.1);
+            set_box_link(&$l1, "upper_arm", 0.3);
+            set_box_link(&$l2, "fore_arm", 0.3);
+            set_box_link(&$l3, "hand", 0.1);
 
             let $arm = k::SerialChain::new_unchecked(
                 k::Chain::from_root($fixed)
@@ -146,8 +192,10 @@ macro_rules! init_and_generate_data {
             rotation, rotation, rotation, rotation,
             translation, translation, translation, translation,
             rotation, rotation, rotation, rotation,
-            rotation, rotation
+            rotation, rotation,
+            bool
             );
+        let drop_colliding = drop_colliding();
         
         let mut rows = Vec::<row>::new();
         // let mut rows = Vec::<String>::new();
@@ -156,6 +204,7 @@ macro_rules! init_and_generate_data {
             $fixed, $l0, $l1, $l2, $l3,
             $arm, $solver, $constraints, $target, $angles, $end
         );
+        let checker = collision_checker(&$arm);
 
         for (_x, _y, _z) in $possible_targets.iter() {
             $target.translation.vector[0] = *_x;
@@ -223,6 +272,11 @@ macro_rules! init_and_generate_data {
                                         (v[0], v[1], v[2], v[3])
                                     };
 
+                                let colliding = checker.is_colliding(&$arm);
+                                if colliding && drop_colliding {
+                                    return;
+                                }
+
                                 rows.push((
                                         _l0, _l1, _l2, _l3,
                                         _l0_rot, _l1_rot, _l2_rot, _l3_rot,
@@ -230,7 +284,8 @@ macro_rules! init_and_generate_data {
                                         l0_final_rot, l1_final_rot, l2_final_rot, l3_final_rot,
                                         l0_trans, l1_trans, l2_trans, l3_trans,
                                         l0_trans_rot, l1_trans_rot, l2_trans_rot, l3_trans_rot,
-                                        a_joint_pos, b_joint_pos
+                                        a_joint_pos, b_joint_pos,
+                                        colliding
                                 ));
                             },
                             Err(_) => {}
@@ -267,7 +322,8 @@ macro_rules! init_and_generate_data {
                                         \"l2_rot_trans\": {:?},\n
                                         \"l3_rot_trans\": {:?},\n
                                         \"a_joint_pos\": {:?},\n
-                                        \"b_joint_pos\": {:?}\n
+                                        \"b_joint_pos\": {:?},\n
+                                        \"colliding\": {:?}\n
                                         }},\n
                                     ", row.0, row.1, row.2, row.3,
                                     row.4, row.5, row.6, row.7,
@@ -275,7 +331,7 @@ macro_rules! init_and_generate_data {
                                     row.12, row.13, row.14, row.15,
                                     row.16, row.17, row.18, row.19,
                                     row.20, row.21, row.22, row.23,
-                                    row.24, row.25);
+                                    row.24, row.25, row.26);
             data = format!("{}{}", data, row_string);
         });
 
@@ -292,6 +348,8 @@ fn main() {
         arm, solver, constraints, target, angles, end
     );
 
+    let checker = collision_checker(&arm);
+    let drop_colliding_targets = drop_colliding();
     let mut possible_targets = Vec::<(f32, f32, f32)>::new();
     let range = -20..20;
     let step = 0.05;
@@ -306,6 +364,7 @@ fn main() {
                 target.translation.vector[2] += (z as f32) * step;
 
                 match solver.solve_with_constraints(&arm, &target, &constraints) {
+                    Ok(_) if drop_colliding_targets && checker.is_colliding(&arm) => {}
                     Ok(_) => {
                             arm.update_transforms();
                             let t = l3.world_transform().expect("target 3").translation.vector.data.to_vec(); 
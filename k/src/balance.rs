@@ -0,0 +1,479 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Balance of legged robots using the zero-moment point (ZMP) and the support polygon
+//!
+//! The ground is the horizontal plane and the gravity is along -Z of the world.
+use na::{self, Matrix3, Point3, Real, Vector2, Vector3};
+
+use chain::*;
+use errors::*;
+use funcs::*;
+use link::*;
+
+/// Number of the vertices to approximate the circle of a cylinder
+const CYLINDER_DIVISION: usize = 16;
+
+/// Calculate the zero-moment point on the plane of `ground_height`
+///
+/// The positions, velocities and accelerations of the joints are used.
+/// (From 'Humanoid Robot (Kajita)' P.79)
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::link::*;
+///
+/// let j0 = JointBuilder::new().into_node();
+/// let j1 = JointBuilder::new()
+///     .translation(Translation3::new(0.1, 0.0, 1.0))
+///     .into_node();
+/// j1.set_link(Some(LinkBuilder::new().inertial(Inertial::from_mass(1.0)).finalize()));
+/// j1.set_parent(&j0);
+/// let tree = Chain::<f64>::from_root(j0);
+/// // static case: the projection of the center of mass
+/// let zmp = k::balance::zero_moment_point(&tree, 9.8, 0.0).unwrap();
+/// assert!((zmp.x - 0.1).abs() < 1e-10);
+/// assert_eq!(zmp.z, 0.0);
+/// ```
+pub fn zero_moment_point<T>(
+    chain: &Chain<T>,
+    gravity: T,
+    ground_height: T,
+) -> Result<Vector3<T>, DynamicsError>
+where
+    T: Real,
+{
+    chain.update_accelerations();
+    let mut total_mass = T::zero();
+    let mut com = Vector3::zeros();
+    let mut momentum_rate = Vector3::zeros();
+    let mut angular_momentum_rate = Vector3::zeros();
+    for node in chain.iter() {
+        let link = node.link();
+        let inertial = match *link {
+            Some(ref link) if link.inertial.mass > T::zero() => &link.inertial,
+            _ => continue,
+        };
+        let trans = node.world_transform().expect("cache must exist");
+        let velocity = node.world_velocity().expect("cache must exist");
+        let acceleration = node.world_acceleration().expect("cache must exist");
+        let inertial_trans = trans * inertial.origin();
+        let c = inertial_trans.translation.vector;
+        let r = c - trans.translation.vector;
+        let w = velocity.rotation;
+        let dw = acceleration.rotation;
+        let dv_c = acceleration.translation + dw.cross(&r) + w.cross(&w.cross(&r));
+        let rot = inertial_trans.rotation.to_rotation_matrix();
+        let world_inertia: Matrix3<T> = rot * inertial.inertia * rot.transpose();
+        let p_dot = dv_c * inertial.mass;
+        total_mass += inertial.mass;
+        com += c * inertial.mass;
+        momentum_rate += p_dot;
+        angular_momentum_rate +=
+            c.cross(&p_dot) + world_inertia * dw + w.cross(&(world_inertia * w));
+    }
+    if total_mass <= T::zero() {
+        return Err(DynamicsError::ZeroMassError);
+    }
+    let com = com / total_mass;
+    let vertical_force = total_mass * gravity + momentum_rate.z;
+    if vertical_force <= T::zero() {
+        return Err(DynamicsError::ZmpUndefinedError {
+            error: format!("vertical force = {}", vertical_force),
+        });
+    }
+    Ok(Vector3::new(
+        (total_mass * gravity * com.x + ground_height * momentum_rate.x - angular_momentum_rate.y)
+            / vertical_force,
+        (total_mass * gravity * com.y + ground_height * momentum_rate.y + angular_momentum_rate.x)
+            / vertical_force,
+        ground_height,
+    ))
+}
+
+/// Convex polygon on the ground which contains all contact points
+#[derive(Debug, Clone)]
+pub struct SupportPolygon<T: Real> {
+    /// vertices in counter-clockwise order
+    vertices: Vec<Vector2<T>>,
+    height: T,
+}
+
+fn cross2<T: Real>(o: &Vector2<T>, a: &Vector2<T>, b: &Vector2<T>) -> T {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+fn distance_to_segment<T: Real>(p: &Vector2<T>, a: &Vector2<T>, b: &Vector2<T>) -> T {
+    let ab = b - a;
+    let len2 = ab.norm_squared();
+    if len2 <= T::default_epsilon() {
+        return (p - a).norm();
+    }
+    let t = ((p - a).dot(&ab) / len2).max(T::zero()).min(T::one());
+    (p - (a + ab * t)).norm()
+}
+
+impl<T> SupportPolygon<T>
+where
+    T: Real,
+{
+    /// Create the convex hull of `points` on the ground of `height`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    /// extern crate k;
+    /// use k::balance::SupportPolygon;
+    /// use na::Vector2;
+    ///
+    /// let polygon = SupportPolygon::<f64>::from_points(&[
+    ///     Vector2::new(0.0, 0.0),
+    ///     Vector2::new(1.0, 0.0),
+    ///     Vector2::new(0.5, 0.5),
+    ///     Vector2::new(1.0, 1.0),
+    ///     Vector2::new(0.0, 1.0),
+    /// ], 0.0);
+    /// assert_eq!(polygon.vertices().len(), 4);
+    /// assert!(polygon.contains(&Vector2::new(0.2, 0.3)));
+    /// assert!((polygon.margin(&Vector2::new(0.5, 0.4)) - 0.4).abs() < 1e-10);
+    /// assert!((polygon.margin(&Vector2::new(2.0, 0.5)) + 1.0).abs() < 1e-10);
+    /// ```
+    pub fn from_points(points: &[Vector2<T>], height: T) -> Self {
+        let mut sorted = points.to_vec();
+        sorted.sort_by(|a, b| {
+            a.x.partial_cmp(&b.x)
+                .unwrap()
+                .then(a.y.partial_cmp(&b.y).unwrap())
+        });
+        sorted.dedup_by(|a, b| (*a - *b).norm() <= T::default_epsilon());
+        if sorted.len() < 3 {
+            return Self {
+                vertices: sorted,
+                height,
+            };
+        }
+        // Andrew's monotone chain
+        let mut lower: Vec<Vector2<T>> = Vec::new();
+        for p in &sorted {
+            while lower.len() >= 2
+                && cross2(&lower[lower.len() - 2], &lower[lower.len() - 1], p) <= T::zero()
+            {
+                lower.pop();
+            }
+            lower.push(*p);
+        }
+        let mut upper: Vec<Vector2<T>> = Vec::new();
+        for p in sorted.iter().rev() {
+            while upper.len() >= 2
+                && cross2(&upper[upper.len() - 2], &upper[upper.len() - 1], p) <= T::zero()
+            {
+                upper.pop();
+            }
+            upper.push(*p);
+        }
+        lower.pop();
+        upper.pop();
+        lower.extend(upper);
+        Self {
+            vertices: lower,
+            height,
+        }
+    }
+    /// Vertices in counter-clockwise order
+    pub fn vertices(&self) -> &[Vector2<T>] {
+        &self.vertices
+    }
+    /// Height of the ground
+    pub fn height(&self) -> T {
+        self.height
+    }
+    /// Check if the point is inside (or on the edge of) the polygon
+    pub fn contains(&self, point: &Vector2<T>) -> bool {
+        self.margin(point) >= T::zero()
+    }
+    /// Signed distance from the edges of the polygon
+    ///
+    /// Positive inside, negative outside. If the polygon is degenerated
+    /// (a point or a segment) it is never positive.
+    pub fn margin(&self, point: &Vector2<T>) -> T {
+        let n = self.vertices.len();
+        if n == 0 {
+            return -T::max_value();
+        }
+        if n == 1 {
+            return -(point - self.vertices[0]).norm();
+        }
+        let distance = (0..n)
+            .map(|i| distance_to_segment(point, &self.vertices[i], &self.vertices[(i + 1) % n]))
+            .fold(T::max_value(), |a, b| a.min(b));
+        let inside = n >= 3
+            && (0..n).all(|i| {
+                cross2(&self.vertices[i], &self.vertices[(i + 1) % n], point) >= T::zero()
+            });
+        if inside {
+            distance
+        } else {
+            -distance
+        }
+    }
+}
+
+fn contact_candidates<T>(collision: &Collision<T>) -> Vec<Point3<T>>
+where
+    T: Real,
+{
+    let trans = match *collision.world_transform() {
+        Some(trans) => trans,
+        None => return Vec::new(),
+    };
+    let half: T = na::convert(0.5);
+    let local_points = match collision.geometry {
+        Geometry::Box {
+            depth,
+            width,
+            height,
+        } => {
+            let mut points = Vec::new();
+            for &sx in &[-half, half] {
+                for &sy in &[-half, half] {
+                    for &sz in &[-half, half] {
+                        points.push(Point3::new(depth * sx, width * sy, height * sz));
+                    }
+                }
+            }
+            points
+        }
+        Geometry::Cylinder { radius, length } => {
+            let mut points = Vec::new();
+            for i in 0..CYLINDER_DIVISION {
+                let angle = T::two_pi() * na::convert(i as f64 / CYLINDER_DIVISION as f64);
+                for &sz in &[-half, half] {
+                    points.push(Point3::new(
+                        radius * angle.cos(),
+                        radius * angle.sin(),
+                        length * sz,
+                    ));
+                }
+            }
+            points
+        }
+        Geometry::Sphere { radius } => {
+            // the lowest point in the world
+            let down = trans.rotation.inverse() * Vector3::new(T::zero(), T::zero(), -radius);
+            vec![Point3::from(down)]
+        }
+        Geometry::Mesh { .. } => Vec::new(),
+    };
+    local_points.iter().map(|p| trans * p).collect()
+}
+
+/// Checker of the static / dynamic stability of legged robots
+///
+/// The collision shapes of `foot_links` are assumed to be on the ground.
+/// The lowest point of them is the ground, and the points within
+/// `contact_tolerance` from it are used as the contact points.
+/// `Box`, `Cylinder` and `Sphere` are supported.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::link::*;
+/// use k::balance::BalanceChecker;
+///
+/// let body = JointBuilder::<f64>::new().name("body").into_node();
+/// body.set_link(Some(LinkBuilder::new()
+///     .name("body_link")
+///     .inertial(Inertial::from_mass(5.0))
+///     .add_collision(Collision::new(
+///         "sole".to_owned(),
+///         Isometry3::translation(0.0, 0.0, -0.5),
+///         Geometry::Box { depth: 0.2, width: 0.1, height: 0.02 }))
+///     .finalize()));
+/// let chain = Chain::from_root(body);
+/// let checker = BalanceChecker::new(&["body_link"]);
+/// let margin = checker.static_margin(&chain).unwrap();
+/// assert!((margin - 0.05).abs() < 1e-10);
+/// assert!(checker.is_statically_stable(&chain, 0.01).unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct BalanceChecker<T: Real> {
+    /// names of the links which touch the ground
+    pub foot_links: Vec<String>,
+    /// gravitational acceleration
+    pub gravity: T,
+    /// allowable height from the lowest point to be a contact point
+    pub contact_tolerance: T,
+}
+
+impl<T> BalanceChecker<T>
+where
+    T: Real,
+{
+    /// Create checker with the standard gravity and 1 mm contact tolerance
+    pub fn new(foot_links: &[&str]) -> Self {
+        Self {
+            foot_links: foot_links.iter().map(|name| (*name).to_owned()).collect(),
+            gravity: na::convert(9.80665),
+            contact_tolerance: na::convert(0.001),
+        }
+    }
+    /// Build the support polygon from the current transforms of the foot links
+    pub fn support_polygon(&self, chain: &Chain<T>) -> Result<SupportPolygon<T>, DynamicsError> {
+        chain.update_link_transforms();
+        let mut points = Vec::new();
+        for name in &self.foot_links {
+            let node = chain
+                .find_link(name)
+                .ok_or_else(|| DynamicsError::LinkNotFoundError {
+                    link_name: name.to_owned(),
+                })?;
+            if let Some(ref link) = *node.link() {
+                for collision in &link.collisions {
+                    points.extend(contact_candidates(collision));
+                }
+            }
+        }
+        if points.is_empty() {
+            return Err(DynamicsError::SupportPolygonError {
+                error: format!("no supported collision shapes in {:?}", self.foot_links),
+            });
+        }
+        let ground = points.iter().fold(T::max_value(), |min, p| min.min(p.z));
+        let contacts = points
+            .iter()
+            .filter(|p| p.z - ground <= self.contact_tolerance)
+            .map(|p| Vector2::new(p.x, p.y))
+            .collect::<Vec<_>>();
+        Ok(SupportPolygon::from_points(&contacts, ground))
+    }
+    /// Margin of the projected center of mass in the support polygon
+    ///
+    /// Positive means statically stable.
+    pub fn static_margin(&self, chain: &Chain<T>) -> Result<T, DynamicsError> {
+        let polygon = self.support_polygon(chain)?;
+        let com = center_of_mass(chain)?;
+        Ok(polygon.margin(&Vector2::new(com.x, com.y)))
+    }
+    /// Margin of the zero-moment point in the support polygon
+    ///
+    /// The joint velocities and accelerations are used. Positive means dynamically stable.
+    pub fn dynamic_margin(&self, chain: &Chain<T>) -> Result<T, DynamicsError> {
+        let polygon = self.support_polygon(chain)?;
+        let zmp = zero_moment_point(chain, self.gravity, polygon.height())?;
+        Ok(polygon.margin(&Vector2::new(zmp.x, zmp.y)))
+    }
+    /// Check if the static margin is larger than `min_margin`
+    pub fn is_statically_stable(
+        &self,
+        chain: &Chain<T>,
+        min_margin: T,
+    ) -> Result<bool, DynamicsError> {
+        Ok(self.static_margin(chain)? >= min_margin)
+    }
+    /// Check if the dynamic margin is larger than `min_margin`
+    pub fn is_dynamically_stable(
+        &self,
+        chain: &Chain<T>,
+        min_margin: T,
+    ) -> Result<bool, DynamicsError> {
+        Ok(self.dynamic_margin(chain)? >= min_margin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joint::*;
+    use na::{Isometry3, Translation3};
+    use node::*;
+
+    fn foot(name: &str, y: f64) -> Node<f64> {
+        let node = JointBuilder::new()
+            .name(name)
+            .translation(Translation3::new(0.0, y, -0.8))
+            .into_node();
+        node.set_link(Some(
+            LinkBuilder::new()
+                .name(name)
+                .inertial(Inertial::from_mass(1.0))
+                .add_collision(Collision::new(
+                    "sole".to_owned(),
+                    Isometry3::translation(0.0, 0.0, -0.05),
+                    Geometry::Box {
+                        depth: 0.2,
+                        width: 0.1,
+                        height: 0.02,
+                    },
+                ))
+                .finalize(),
+        ));
+        node
+    }
+
+    fn create_biped() -> (Chain<f64>, Node<f64>) {
+        let slide = JointBuilder::new()
+            .name("slide_x")
+            .joint_type(JointType::Linear {
+                axis: Vector3::x_axis(),
+            })
+            .translation(Translation3::new(0.0, 0.0, 1.0))
+            .into_node();
+        slide.set_link(Some(
+            LinkBuilder::new()
+                .name("torso")
+                .inertial(Inertial::from_mass(8.0))
+                .finalize(),
+        ));
+        let root = JointBuilder::new().name("root").into_node();
+        let l_foot = foot("l_foot", 0.1);
+        let r_foot = foot("r_foot", -0.1);
+        slide.set_parent(&root);
+        l_foot.set_parent(&root);
+        r_foot.set_parent(&root);
+        (Chain::from_root(root), slide)
+    }
+
+    #[test]
+    fn test_support_polygon() {
+        let (chain, _) = create_biped();
+        let checker = BalanceChecker::new(&["l_foot", "r_foot"]);
+        let polygon = checker.support_polygon(&chain).unwrap();
+        assert_eq!(polygon.vertices().len(), 4);
+        assert!((polygon.height() + 0.86).abs() < 1e-10);
+        assert!((checker.static_margin(&chain).unwrap() - 0.1).abs() < 1e-10);
+        assert!(BalanceChecker::<f64>::new(&["hand"])
+            .support_polygon(&chain)
+            .is_err());
+    }
+
+    #[test]
+    fn test_zmp_moves_with_acceleration() {
+        let (chain, slide) = create_biped();
+        let checker = BalanceChecker::new(&["l_foot", "r_foot"]);
+        slide.set_joint_position(0.05).unwrap();
+        assert!(checker.is_statically_stable(&chain, 0.0).unwrap());
+        slide.set_joint_acceleration(2.0).unwrap();
+        let zmp = zero_moment_point(&chain, checker.gravity, -0.86).unwrap();
+        // only the torso (8kg of 10kg at z = 1.0) is accelerated
+        let com = center_of_mass(&chain).unwrap();
+        let expected = com.x - (1.0 + 0.86) * 2.0 * 8.0 / 10.0 / checker.gravity;
+        assert!((zmp.x - expected).abs() < 1e-10);
+        assert!(!checker.is_dynamically_stable(&chain, 0.0).unwrap());
+    }
+}
@@ -0,0 +1,296 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Move the end of `SerialChain` along straight lines and circular arcs
+use na::{self, Isometry3, Real, Translation3, Unit, UnitQuaternion, Vector3};
+
+use chain::*;
+use errors::*;
+use ik::*;
+
+/// Interpolate two poses
+///
+/// The translation is linear and the rotation is SLERP (the shorter way).
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let a = Isometry3::<f64>::translation(0.0, 0.0, 0.0);
+/// let b = Isometry3::from_parts(
+///     Translation3::new(1.0, 0.0, 0.0),
+///     UnitQuaternion::from_euler_angles(0.0, 0.0, 1.0));
+/// let middle = k::cartesian::interpolate_pose(&a, &b, 0.5);
+/// assert!((middle.translation.vector.x - 0.5).abs() < 1e-10);
+/// assert!((middle.rotation.angle() - 0.5).abs() < 1e-10);
+/// ```
+pub fn interpolate_pose<T>(start: &Isometry3<T>, end: &Isometry3<T>, ratio: T) -> Isometry3<T>
+where
+    T: Real,
+{
+    let translation =
+        start.translation.vector + (end.translation.vector - start.translation.vector) * ratio;
+    // q and -q are the same rotation, use the one closer to start
+    let end_rotation = if start.rotation.coords.dot(&end.rotation.coords) < T::zero() {
+        UnitQuaternion::new_unchecked(-end.rotation.into_inner())
+    } else {
+        end.rotation
+    };
+    Isometry3::from_parts(
+        Translation3::from(translation),
+        start.rotation.slerp(&end_rotation, ratio),
+    )
+}
+
+/// Plan joint positions to move the end of the arm along cartesian paths
+///
+/// Each waypoint is solved by the IK solver with the previous solution as the
+/// initial positions. If any joint moves more than `max_joint_step` between two
+/// waypoints, it is regarded as a discontinuity (e.g. flipping the elbow).
+/// Continuous joints are compared by the shortest angle.
+///
+/// The joint positions of the arm are restored after planning.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::cartesian::CartesianPlanner;
+///
+/// let l0 = JointBuilder::new()
+///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+///     .into_node();
+/// let l1 = JointBuilder::new()
+///     .translation(Translation3::new(0.5, 0.0, 0.0))
+///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+///     .into_node();
+/// let l2 = JointBuilder::new()
+///     .translation(Translation3::new(0.5, 0.0, 0.0))
+///     .into_node();
+/// connect![l0 => l1 => l2];
+/// let arm = SerialChain::<f64>::from_end(&l2);
+/// arm.set_joint_positions(&[0.3, 1.2]).unwrap();
+///
+/// let mut planner = CartesianPlanner::new();
+/// planner.constraints = Constraints {
+///     position_z: false,
+///     rotation_x: false,
+///     rotation_y: false,
+///     rotation_z: false,
+///     ..Default::default()
+/// };
+/// let mut target = arm.end_transform();
+/// target.translation.vector.x += 0.1;
+/// let solver = JacobianIKSolver::new(0.0001, 0.001, 0.5, 100);
+/// let path = planner.plan_line(&solver, &arm, &target).unwrap();
+/// assert!(path.len() > 2);
+/// // the arm is not moved
+/// assert_eq!(arm.joint_positions(), vec![0.3, 1.2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CartesianPlanner<T: Real> {
+    /// Max translation between waypoints
+    pub step_length: T,
+    /// Max rotation angle between waypoints
+    pub step_angle: T,
+    /// Max change of a joint position between waypoints
+    pub max_joint_step: T,
+    /// Constraints for the IK solver
+    pub constraints: Constraints,
+}
+
+impl<T> Default for CartesianPlanner<T>
+where
+    T: Real,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> CartesianPlanner<T>
+where
+    T: Real,
+{
+    /// Create planner with 1cm, 0.05rad steps and 0.5 max joint step
+    pub fn new() -> Self {
+        Self {
+            step_length: na::convert(0.01),
+            step_angle: na::convert(0.05),
+            max_joint_step: na::convert(0.5),
+            constraints: Constraints::default(),
+        }
+    }
+
+    fn num_steps(&self, length: T, angle: T) -> Result<usize, CartesianPathError> {
+        // zero or negative steps never reach the end
+        if !(self.step_length > T::zero() && self.step_angle > T::zero()) {
+            return Err(CartesianPathError::InvalidArgumentsError {
+                error: format!(
+                    "steps must be positive, but step_length = {}, step_angle = {}",
+                    self.step_length, self.step_angle
+                ),
+            });
+        }
+        let steps = (length / self.step_length)
+            .max(angle.abs() / self.step_angle)
+            .ceil();
+        match na::try_convert::<T, f64>(steps) {
+            Some(steps) if steps.is_finite() => Ok(steps.max(1.0) as usize),
+            _ => Err(CartesianPathError::InvalidArgumentsError {
+                error: format!("invalid length {} or angle {}", length, angle),
+            }),
+        }
+    }
+
+    /// Poses on the straight line from `start` to `end` (both included)
+    ///
+    /// Returns `InvalidArgumentsError` if the steps are not positive.
+    pub fn line_poses(
+        &self,
+        start: &Isometry3<T>,
+        end: &Isometry3<T>,
+    ) -> Result<Vec<Isometry3<T>>, CartesianPathError> {
+        let length = (end.translation.vector - start.translation.vector).norm();
+        let angle = start.rotation.angle_to(&end.rotation);
+        let n = self.num_steps(length, angle)?;
+        Ok((0..=n)
+            .map(|i| interpolate_pose(start, end, na::convert(i as f64 / n as f64)))
+            .collect())
+    }
+
+    /// Poses on the circular arc (both ends included)
+    ///
+    /// `start` is rotated by `angle` around `axis` which passes `center`.
+    /// The rotation of the pose is also rotated as a rigid body.
+    /// Returns `InvalidArgumentsError` if the steps are not positive.
+    pub fn arc_poses(
+        &self,
+        start: &Isometry3<T>,
+        center: &Vector3<T>,
+        axis: &Unit<Vector3<T>>,
+        angle: T,
+    ) -> Result<Vec<Isometry3<T>>, CartesianPathError> {
+        let radial = start.translation.vector - center;
+        let radius = (radial - axis.into_inner() * axis.dot(&radial)).norm();
+        let n = self.num_steps(radius * angle.abs(), angle)?;
+        Ok((0..=n)
+            .map(|i| {
+                let rot =
+                    UnitQuaternion::from_axis_angle(axis, angle * na::convert(i as f64 / n as f64));
+                Isometry3::from_parts(
+                    Translation3::from(center + rot * radial),
+                    rot * start.rotation,
+                )
+            })
+            .collect())
+    }
+
+    /// Solve IK for all `poses` and returns the joint positions for them
+    pub fn plan_poses<S>(
+        &self,
+        solver: &S,
+        arm: &SerialChain<T>,
+        poses: &[Isometry3<T>],
+    ) -> Result<Vec<Vec<T>>, CartesianPathError>
+    where
+        S: InverseKinematicsSolver<T>,
+    {
+        if poses.is_empty() {
+            return Err(CartesianPathError::InvalidArgumentsError {
+                error: "poses are empty".to_owned(),
+            });
+        }
+        let orig_positions = arm.joint_positions();
+        let result = self.solve_poses(solver, arm, poses);
+        arm.set_joint_positions_unchecked(&orig_positions);
+        result
+    }
+
+    fn solve_poses<S>(
+        &self,
+        solver: &S,
+        arm: &SerialChain<T>,
+        poses: &[Isometry3<T>],
+    ) -> Result<Vec<Vec<T>>, CartesianPathError>
+    where
+        S: InverseKinematicsSolver<T>,
+    {
+        let mut path = Vec::with_capacity(poses.len());
+        let mut last = arm.joint_positions();
+        for (index, pose) in poses.iter().enumerate() {
+            solver
+                .solve_with_constraints(arm, pose, &self.constraints)
+                .map_err(|error| CartesianPathError::IKFailedError { index, error })?;
+            let positions = arm.joint_positions();
+            if index > 0 {
+                let steps = arm.joint_position_differences(&last, &positions);
+                for (joint_index, (step, (prev, next))) in steps
+                    .iter()
+                    .zip(last.iter().zip(positions.iter()))
+                    .enumerate()
+                {
+                    if step.abs() > self.max_joint_step {
+                        return Err(CartesianPathError::DiscontinuityError {
+                            index,
+                            message: format!(
+                                "joint[{}] moved {} -> {}, max step = {}",
+                                joint_index, prev, next, self.max_joint_step
+                            ),
+                        });
+                    }
+                }
+            }
+            last = positions.clone();
+            path.push(positions);
+        }
+        Ok(path)
+    }
+
+    /// Plan the straight motion from the current end transform to `target`
+    ///
+    /// The first element is the solution for the current end transform.
+    pub fn plan_line<S>(
+        &self,
+        solver: &S,
+        arm: &SerialChain<T>,
+        target: &Isometry3<T>,
+    ) -> Result<Vec<Vec<T>>, CartesianPathError>
+    where
+        S: InverseKinematicsSolver<T>,
+    {
+        let poses = self.line_poses(&arm.end_transform(), target)?;
+        self.plan_poses(solver, arm, &poses)
+    }
+
+    /// Plan the circular motion of the current end transform
+    ///
+    /// See `arc_poses()` for the arguments.
+    pub fn plan_arc<S>(
+        &self,
+        solver: &S,
+        arm: &SerialChain<T>,
+        center: &Vector3<T>,
+        axis: &Unit<Vector3<T>>,
+        angle: T,
+    ) -> Result<Vec<Vec<T>>, CartesianPathError>
+    where
+        S: InverseKinematicsSolver<T>,
+    {
+        let poses = self.arc_poses(&arm.end_transform(), center, axis, angle)?;
+        self.plan_poses(solver, arm, &poses)
+    }
+}
@@ -13,7 +13,7 @@
   See the License for the specific language governing permissions and
   limitations under the License.
 */
-use na::{Isometry3, Real};
+use na::{Isometry3, Point3, Real, Vector3};
 use std::fmt::{self, Display};
 use std::ops::Deref;
 
@@ -133,7 +133,7 @@ impl<T: Real> Chain<T> {
             .cloned()
             .collect::<Vec<_>>();
         Chain {
-            dof: movable_joints.len(),
+            dof: movable_joints.iter().map(|node| node.joint().dof()).sum(),
             contained_joints,
             movable_joints,
         }
@@ -161,13 +161,17 @@ impl<T: Real> Chain<T> {
     pub fn from_end(end_joint: &Node<T>) -> Chain<T> {
         let mut contained_joints = end_joint.iter_ancestors().collect::<Vec<_>>();
         contained_joints.reverse();
+        Self::from_contained_joints(contained_joints)
+    }
+    /// Chain of the joints ordered from the parent to the children
+    fn from_contained_joints(contained_joints: Vec<Node<T>>) -> Chain<T> {
         let movable_joints = contained_joints
             .iter()
             .filter(|joint| joint.joint().is_movable())
             .cloned()
             .collect::<Vec<_>>();
         Chain {
-            dof: movable_joints.len(),
+            dof: movable_joints.iter().map(|node| node.joint().dof()).sum(),
             movable_joints,
             contained_joints,
         }
@@ -259,31 +263,95 @@ impl<T: Real> Chain<T> {
     pub fn find(&self, joint_name: &str) -> Option<&Node<T>> {
         self.iter().find(|joint| joint.joint().name == joint_name)
     }
+    /// Find the node which has the link of `link_name`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let l0 = JointBuilder::<f64>::new().name("j0").into_node();
+    /// l0.set_link(Some(LinkBuilder::new().name("base").finalize()));
+    /// let tree = Chain::from_root(l0);
+    /// assert_eq!(tree.find_link("base").unwrap().joint().name, "j0");
+    /// assert!(tree.find_link("arm").is_none());
+    /// ```
+    pub fn find_link(&self, link_name: &str) -> Option<&Node<T>> {
+        self.iter().find(|node| match *node.link() {
+            Some(ref link) => link.name == link_name,
+            None => false,
+        })
+    }
+    /// Pairs of the movable joints and their part of `values`
+    ///
+    /// Multi DOF joints take `dof()` values. The joints which are not covered by
+    /// `values` are ignored.
+    fn split_by_joints<'a>(&'a self, values: &'a [T]) -> Vec<(&'a Node<T>, &'a [T])> {
+        let mut pairs = Vec::with_capacity(self.movable_joints.len());
+        let mut index = 0;
+        for node in &self.movable_joints {
+            let dof = node.joint().dof();
+            if index + dof > values.len() {
+                break;
+            }
+            pairs.push((node, &values[index..index + dof]));
+            index += dof;
+        }
+        pairs
+    }
+
+    fn check_size(&self, input: usize) -> Result<(), JointError> {
+        if input != self.dof {
+            return Err(JointError::SizeMismatchError {
+                input,
+                required: self.dof,
+            });
+        }
+        Ok(())
+    }
+
     /// Get the positions of the joints
     ///
-    /// `FixedJoint` is ignored. the length is the same with `dof()`
+    /// `FixedJoint` is ignored. the length is the same with `dof()`.
+    /// Multi DOF joints have `Joint::dof()` elements in the order of `JointType::motions()`.
     pub fn joint_positions(&self) -> Vec<T> {
-        self.iter_joints()
-            .map(|joint| {
-                joint
-                    .joint_position()
-                    .expect("movable joint must has position")
-            })
-            .collect()
+        let mut positions = Vec::with_capacity(self.dof);
+        for joint in self.iter_joints() {
+            positions.extend_from_slice(joint.joint_positions());
+        }
+        positions
     }
 
     /// Set the positions of the joints
     ///
     /// `FixedJoints` are ignored. the input number must be equal with `dof()`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let base = JointBuilder::new()
+    ///     .joint_type(JointType::Floating)
+    ///     .into_node();
+    /// let arm = JointBuilder::new()
+    ///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// arm.set_parent(&base);
+    /// let chain = Chain::<f64>::from_root(base);
+    /// assert_eq!(chain.dof(), 7);
+    /// chain.set_joint_positions(&[1.0, 2.0, 3.0, 0.0, 0.0, 0.5, 0.2]).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![1.0, 2.0, 3.0, 0.0, 0.0, 0.5, 0.2]);
+    /// ```
     pub fn set_joint_positions(&self, positions_vec: &[T]) -> Result<(), JointError> {
-        if positions_vec.len() != self.dof {
-            return Err(JointError::SizeMismatchError {
-                input: positions_vec.len(),
-                required: self.dof,
-            });
-        }
-        for (mut joint, position) in self.movable_joints.iter().zip(positions_vec.iter()) {
-            joint.set_joint_position(*position)?;
+        self.check_size(positions_vec.len())?;
+        for (node, positions) in self.split_by_joints(positions_vec) {
+            if positions.len() == 1 {
+                node.set_joint_position(positions[0])?;
+            } else {
+                node.set_joint_positions(positions)?;
+            }
         }
         Ok(())
     }
@@ -291,13 +359,55 @@ impl<T: Real> Chain<T> {
     /// Fast, but without check, dangerous `set_joint_positions`
     #[inline]
     pub fn set_joint_positions_unchecked(&self, positions_vec: &[T]) {
-        for (mut joint, position) in self.movable_joints.iter().zip(positions_vec.iter()) {
-            joint.set_joint_position_unchecked(*position);
+        for (node, positions) in self.split_by_joints(positions_vec) {
+            node.0
+                .borrow_mut()
+                .joint
+                .set_joint_positions_unchecked(positions);
+        }
+    }
+
+    /// Differences of the joint positions from `from` to `to`
+    ///
+    /// Continuous joints take the shorter way around.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let j0 = JointBuilder::new()
+    ///     .joint_type(JointType::Continuous{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// let j1 = JointBuilder::new()
+    ///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// j1.set_parent(&j0);
+    /// let chain = Chain::<f64>::from_root(j0);
+    /// let diff = chain.joint_position_differences(&[3.0, 3.0], &[-3.0, -3.0]);
+    /// assert!((diff[0] - (2.0 * std::f64::consts::PI - 6.0)).abs() < 1e-10);
+    /// assert_eq!(diff[1], -6.0);
+    /// ```
+    pub fn joint_position_differences(&self, from: &[T], to: &[T]) -> Vec<T> {
+        let mut differences = Vec::with_capacity(self.dof);
+        for (node, from) in self.split_by_joints(from) {
+            let index = differences.len();
+            let joint = node.joint();
+            for (i, f) in from.iter().enumerate() {
+                differences.push(joint.position_difference(*f, to[index + i]));
+            }
         }
+        differences
     }
 
     /// Update world_transform() of the joints
+    ///
+    /// The parent of the root may be out of the chain (see `SerialChain::from_nodes()`),
+    /// its transform is updated from its ancestors.
     pub fn update_transforms(&self) -> Vec<Isometry3<T>> {
+        if let Some(parent) = self.iter().next().and_then(|root| root.parent()) {
+            parent.updated_world_transform();
+        }
         self.iter()
             .map(|node| {
                 let parent_transform = node.parent_world_transform().expect("cache must exist");
@@ -308,74 +418,415 @@ impl<T: Real> Chain<T> {
             .collect()
     }
 
+    /// Get the velocities of the joints
+    ///
+    /// `FixedJoint` is ignored. the length is the same with `dof()`
+    pub fn joint_velocities(&self) -> Vec<T> {
+        let mut velocities = Vec::with_capacity(self.dof);
+        for joint in self.iter_joints() {
+            velocities.extend_from_slice(joint.joint_velocities());
+        }
+        velocities
+    }
+
+    /// Set the velocities of the joints
+    ///
+    /// `FixedJoints` are ignored. the input number must be equal with `dof()`
+    pub fn set_joint_velocities(&self, velocities_vec: &[T]) -> Result<(), JointError> {
+        self.check_size(velocities_vec.len())?;
+        for (node, velocities) in self.split_by_joints(velocities_vec) {
+            if velocities.len() == 1 {
+                node.set_joint_velocity(velocities[0])?;
+            } else {
+                node.set_joint_velocities(velocities)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fast, but without check, dangerous `set_joint_velocities`
+    #[inline]
+    pub fn set_joint_velocities_unchecked(&self, velocities_vec: &[T]) {
+        for (node, velocities) in self.split_by_joints(velocities_vec) {
+            node.0
+                .borrow_mut()
+                .joint
+                .set_joint_velocities_unchecked(velocities);
+        }
+    }
+
+    /// Get the accelerations of the joints
+    ///
+    /// `FixedJoint` is ignored. the length is the same with `dof()`
+    pub fn joint_accelerations(&self) -> Vec<T> {
+        let mut accelerations = Vec::with_capacity(self.dof);
+        for joint in self.iter_joints() {
+            accelerations.extend_from_slice(joint.joint_accelerations());
+        }
+        accelerations
+    }
+
+    /// Set the accelerations of the joints
+    ///
+    /// `FixedJoints` are ignored. the input number must be equal with `dof()`
+    pub fn set_joint_accelerations(&self, accelerations_vec: &[T]) -> Result<(), JointError> {
+        self.check_size(accelerations_vec.len())?;
+        for (node, accelerations) in self.split_by_joints(accelerations_vec) {
+            if accelerations.len() == 1 {
+                node.set_joint_acceleration(accelerations[0])?;
+            } else {
+                node.set_joint_accelerations(accelerations)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Update world_velocity() of the joints
+    ///
+    /// `translation` is the linear velocity of the joint origin and `rotation` is
+    /// the angular velocity, both in the world frame.
+    ///
+    /// For the node `i` with the parent `p`, `r = p_i - p_p` and the world joint axis `z_i`,
+    ///
+    /// * `w_i = w_p (+ z_i * dq_i if rotational)`
+    /// * `v_i = v_p + w_p x r (+ z_i * dq_i if linear)`
+    ///
+    /// Multi DOF joints add the terms for each motion.
+    ///
+    /// The parent of the root is regarded as fixed, even if it is out of the chain
+    /// (see `SerialChain::from_nodes()`), so the velocities are relative to the base.
     pub fn update_velocities(&self) -> Vec<Velocity<T>> {
         self.update_transforms();
         self.iter()
-            .map(|node| {
+            .enumerate()
+            .map(|(index, node)| {
                 let parent_transform = node
                     .parent_world_transform()
                     .expect("transform cache must exist");
-                let parent_velocity = node
-                    .parent_world_velocity()
-                    .expect("velocity cache must exist");
-                let velocity = match node.joint().joint_type {
-                    JointType::Fixed => parent_velocity,
-                    JointType::Rotational { axis } => {
-                        let parent = node.parent().expect("parent must exist");
-                        let parent_vel = parent.joint().origin().translation.vector;
-                        Velocity::from_parts(
-                            parent_velocity.translation
-                                + parent_velocity.rotation.cross(
-                                    &(parent_transform.rotation.to_rotation_matrix() * parent_vel),
-                                ),
-                            parent_velocity.rotation
-                                + node
-                                    .world_transform()
-                                    .expect("cache must exist")
-                                    .rotation
-                                    .to_rotation_matrix()
-                                    * (axis.into_inner() * node.joint().joint_velocity().unwrap()),
-                        )
-                    }
-                    JointType::Linear { axis } => Velocity::from_parts(
-                        parent_velocity.translation
-                            + node
-                                .world_transform()
-                                .expect("cache must exist")
-                                .rotation
-                                .to_rotation_matrix()
-                                * (axis.into_inner() * node.joint().joint_velocity().unwrap()),
-                        // TODO: Is this true??
-                        parent_velocity.rotation,
-                    ),
+                let parent_velocity = if index == 0 {
+                    Velocity::zero()
+                } else {
+                    node.parent_world_velocity()
+                        .expect("velocity cache must exist")
                 };
-                node.joint().set_world_velocity(velocity);
+                let trans = node.world_transform().expect("cache must exist");
+                let lever = trans.translation.vector - parent_transform.translation.vector;
+                let mut velocity = Velocity::from_parts(
+                    parent_velocity.translation + parent_velocity.rotation.cross(&lever),
+                    parent_velocity.rotation,
+                );
+                let joint = node.joint();
+                for ((motion, frame), dq) in joint.world_motions().zip(joint.joint_velocities()) {
+                    match motion {
+                        Motion::Rotation(axis) => {
+                            velocity.rotation += frame.rotation * (axis.into_inner() * *dq);
+                        }
+                        Motion::Translation(axis) => {
+                            velocity.translation += frame.rotation * (axis.into_inner() * *dq);
+                        }
+                    }
+                }
+                joint.set_world_velocity(velocity);
                 velocity
             })
             .collect()
     }
 
+    /// Update world_acceleration() of the joints
+    ///
+    /// This calls `update_velocities()` internally. With the same notation as
+    /// `update_velocities()` and the angular acceleration `a`,
+    ///
+    /// * `a_i = a_p + w_p x z_i * dq_i + z_i * ddq_i` (rotational)
+    /// * `dv_i = dv_p + a_p x r + w_p x (w_p x r) + 2 * w_p x z_i * dq_i + z_i * ddq_i` (linear)
+    ///
+    /// The parent of the root is fixed, as in `update_velocities()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = JointBuilder::new()
+    ///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// let l1 = JointBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_velocities(&[2.0]).unwrap();
+    /// chain.update_accelerations();
+    /// // centripetal acceleration: w^2 * r toward the center
+    /// let acc = l1.world_acceleration().unwrap();
+    /// assert!((acc.translation.x + 4.0).abs() < 1e-10);
+    /// ```
+    pub fn update_accelerations(&self) -> Vec<Acceleration<T>> {
+        self.update_velocities();
+        self.iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let parent_transform = node
+                    .parent_world_transform()
+                    .expect("transform cache must exist");
+                // the parent of the root is fixed as in update_velocities()
+                let (parent_velocity, parent_acceleration) = if index == 0 {
+                    (Velocity::zero(), Acceleration::zero())
+                } else {
+                    (
+                        node.parent_world_velocity()
+                            .expect("velocity cache must exist"),
+                        node.parent_world_acceleration()
+                            .expect("acceleration cache must exist"),
+                    )
+                };
+                let trans = node.world_transform().expect("cache must exist");
+                let lever = trans.translation.vector - parent_transform.translation.vector;
+                let w_p = parent_velocity.rotation;
+                let mut acceleration = Acceleration::from_parts(
+                    parent_acceleration.translation
+                        + parent_acceleration.rotation.cross(&lever)
+                        + w_p.cross(&w_p.cross(&lever)),
+                    parent_acceleration.rotation,
+                );
+                let joint = node.joint();
+                // angular velocity after each motion of multi DOF joint
+                let mut w = w_p;
+                let derivatives = joint
+                    .joint_velocities()
+                    .iter()
+                    .zip(joint.joint_accelerations().iter());
+                for ((motion, frame), (dq, ddq)) in joint.world_motions().zip(derivatives) {
+                    match motion {
+                        Motion::Rotation(axis) => {
+                            let z = frame.rotation * axis.into_inner();
+                            acceleration.rotation += w.cross(&z) * *dq + z * *ddq;
+                            w += z * *dq;
+                        }
+                        Motion::Translation(axis) => {
+                            let z = frame.rotation * axis.into_inner();
+                            acceleration.translation += w.cross(&z) * (*dq + *dq) + z * *ddq;
+                        }
+                    }
+                }
+                joint.set_world_acceleration(acceleration);
+                acceleration
+            })
+            .collect()
+    }
+
     /// Update transforms of the links
+    ///
+    /// The link is attached to the end of the joint, so the origins of the
+    /// inertial, collisions and visuals are relative to `world_transform()` of the node.
+    /// This is the URDF convention: the frame of a link is the frame of its parent
+    /// joint after the joint motion, so the shapes move together with their own joint.
     pub fn update_link_transforms(&self) {
         self.update_transforms();
         self.iter().for_each(|node| {
-            let parent_transform = node.parent_world_transform().expect("cache must exist");
+            let transform = node.world_transform().expect("cache must exist");
             let mut node_mut = node.0.borrow_mut();
             if let Some(ref mut link) = node_mut.link {
-                let inertial_trans = parent_transform * link.inertial.origin();
+                let inertial_trans = transform * link.inertial.origin();
                 link.inertial.set_world_transform(inertial_trans);
-                for mut c in &mut link.collisions {
-                    let c_trans = parent_transform * c.origin();
+                for c in &mut link.collisions {
+                    let c_trans = transform * c.origin();
                     c.set_world_transform(c_trans);
                 }
-                for mut v in &mut link.visuals {
-                    let v_trans = parent_transform * v.origin();
+                for v in &mut link.visuals {
+                    let v_trans = transform * v.origin();
                     v.set_world_transform(v_trans);
                 }
             }
         });
     }
+
+    /// Find the node and the offset from it by the name of a joint, a link or a frame
+    pub(crate) fn find_frame(&self, name: &str) -> Result<(Node<T>, Isometry3<T>), FrameError> {
+        if let Some(node) = self.find(name).or_else(|| self.find_link(name)) {
+            return Ok((node.clone(), Isometry3::identity()));
+        }
+        for node in self.iter() {
+            if let Some(frame) = node.0.borrow().frames.iter().find(|f| f.name == name) {
+                return Ok((node.clone(), frame.offset));
+            }
+        }
+        Err(FrameError::NotFoundError {
+            name: name.to_owned(),
+        })
+    }
+    /// Attach the named frame to the joint, the link or the frame named `parent`
+    ///
+    /// The frame is fixed at `offset` from `parent`, like a tool center point or a
+    /// camera, without adding `Fixed` joints. The name must be different from the
+    /// joints, the links and the other frames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = JointBuilder::new()
+    ///     .name("shoulder")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = JointBuilder::new()
+    ///     .name("wrist")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.add_frame("tool", "wrist", Isometry3::translation(0.5, 0.0, 0.0)).unwrap();
+    /// chain.add_frame("camera", "tool", Isometry3::translation(0.0, 0.0, 0.1)).unwrap();
+    /// assert!(chain.add_frame("wrist", "tool", Isometry3::identity()).is_err());
+    ///
+    /// chain.set_joint_positions(&[0.0, ::std::f64::consts::FRAC_PI_2]).unwrap();
+    /// let shoulder_to_tool = chain.transform("shoulder", "tool").unwrap();
+    /// assert!((shoulder_to_tool.translation.vector - Vector3::new(1.0, 0.5, 0.0)).norm() < 1e-10);
+    /// let camera = chain.transform("tool", "camera").unwrap();
+    /// assert!((camera.translation.vector - Vector3::new(0.0, 0.0, 0.1)).norm() < 1e-10);
+    /// ```
+    pub fn add_frame(
+        &self,
+        name: &str,
+        parent: &str,
+        offset: Isometry3<T>,
+    ) -> Result<(), FrameError> {
+        if self.find_frame(name).is_ok() {
+            return Err(FrameError::DuplicateNameError {
+                name: name.to_owned(),
+            });
+        }
+        let (node, parent_offset) = self.find_frame(parent)?;
+        node.0.borrow_mut().frames.push(Frame {
+            name: name.to_owned(),
+            offset: parent_offset * offset,
+        });
+        Ok(())
+    }
+    /// Remove the named frame which is added by `add_frame()`
+    pub fn remove_frame(&self, name: &str) -> Result<(), FrameError> {
+        for node in self.iter() {
+            let mut node_mut = node.0.borrow_mut();
+            if let Some(index) = node_mut.frames.iter().position(|f| f.name == name) {
+                node_mut.frames.remove(index);
+                return Ok(());
+            }
+        }
+        Err(FrameError::NotFoundError {
+            name: name.to_owned(),
+        })
+    }
+    /// Names of the frames which are added by `add_frame()`
+    pub fn frame_names(&self) -> Vec<String> {
+        self.iter()
+            .flat_map(|node| {
+                node.0
+                    .borrow()
+                    .frames
+                    .iter()
+                    .map(|f| f.name.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+    /// Pose of the joint, the link or the frame in the world
+    ///
+    /// It is calculated from the current joint positions of the ancestors, so
+    /// `update_transforms()` is not required.
+    pub fn frame_transform(&self, name: &str) -> Result<Isometry3<T>, FrameError> {
+        let (node, offset) = self.find_frame(name)?;
+        Ok(node.updated_world_transform() * offset)
+    }
+    /// Pose of the frame `to` seen from the frame `from`
+    ///
+    /// The frames are the names of the joints, the links or the frames.
+    /// The positions in `to` are converted to `from` by multiplying it.
+    pub fn transform(&self, from: &str, to: &str) -> Result<Isometry3<T>, FrameError> {
+        Ok(self.frame_transform(from)?.inverse() * self.frame_transform(to)?)
+    }
+    /// Convert the point in the frame `to` into the frame `from`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    /// extern crate k;
+    /// use k::*;
+    ///
+    /// # fn main() {
+    /// let node = JointBuilder::new().name("base").into_node();
+    /// let chain = Chain::<f64>::from_root(node);
+    /// chain.add_frame("camera", "base", Isometry3::translation(0.0, 0.0, 1.0)).unwrap();
+    /// let point = chain
+    ///     .transform_point("base", "camera", &na::Point3::new(1.0, 0.0, 0.0))
+    ///     .unwrap();
+    /// assert_eq!(point, na::Point3::new(1.0, 0.0, 1.0));
+    /// let vector = chain
+    ///     .transform_vector("base", "camera", &Vector3::new(1.0, 0.0, 0.0))
+    ///     .unwrap();
+    /// assert_eq!(vector, Vector3::new(1.0, 0.0, 0.0));
+    /// # }
+    /// ```
+    pub fn transform_point(
+        &self,
+        from: &str,
+        to: &str,
+        point: &Point3<T>,
+    ) -> Result<Point3<T>, FrameError> {
+        Ok(self.transform(from, to)? * point)
+    }
+    /// Convert the vector in the frame `to` into the frame `from`, only rotated
+    pub fn transform_vector(
+        &self,
+        from: &str,
+        to: &str,
+        vector: &Vector3<T>,
+    ) -> Result<Vector3<T>, FrameError> {
+        Ok(self.transform(from, to)? * vector)
+    }
+
+    /// Estimate the inertials of the massless links from their shapes
+    ///
+    /// Useful for URDF which lacks `<inertial>`. `density` is used for all shapes.
+    /// The links which have mass, or whose shapes can not be estimated (`Mesh` which is not
+    /// loaded by `load_meshes()`) are not changed.
+    /// Returns the number of the updated links.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let node = JointBuilder::<f64>::new().into_node();
+    /// node.set_link(Some(LinkBuilder::new()
+    ///     .add_collision(Collision::new(
+    ///         "box".to_owned(),
+    ///         Isometry3::identity(),
+    ///         Geometry::Box { depth: 0.1, width: 0.1, height: 0.1 }))
+    ///     .finalize()));
+    /// let chain = Chain::from_root(node);
+    /// assert_eq!(chain.fill_missing_inertials(1000.0), 1);
+    /// assert!((chain.iter_links().next().unwrap().inertial.mass - 1.0).abs() < 1e-10);
+    /// ```
+    pub fn fill_missing_inertials(&self, density: T) -> usize {
+        let mut count = 0;
+        for node in self.iter() {
+            let mut node_mut = node.0.borrow_mut();
+            if let Some(ref mut link) = node_mut.link {
+                if link.inertial.mass > T::zero() {
+                    continue;
+                }
+                if let Some(inertial) = link.estimate_inertial(density) {
+                    link.inertial = inertial;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
 }
 
 #[derive(Debug)]
@@ -384,6 +835,8 @@ impl<T: Real> Chain<T> {
 /// All joints are connected sequentially.
 pub struct SerialChain<T: Real> {
     inner: Chain<T>,
+    /// Tool center point from the end joint
+    tcp_offset: Isometry3<T>,
 }
 
 impl<T> SerialChain<T>
@@ -395,7 +848,10 @@ where
     /// If the input Chain has any branches it causes serious bugs.
     ///
     pub fn new_unchecked(inner: Chain<T>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            tcp_offset: Isometry3::identity(),
+        }
     }
     /// Convert Chain into SerialChain
     ///
@@ -427,7 +883,7 @@ where
                 }
             }
         }
-        Some(Self { inner })
+        Some(Self::new_unchecked(inner))
     }
     /// Create SerialChain from the end `Node`
     ///
@@ -438,19 +894,132 @@ where
     /// let s_chain = k::SerialChain::from_end(&node);
     /// ```
     pub fn from_end(end_joint: &Node<T>) -> SerialChain<T> {
-        SerialChain {
-            inner: Chain::from_end(end_joint),
+        SerialChain::new_unchecked(Chain::from_end(end_joint))
+    }
+    /// Create SerialChain from the children of `base` to `end`
+    ///
+    /// `base` is not contained, and `end_transform()`, `jacobian()` and the IK solvers
+    /// work in the frame of `base` (its `world_transform()`). It is useful for a sub
+    /// chain on a moving body, like from the torso to the wrist of a humanoid.
+    /// Returns `None` if `base` is not an ancestor of `end`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let waist = JointBuilder::new()
+    ///     .name("waist")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let torso = JointBuilder::new()
+    ///     .name("torso")
+    ///     .translation(Translation3::new(0.0, 0.0, 1.0))
+    ///     .into_node();
+    /// let shoulder = JointBuilder::new()
+    ///     .name("shoulder")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .translation(Translation3::new(0.0, 0.2, 0.0))
+    ///     .into_node();
+    /// let wrist = JointBuilder::new()
+    ///     .name("wrist")
+    ///     .translation(Translation3::new(0.5, 0.0, 0.0))
+    ///     .into_node();
+    /// connect![waist => torso => shoulder => wrist];
+    ///
+    /// let arm = SerialChain::<f64>::from_nodes(&torso, &wrist).unwrap();
+    /// assert_eq!(arm.dof(), 1);
+    /// // the waist moves the base, but not the end in the torso frame
+    /// waist.set_joint_position(1.0).unwrap();
+    /// assert_eq!(arm.end_transform().translation.vector, Vector3::new(0.5, 0.2, 0.0));
+    ///
+    /// shoulder.set_joint_position(0.3).unwrap();
+    /// let mut target = arm.end_transform();
+    /// target.translation.vector = Vector3::new(0.0, 0.7, 0.0);
+    /// let mut constraints = Constraints::default();
+    /// constraints.rotation_x = false;
+    /// constraints.rotation_y = false;
+    /// constraints.rotation_z = false;
+    /// constraints.position_z = false;
+    /// constraints.position_y = false;
+    /// JacobianIKSolver::default()
+    ///     .solve_with_constraints(&arm, &target, &constraints)
+    ///     .unwrap();
+    /// assert!(arm.end_transform().translation.vector.x.abs() < 0.01);
+    /// assert!(SerialChain::from_nodes(&wrist, &torso).is_none());
+    /// ```
+    pub fn from_nodes(base: &Node<T>, end: &Node<T>) -> Option<SerialChain<T>> {
+        let mut contained_joints = Vec::new();
+        for node in end.iter_ancestors() {
+            if node == *base {
+                if contained_joints.is_empty() {
+                    return None;
+                }
+                contained_joints.reverse();
+                return Some(Self::new_unchecked(Chain::from_contained_joints(
+                    contained_joints,
+                )));
+            }
+            contained_joints.push(node);
+        }
+        None
+    }
+    /// Transform of the base frame, which is the parent of the first joint
+    ///
+    /// It is the identity if the first joint is the root.
+    pub fn base_transform(&self) -> Isometry3<T> {
+        match self.iter().next().and_then(|node| node.parent()) {
+            Some(parent) => parent.updated_world_transform(),
+            None => Isometry3::identity(),
         }
     }
     /// Safely unwrap and returns inner `Chain` instance
     pub fn unwrap(self) -> Chain<T> {
         self.inner
     }
-    /// Calculate transform of the end joint
+    /// Calculate transform of the tool center point in the base frame
+    ///
+    /// It is the end joint if the TCP offset is not set.
     pub fn end_transform(&self) -> Isometry3<T> {
         self.iter().fold(Isometry3::identity(), |trans, joint| {
             trans * joint.joint().local_transform()
-        })
+        }) * self.tcp_offset
+    }
+    /// Tool center point (TCP) offset from the end joint
+    pub fn tcp_offset(&self) -> &Isometry3<T> {
+        &self.tcp_offset
+    }
+    /// Set the tool center point (TCP) offset from the end joint
+    ///
+    /// `end_transform()`, `jacobian()` and the IK solvers use the TCP, so the tools can
+    /// be swapped without rebuilding the chain.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = JointBuilder::new()
+    ///     .name("yaw")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = JointBuilder::new()
+    ///     .name("wrist")
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let mut arm = SerialChain::<f64>::from_end(&l1);
+    /// arm.set_tcp_offset(Isometry3::translation(0.5, 0.0, 0.0));
+    /// assert_eq!(arm.end_transform().translation.vector, Vector3::new(1.5, 0.0, 0.0));
+    ///
+    /// // the end joint is at (1.0, 0.0, 0.0), the TCP follows it
+    /// arm.set_joint_positions(&[::std::f64::consts::FRAC_PI_2]).unwrap();
+    /// assert!((arm.end_transform().translation.vector - Vector3::new(0.0, 1.5, 0.0)).norm() < 1e-10);
+    /// let jacobi = jacobian(&arm);
+    /// assert!((jacobi[(0, 0)] + 1.5).abs() < 1e-10);
+    /// ```
+    pub fn set_tcp_offset(&mut self, tcp_offset: Isometry3<T>) {
+        self.tcp_offset = tcp_offset;
     }
 }
 
@@ -616,3 +1185,322 @@ fn test_mimic() {
     assert_eq!(positions[1], 0.2);
     assert_eq!(positions[2], 0.9);
 }
+
+#[test]
+fn test_mimic_multi_dof() {
+    use super::joint::*;
+    use super::node::*;
+    use na;
+
+    let joint0 = JointBuilder::new()
+        .name("j0")
+        .joint_type(JointType::Spherical)
+        .into_node();
+    let joint1 = JointBuilder::new()
+        .name("j1")
+        .translation(na::Translation3::new(0.0, 0.0, 1.0))
+        .joint_type(JointType::Spherical)
+        .into_node();
+    joint1.set_parent(&joint0);
+    joint1.set_mimic_parent(&joint0, Mimic::new(2.0, 0.1));
+    let arm = Chain::from_root(joint0.clone());
+    assert_eq!(arm.dof(), 6);
+
+    arm.set_joint_positions(&[0.1, 0.2, 0.3, 0.0, 0.0, 0.0])
+        .unwrap();
+    let positions = arm.joint_positions();
+    assert_eq!(positions[..3].to_vec(), vec![0.1, 0.2, 0.3]);
+    for (p, expected) in positions[3..].iter().zip(&[0.3f64, 0.5, 0.7]) {
+        assert!((p - expected).abs() < 1e-10);
+    }
+    arm.set_joint_velocities(&[1.0, 2.0, 3.0, 0.0, 0.0, 0.0])
+        .unwrap();
+    assert_eq!(arm.joint_velocities()[3..].to_vec(), vec![2.0, 4.0, 6.0]);
+    arm.set_joint_accelerations(&[-1.0, 0.0, 1.0, 0.0, 0.0, 0.0])
+        .unwrap();
+    assert_eq!(
+        arm.joint_accelerations()[3..].to_vec(),
+        vec![-2.0, 0.0, 2.0]
+    );
+
+    // the mimic child must have the same dof
+    let joint2 = JointBuilder::new()
+        .name("j2")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::z_axis(),
+        })
+        .into_node();
+    joint2.set_parent(&joint1);
+    joint2.set_mimic_parent(&joint0, Mimic::new(1.0, 0.0));
+    match joint0.set_joint_positions(&[0.0, 0.0, 0.0]) {
+        Err(JointError::MimicError { ref to, .. }) => assert_eq!(to, "j2"),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_frames() {
+    use super::joint::*;
+    use super::node::*;
+    use na;
+
+    let joint0 = JointBuilder::new()
+        .name("j0")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::z_axis(),
+        })
+        .into_node();
+    let joint1 = JointBuilder::new()
+        .name("j1")
+        .translation(na::Translation3::new(1.0, 0.0, 0.0))
+        .joint_type(JointType::Linear {
+            axis: na::Vector3::x_axis(),
+        })
+        .into_node();
+    joint1.set_parent(&joint0);
+    let chain = Chain::<f64>::from_root(joint0);
+    chain
+        .add_frame("tcp", "j1", Isometry3::translation(0.5, 0.0, 0.0))
+        .unwrap();
+    assert_eq!(chain.frame_names(), vec!["tcp"]);
+    assert!(chain.add_frame("tcp", "j0", Isometry3::identity()).is_err());
+    assert!(chain
+        .add_frame("camera", "j2", Isometry3::identity())
+        .is_err());
+
+    chain.update_transforms();
+    let tcp = chain.frame_transform("tcp").unwrap();
+    assert!((tcp.translation.vector - na::Vector3::new(1.5, 0.0, 0.0)).norm() < 1e-10);
+    // the stale cache of j1 must not be used after j0 moves
+    chain
+        .set_joint_positions(&[::std::f64::consts::FRAC_PI_2, 0.5])
+        .unwrap();
+    let tcp = chain.frame_transform("tcp").unwrap();
+    assert!((tcp.translation.vector - na::Vector3::new(0.0, 2.0, 0.0)).norm() < 1e-10);
+    let vector = chain
+        .transform_vector("j0", "tcp", &na::Vector3::new(1.0, 0.0, 0.0))
+        .unwrap();
+    assert!((vector - na::Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-10);
+    let point = chain
+        .transform_point("tcp", "j0", &na::Point3::origin())
+        .unwrap();
+    assert!((point.coords - na::Vector3::new(-2.0, 0.0, 0.0)).norm() < 1e-10);
+
+    chain.remove_frame("tcp").unwrap();
+    assert!(chain.remove_frame("tcp").is_err());
+    assert!(chain.frame_transform("tcp").is_err());
+}
+
+#[test]
+fn test_serial_chain_from_nodes() {
+    use super::funcs::jacobian;
+    use super::joint::*;
+    use super::node::*;
+    use na;
+
+    let waist = JointBuilder::new()
+        .name("waist")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::y_axis(),
+        })
+        .into_node();
+    let shoulder = JointBuilder::new()
+        .name("shoulder")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::z_axis(),
+        })
+        .translation(na::Translation3::new(0.0, 0.2, 1.0))
+        .into_node();
+    let elbow = JointBuilder::new()
+        .name("elbow")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::y_axis(),
+        })
+        .translation(na::Translation3::new(0.3, 0.0, 0.0))
+        .into_node();
+    let wrist = JointBuilder::new()
+        .name("wrist")
+        .translation(na::Translation3::new(0.3, 0.0, 0.0))
+        .into_node();
+    elbow.set_parent(&shoulder);
+    shoulder.set_parent(&waist);
+    wrist.set_parent(&elbow);
+    let arm = SerialChain::<f64>::from_nodes(&waist, &wrist).unwrap();
+    assert_eq!(arm.dof(), 2);
+    assert!(SerialChain::from_nodes(&wrist, &wrist).is_none());
+    assert!(SerialChain::from_nodes(&elbow, &shoulder).is_none());
+
+    waist.set_joint_position(0.7).unwrap();
+    arm.set_joint_positions(&[0.3, -0.5]).unwrap();
+    let world = arm.update_transforms();
+    let base = arm.base_transform();
+    let end = arm.end_transform();
+    let diff = (base * end).translation.vector - world.last().unwrap().translation.vector;
+    assert!(diff.norm() < 1e-10);
+
+    // the jacobian is in the base frame
+    let jacobi = jacobian(&arm);
+    let eps = 1e-6;
+    for i in 0..2 {
+        let mut positions = vec![0.3, -0.5];
+        positions[i] += eps;
+        arm.set_joint_positions(&positions).unwrap();
+        let moved = arm.end_transform();
+        let velocity = (moved.translation.vector - end.translation.vector) / eps;
+        for j in 0..3 {
+            assert!((velocity[j] - jacobi[(j, i)]).abs() < 1e-4);
+        }
+    }
+
+    // the base (shoulder) of the sub chain has a parent out of the chain, it is fixed
+    let sub = SerialChain::<f64>::from_nodes(&shoulder, &wrist).unwrap();
+    assert_eq!(sub.dof(), 1);
+    sub.set_joint_velocities(&[1.0]).unwrap();
+    sub.set_joint_accelerations(&[0.5]).unwrap();
+    let velocities = sub.update_velocities();
+    let accelerations = sub.update_accelerations();
+    let trans = sub.update_transforms();
+    // the elbow rotates around its y axis at the fixed origin
+    let axis = trans[0].rotation * na::Vector3::y();
+    assert!(velocities[0].translation.norm() < 1e-10);
+    assert!((velocities[0].rotation - axis).norm() < 1e-10);
+    assert!(accelerations[0].translation.norm() < 1e-10);
+    assert!((accelerations[0].rotation - axis * 0.5).norm() < 1e-10);
+    // the wrist moves around the elbow: v = w x r, a = dw x r + w x (w x r)
+    let lever = trans[1].translation.vector - trans[0].translation.vector;
+    assert!((velocities[1].translation - axis.cross(&lever)).norm() < 1e-10);
+    let expected = (axis * 0.5).cross(&lever) + axis.cross(&axis.cross(&lever));
+    assert!((accelerations[1].translation - expected).norm() < 1e-10);
+}
+
+#[test]
+fn test_frames_of_siblings() {
+    use super::joint::*;
+    use super::node::*;
+    use na;
+
+    let joint0 = JointBuilder::new()
+        .name("j0")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::z_axis(),
+        })
+        .into_node();
+    let a = JointBuilder::new()
+        .name("a")
+        .translation(na::Translation3::new(1.0, 0.0, 0.0))
+        .into_node();
+    let b = JointBuilder::new()
+        .name("b")
+        .translation(na::Translation3::new(0.0, 1.0, 0.0))
+        .into_node();
+    a.set_parent(&joint0);
+    b.set_parent(&joint0);
+    let chain = Chain::<f64>::from_root(joint0);
+    chain
+        .add_frame("a_tip", "a", Isometry3::translation(0.0, 0.0, 1.0))
+        .unwrap();
+    chain.update_transforms();
+    // move only the shared parent, then look up the siblings in turn
+    chain
+        .set_joint_positions(&[::std::f64::consts::FRAC_PI_2])
+        .unwrap();
+    let a_trans = chain.frame_transform("a").unwrap();
+    assert!((a_trans.translation.vector - na::Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-10);
+    let b_trans = chain.frame_transform("b").unwrap();
+    assert!((b_trans.translation.vector - na::Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-10);
+    let a_tip = chain.transform("b", "a_tip").unwrap();
+    assert!((a_tip.translation.vector - na::Vector3::new(1.0, -1.0, 1.0)).norm() < 1e-10);
+}
+
+#[test]
+fn test_serial_chain_from_nodes_with_moving_base() {
+    use super::joint::*;
+    use super::node::*;
+    use na;
+
+    let waist = JointBuilder::new()
+        .name("waist")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::z_axis(),
+        })
+        .into_node();
+    let head = JointBuilder::new()
+        .name("head")
+        .translation(na::Translation3::new(0.0, 0.0, 1.5))
+        .into_node();
+    let torso = JointBuilder::new()
+        .name("torso")
+        .translation(na::Translation3::new(0.0, 0.0, 1.0))
+        .into_node();
+    let shoulder = JointBuilder::new()
+        .name("shoulder")
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::z_axis(),
+        })
+        .translation(na::Translation3::new(0.0, 0.2, 0.0))
+        .into_node();
+    let wrist = JointBuilder::new()
+        .name("wrist")
+        .translation(na::Translation3::new(0.5, 0.0, 0.0))
+        .into_node();
+    head.set_parent(&waist);
+    torso.set_parent(&waist);
+    shoulder.set_parent(&torso);
+    wrist.set_parent(&shoulder);
+    let body = Chain::<f64>::from_root(waist.clone());
+    let arm = SerialChain::from_nodes(&torso, &wrist).unwrap();
+    body.update_transforms();
+    assert!(arm.base_transform().translation.vector.x.abs() < 1e-10);
+
+    // move the joint above the base, and refill the cache of the waist by the sibling
+    waist
+        .set_joint_position(::std::f64::consts::FRAC_PI_2)
+        .unwrap();
+    body.frame_transform("head").unwrap();
+    let base = arm.base_transform();
+    assert!((base.rotation.angle() - ::std::f64::consts::FRAC_PI_2).abs() < 1e-10);
+    assert!((base.translation.vector - na::Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-10);
+    let end = arm.end_transform();
+    assert!((end.translation.vector - na::Vector3::new(0.5, 0.2, 0.0)).norm() < 1e-10);
+    let world = arm.update_transforms();
+    let expected = na::Vector3::new(-0.2, 0.5, 1.0);
+    assert!((world.last().unwrap().translation.vector - expected).norm() < 1e-10);
+    assert!(((base * end).translation.vector - expected).norm() < 1e-10);
+}
+
+#[test]
+fn test_update_link_transforms() {
+    use super::joint::*;
+    use super::link::*;
+    use super::node::*;
+    use na;
+
+    let joint0 = JointBuilder::new()
+        .name("j0")
+        .translation(na::Translation3::new(0.0, 0.0, 1.0))
+        .joint_type(JointType::Rotational {
+            axis: na::Vector3::z_axis(),
+        })
+        .into_node();
+    joint0.set_link(Some(
+        LinkBuilder::new()
+            .name("l0")
+            .add_collision(Collision::new(
+                "ball".to_owned(),
+                na::Isometry3::translation(1.0, 0.0, 0.0),
+                Geometry::Sphere { radius: 0.1 },
+            ))
+            .finalize(),
+    ));
+    let arm = Chain::from_root(joint0.clone());
+    arm.set_joint_positions(&[::std::f64::consts::FRAC_PI_2])
+        .unwrap();
+    arm.update_link_transforms();
+
+    let link = joint0.link();
+    let trans = link.as_ref().unwrap().collisions[0]
+        .world_transform()
+        .unwrap();
+    // the ball rotates with j0 itself, not only with the parent of j0
+    assert!((trans.translation.vector - na::Vector3::new(0.0, 1.0, 1.0)).norm() < 1e-6);
+}
@@ -0,0 +1,1389 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Collision checking between the links
+//!
+//! `Box`, `Cylinder` and `Sphere` of `Collision` are supported. `Mesh` is checked
+//! as the convex hull of its vertices if it is loaded by `Chain::load_meshes()`,
+//! otherwise it is ignored. The shapes are checked by GJK algorithm, and the
+//! penetration depths are calculated by EPA for the signed distances to `Obstacle`s.
+//!
+//! # Examples
+//!
+//! ```
+//! use k::*;
+//! use k::link::*;
+//!
+//! fn box_link(name: &str) -> Link<f64> {
+//!     LinkBuilder::new()
+//!         .name(name)
+//!         .add_collision(Collision::new(
+//!             name.to_owned(),
+//!             Isometry3::translation(0.0, 0.0, 0.5),
+//!             Geometry::Box { depth: 0.2, width: 0.2, height: 1.0 }))
+//!         .finalize()
+//! }
+//! let j0 = JointBuilder::new().name("j0").into_node();
+//! let j1 = JointBuilder::new()
+//!     .name("j1")
+//!     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+//!     .translation(Translation3::new(0.0, 0.0, 1.0))
+//!     .into_node();
+//! let j2 = JointBuilder::new()
+//!     .name("j2")
+//!     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+//!     .translation(Translation3::new(0.0, 0.0, 1.0))
+//!     .into_node();
+//! j0.set_link(Some(box_link("l0")));
+//! j1.set_link(Some(box_link("l1")));
+//! j2.set_link(Some(box_link("l2")));
+//! j1.set_parent(&j0);
+//! j2.set_parent(&j1);
+//! let chain = Chain::from_root(j0);
+//! // adjacent links are always allowed to collide
+//! assert!(chain.self_collisions().is_empty());
+//! // fold l2 back onto l0
+//! chain.set_joint_positions(&[2.8, 2.8]).unwrap();
+//! assert_eq!(chain.self_collisions(), vec![("l0".to_owned(), "l2".to_owned())]);
+//! ```
+use na::{self, DVector, Isometry3, Point3, Real, Unit, Vector3};
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use chain::*;
+use funcs::*;
+use link::*;
+use mesh::TriangleMesh;
+
+/// Convex shape in its local frame
+#[derive(Debug, Clone)]
+pub enum Shape<T: Real> {
+    Sphere {
+        radius: T,
+    },
+    /// Box centered at the origin
+    Box {
+        half_extents: Vector3<T>,
+    },
+    /// Cylinder along Z axis centered at the origin
+    Cylinder {
+        radius: T,
+        half_length: T,
+    },
+    /// Capsule along Z axis centered at the origin, `half_length` is of the line segment
+    Capsule {
+        radius: T,
+        half_length: T,
+    },
+    /// Convex hull of the vertices of the mesh
+    ConvexHull(Arc<TriangleMesh<T>>),
+}
+
+impl<T> Shape<T>
+where
+    T: Real,
+{
+    /// Shape of the collision, `None` if it is `Mesh` which is not loaded
+    pub fn from_collision(collision: &Collision<T>) -> Option<Self> {
+        let half = na::convert::<f64, T>(0.5);
+        match collision.geometry {
+            Geometry::Box {
+                depth,
+                width,
+                height,
+            } => Some(Shape::Box {
+                half_extents: Vector3::new(depth, width, height) * half,
+            }),
+            Geometry::Cylinder { radius, length } => Some(Shape::Cylinder {
+                radius,
+                half_length: length * half,
+            }),
+            Geometry::Sphere { radius } => Some(Shape::Sphere { radius }),
+            Geometry::Mesh { .. } => collision.mesh.clone().map(Shape::ConvexHull),
+        }
+    }
+    /// The farthest point of the shape in `direction`
+    pub fn support(&self, direction: &Vector3<T>) -> Point3<T> {
+        let sign = |v: T, extent: T| if v < T::zero() { -extent } else { extent };
+        match *self {
+            Shape::Sphere { radius } => match direction.try_normalize(T::default_epsilon()) {
+                Some(normal) => Point3::from(normal * radius),
+                None => Point3::new(radius, T::zero(), T::zero()),
+            },
+            Shape::Box { ref half_extents } => Point3::new(
+                sign(direction.x, half_extents.x),
+                sign(direction.y, half_extents.y),
+                sign(direction.z, half_extents.z),
+            ),
+            Shape::Cylinder {
+                radius,
+                half_length,
+            } => {
+                let radial = direction.xy().norm();
+                let (x, y) = if radial > T::default_epsilon() {
+                    (direction.x / radial * radius, direction.y / radial * radius)
+                } else {
+                    (radius, T::zero())
+                };
+                Point3::new(x, y, sign(direction.z, half_length))
+            }
+            Shape::Capsule {
+                radius,
+                half_length,
+            } => {
+                let end = Vector3::new(T::zero(), T::zero(), sign(direction.z, half_length));
+                Shape::Sphere { radius }.support(direction) + end
+            }
+            Shape::ConvexHull(ref mesh) => mesh
+                .vertices
+                .iter()
+                .max_by(|a, b| {
+                    a.coords
+                        .dot(direction)
+                        .partial_cmp(&b.coords.dot(direction))
+                        .unwrap_or(::std::cmp::Ordering::Equal)
+                })
+                .cloned()
+                .unwrap_or_else(Point3::origin),
+        }
+    }
+    /// Radius of the sphere around the origin which contains the shape
+    pub fn bounding_radius(&self) -> T {
+        match *self {
+            Shape::Sphere { radius } => radius,
+            Shape::Box { ref half_extents } => half_extents.norm(),
+            Shape::Cylinder {
+                radius,
+                half_length,
+            } => (radius * radius + half_length * half_length).sqrt(),
+            Shape::Capsule {
+                radius,
+                half_length,
+            } => radius + half_length,
+            Shape::ConvexHull(ref mesh) => mesh
+                .vertices
+                .iter()
+                .fold(T::zero(), |r, v| r.max(v.coords.norm())),
+        }
+    }
+}
+
+/// Shape placed in the world
+type Placed<'a, T> = (&'a Shape<T>, &'a Isometry3<T>);
+
+fn support_world<T: Real>(placed: Placed<T>, direction: &Vector3<T>) -> Point3<T> {
+    let (shape, transform) = placed;
+    transform * shape.support(&(transform.rotation.inverse() * direction))
+}
+
+/// Vertex of the simplex in the Minkowski difference `a - b`
+#[derive(Debug, Clone, Copy)]
+struct SupportPoint<T: Real> {
+    w: Vector3<T>,
+    a: Point3<T>,
+    b: Point3<T>,
+}
+
+fn support_point<T: Real>(a: Placed<T>, b: Placed<T>, direction: &Vector3<T>) -> SupportPoint<T> {
+    let pa = support_world(a, direction);
+    let pb = support_world(b, &-direction);
+    SupportPoint {
+        w: pa - pb,
+        a: pa,
+        b: pb,
+    }
+}
+
+/// Closest point to the origin on the segment, and the weights
+fn closest_on_segment<T: Real>(a: &Vector3<T>, b: &Vector3<T>) -> (Vector3<T>, Vec<T>) {
+    let ab = b - a;
+    let length2 = ab.norm_squared();
+    let t = if length2 > T::zero() {
+        (-a.dot(&ab) / length2).max(T::zero()).min(T::one())
+    } else {
+        T::zero()
+    };
+    (a + ab * t, vec![T::one() - t, t])
+}
+
+/// Closest point to the origin on the triangle, and the weights (Ericson, Real-Time Collision Detection)
+fn closest_on_triangle<T: Real>(
+    a: &Vector3<T>,
+    b: &Vector3<T>,
+    c: &Vector3<T>,
+) -> (Vector3<T>, Vec<T>) {
+    let (zero, one) = (T::zero(), T::one());
+    let ab = b - a;
+    let ac = c - a;
+    let d1 = -ab.dot(a);
+    let d2 = -ac.dot(a);
+    if d1 <= zero && d2 <= zero {
+        return (*a, vec![one, zero, zero]);
+    }
+    let d3 = -ab.dot(b);
+    let d4 = -ac.dot(b);
+    if d3 >= zero && d4 <= d3 {
+        return (*b, vec![zero, one, zero]);
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= zero && d1 >= zero && d3 <= zero {
+        let v = d1 / (d1 - d3);
+        return (a + ab * v, vec![one - v, v, zero]);
+    }
+    let d5 = -ab.dot(c);
+    let d6 = -ac.dot(c);
+    if d6 >= zero && d5 <= d6 {
+        return (*c, vec![zero, zero, one]);
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= zero && d2 >= zero && d6 <= zero {
+        let w = d2 / (d2 - d6);
+        return (a + ac * w, vec![one - w, zero, w]);
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= zero && d4 - d3 >= zero && d5 - d6 >= zero {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (b + (c - b) * w, vec![zero, one - w, w]);
+    }
+    let sum = va + vb + vc;
+    if sum <= zero {
+        // degenerate triangle
+        let (p, w) = closest_on_segment(a, b);
+        let (q, u) = closest_on_segment(a, c);
+        return if p.norm_squared() <= q.norm_squared() {
+            (p, vec![w[0], w[1], zero])
+        } else {
+            (q, vec![u[0], zero, u[1]])
+        };
+    }
+    let v = vb / sum;
+    let w = vc / sum;
+    (a + ab * v + ac * w, vec![one - v - w, v, w])
+}
+
+/// Closest point to the origin on the simplex and the weights, `None` if the origin is in the tetrahedron
+fn closest_on_simplex<T: Real>(points: &[Vector3<T>]) -> Option<(Vector3<T>, Vec<T>)> {
+    match points.len() {
+        1 => Some((points[0], vec![T::one()])),
+        2 => Some(closest_on_segment(&points[0], &points[1])),
+        3 => Some(closest_on_triangle(&points[0], &points[1], &points[2])),
+        _ => {
+            const FACES: [[usize; 4]; 4] = [[0, 1, 2, 3], [0, 1, 3, 2], [0, 2, 3, 1], [1, 2, 3, 0]];
+            let mut inside = true;
+            let mut best: Option<(Vector3<T>, Vec<T>)> = None;
+            for face in &FACES {
+                let (a, b, c, d) = (
+                    &points[face[0]],
+                    &points[face[1]],
+                    &points[face[2]],
+                    &points[face[3]],
+                );
+                let normal = (b - a).cross(&(c - a));
+                let side_of_origin = -normal.dot(a);
+                let side_of_opposite = normal.dot(&(d - a));
+                if side_of_opposite == T::zero() || side_of_origin * side_of_opposite < T::zero() {
+                    inside = false;
+                }
+                let (p, w) = closest_on_triangle(a, b, c);
+                let is_closer = match best {
+                    Some((ref q, _)) => p.norm_squared() < q.norm_squared(),
+                    None => true,
+                };
+                if is_closer {
+                    let mut weights = vec![T::zero(); 4];
+                    for i in 0..3 {
+                        weights[face[i]] = w[i];
+                    }
+                    best = Some((p, weights));
+                }
+            }
+            if inside {
+                None
+            } else {
+                best
+            }
+        }
+    }
+}
+
+/// Closest points of the two shapes in the world frame
+///
+/// `point_b - point_a` is `normal * distance`.
+#[derive(Debug, Clone)]
+pub struct Closest<T: Real> {
+    /// Signed distance, negative if the shapes penetrate each other
+    pub distance: T,
+    /// Point on the first shape
+    pub point_a: Point3<T>,
+    /// Point on the second shape
+    pub point_b: Point3<T>,
+    /// Unit vector to separate the second shape from the first shape
+    ///
+    /// It is zero if the shapes are just touching.
+    pub normal: Vector3<T>,
+}
+
+impl<T> Closest<T>
+where
+    T: Real,
+{
+    fn from_simplex(
+        simplex: &[SupportPoint<T>],
+        weights: &[T],
+        distance: T,
+        normal: Vector3<T>,
+    ) -> Self {
+        let mut point_a = Vector3::zeros();
+        let mut point_b = Vector3::zeros();
+        for (s, w) in simplex.iter().zip(weights) {
+            point_a += s.a.coords * *w;
+            point_b += s.b.coords * *w;
+        }
+        Closest {
+            distance,
+            point_a: Point3::from(point_a),
+            point_b: Point3::from(point_b),
+            normal,
+        }
+    }
+}
+
+const GJK_MAX_ITERATIONS: usize = 64;
+
+/// GJK distance algorithm, returns the simplex containing the origin if the shapes intersect
+fn gjk<T: Real>(a: Placed<T>, b: Placed<T>) -> Result<Closest<T>, Vec<SupportPoint<T>>> {
+    let relative_tolerance = T::default_epsilon().sqrt();
+    let absolute_tolerance = T::default_epsilon() * na::convert(1000.0);
+    let mut direction = b.1.translation.vector - a.1.translation.vector;
+    if direction.norm_squared() == T::zero() {
+        direction = Vector3::x();
+    }
+    let first = support_point(a, b, &-direction);
+    let mut simplex = vec![first];
+    let mut weights = vec![T::one()];
+    let mut v = first.w;
+    for _ in 0..GJK_MAX_ITERATIONS {
+        let v2 = v.norm_squared();
+        if v2 <= absolute_tolerance {
+            return Err(simplex);
+        }
+        let p = support_point(a, b, &-v);
+        // no more progress
+        if v2 - v.dot(&p.w) <= relative_tolerance * v2 || simplex.iter().any(|s| s.w == p.w) {
+            break;
+        }
+        simplex.push(p);
+        let points = simplex.iter().map(|s| s.w).collect::<Vec<_>>();
+        let (closest, new_weights) = match closest_on_simplex(&points) {
+            Some(closest) => closest,
+            None => return Err(simplex),
+        };
+        let (reduced, reduced_weights): (Vec<_>, Vec<_>) = simplex
+            .iter()
+            .zip(new_weights)
+            .filter(|&(_, w)| w > T::zero())
+            .map(|(s, w)| (*s, w))
+            .unzip();
+        simplex = reduced;
+        weights = reduced_weights;
+        v = closest;
+    }
+    let distance = v.norm();
+    Ok(Closest::from_simplex(
+        &simplex,
+        &weights,
+        distance,
+        -v / distance,
+    ))
+}
+
+/// Add the support points to make the simplex containing the origin a tetrahedron
+///
+/// Returns `false` if the Minkowski difference is flat.
+fn expand_simplex<T: Real>(a: Placed<T>, b: Placed<T>, simplex: &mut Vec<SupportPoint<T>>) -> bool {
+    let tolerance = T::default_epsilon().sqrt();
+    while simplex.len() < 4 {
+        let w0 = simplex[0].w;
+        let directions = match simplex.len() {
+            1 => vec![Vector3::x(), Vector3::y(), Vector3::z()],
+            2 => {
+                let edge = simplex[1].w - w0;
+                let axis = if edge.x.abs() < edge.y.abs() && edge.x.abs() < edge.z.abs() {
+                    Vector3::x()
+                } else if edge.y.abs() < edge.z.abs() {
+                    Vector3::y()
+                } else {
+                    Vector3::z()
+                };
+                let u = edge.cross(&axis);
+                vec![u, edge.cross(&u)]
+            }
+            _ => vec![(simplex[1].w - w0).cross(&(simplex[2].w - w0))],
+        };
+        let is_new_dimension = |w: &Vector3<T>| {
+            let d = w - w0;
+            match simplex.len() {
+                1 => d.norm() > tolerance,
+                2 => {
+                    let edge = simplex[1].w - w0;
+                    d.cross(&edge).norm() > tolerance * edge.norm()
+                }
+                _ => {
+                    let normal = (simplex[1].w - w0).cross(&(simplex[2].w - w0));
+                    d.dot(&normal).abs() > tolerance * normal.norm()
+                }
+            }
+        };
+        let found = directions
+            .iter()
+            .flat_map(|d| vec![*d, -d])
+            .map(|d| support_point(a, b, &d))
+            .find(|p| is_new_dimension(&p.w));
+        match found {
+            Some(p) => simplex.push(p),
+            None => return false,
+        }
+    }
+    true
+}
+
+const EPA_MAX_ITERATIONS: usize = 128;
+
+/// Expanding polytope algorithm for the penetration depth
+fn epa<T: Real>(a: Placed<T>, b: Placed<T>, mut vertices: Vec<SupportPoint<T>>) -> Closest<T> {
+    let tolerance = T::default_epsilon().sqrt();
+    if !expand_simplex(a, b, &mut vertices) {
+        // touching
+        let points = vertices.iter().map(|s| s.w).collect::<Vec<_>>();
+        let weights = closest_on_simplex(&points).map_or(vec![T::one()], |(_, w)| w);
+        return Closest::from_simplex(&vertices, &weights, T::zero(), Vector3::zeros());
+    }
+    let face_normal = |vertices: &[SupportPoint<T>], face: &[usize; 3]| {
+        let w0 = vertices[face[0]].w;
+        (vertices[face[1]].w - w0).cross(&(vertices[face[2]].w - w0))
+    };
+    let mut faces = vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]];
+    for (face, opposite) in faces.iter_mut().zip(&[3, 2, 1, 0]) {
+        let normal = face_normal(&vertices, face);
+        if normal.dot(&(vertices[*opposite].w - vertices[face[0]].w)) > T::zero() {
+            face.swap(1, 2);
+        }
+    }
+    let mut nearest = None;
+    for _ in 0..EPA_MAX_ITERATIONS {
+        nearest = None;
+        for (i, face) in faces.iter().enumerate() {
+            let normal = match face_normal(&vertices, face).try_normalize(T::default_epsilon()) {
+                Some(normal) => normal,
+                None => continue,
+            };
+            let distance = normal.dot(&vertices[face[0]].w);
+            let is_nearer = match nearest {
+                Some((_, _, nearest_distance)) => distance < nearest_distance,
+                None => true,
+            };
+            if is_nearer {
+                nearest = Some((i, normal, distance));
+            }
+        }
+        let (_, normal, distance) = match nearest {
+            Some(nearest) => nearest,
+            None => break,
+        };
+        let p = support_point(a, b, &normal);
+        if p.w.dot(&normal) - distance <= tolerance * distance.max(T::one()) {
+            break;
+        }
+        vertices.push(p);
+        let new = vertices.len() - 1;
+        // remove the faces seen from the new vertex and connect it to the horizon
+        let mut horizon: Vec<(usize, usize)> = Vec::new();
+        faces.retain(|face| {
+            let visible =
+                face_normal(&vertices, face).dot(&(p.w - vertices[face[0]].w)) > T::zero();
+            if visible {
+                for &(e0, e1) in &[(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                    match horizon.iter().position(|&edge| edge == (e1, e0)) {
+                        Some(index) => {
+                            horizon.swap_remove(index);
+                        }
+                        None => horizon.push((e0, e1)),
+                    }
+                }
+            }
+            !visible
+        });
+        faces.extend(horizon.into_iter().map(|(e0, e1)| [e0, e1, new]));
+    }
+    let (index, normal, distance) = match nearest {
+        Some(nearest) => nearest,
+        None => {
+            return Closest::from_simplex(&vertices[..1], &[T::one()], T::zero(), Vector3::zeros())
+        }
+    };
+    let face = faces[index];
+    let projected = normal * distance;
+    let (_, weights) = closest_on_triangle(
+        &(vertices[face[0]].w - projected),
+        &(vertices[face[1]].w - projected),
+        &(vertices[face[2]].w - projected),
+    );
+    let simplex = face.iter().map(|i| vertices[*i]).collect::<Vec<_>>();
+    Closest::from_simplex(&simplex, &weights, -distance.max(T::zero()), normal)
+}
+
+/// Signed distance and the closest points of the two shapes
+///
+/// If the shapes penetrate each other, the distance is minus the penetration depth and
+/// the points are the deepest points.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::collision::*;
+///
+/// let ball = Shape::<f64>::Sphere { radius: 0.5 };
+/// let cube = Shape::Box { half_extents: Vector3::new(0.5, 0.5, 0.5) };
+/// let closest = signed_distance(&ball, &Isometry3::identity(), &cube, &Isometry3::translation(0.8, 0.0, 0.0));
+/// assert!((closest.distance + 0.2).abs() < 1e-6);
+/// assert!((closest.normal - Vector3::x()).norm() < 1e-6);
+/// ```
+pub fn signed_distance<T: Real>(
+    shape1: &Shape<T>,
+    transform1: &Isometry3<T>,
+    shape2: &Shape<T>,
+    transform2: &Isometry3<T>,
+) -> Closest<T> {
+    let (a, b) = ((shape1, transform1), (shape2, transform2));
+    match gjk(a, b) {
+        Ok(closest) => closest,
+        Err(simplex) => epa(a, b, simplex),
+    }
+}
+
+/// Check if the two shapes intersect (touching is intersection)
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::collision::*;
+///
+/// let ball = Shape::<f64>::Sphere { radius: 0.5 };
+/// let cube = Shape::Box { half_extents: Vector3::new(0.5, 0.5, 0.5) };
+/// let rotated = Isometry3::new(Vector3::new(1.1, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.7));
+/// assert!(intersects(&ball, &Isometry3::identity(), &cube, &rotated));
+/// let far = Isometry3::translation(1.6, 0.0, 0.0);
+/// assert!(!intersects(&ball, &Isometry3::identity(), &cube, &far));
+/// ```
+pub fn intersects<T: Real>(
+    shape1: &Shape<T>,
+    transform1: &Isometry3<T>,
+    shape2: &Shape<T>,
+    transform2: &Isometry3<T>,
+) -> bool {
+    let center_distance = (transform1.translation.vector - transform2.translation.vector).norm();
+    if center_distance > shape1.bounding_radius() + shape2.bounding_radius() {
+        return false;
+    }
+    gjk((shape1, transform1), (shape2, transform2)).is_err()
+}
+
+/// Closest points of the two shapes, `None` if they intersect
+pub fn closest_points<T: Real>(
+    shape1: &Shape<T>,
+    transform1: &Isometry3<T>,
+    shape2: &Shape<T>,
+    transform2: &Isometry3<T>,
+) -> Option<Closest<T>> {
+    gjk((shape1, transform1), (shape2, transform2)).ok()
+}
+
+/// Obstacle in the world
+#[derive(Debug, Clone)]
+pub enum Obstacle<T: Real> {
+    /// Convex shape at the pose in the world
+    Shape {
+        shape: Shape<T>,
+        transform: Isometry3<T>,
+    },
+    /// Half space of the points `x` with `normal.dot(x) <= offset`
+    Plane { normal: Unit<Vector3<T>>, offset: T },
+}
+
+impl<T> Obstacle<T>
+where
+    T: Real,
+{
+    /// Signed distance from the shape, `point_b` of the output is on the obstacle
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    /// extern crate k;
+    /// use k::*;
+    /// use k::collision::*;
+    ///
+    /// # fn main() {
+    /// let ground = Obstacle::Plane { normal: Vector3::z_axis(), offset: 0.0 };
+    /// let ball = Shape::<f64>::Sphere { radius: 0.5 };
+    /// let closest = ground.signed_distance(&ball, &Isometry3::translation(1.0, 0.0, 0.3));
+    /// assert!((closest.distance + 0.2).abs() < 1e-6);
+    /// assert_eq!(closest.point_b, na::Point3::new(1.0, 0.0, 0.0));
+    /// # }
+    /// ```
+    pub fn signed_distance(&self, shape: &Shape<T>, transform: &Isometry3<T>) -> Closest<T> {
+        match *self {
+            Obstacle::Shape {
+                shape: ref obstacle,
+                transform: ref obstacle_transform,
+            } => signed_distance(shape, transform, obstacle, obstacle_transform),
+            Obstacle::Plane { normal, offset } => {
+                let deepest = support_world((shape, transform), &-normal.into_inner());
+                let distance = normal.dot(&deepest.coords) - offset;
+                Closest {
+                    distance,
+                    point_a: deepest,
+                    point_b: deepest - normal.into_inner() * distance,
+                    normal: -normal.into_inner(),
+                }
+            }
+        }
+    }
+}
+
+/// Signed distance between a link and an obstacle
+#[derive(Debug, Clone)]
+pub struct LinkDistance<T: Real> {
+    /// Name of the link
+    pub link: String,
+    /// Index of the obstacle
+    pub obstacle: usize,
+    /// `point_a` is on the link and `point_b` is on the obstacle
+    pub closest: Closest<T>,
+}
+
+/// Pairs of the links which are not checked
+///
+/// The order of the names of a pair does not matter. The pairs of a link and an
+/// obstacle of `CollisionChecker` (by its index) can be allowed too.
+#[derive(Debug, Clone, Default)]
+pub struct AllowedCollisionMatrix {
+    pairs: HashSet<(String, String)>,
+    obstacle_pairs: HashSet<(String, usize)>,
+}
+
+fn ordered_pair(link1: &str, link2: &str) -> (String, String) {
+    if link1 <= link2 {
+        (link1.to_owned(), link2.to_owned())
+    } else {
+        (link2.to_owned(), link1.to_owned())
+    }
+}
+
+impl AllowedCollisionMatrix {
+    /// Create matrix which allows nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create matrix which allows the links connected by a joint
+    ///
+    /// The nodes without links are skipped, so the links connected by
+    /// the joints without links are also allowed.
+    pub fn from_adjacent_links<T: Real>(chain: &Chain<T>) -> Self {
+        let mut matrix = Self::new();
+        for node in chain.iter() {
+            let link = node.link();
+            let link_name = match *link {
+                Some(ref link) => &link.name,
+                None => continue,
+            };
+            let parent_link = node
+                .iter_ancestors()
+                .skip(1)
+                .find_map(|ancestor| ancestor.link().as_ref().map(|l| l.name.clone()));
+            if let Some(parent_link) = parent_link {
+                matrix.allow(&parent_link, link_name);
+            }
+        }
+        matrix
+    }
+    /// Allow the collision between the links
+    pub fn allow(&mut self, link1: &str, link2: &str) {
+        self.pairs.insert(ordered_pair(link1, link2));
+    }
+    /// Check the collision between the links again
+    pub fn disallow(&mut self, link1: &str, link2: &str) {
+        self.pairs.remove(&ordered_pair(link1, link2));
+    }
+    pub fn is_allowed(&self, link1: &str, link2: &str) -> bool {
+        self.pairs.contains(&ordered_pair(link1, link2))
+    }
+    /// Allow the collision between the link and the obstacle of the index
+    pub fn allow_obstacle(&mut self, link: &str, obstacle: usize) {
+        self.obstacle_pairs.insert((link.to_owned(), obstacle));
+    }
+    /// Check the collision between the link and the obstacle again
+    pub fn disallow_obstacle(&mut self, link: &str, obstacle: usize) {
+        self.obstacle_pairs.remove(&(link.to_owned(), obstacle));
+    }
+    pub fn is_obstacle_allowed(&self, link: &str, obstacle: usize) -> bool {
+        self.obstacle_pairs.contains(&(link.to_owned(), obstacle))
+    }
+}
+
+/// Colliding pair found by `CollisionChecker`
+///
+/// `distance` is the signed distance of the pair, negative if they penetrate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Colliding {
+    /// Names of the two links
+    Links {
+        link1: String,
+        link2: String,
+        distance: f64,
+    },
+    /// Name of the link and the index of the obstacle
+    Obstacle {
+        link: String,
+        obstacle: usize,
+        distance: f64,
+    },
+}
+
+impl Colliding {
+    /// Signed distance of the pair
+    pub fn distance(&self) -> f64 {
+        match *self {
+            Colliding::Links { distance, .. } | Colliding::Obstacle { distance, .. } => distance,
+        }
+    }
+}
+
+impl Display for Colliding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Colliding::Links {
+                ref link1,
+                ref link2,
+                distance,
+            } => write!(f, "{} <-> {} ({})", link1, link2, distance),
+            Colliding::Obstacle {
+                ref link,
+                obstacle,
+                distance,
+            } => write!(f, "{} <-> obstacle[{}] ({})", link, obstacle, distance),
+        }
+    }
+}
+
+/// Minimum signed distance between the shapes of two links
+fn links_distance<T: Real>(
+    shapes1: &[(Shape<T>, Isometry3<T>)],
+    shapes2: &[(Shape<T>, Isometry3<T>)],
+) -> Option<T> {
+    let mut min: Option<T> = None;
+    for (s1, t1) in shapes1 {
+        for (s2, t2) in shapes2 {
+            let distance = signed_distance(s1, t1, s2, t2).distance;
+            min = match min {
+                Some(min) if min <= distance => Some(min),
+                _ => Some(distance),
+            };
+        }
+    }
+    min
+}
+
+fn to_f64<T: Real>(value: T) -> f64 {
+    na::try_convert(value).unwrap_or(0.0)
+}
+
+/// Checker of the self collisions and the collisions with the obstacles
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::collision::*;
+/// use k::link::*;
+///
+/// let j0 = JointBuilder::new()
+///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+///     .into_node();
+/// j0.set_link(Some(LinkBuilder::new()
+///     .name("ball")
+///     .add_collision(Collision::new(
+///         "ball".to_owned(), Isometry3::identity(), Geometry::Sphere { radius: 0.1 }))
+///     .finalize()));
+/// let chain = Chain::<f64>::from_root(j0);
+/// let checker = CollisionChecker::new()
+///     .obstacle(Obstacle::Plane { normal: Vector3::z_axis(), offset: -1.0 })
+///     .margin(0.05);
+/// assert!(!checker.is_colliding(&chain));
+/// chain.set_joint_positions(&[-0.86]).unwrap();
+/// let collisions = checker.collisions(&chain);
+/// assert_eq!(collisions.len(), 1);
+/// match collisions[0] {
+///     Colliding::Obstacle { ref link, obstacle, distance } => {
+///         assert_eq!(link, "ball");
+///         assert_eq!(obstacle, 0);
+///         assert!((distance - 0.04).abs() < 1e-6);
+///     }
+///     _ => panic!("must collide with the plane"),
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CollisionChecker<T: Real> {
+    /// Pairs of the links (and the obstacles) which are not checked, the adjacent links if `None`
+    pub allowed_collision_matrix: Option<AllowedCollisionMatrix>,
+    /// Check the collisions between the links of the chain
+    pub check_self_collisions: bool,
+    pub obstacles: Vec<Obstacle<T>>,
+    /// The links closer than this distance to the obstacles are colliding
+    pub margin: T,
+}
+
+impl<T> CollisionChecker<T>
+where
+    T: Real,
+{
+    /// Create the checker of the self collisions without obstacles
+    pub fn new() -> Self {
+        CollisionChecker {
+            allowed_collision_matrix: None,
+            check_self_collisions: true,
+            obstacles: Vec::new(),
+            margin: T::zero(),
+        }
+    }
+    pub fn allowed_collision_matrix(mut self, matrix: AllowedCollisionMatrix) -> Self {
+        self.allowed_collision_matrix = Some(matrix);
+        self
+    }
+    pub fn check_self_collisions(mut self, check: bool) -> Self {
+        self.check_self_collisions = check;
+        self
+    }
+    pub fn obstacle(mut self, obstacle: Obstacle<T>) -> Self {
+        self.obstacles.push(obstacle);
+        self
+    }
+    pub fn margin(mut self, margin: T) -> Self {
+        self.margin = margin;
+        self
+    }
+    /// All colliding pairs with the current joint positions of `chain`
+    pub fn collisions(&self, chain: &Chain<T>) -> Vec<Colliding> {
+        let mut collisions = Vec::new();
+        if self.check_self_collisions {
+            let pairs = match self.allowed_collision_matrix {
+                Some(ref matrix) => chain.self_collisions_with(matrix),
+                None => chain.self_collisions(),
+            };
+            if !pairs.is_empty() {
+                let links = chain.link_collision_shapes();
+                let shapes_of = |name: &str| {
+                    links
+                        .iter()
+                        .find(|(link, _)| link == name)
+                        .map(|(_, shapes)| shapes.as_slice())
+                        .unwrap_or(&[])
+                };
+                collisions.extend(pairs.into_iter().map(|(link1, link2)| {
+                    let distance =
+                        links_distance(shapes_of(&link1), shapes_of(&link2)).map_or(0.0, to_f64);
+                    Colliding::Links {
+                        link1,
+                        link2,
+                        distance,
+                    }
+                }));
+            }
+        }
+        collisions.extend(self.colliding_obstacles(chain).into_iter().map(|distance| {
+            Colliding::Obstacle {
+                link: distance.link,
+                obstacle: distance.obstacle,
+                distance: to_f64(distance.closest.distance),
+            }
+        }));
+        collisions
+    }
+    /// Check if `chain` has any collisions
+    pub fn is_colliding(&self, chain: &Chain<T>) -> bool {
+        !self.collisions(chain).is_empty()
+    }
+    /// Distances between the links and the obstacles which are within `margin`
+    ///
+    /// The pairs allowed by `allowed_collision_matrix` are ignored.
+    pub fn colliding_obstacles(&self, chain: &Chain<T>) -> Vec<LinkDistance<T>> {
+        if self.obstacles.is_empty() {
+            return Vec::new();
+        }
+        chain
+            .obstacle_distances(&self.obstacles)
+            .into_iter()
+            .filter(|distance| distance.closest.distance <= self.margin)
+            .filter(|distance| match self.allowed_collision_matrix {
+                Some(ref matrix) => !matrix.is_obstacle_allowed(&distance.link, distance.obstacle),
+                None => true,
+            })
+            .collect()
+    }
+}
+
+impl<T> Default for CollisionChecker<T>
+where
+    T: Real,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shapes of the collisions of the link in the world
+type LinkShapes<T> = (String, Vec<(Shape<T>, Isometry3<T>)>);
+
+impl<T> Chain<T>
+where
+    T: Real,
+{
+    /// Shapes of the collisions of the links with the current joint positions
+    pub(crate) fn link_collision_shapes(&self) -> Vec<LinkShapes<T>> {
+        self.update_link_transforms();
+        self.iter_links()
+            .map(|link| {
+                let shapes = link
+                    .collisions
+                    .iter()
+                    .filter_map(|collision| {
+                        let transform = (*collision.world_transform())?;
+                        let shape = Shape::from_collision(collision)?;
+                        Some((shape, transform))
+                    })
+                    .collect();
+                (link.name.clone(), shapes)
+            })
+            .collect()
+    }
+    /// Minimum signed distances between the links and the obstacles
+    ///
+    /// All pairs of the links with collisions and the obstacles are returned.
+    /// The world transforms are updated.
+    pub fn obstacle_distances(&self, obstacles: &[Obstacle<T>]) -> Vec<LinkDistance<T>> {
+        let mut distances = Vec::new();
+        for (name, shapes) in self.link_collision_shapes() {
+            for (index, obstacle) in obstacles.iter().enumerate() {
+                let nearest = shapes
+                    .iter()
+                    .map(|(shape, transform)| obstacle.signed_distance(shape, transform))
+                    .min_by(|a, b| {
+                        a.distance
+                            .partial_cmp(&b.distance)
+                            .unwrap_or(::std::cmp::Ordering::Equal)
+                    });
+                if let Some(closest) = nearest {
+                    distances.push(LinkDistance {
+                        link: name.clone(),
+                        obstacle: index,
+                        closest,
+                    });
+                }
+            }
+        }
+        distances
+    }
+    /// Gradient of the distance with respect to the joint positions
+    ///
+    /// The obstacle is fixed and the closest point on the link moves with the link.
+    /// The length is `dof()`. It is zero if the link is not found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::collision::*;
+    /// use k::link::*;
+    ///
+    /// let j0 = JointBuilder::new()
+    ///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// j0.set_link(Some(LinkBuilder::new()
+    ///     .name("ball")
+    ///     .add_collision(Collision::new(
+    ///         "ball".to_owned(), Isometry3::identity(), Geometry::Sphere { radius: 0.1 }))
+    ///     .finalize()));
+    /// let chain = Chain::<f64>::from_root(j0);
+    /// let ground = Obstacle::Plane { normal: Vector3::z_axis(), offset: -1.0 };
+    /// let distances = chain.obstacle_distances(&[ground]);
+    /// assert!((distances[0].closest.distance - 0.9).abs() < 1e-6);
+    /// let gradient = chain.distance_gradient(&distances[0]);
+    /// assert!((gradient[0] - 1.0).abs() < 1e-6);
+    /// ```
+    pub fn distance_gradient(&self, distance: &LinkDistance<T>) -> DVector<T> {
+        match self.find_link(&distance.link) {
+            Some(node) => {
+                let jacobi = point_jacobian(self, node, &distance.closest.point_a.coords);
+                -jacobi.transpose() * distance.closest.normal
+            }
+            None => DVector::zeros(self.dof()),
+        }
+    }
+    /// Pairs of the names of the colliding links, except for the adjacent links
+    pub fn self_collisions(&self) -> Vec<(String, String)> {
+        self.self_collisions_with(&AllowedCollisionMatrix::from_adjacent_links(self))
+    }
+    /// Pairs of the names of the colliding links, except for the pairs allowed by `matrix`
+    ///
+    /// The names in a pair are sorted. The world transforms are updated.
+    pub fn self_collisions_with(&self, matrix: &AllowedCollisionMatrix) -> Vec<(String, String)> {
+        let links = self.link_collision_shapes();
+        let mut pairs = Vec::new();
+        for (i, (name1, shapes1)) in links.iter().enumerate() {
+            for (name2, shapes2) in &links[i + 1..] {
+                if name1 == name2 || matrix.is_allowed(name1, name2) {
+                    continue;
+                }
+                let colliding = shapes1
+                    .iter()
+                    .any(|(s1, t1)| shapes2.iter().any(|(s2, t2)| intersects(s1, t1, s2, t2)));
+                if colliding {
+                    pairs.push(ordered_pair(name1, name2));
+                }
+            }
+        }
+        pairs.sort();
+        pairs
+    }
+}
+
+#[test]
+fn test_gjk() {
+    let identity = Isometry3::identity();
+    let sphere = Shape::Sphere { radius: 1.0 };
+    let cube = Shape::Box {
+        half_extents: Vector3::new(0.5, 0.5, 0.5),
+    };
+    let cylinder = Shape::Cylinder {
+        radius: 0.5,
+        half_length: 1.0,
+    };
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+
+    let t = Isometry3::translation(3.0, 0.0, 0.0);
+    let closest = gjk((&sphere, &identity), (&sphere, &t)).unwrap();
+    assert!(close(closest.distance, 1.0));
+    assert!(close(closest.point_a.x, 1.0));
+    assert!(close(closest.point_b.x, 2.0));
+
+    // corner of the rotated box to the face of the box
+    let rotated = Isometry3::new(
+        Vector3::new(0.0, 0.0, 2.0),
+        Vector3::new(::std::f64::consts::FRAC_PI_4, 0.0, 0.0),
+    );
+    let closest = gjk((&cube, &identity), (&cube, &rotated)).unwrap();
+    assert!(close(closest.distance, 1.5 - 0.5f64.sqrt()));
+    assert!(close(closest.point_a.z, 0.5));
+    assert!(close(closest.point_b.y, 0.0));
+
+    // side of the cylinder
+    let t = Isometry3::translation(0.0, 1.5, 0.3);
+    let closest = gjk((&cylinder, &identity), (&cube, &t)).unwrap();
+    assert!(close(closest.distance, 0.5));
+    assert!(close(closest.point_a.y, 0.5));
+    // end of the cylinder
+    let t = Isometry3::translation(0.2, 0.1, 2.0);
+    assert!(close(
+        gjk((&cylinder, &identity), (&cube, &t)).unwrap().distance,
+        0.5
+    ));
+    assert!(gjk((&cylinder, &identity), (&sphere, &identity)).is_err());
+    let t = Isometry3::translation(0.0, 0.9, 0.9);
+    assert!(gjk((&cylinder, &identity), (&cube, &t)).is_err());
+
+    // tetrahedron
+    let hull = Shape::ConvexHull(Arc::new(
+        TriangleMesh::from_obj_str("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\n").unwrap(),
+    ));
+    let t = Isometry3::translation(1.0, 1.0, 1.0);
+    let closest = gjk((&hull, &identity), (&sphere, &t)).unwrap();
+    let expected = (Vector3::new(1.0, 1.0, 1.0) - Vector3::new(1.0, 1.0, 1.0) / 3.0).norm() - 1.0;
+    assert!(close(closest.distance, expected));
+    assert!((closest.point_a - Point3::new(1.0, 1.0, 1.0) / 3.0).norm() < 1e-5);
+    let t = Isometry3::translation(0.8, 0.8, 0.8);
+    assert!(intersects(&hull, &identity, &sphere, &t));
+    assert_eq!(hull.bounding_radius(), 1.0);
+}
+
+#[test]
+fn test_self_collisions() {
+    use joint::*;
+    use node::*;
+    let node = |name: &str, z: f64, geometry: Geometry<f64>| {
+        let node = JointBuilder::new()
+            .name(name)
+            .joint_type(JointType::Rotational {
+                axis: Vector3::x_axis(),
+            })
+            .translation(na::Translation3::new(0.0, 0.0, z))
+            .into_node();
+        node.set_link(Some(
+            LinkBuilder::new()
+                .name(&format!("{}_link", name))
+                .add_collision(Collision::new(
+                    name.to_owned(),
+                    Isometry3::translation(0.0, 0.0, 0.5),
+                    geometry,
+                ))
+                .finalize(),
+        ));
+        node
+    };
+    let root = node(
+        "base",
+        0.0,
+        Geometry::Box {
+            depth: 0.2,
+            width: 0.2,
+            height: 1.0,
+        },
+    );
+    let upper = node(
+        "upper",
+        1.0,
+        Geometry::Cylinder {
+            radius: 0.1,
+            length: 1.0,
+        },
+    );
+    // the joint without link between upper and lower
+    let offset = JointBuilder::new()
+        .name("offset")
+        .translation(na::Translation3::new(0.0, 0.0, 1.0))
+        .into_node();
+    let lower = node("lower", 0.0, Geometry::Sphere { radius: 0.3 });
+    upper.set_parent(&root);
+    offset.set_parent(&upper);
+    lower.set_parent(&offset);
+    let chain = Chain::from_root(root.clone());
+    let matrix = AllowedCollisionMatrix::from_adjacent_links(&chain);
+    assert!(matrix.is_allowed("base_link", "upper_link"));
+    assert!(matrix.is_allowed("lower_link", "upper_link"));
+    assert!(!matrix.is_allowed("base_link", "lower_link"));
+    assert!(chain.self_collisions().is_empty());
+
+    // all links overlap, only base and lower are reported
+    root.set_joint_position(0.0).unwrap();
+    upper.set_joint_position(3.0).unwrap();
+    lower.set_joint_position(3.0).unwrap();
+    assert_eq!(
+        chain.self_collisions(),
+        vec![("base_link".to_owned(), "lower_link".to_owned())]
+    );
+    let mut matrix = AllowedCollisionMatrix::new();
+    assert_eq!(chain.self_collisions_with(&matrix).len(), 3);
+    matrix.allow("lower_link", "base_link");
+    assert_eq!(chain.self_collisions_with(&matrix).len(), 2);
+    matrix.disallow("base_link", "lower_link");
+    assert_eq!(chain.self_collisions_with(&matrix).len(), 3);
+}
+
+#[test]
+fn test_signed_distance() {
+    let identity = Isometry3::identity();
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+    let sphere = Shape::Sphere { radius: 1.0 };
+    let cube = Shape::Box {
+        half_extents: Vector3::new(0.5, 0.5, 0.5),
+    };
+    let capsule = Shape::Capsule {
+        radius: 0.2,
+        half_length: 1.0,
+    };
+
+    let t = Isometry3::translation(0.0, 1.5, 0.0);
+    let closest = signed_distance(&sphere, &identity, &sphere, &t);
+    assert!(close(closest.distance, -0.5));
+    assert!(close(closest.normal.y, 1.0));
+    assert!(close(closest.point_a.y, 1.0));
+    assert!(close(closest.point_b.y, 0.5));
+
+    // the same center
+    let closest = signed_distance(&cube, &identity, &cube, &identity);
+    assert!(close(closest.distance, -1.0));
+    let t = Isometry3::translation(0.3, 0.9, 0.2);
+    let closest = signed_distance(&cube, &identity, &cube, &t);
+    assert!(close(closest.distance, -0.1));
+    assert!(close(closest.normal.y, 1.0));
+    let diff = closest.point_b - closest.point_a;
+    assert!((diff - closest.normal * closest.distance).norm() < 1e-4);
+
+    // separated
+    let t = Isometry3::translation(0.0, 0.0, 2.0);
+    let closest = signed_distance(&capsule, &identity, &cube, &t);
+    assert!(close(closest.distance, 0.3));
+    assert!(close(closest.normal.z, 1.0));
+    assert!(signed_distance(&capsule, &identity, &sphere, &t).distance < 0.0);
+
+    let ground: Obstacle<f64> = Obstacle::Plane {
+        normal: Vector3::z_axis(),
+        offset: 0.5,
+    };
+    let rotated = Isometry3::new(
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(::std::f64::consts::FRAC_PI_2, 0.0, 0.0),
+    );
+    let closest = ground.signed_distance(&capsule, &rotated);
+    assert!(close(closest.distance, 0.3));
+    let closest = ground.signed_distance(&capsule, &identity);
+    assert!(close(closest.distance, -1.7));
+    assert!(close(closest.point_a.z, -1.2));
+}
+
+#[test]
+fn test_distance_gradient() {
+    use joint::*;
+    use node::*;
+    let j0 = JointBuilder::new()
+        .joint_type(JointType::Rotational {
+            axis: Vector3::y_axis(),
+        })
+        .into_node();
+    let j1 = JointBuilder::new()
+        .joint_type(JointType::Rotational {
+            axis: Vector3::y_axis(),
+        })
+        .translation(na::Translation3::new(0.0, 0.0, 1.0))
+        .into_node();
+    j1.set_link(Some(
+        LinkBuilder::new()
+            .name("arm")
+            .add_collision(Collision::new(
+                "arm".to_owned(),
+                Isometry3::translation(0.0, 0.0, 0.5),
+                Geometry::Box {
+                    depth: 0.2,
+                    width: 0.2,
+                    height: 1.0,
+                },
+            ))
+            .finalize(),
+    ));
+    j1.set_parent(&j0);
+    let chain = Chain::<f64>::from_root(j0);
+    let obstacles = [
+        Obstacle::Shape {
+            shape: Shape::Sphere { radius: 0.2 },
+            transform: Isometry3::translation(1.0, 0.5, 1.5),
+        },
+        Obstacle::Plane {
+            normal: Vector3::z_axis(),
+            offset: 0.0,
+        },
+        Obstacle::Shape {
+            shape: Shape::Sphere { radius: 0.3 },
+            transform: Isometry3::translation(0.75, 0.0, 1.25),
+        },
+    ];
+    let positions = [0.3, 0.5];
+    chain.set_joint_positions(&positions).unwrap();
+    let distances = chain.obstacle_distances(&obstacles);
+    assert_eq!(distances.len(), 3);
+    assert!(distances[2].closest.distance < 0.0);
+    for distance in &distances {
+        assert_eq!(distance.link, "arm");
+        let gradient = chain.distance_gradient(distance);
+        for i in 0..2 {
+            let delta = 1e-4;
+            let mut moved = positions;
+            let mut moved_distance = |d: f64| {
+                moved[i] = positions[i] + d;
+                chain.set_joint_positions(&moved).unwrap();
+                chain.obstacle_distances(&obstacles)[distance.obstacle]
+                    .closest
+                    .distance
+            };
+            let numerical = (moved_distance(delta) - moved_distance(-delta)) / (2.0 * delta);
+            assert!((numerical - gradient[i]).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn test_collision_checker_allowed_obstacle() {
+    use joint::*;
+    use node::*;
+
+    let j0 = JointBuilder::new()
+        .joint_type(JointType::Linear {
+            axis: Vector3::z_axis(),
+        })
+        .into_node();
+    let j1 = JointBuilder::new()
+        .translation(na::Translation3::new(0.0, 0.0, 1.0))
+        .into_node();
+    let sphere = |name: &str| {
+        LinkBuilder::new()
+            .name(name)
+            .add_collision(Collision::new(
+                name.to_owned(),
+                Isometry3::identity(),
+                Geometry::Sphere { radius: 0.1 },
+            ))
+            .finalize()
+    };
+    j0.set_link(Some(sphere("base")));
+    j1.set_link(Some(sphere("tip")));
+    j1.set_parent(&j0);
+    let chain = Chain::<f64>::from_root(j0);
+    let ground = Obstacle::Plane {
+        normal: Vector3::z_axis(),
+        offset: 0.0,
+    };
+    chain.set_joint_positions(&[0.05]).unwrap();
+    let checker = CollisionChecker::new().obstacle(ground.clone());
+    let collisions = checker.collisions(&chain);
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(
+        collisions[0],
+        Colliding::Obstacle {
+            link: "base".to_owned(),
+            obstacle: 0,
+            distance: collisions[0].distance(),
+        }
+    );
+    assert!((collisions[0].distance() + 0.05).abs() < 1e-6);
+
+    let mut matrix = AllowedCollisionMatrix::from_adjacent_links(&chain);
+    matrix.allow_obstacle("base", 0);
+    let checker = checker.allowed_collision_matrix(matrix);
+    assert!(!checker.is_colliding(&chain));
+    chain.set_joint_positions(&[-1.05]).unwrap();
+    let collisions = checker.collisions(&chain);
+    assert_eq!(collisions.len(), 1);
+    match collisions[0] {
+        Colliding::Obstacle {
+            ref link, distance, ..
+        } => {
+            assert_eq!(link, "tip");
+            assert!((distance + 0.15).abs() < 1e-6);
+        }
+        _ => panic!("tip must collide with the ground"),
+    }
+}
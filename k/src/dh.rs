@@ -0,0 +1,586 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Build `SerialChain` from Denavit-Hartenberg parameters
+//!
+//! # Examples
+//!
+//! ```
+//! use k::dh::*;
+//!
+//! // planar arm with two links of 0.5
+//! let table = DhTable::new(Convention::Standard)
+//!     .row(DhParameter::new(0.5f64, 0.0, 0.0, 0.0).name("shoulder"))
+//!     .row(DhParameter::new(0.5, 0.0, 0.0, 0.0).name("elbow"));
+//! let arm = table.to_serial_chain();
+//! arm.set_joint_positions(&[0.0, std::f64::consts::FRAC_PI_2]).unwrap();
+//! let end = arm.end_transform();
+//! assert!((end.translation.vector.x - 0.5).abs() < 1e-10);
+//! assert!((end.translation.vector.y - 0.5).abs() < 1e-10);
+//! ```
+use na::{Isometry3, Real, Translation3, UnitQuaternion, Vector3};
+
+use chain::*;
+use errors::*;
+use joint::*;
+use node::*;
+
+/// Name of the node at the end of the chain created by `DhTable`
+pub const END_NODE_NAME: &str = "dh_end";
+
+/// Convention of DH parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Convention {
+    /// Classic DH: `Rz(theta) * Tz(d) * Tx(a) * Rx(alpha)`
+    Standard,
+    /// Modified DH (Craig): `Rx(alpha) * Tx(a) * Rz(theta) * Tz(d)`,
+    /// `a` and `alpha` are the ones of the previous link
+    Modified,
+}
+
+/// Type of the joint of a DH row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DhJointType {
+    /// Rotates around Z, the position is added to `theta`
+    Revolute,
+    /// Same as `Revolute`, but without limits (`JointType::Continuous`)
+    Continuous,
+    /// Moves along Z, the position is added to `d`
+    Prismatic,
+    /// No joint, only the transform
+    Fixed,
+}
+
+/// One row of DH table
+#[derive(Debug, Clone)]
+pub struct DhParameter<T: Real> {
+    /// Name of the joint
+    pub name: String,
+    /// Link length
+    pub a: T,
+    /// Link twist
+    pub alpha: T,
+    /// Link offset
+    pub d: T,
+    /// Joint angle
+    pub theta: T,
+    /// Type of the joint
+    pub joint_type: DhJointType,
+    /// Limits of the joint position
+    pub limits: Option<Range<T>>,
+}
+
+impl<T> DhParameter<T>
+where
+    T: Real,
+{
+    /// Create revolute row without limits
+    pub fn new(a: T, alpha: T, d: T, theta: T) -> Self {
+        DhParameter {
+            name: "".to_owned(),
+            a,
+            alpha,
+            d,
+            theta,
+            joint_type: DhJointType::Revolute,
+            limits: None,
+        }
+    }
+    /// Set the name of the joint
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+    /// Set the type of the joint
+    pub fn joint_type(mut self, joint_type: DhJointType) -> Self {
+        self.joint_type = joint_type;
+        self
+    }
+    /// Set the limits of the joint
+    pub fn limits(mut self, limits: Option<Range<T>>) -> Self {
+        self.limits = limits;
+        self
+    }
+    /// Transform of this row when the joint is at `position`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::dh::*;
+    ///
+    /// let row = DhParameter::new(1.0f64, 0.0, 0.5, 0.0);
+    /// let trans = row.transform(std::f64::consts::FRAC_PI_2, Convention::Standard);
+    /// assert!(trans.translation.vector.x.abs() < 1e-10);
+    /// assert!((trans.translation.vector.y - 1.0).abs() < 1e-10);
+    /// assert!((trans.translation.vector.z - 0.5).abs() < 1e-10);
+    /// ```
+    pub fn transform(&self, position: T, convention: Convention) -> Isometry3<T> {
+        let (theta, d) = match self.joint_type {
+            DhJointType::Revolute | DhJointType::Continuous => (self.theta + position, self.d),
+            DhJointType::Prismatic => (self.theta, self.d + position),
+            DhJointType::Fixed => (self.theta, self.d),
+        };
+        let z = rotation_z(theta) * translation_z(d);
+        let x = translation_x(self.a) * rotation_x(self.alpha);
+        match convention {
+            Convention::Standard => z * x,
+            Convention::Modified => {
+                rotation_x(self.alpha)
+                    * translation_x(self.a)
+                    * rotation_z(theta)
+                    * translation_z(d)
+            }
+        }
+    }
+    fn k_joint_type(&self) -> JointType<T> {
+        match self.joint_type {
+            DhJointType::Revolute => JointType::Rotational {
+                axis: Vector3::z_axis(),
+            },
+            DhJointType::Continuous => JointType::Continuous {
+                axis: Vector3::z_axis(),
+            },
+            DhJointType::Prismatic => JointType::Linear {
+                axis: Vector3::z_axis(),
+            },
+            DhJointType::Fixed => JointType::Fixed,
+        }
+    }
+}
+
+fn rotation_x<T: Real>(angle: T) -> Isometry3<T> {
+    Isometry3::from_parts(
+        Translation3::identity(),
+        UnitQuaternion::from_axis_angle(&Vector3::x_axis(), angle),
+    )
+}
+
+fn rotation_z<T: Real>(angle: T) -> Isometry3<T> {
+    Isometry3::from_parts(
+        Translation3::identity(),
+        UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle),
+    )
+}
+
+fn translation_x<T: Real>(length: T) -> Isometry3<T> {
+    Isometry3::from_parts(
+        Translation3::new(length, T::zero(), T::zero()),
+        UnitQuaternion::identity(),
+    )
+}
+
+fn translation_z<T: Real>(length: T) -> Isometry3<T> {
+    Isometry3::from_parts(
+        Translation3::new(T::zero(), T::zero(), length),
+        UnitQuaternion::identity(),
+    )
+}
+
+fn is_small<T: Real>(value: T) -> bool {
+    value.abs() <= T::default_epsilon().sqrt()
+}
+
+/// Decompose `trans` to `(a, alpha, d, theta)`, or returns the reason of the failure
+fn decompose<T>(trans: &Isometry3<T>, convention: Convention) -> Result<(T, T, T, T), String>
+where
+    T: Real,
+{
+    let rot = trans.rotation.to_rotation_matrix();
+    let r = rot.matrix();
+    let p = trans.translation.vector;
+    let (a, alpha, d, theta) = match convention {
+        // R = Rz(theta) * Rx(alpha), p = (a cos(theta), a sin(theta), d)
+        Convention::Standard => {
+            if !is_small(r[(2, 0)]) {
+                return Err(format!("X axis is not perpendicular to Z: {}", r[(2, 0)]));
+            }
+            let theta = r[(1, 0)].atan2(r[(0, 0)]);
+            let alpha = r[(2, 1)].atan2(r[(2, 2)]);
+            (p.x * theta.cos() + p.y * theta.sin(), alpha, p.z, theta)
+        }
+        // R = Rx(alpha) * Rz(theta), p = (a, -sin(alpha) d, cos(alpha) d)
+        Convention::Modified => {
+            if !is_small(r[(0, 2)]) {
+                return Err(format!("Z axis is not perpendicular to X: {}", r[(0, 2)]));
+            }
+            let theta = (-r[(0, 1)]).atan2(r[(0, 0)]);
+            let alpha = (-r[(1, 2)]).atan2(r[(2, 2)]);
+            (p.x, alpha, -p.y * alpha.sin() + p.z * alpha.cos(), theta)
+        }
+    };
+    let row = DhParameter::new(a, alpha, d, theta).joint_type(DhJointType::Fixed);
+    let error = row.transform(T::zero(), convention).inverse() * trans;
+    if !is_small(error.translation.vector.norm()) {
+        return Err(format!(
+            "translation {} can not be represented",
+            trans.translation.vector
+        ));
+    }
+    if !is_small(error.rotation.angle()) {
+        let (roll, pitch, yaw) = trans.rotation.euler_angles();
+        return Err(format!(
+            "rotation (roll, pitch, yaw) = ({}, {}, {}) can not be represented",
+            roll, pitch, yaw
+        ));
+    }
+    Ok((a, alpha, d, theta))
+}
+
+/// DH table
+///
+/// `base` is the transform from the root of the chain to the first joint.
+#[derive(Debug, Clone)]
+pub struct DhTable<T: Real> {
+    /// Convention of the parameters
+    pub convention: Convention,
+    /// Rows from the root to the end
+    pub rows: Vec<DhParameter<T>>,
+    /// Transform of the first joint frame
+    pub base: Isometry3<T>,
+}
+
+impl<T> DhTable<T>
+where
+    T: Real,
+{
+    /// Create empty table
+    pub fn new(convention: Convention) -> Self {
+        DhTable {
+            convention,
+            rows: Vec::new(),
+            base: Isometry3::identity(),
+        }
+    }
+    /// Add the row at the end
+    pub fn row(mut self, row: DhParameter<T>) -> Self {
+        self.rows.push(row);
+        self
+    }
+    /// Set the transform of the first joint frame
+    pub fn base(mut self, base: Isometry3<T>) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Create the nodes and returns the root and the end
+    ///
+    /// The end node is a fixed joint named `END_NODE_NAME`.
+    pub fn to_nodes(&self) -> (Node<T>, Node<T>) {
+        let root = JointBuilder::new().name("dh_base").into_node();
+        let mut parent = root.clone();
+        // transform which is not used by the previous joints yet
+        let mut rest = self.base;
+        for (i, row) in self.rows.iter().enumerate() {
+            let name = if row.name.is_empty() {
+                format!("joint{}", i + 1)
+            } else {
+                row.name.clone()
+            };
+            let fixed_part = row.transform(T::zero(), self.convention);
+            // the motion is applied at the frame before Tx(a) * Rx(alpha) for standard,
+            // and after the whole transform for modified
+            let (origin, next_rest) = match self.convention {
+                Convention::Standard => (rest, fixed_part),
+                Convention::Modified => (rest * fixed_part, Isometry3::identity()),
+            };
+            let node = JointBuilder::new()
+                .name(&name)
+                .joint_type(row.k_joint_type())
+                .limits(row.limits)
+                .origin(origin)
+                .into_node();
+            node.set_parent(&parent);
+            parent = node;
+            rest = next_rest;
+        }
+        let end = JointBuilder::new()
+            .name(END_NODE_NAME)
+            .origin(rest)
+            .into_node();
+        end.set_parent(&parent);
+        (root, end)
+    }
+
+    /// Create `SerialChain` from the table
+    pub fn to_serial_chain(&self) -> SerialChain<T> {
+        let (_root, end) = self.to_nodes();
+        SerialChain::from_end(&end)
+    }
+
+    /// Extract DH parameters from the chain
+    ///
+    /// All the movable joints must be `Rotational`, `Continuous` or `Linear` around or
+    /// along Z axis, and the transforms between them must be DH form.
+    /// Fixed joints are merged. The positions of the joints are ignored (assumed zero).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::dh::*;
+    ///
+    /// let table = DhTable::new(Convention::Modified)
+    ///     .row(DhParameter::new(0.0f64, 0.0, 0.3, 0.0).name("j1"))
+    ///     .row(DhParameter::new(0.2, -std::f64::consts::FRAC_PI_2, 0.0, 0.4).name("j2"))
+    ///     .row(DhParameter::new(0.5, 0.0, 0.1, 0.0).joint_type(DhJointType::Prismatic));
+    /// let arm = table.to_serial_chain();
+    /// let extracted = DhTable::from_serial_chain(&arm, Convention::Modified).unwrap();
+    /// assert_eq!(extracted.rows.len(), 3);
+    /// assert_eq!(extracted.rows[1].name, "j2");
+    /// assert!((extracted.rows[1].alpha + std::f64::consts::FRAC_PI_2).abs() < 1e-10);
+    /// assert_eq!(extracted.rows[2].joint_type, DhJointType::Prismatic);
+    /// ```
+    pub fn from_serial_chain(
+        chain: &SerialChain<T>,
+        convention: Convention,
+    ) -> Result<Self, DhError> {
+        // joints and the transforms from the previous joint (or the root) at zero position
+        let mut joints = Vec::new();
+        let mut offset = Isometry3::identity();
+        for node in chain.iter() {
+            let joint = node.joint();
+            offset *= *joint.origin();
+            if !joint.is_movable() {
+                continue;
+            }
+            let (dh_type, axis) = match joint.joint_type {
+                JointType::Rotational { axis } => (DhJointType::Revolute, axis),
+                JointType::Continuous { axis } => (DhJointType::Continuous, axis),
+                JointType::Linear { axis } => (DhJointType::Prismatic, axis),
+                _ => {
+                    return Err(DhError::UnsupportedJointError {
+                        joint_name: joint.name.clone(),
+                        message: format!("{} is not single DOF", joint.joint_type),
+                    })
+                }
+            };
+            if !is_small((axis.into_inner() - Vector3::z()).norm()) {
+                return Err(DhError::UnsupportedJointError {
+                    joint_name: joint.name.clone(),
+                    message: format!("axis must be +Z, but {:?}", axis.into_inner()),
+                });
+            }
+            joints.push((joint.name.clone(), dh_type, joint.limits, offset));
+            offset = Isometry3::identity();
+        }
+        // `offset` is from the last joint to the end
+        let not_dh_form = |joint_name: &str, message: String| DhError::NotDhFormError {
+            joint_name: joint_name.to_owned(),
+            message,
+        };
+        let mut table = DhTable::new(convention);
+        let mut rows = Vec::with_capacity(joints.len());
+        match convention {
+            Convention::Standard => {
+                // the transform to joint i + 1 is row i
+                for (i, (name, _, _, trans)) in joints.iter().enumerate() {
+                    if i == 0 {
+                        table.base = *trans;
+                    } else {
+                        rows.push(decompose(trans, convention).map_err(|m| not_dh_form(name, m))?);
+                    }
+                }
+                let end_name = joints.last().map(|j| j.0.clone()).unwrap_or_default();
+                rows.push(decompose(&offset, convention).map_err(|m| not_dh_form(&end_name, m))?);
+            }
+            Convention::Modified => {
+                // the transform to joint i is row i
+                for (i, (name, _, _, trans)) in joints.iter().enumerate() {
+                    match decompose(trans, convention) {
+                        Ok(row) => rows.push(row),
+                        // put it to the base if it is the first one
+                        Err(_) if i == 0 => {
+                            table.base = *trans;
+                            rows.push((T::zero(), T::zero(), T::zero(), T::zero()));
+                        }
+                        Err(message) => return Err(not_dh_form(name, message)),
+                    }
+                }
+                if !is_small(offset.translation.vector.norm()) || !is_small(offset.rotation.angle())
+                {
+                    let end_name = chain
+                        .iter()
+                        .last()
+                        .map(|node| node.joint().name.clone())
+                        .unwrap_or_default();
+                    return Err(not_dh_form(
+                        &end_name,
+                        "the end must be the frame of the last joint".to_owned(),
+                    ));
+                }
+            }
+        }
+        table.rows = joints
+            .into_iter()
+            .zip(rows)
+            .map(
+                |((name, joint_type, limits, _), (a, alpha, d, theta))| DhParameter {
+                    name,
+                    a,
+                    alpha,
+                    d,
+                    theta,
+                    joint_type,
+                    limits,
+                },
+            )
+            .collect();
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+fn puma560() -> DhTable<f64> {
+    use std::f64::consts::FRAC_PI_2;
+    DhTable::new(Convention::Standard)
+        .row(DhParameter::new(0.0, FRAC_PI_2, 0.0, 0.0).name("j1"))
+        .row(DhParameter::new(0.4318, 0.0, 0.0, 0.0).name("j2"))
+        .row(DhParameter::new(0.0203, -FRAC_PI_2, 0.15005, 0.0).name("j3"))
+        .row(DhParameter::new(0.0, FRAC_PI_2, 0.4318, 0.0).name("j4"))
+        .row(DhParameter::new(0.0, -FRAC_PI_2, 0.0, 0.0).name("j5"))
+        .row(
+            DhParameter::new(0.0, 0.0, 0.0, 0.0)
+                .name("j6")
+                .limits(Some((-1.0..=1.0).into())),
+        )
+}
+
+#[test]
+fn test_standard_dh_fk() {
+    let table = puma560();
+    let arm = table.to_serial_chain();
+    assert_eq!(arm.dof(), 6);
+    let q = [0.1, -0.4, 0.6, 0.3, -0.8, 0.5];
+    arm.set_joint_positions(&q).unwrap();
+    let expected = table
+        .rows
+        .iter()
+        .zip(q.iter())
+        .fold(Isometry3::identity(), |trans, (row, q)| {
+            trans * row.transform(*q, Convention::Standard)
+        });
+    let end = arm.end_transform();
+    assert!((end.translation.vector - expected.translation.vector).norm() < 1e-10);
+    assert!(end.rotation.angle_to(&expected.rotation) < 1e-10);
+    assert!(arm
+        .set_joint_positions(&[0.0, 0.0, 0.0, 0.0, 0.0, 1.5])
+        .is_err());
+}
+
+#[test]
+fn test_extract_dh() {
+    let table = puma560();
+    let arm = table.to_serial_chain();
+    let extracted = DhTable::from_serial_chain(&arm, Convention::Standard).unwrap();
+    assert_eq!(extracted.rows.len(), 6);
+    for (a, b) in table.rows.iter().zip(extracted.rows.iter()) {
+        assert_eq!(a.name, b.name);
+        assert!((a.a - b.a).abs() < 1e-10);
+        assert!((a.alpha - b.alpha).abs() < 1e-10);
+        assert!((a.d - b.d).abs() < 1e-10);
+        assert!((a.theta - b.theta).abs() < 1e-10);
+    }
+    assert!(extracted.rows[5].limits.is_some());
+
+    // the chain rebuilt from the extracted table gives the same FK
+    let rebuilt = extracted.to_serial_chain();
+    let q = [0.3, 0.2, -0.5, 0.1, 0.4, -0.2];
+    arm.set_joint_positions(&q).unwrap();
+    rebuilt.set_joint_positions(&q).unwrap();
+    let diff = arm.end_transform().inverse() * rebuilt.end_transform();
+    assert!(diff.translation.vector.norm() < 1e-10);
+    assert!(diff.rotation.angle() < 1e-10);
+
+    // Y axis can not be represented
+    let j0 = JointBuilder::new()
+        .joint_type(JointType::Rotational {
+            axis: Vector3::y_axis(),
+        })
+        .into_node();
+    let j1 = JointBuilder::new()
+        .translation(Translation3::new(0.0, 0.0, 1.0))
+        .into_node();
+    j1.set_parent(&j0);
+    let arm = SerialChain::<f64>::from_end(&j1);
+    match DhTable::from_serial_chain(&arm, Convention::Standard) {
+        Err(DhError::UnsupportedJointError { .. }) => {}
+        _ => panic!("must fail"),
+    }
+}
+
+#[test]
+fn test_modified_dh_from_chain() {
+    // the arm of examples/my_arm.rs: all joints are Z axis with X offsets
+    let l0 = JointBuilder::new()
+        .name("torso_linear")
+        .joint_type(JointType::Linear {
+            axis: Vector3::z_axis(),
+        })
+        .translation(Translation3::new(0.1, 0.0, 0.1))
+        .into_node();
+    let l1 = JointBuilder::new()
+        .name("shoulder_yaw")
+        .joint_type(JointType::Rotational {
+            axis: Vector3::z_axis(),
+        })
+        .translation(Translation3::new(0.3, 0.0, 0.0))
+        .into_node();
+    let l2 = JointBuilder::new()
+        .name("elbow_yaw")
+        .joint_type(JointType::Rotational {
+            axis: Vector3::z_axis(),
+        })
+        .translation(Translation3::new(0.3, 0.0, 0.0))
+        .into_node();
+    l1.set_parent(&l0);
+    l2.set_parent(&l1);
+    let arm = SerialChain::<f64>::from_end(&l2);
+    let table = DhTable::from_serial_chain(&arm, Convention::Modified).unwrap();
+    assert_eq!(table.rows[0].joint_type, DhJointType::Prismatic);
+    assert!((table.rows[0].a - 0.1).abs() < 1e-10);
+    assert!((table.rows[0].d - 0.1).abs() < 1e-10);
+    assert!((table.rows[2].a - 0.3).abs() < 1e-10);
+    let rebuilt = table.to_serial_chain();
+    let q = [0.2, 0.6, -1.2];
+    arm.set_joint_positions(&q).unwrap();
+    rebuilt.set_joint_positions(&q).unwrap();
+    let diff = arm.end_transform().inverse() * rebuilt.end_transform();
+    assert!(diff.translation.vector.norm() < 1e-10);
+    assert!(diff.rotation.angle() < 1e-10);
+}
+
+#[test]
+fn test_decompose_error() {
+    let rotated = |roll: f64, pitch: f64, yaw: f64| {
+        Isometry3::from_parts(
+            Translation3::identity(),
+            UnitQuaternion::from_euler_angles(roll, pitch, yaw),
+        )
+    };
+    assert!(decompose(&rotated(0.3, 0.0, 0.5), Convention::Standard).is_ok());
+    assert!(decompose(
+        &(rotated(0.3, 0.0, 0.0) * rotated(0.0, 0.0, 0.5)),
+        Convention::Modified
+    )
+    .is_ok());
+    // the rotation part fails
+    let message = decompose(&rotated(0.0, 0.4, 0.0), Convention::Standard).unwrap_err();
+    assert!(!message.contains("translation"), "{}", message);
+    let message = decompose(&rotated(0.0, 0.4, 0.0), Convention::Modified).unwrap_err();
+    assert!(!message.contains("translation"), "{}", message);
+    // the translation part fails
+    let message =
+        decompose(&Isometry3::translation(0.0, 0.5, 0.0), Convention::Standard).unwrap_err();
+    assert!(message.starts_with("translation"), "{}", message);
+}
@@ -13,6 +13,7 @@
   See the License for the specific language governing permissions and
   limitations under the License.
 */
+use collision::Colliding;
 
 /// The reason of joint error
 #[derive(Debug, Clone, Fail)]
@@ -60,6 +61,8 @@ pub enum IKError {
     JointOutOfLimitError { error: JointError },
     #[fail(display = "invalid arguments {:?}", error)]
     InvalidArgumentsError { error: String },
+    #[fail(display = "solution is in collision {:?}", collisions)]
+    CollisionError { collisions: Vec<Colliding> },
 }
 
 impl From<JointError> for IKError {
@@ -67,3 +70,284 @@ impl From<JointError> for IKError {
         IKError::JointOutOfLimitError { error }
     }
 }
+
+/// The reason of the fail of mass property or dynamics calculation
+#[derive(Debug, Clone, Fail)]
+pub enum DynamicsError {
+    /// None of the links has mass
+    #[fail(display = "total mass of the chain is zero")]
+    ZeroMassError,
+    /// The link is not found in the chain
+    #[fail(display = "link {} is not found", link_name)]
+    LinkNotFoundError { link_name: String },
+    /// No contact shapes to build the support polygon
+    #[fail(display = "support polygon error {:?}", error)]
+    SupportPolygonError { error: String },
+    /// Zero-moment point is not defined, e.g. the vertical reaction force is not positive
+    #[fail(display = "zmp is not defined {:?}", error)]
+    ZmpUndefinedError { error: String },
+}
+
+/// The reason of the fail of trajectory generation
+#[derive(Debug, Clone, Fail)]
+pub enum TrajectoryError {
+    /// Gave invalid waypoints, times or limits
+    #[fail(display = "invalid arguments {:?}", error)]
+    InvalidArgumentsError { error: String },
+    /// The trajectory exceeds the velocity limit of the joint
+    #[fail(display = "joint[{}] exceeds velocity limit: {}", joint_index, message)]
+    VelocityLimitError {
+        /// index of the joint
+        joint_index: usize,
+        /// detail error message
+        message: String,
+    },
+    /// The trajectory exceeds the acceleration limit of the joint
+    #[fail(
+        display = "joint[{}] exceeds acceleration limit: {}",
+        joint_index, message
+    )]
+    AccelerationLimitError {
+        /// index of the joint
+        joint_index: usize,
+        /// detail error message
+        message: String,
+    },
+    /// No timing satisfies the limits around the waypoint
+    #[fail(display = "path is infeasible at waypoints[{}]: {}", index, message)]
+    PathInfeasibleError {
+        /// index of the waypoint
+        index: usize,
+        /// detail error message
+        message: String,
+    },
+}
+
+/// The reason of the fail of cartesian path planning
+#[derive(Debug, Fail)]
+pub enum CartesianPathError {
+    /// Inverse kinematics failed at the waypoint
+    #[fail(display = "ik failed at waypoint[{}]: {}", index, error)]
+    IKFailedError {
+        /// index of the waypoint
+        index: usize,
+        /// error of the ik solver
+        error: IKError,
+    },
+    /// The solution jumped in joint space (e.g. flipped to the other branch)
+    #[fail(
+        display = "joint space discontinuity at waypoint[{}]: {}",
+        index, message
+    )]
+    DiscontinuityError {
+        /// index of the waypoint
+        index: usize,
+        /// detail error message
+        message: String,
+    },
+    #[fail(display = "invalid arguments {:?}", error)]
+    InvalidArgumentsError { error: String },
+}
+
+/// The reason why the chain can not be converted to DH parameters
+#[derive(Debug, Clone, Fail)]
+pub enum DhError {
+    /// The joint is not a single DOF joint around or along its Z axis
+    #[fail(display = "joint {} is not supported: {}", joint_name, message)]
+    UnsupportedJointError {
+        /// name of the joint
+        joint_name: String,
+        /// detail error message
+        message: String,
+    },
+    /// The transform to the joint can not be represented by DH parameters
+    #[fail(
+        display = "transform to joint {} is not DH form: {}",
+        joint_name, message
+    )]
+    NotDhFormError {
+        /// name of the joint
+        joint_name: String,
+        /// detail error message
+        message: String,
+    },
+}
+
+/// The reason why the chain can not be exported as URDF
+#[derive(Debug, Clone, Fail)]
+pub enum UrdfExportError {
+    /// The joint type does not exist in URDF
+    #[fail(display = "joint {} is not supported: {}", joint_name, message)]
+    UnsupportedJointError {
+        /// name of the joint
+        joint_name: String,
+        /// detail error message
+        message: String,
+    },
+}
+
+/// A problem of URDF found by `k::urdf::validate()`
+#[derive(Debug, Clone, PartialEq, Fail)]
+pub enum UrdfProblem {
+    /// The joint mimics the joint which does not exist
+    #[fail(
+        display = "joint {} mimics {}, which is not found",
+        joint_name, mimic_joint_name
+    )]
+    MimicJointNotFound {
+        /// name of the mimic joint
+        joint_name: String,
+        /// name of the joint to be mimicked
+        mimic_joint_name: String,
+    },
+    /// The link used by the joint does not exist
+    #[fail(display = "link {} of joint {} is not found", link_name, joint_name)]
+    LinkNotFound {
+        /// name of the joint
+        joint_name: String,
+        /// name of the missing link
+        link_name: String,
+    },
+    /// Two or more joints have the same name
+    #[fail(display = "joint name {} is used {} times", joint_name, count)]
+    DuplicateJointName {
+        /// name of the joints
+        joint_name: String,
+        /// number of the joints which have the name
+        count: usize,
+    },
+    /// Two or more links have the same name
+    #[fail(display = "link name {} is used {} times", link_name, count)]
+    DuplicateLinkName {
+        /// name of the links
+        link_name: String,
+        /// number of the links which have the name
+        count: usize,
+    },
+    /// The link is the child of two or more joints
+    #[fail(
+        display = "link {} has multiple parent joints {:?}",
+        link_name, joint_names
+    )]
+    MultipleParentJoints {
+        /// name of the link
+        link_name: String,
+        /// names of the parent joints
+        joint_names: Vec<String>,
+    },
+    /// There are two or more links which are not the child of any joints
+    #[fail(display = "multiple root links {:?}", link_names)]
+    MultipleRootLinks {
+        /// names of the root links
+        link_names: Vec<String>,
+    },
+    /// All links are the child of some joint
+    #[fail(display = "no root link")]
+    NoRootLink,
+    /// The joints make a loop
+    #[fail(display = "joints make a cycle {:?}", joint_names)]
+    Cycle {
+        /// names of the joints in the cycle
+        joint_names: Vec<String>,
+    },
+    /// The axis of the joint can not be normalized
+    #[fail(display = "axis of joint {} is zero length", joint_name)]
+    ZeroLengthAxis {
+        /// name of the joint
+        joint_name: String,
+    },
+}
+
+/// The reason of the fail of loading URDF
+#[derive(Debug, Clone, Fail)]
+pub enum UrdfLoadError {
+    /// Failed to read or parse the file
+    #[fail(display = "failed to read urdf {:?}", error)]
+    ReadError { error: String },
+    /// The robot has problems, all of them are listed
+    #[fail(display = "invalid urdf {:?}", problems)]
+    InvalidUrdfError { problems: Vec<UrdfProblem> },
+}
+
+/// The reason of the fail of importing SDF or MJCF
+#[derive(Debug, Clone, Fail)]
+pub enum ImportError {
+    /// Failed to read the file or parse XML
+    #[fail(display = "failed to parse {:?}", error)]
+    ParseError { error: String },
+    /// The model is broken, e.g. the link of the joint is not found
+    #[fail(display = "invalid model {:?}", error)]
+    InvalidModelError { error: String },
+}
+
+/// The reason of the fail of expanding xacro
+#[derive(Debug, Clone, Fail)]
+pub enum XacroError {
+    /// Failed to parse XML
+    #[fail(display = "failed to parse {:?}", error)]
+    ParseError { error: String },
+    /// Failed to read the included file
+    #[fail(display = "failed to include {}: {}", filename, error)]
+    IncludeError { filename: String, error: String },
+    /// Failed to evaluate `${...}` or `$(...)`
+    #[fail(display = "failed to evaluate {:?}: {}", expression, message)]
+    ExpressionError { expression: String, message: String },
+    /// The property, arg, macro or block is not defined
+    #[fail(display = "{} {} is not defined", kind, name)]
+    UndefinedError { kind: String, name: String },
+    /// The macro is called with wrong parameters
+    #[fail(display = "macro {}: {}", macro_name, message)]
+    MacroArgumentError { macro_name: String, message: String },
+    /// The xacro element is not supported, or macros are nested too deeply
+    #[fail(display = "unsupported {}: {}", element, message)]
+    UnsupportedError { element: String, message: String },
+}
+
+/// The reason of the fail of loading mesh
+#[derive(Debug, Clone, Fail)]
+pub enum MeshError {
+    /// Failed to find or read the file
+    #[fail(display = "failed to read {}: {}", filename, error)]
+    ReadError { filename: String, error: String },
+    /// The contents of the file are broken
+    #[fail(display = "failed to parse mesh: {}", error)]
+    ParseError { error: String },
+    /// The extension is neither `stl` nor `obj`
+    #[fail(display = "unsupported mesh format {}", filename)]
+    UnsupportedFormatError { filename: String },
+}
+
+/// The reason of the fail of motion planning
+#[derive(Debug, Clone, Fail)]
+pub enum PlanningError {
+    #[fail(display = "invalid arguments {:?}", error)]
+    InvalidArgumentsError { error: String },
+    /// The start or goal positions are rejected by the validity checker
+    #[fail(display = "{} positions are invalid", name)]
+    InvalidPositionsError { name: String },
+    /// No path is found
+    #[fail(display = "path not found in {} iterations", iterations)]
+    PathNotFoundError { iterations: usize },
+}
+
+/// The reason of the fail of the lookup of the named frames
+#[derive(Debug, Clone, Fail)]
+pub enum FrameError {
+    /// The name is already used by a joint, a link or a frame
+    #[fail(display = "frame name {} is already used", name)]
+    DuplicateNameError { name: String },
+    /// No joint, link or frame has the name
+    #[fail(display = "frame {} is not found", name)]
+    NotFoundError { name: String },
+}
+
+/// The reason of the fail of the operations on `World`
+#[derive(Debug, Clone, Fail)]
+pub enum WorldError {
+    /// The name is already used by a robot or an object
+    #[fail(display = "name {} is already used", name)]
+    DuplicateNameError { name: String },
+    /// No robot, object or frame has the name
+    #[fail(display = "{} is not found", name)]
+    NotFoundError { name: String },
+}
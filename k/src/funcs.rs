@@ -1,35 +1,40 @@
 use chain::*;
+use errors::*;
 use joint::*;
-use na::{DMatrix, Real, Vector3};
+use link::*;
+use na::{DMatrix, Isometry3, Real, Vector3};
+use node::*;
 
 /// Calculate Jacobian of the serial chain (manipulator).
+///
+/// It is in the base frame of the chain (see `SerialChain::base_transform()`).
 pub fn jacobian<T>(arm: &SerialChain<T>) -> DMatrix<T>
 where
     T: Real,
 {
     let dof = arm.dof();
     let t_n = arm.end_transform();
+    let base_inv = arm.base_transform().inverse();
     arm.update_transforms();
     let p_n = t_n.translation;
-    let jacobi_vec = arm
-        .iter_joints()
-        .map(|joint| {
-            let t_i = joint.world_transform().unwrap();
-            match joint.joint_type {
-                JointType::Linear { axis } => {
+    let mut jacobi_vec = Vec::with_capacity(dof);
+    for joint in arm.iter_joints() {
+        for (motion, t_i) in joint.world_motions() {
+            let t_i = base_inv * t_i;
+            jacobi_vec.push(match motion {
+                Motion::Translation(axis) => {
                     let p_i = t_i.rotation * axis;
                     [p_i[0], p_i[1], p_i[2], na::zero(), na::zero(), na::zero()]
                 }
-                JointType::Rotational { axis } => {
+                Motion::Rotation(axis) => {
                     let p_i = t_i.translation;
                     let a_i = t_i.rotation * axis;
                     let dp_i = a_i.cross(&(p_n.vector - p_i.vector));
                     [dp_i[0], dp_i[1], dp_i[2], a_i[0], a_i[1], a_i[2]]
                 }
-                JointType::Fixed => panic!("impossible, bug of jacobian"),
-            }
-        })
-        .collect::<Vec<_>>();
+            });
+        }
+    }
     // Pi: a_i x (p_n - Pi)
     // wi: a_i
     DMatrix::from_fn(6, dof, |r, c| jacobi_vec[c][r])
@@ -37,6 +42,8 @@ where
 
 /// Calculate the center of mass of the chain
 ///
+/// Returns `DynamicsError::ZeroMassError` if no link has mass.
+///
 /// ```
 /// use k::*;
 /// use k::link::*;
@@ -51,9 +58,9 @@ where
 /// j1.set_link(Some(LinkBuilder::new().inertial(Inertial::from_mass(4.0)).finalize()));
 /// j1.set_parent(&j0);
 /// let tree = Chain::from_root(j0);
-/// let com1 = center_of_mass(&tree);
+/// let com1 = center_of_mass(&tree).unwrap();
 /// ```
-pub fn center_of_mass<T>(chain: &Chain<T>) -> Vector3<T>
+pub fn center_of_mass<T>(chain: &Chain<T>) -> Result<Vector3<T>, DynamicsError>
 where
     T: Real,
 {
@@ -70,7 +77,180 @@ where
             }
         }
     });
-    com / total_mass
+    if total_mass <= T::zero() {
+        return Err(DynamicsError::ZeroMassError);
+    }
+    Ok(com / total_mass)
+}
+
+/// Calculate the total inertial of the chain seen from `frame`
+///
+/// `frame` is the pose of the reference frame in the world. The origin of the
+/// output is the center of mass in `frame`, and `inertia` is around it with the
+/// axes of `frame`. Use `Inertial::inertia_at()` to get the inertia around other points.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::link::*;
+///
+/// let j0 = JointBuilder::new().into_node();
+/// let j1 = JointBuilder::new()
+///     .translation(Translation3::new(0.0, 0.0, 1.0))
+///     .into_node();
+/// j0.set_link(Some(LinkBuilder::new().inertial(Inertial::from_mass(1.0)).finalize()));
+/// j1.set_link(Some(LinkBuilder::new().inertial(Inertial::from_mass(1.0)).finalize()));
+/// j1.set_parent(&j0);
+/// let tree = Chain::<f64>::from_root(j0);
+/// let total = composite_inertial(&tree, &Isometry3::identity()).unwrap();
+/// assert_eq!(total.mass, 2.0);
+/// assert_eq!(total.origin().translation.vector.z, 0.5);
+/// assert_eq!(total.inertia[(0, 0)], 0.5);
+/// // around the root
+/// assert_eq!(total.inertia_at(&Vector3::zeros())[(0, 0)], 1.0);
+/// ```
+pub fn composite_inertial<T>(
+    chain: &Chain<T>,
+    frame: &Isometry3<T>,
+) -> Result<Inertial<T>, DynamicsError>
+where
+    T: Real,
+{
+    let frame_inv = frame.inverse();
+    chain.update_transforms();
+    let inertials = chain
+        .iter()
+        .filter_map(|node| {
+            let trans = node.world_transform()?;
+            let link = node.link();
+            let link = link.as_ref()?;
+            let mut inertial = link.inertial.clone();
+            inertial.set_origin(frame_inv * trans * link.inertial.origin());
+            Some(inertial)
+        })
+        .collect::<Vec<_>>();
+    Inertial::merge(&inertials).ok_or(DynamicsError::ZeroMassError)
+}
+
+/// Calculate the Jacobian of the center of mass (3 x `dof()`)
+///
+/// The columns are the same order as `Chain::joint_positions()`.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::link::*;
+///
+/// let j0 = JointBuilder::new()
+///     .joint_type(JointType::Rotational{axis: Vector3::y_axis()})
+///     .into_node();
+/// let j1 = JointBuilder::new()
+///     .translation(Translation3::new(0.0, 0.0, 1.0))
+///     .into_node();
+/// j1.set_link(Some(LinkBuilder::new().inertial(Inertial::from_mass(2.0)).finalize()));
+/// j1.set_parent(&j0);
+/// let tree = Chain::<f64>::from_root(j0);
+/// let jacobi = center_of_mass_jacobian(&tree).unwrap();
+/// assert_eq!(jacobi.shape(), (3, 1));
+/// assert_eq!(jacobi[(0, 0)], 1.0);
+/// ```
+pub fn center_of_mass_jacobian<T>(chain: &Chain<T>) -> Result<DMatrix<T>, DynamicsError>
+where
+    T: Real,
+{
+    chain.update_transforms();
+    let columns = movable_node_columns(chain);
+    let mut total_mass = T::zero();
+    let mut jacobi = DMatrix::zeros(3, chain.dof());
+    for node in chain.iter() {
+        let trans = node.world_transform().expect("cache must exist");
+        let (mass, com) = match *node.link() {
+            Some(ref link) if link.inertial.mass > T::zero() => (
+                link.inertial.mass,
+                (trans * link.inertial.origin()).translation.vector,
+            ),
+            _ => continue,
+        };
+        total_mass += mass;
+        add_point_jacobian(&mut jacobi, &columns, node, &com, mass);
+    }
+    if total_mass <= T::zero() {
+        return Err(DynamicsError::ZeroMassError);
+    }
+    Ok(jacobi / total_mass)
+}
+
+/// Calculate the Jacobian of the position of the point fixed to the node
+///
+/// `point` is in the world frame with the current joint positions. The size is
+/// `3 x chain.dof()` and the columns are in the order of `Chain::joint_positions()`.
+///
+/// ```
+/// use k::*;
+///
+/// let j0 = JointBuilder::new()
+///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+///     .into_node();
+/// let tree = Chain::<f64>::from_root(j0.clone());
+/// let jacobi = point_jacobian(&tree, &j0, &Vector3::new(1.0, 0.0, 0.0));
+/// assert_eq!(jacobi.shape(), (3, 1));
+/// assert_eq!(jacobi[(1, 0)], 1.0);
+/// ```
+pub fn point_jacobian<T>(chain: &Chain<T>, node: &Node<T>, point: &Vector3<T>) -> DMatrix<T>
+where
+    T: Real,
+{
+    chain.update_transforms();
+    let mut jacobi = DMatrix::zeros(3, chain.dof());
+    add_point_jacobian(
+        &mut jacobi,
+        &movable_node_columns(chain),
+        node,
+        point,
+        T::one(),
+    );
+    jacobi
+}
+
+/// Movable nodes and the first column of them
+fn movable_node_columns<T: Real>(chain: &Chain<T>) -> Vec<(&Node<T>, usize)> {
+    let mut columns = Vec::new();
+    let mut column = 0;
+    for node in chain.iter().filter(|node| node.joint().is_movable()) {
+        columns.push((node, column));
+        column += node.joint().dof();
+    }
+    columns
+}
+
+/// Add `scale` times the Jacobian of `point` fixed to `node`, the transforms must be updated
+fn add_point_jacobian<T: Real>(
+    jacobi: &mut DMatrix<T>,
+    columns: &[(&Node<T>, usize)],
+    node: &Node<T>,
+    point: &Vector3<T>,
+    scale: T,
+) {
+    for ancestor in node.iter_ancestors() {
+        let first = match columns.iter().find(|(n, _)| **n == ancestor) {
+            Some((_, first)) => *first,
+            None => continue,
+        };
+        let joint = ancestor.joint();
+        for (i, (motion, t_a)) in joint.world_motions().enumerate() {
+            let column = match motion {
+                Motion::Translation(axis) => t_a.rotation * axis.into_inner(),
+                Motion::Rotation(axis) => {
+                    (t_a.rotation * axis.into_inner()).cross(&(point - t_a.translation.vector))
+                }
+            };
+            for r in 0..3 {
+                jacobi[(r, first + i)] += column[r] * scale;
+            }
+        }
+    }
 }
 
 #[test]
@@ -102,13 +282,78 @@ fn test_update_center_of_mass() {
     j1.set_link(Some(LinkBuilder::new().name("l1").inertial(i1).finalize()));
     j1.set_parent(&j0);
     let tree = Chain::from_root(j0);
-    let com1 = center_of_mass(&tree);
+    let com1 = center_of_mass(&tree).unwrap();
     assert_eq!(com1.x, 0.0);
     assert_eq!(com1.y, 1.0);
     assert_eq!(com1.z, 1.6);
     j1.set_joint_position(0.5).unwrap();
-    let com2 = center_of_mass(&tree);
+    let com2 = center_of_mass(&tree).unwrap();
     assert!((com2.x - 0.383540).abs() < 0.0001);
     assert_eq!(com2.y, 1.0);
     assert!((com2.z - 1.502066).abs() < 0.0001);
 }
+
+#[test]
+fn test_center_of_mass_zero_mass() {
+    use super::node::*;
+    let j0 = JointBuilder::<f64>::new().into_node();
+    let tree = Chain::from_root(j0);
+    assert!(center_of_mass(&tree).is_err());
+    assert!(center_of_mass_jacobian(&tree).is_err());
+    assert!(composite_inertial(&tree, &Isometry3::identity()).is_err());
+}
+
+#[test]
+fn test_center_of_mass_jacobian() {
+    use super::node::*;
+    use na::*;
+    let j0 = JointBuilder::new()
+        .joint_type(JointType::Rotational {
+            axis: Vector3::z_axis(),
+        })
+        .into_node();
+    let j1 = JointBuilder::new()
+        .translation(Translation3::new(0.3, 0.0, 0.1))
+        .joint_type(JointType::Linear {
+            axis: Vector3::x_axis(),
+        })
+        .into_node();
+    let j2 = JointBuilder::new()
+        .translation(Translation3::new(0.0, 0.2, 0.4))
+        .joint_type(JointType::Rotational {
+            axis: Vector3::y_axis(),
+        })
+        .into_node();
+    j0.set_link(Some(
+        LinkBuilder::new()
+            .inertial(Inertial::from_mass(3.0))
+            .finalize(),
+    ));
+    let mut i1 = Inertial::from_mass(1.0);
+    i1.set_origin(Isometry3::translation(0.1, 0.0, 0.0));
+    j1.set_link(Some(LinkBuilder::new().inertial(i1).finalize()));
+    let mut i2 = Inertial::from_mass(2.0);
+    i2.set_origin(Isometry3::translation(0.0, 0.0, 0.5));
+    j2.set_link(Some(LinkBuilder::new().inertial(i2).finalize()));
+    j1.set_parent(&j0);
+    j2.set_parent(&j1);
+    let tree = Chain::<f64>::from_root(j0);
+    let q = vec![0.4, 0.2, -0.6];
+    tree.set_joint_positions(&q).unwrap();
+    let jacobi = center_of_mass_jacobian(&tree).unwrap();
+    let h = 1e-6;
+    for i in 0..q.len() {
+        let mut q_p = q.clone();
+        q_p[i] += h;
+        tree.set_joint_positions(&q_p).unwrap();
+        let com_p = center_of_mass(&tree).unwrap();
+        let mut q_m = q.clone();
+        q_m[i] -= h;
+        tree.set_joint_positions(&q_m).unwrap();
+        let com_m = center_of_mass(&tree).unwrap();
+        let diff = (com_p - com_m) / (2.0 * h);
+        for r in 0..3 {
+            assert!((jacobi[(r, i)] - diff[r]).abs() < 1e-6);
+        }
+    }
+}
@@ -13,9 +13,10 @@
   See the License for the specific language governing permissions and
   limitations under the License.
 */
-use na::{self, DVector, Isometry3, Real, Vector3, Vector6};
+use na::{self, DMatrix, DVector, Isometry3, Real, Vector3, Vector6};
 
 use chain::*;
+use collision::*;
 use errors::*;
 use funcs::*;
 
@@ -88,6 +89,22 @@ fn constraints_to_bool_array(constraints: Constraints) -> [bool; 6] {
     arr
 }
 
+/// Jacobian of the arm without the rows of the unused constraints
+fn constrained_jacobian<T>(arm: &SerialChain<T>, constraints_array: [bool; 6]) -> DMatrix<T>
+where
+    T: Real,
+{
+    let mut jacobi = jacobian(arm);
+    let mut removed_count = 0;
+    for (i, use_i) in constraints_array.iter().enumerate() {
+        if !use_i {
+            jacobi = jacobi.remove_row(i - removed_count);
+            removed_count += 1;
+        }
+    }
+    jacobi
+}
+
 /// IK solver
 pub trait InverseKinematicsSolver<T>
 where
@@ -164,15 +181,8 @@ where
         let t_n = arm.end_transform();
         let err = calc_pose_diff_with_constraints(target_pose, &t_n, constraints_array);
         let orig_positions = arm.joint_positions();
-        let mut jacobi = jacobian(arm);
+        let jacobi = constrained_jacobian(arm, constraints_array);
         let use_dof = constraints_array.into_iter().filter(|x| **x).count();
-        let mut removed_count = 0;
-        for (i, use_i) in constraints_array.iter().enumerate() {
-            if !use_i {
-                jacobi = jacobi.remove_row(i - removed_count);
-                removed_count += 1;
-            }
-        }
         let positions_vec = if dof > use_dof {
             // redundant: pseudo inverse
             self.add_positions_with_multiplier(
@@ -329,3 +339,157 @@ where
         Self::new(na::convert(0.001), na::convert(0.005), na::convert(0.5), 10)
     }
 }
+
+/// IK solver which rejects the solutions in collision
+///
+/// The solution of `solver` is checked by `checker`. If the arm is colliding with
+/// the obstacles, it is pushed away from them in the null space of the constraints
+/// and solved again, up to `num_avoidance_try` times. Self collisions are only rejected.
+/// The joint positions are restored if no collision-free solution is found.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use k::collision::*;
+/// use k::link::*;
+///
+/// let j0 = JointBuilder::new()
+///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+///     .into_node();
+/// let j1 = JointBuilder::new()
+///     .joint_type(JointType::Linear { axis: Vector3::x_axis() })
+///     .into_node();
+/// j1.set_link(Some(LinkBuilder::new()
+///     .name("hand")
+///     .add_collision(Collision::new(
+///         "hand".to_owned(), Isometry3::identity(), Geometry::Sphere { radius: 0.1 }))
+///     .finalize()));
+/// j1.set_parent(&j0);
+/// let arm = SerialChain::new_unchecked(Chain::<f64>::from_root(j0));
+/// let ground = Obstacle::Plane { normal: Vector3::z_axis(), offset: 0.0 };
+/// let solver = CollisionAwareIKSolver::new(
+///     JacobianIKSolver::default(),
+///     CollisionChecker::new().obstacle(ground));
+/// let mut constraints = Constraints::default();
+/// constraints.position_y = false;
+/// constraints.position_z = false;
+/// constraints.rotation_x = false;
+/// constraints.rotation_y = false;
+/// constraints.rotation_z = false;
+/// // the hand is in the ground
+/// solver.solve_with_constraints(&arm, &Isometry3::translation(0.5, 0.0, 0.0), &constraints).unwrap();
+/// let positions = arm.joint_positions();
+/// assert!((positions[1] - 0.5).abs() < 0.01);
+/// assert!(positions[0] >= 0.099);
+/// ```
+#[derive(Debug)]
+pub struct CollisionAwareIKSolver<T, I>
+where
+    T: Real,
+    I: InverseKinematicsSolver<T>,
+{
+    pub solver: I,
+    pub checker: CollisionChecker<T>,
+    /// Chain checked by `checker` instead of the arm, such as the whole robot
+    pub robot: Option<Chain<T>>,
+    pub num_avoidance_try: usize,
+}
+
+impl<T, I> CollisionAwareIKSolver<T, I>
+where
+    T: Real,
+    I: InverseKinematicsSolver<T>,
+{
+    pub fn new(solver: I, checker: CollisionChecker<T>) -> Self {
+        CollisionAwareIKSolver {
+            solver,
+            checker,
+            robot: None,
+            num_avoidance_try: 10,
+        }
+    }
+    pub fn robot(mut self, robot: Chain<T>) -> Self {
+        self.robot = Some(robot);
+        self
+    }
+    pub fn num_avoidance_try(mut self, num_avoidance_try: usize) -> Self {
+        self.num_avoidance_try = num_avoidance_try;
+        self
+    }
+    /// Move the arm out of the obstacles, returns `false` if it can not be moved
+    fn push_away(&self, arm: &SerialChain<T>, constraints_array: [bool; 6]) -> bool {
+        let eps = T::default_epsilon().sqrt();
+        let mut step = DVector::zeros(arm.dof());
+        for distance in self.checker.colliding_obstacles(arm) {
+            let gradient = arm.distance_gradient(&distance);
+            let norm_squared = gradient.norm_squared();
+            if norm_squared > eps {
+                step += gradient
+                    * ((self.checker.margin - distance.closest.distance + eps) / norm_squared);
+            }
+        }
+        // keep the end transform if possible
+        let jacobi = constrained_jacobian(arm, constraints_array);
+        if let Ok(inverse) = jacobi.clone().pseudo_inverse(eps) {
+            let null_step = &step - inverse * (jacobi * &step);
+            if null_step.norm() > eps {
+                step = null_step;
+            }
+        }
+        if step.norm() <= eps {
+            return false;
+        }
+        let mut positions = arm.joint_positions();
+        let mut index = 0;
+        for joint in arm.iter_joints() {
+            for _ in 0..joint.dof() {
+                positions[index] += step[index];
+                if let Some(ref range) = joint.limits {
+                    positions[index] = positions[index].max(range.min).min(range.max);
+                }
+                index += 1;
+            }
+        }
+        arm.set_joint_positions_unchecked(&positions);
+        true
+    }
+}
+
+impl<T, I> InverseKinematicsSolver<T> for CollisionAwareIKSolver<T, I>
+where
+    T: Real,
+    I: InverseKinematicsSolver<T>,
+{
+    fn solve_with_constraints(
+        &self,
+        arm: &SerialChain<T>,
+        target_pose: &Isometry3<T>,
+        constraints: &Constraints,
+    ) -> Result<(), IKError> {
+        let constraints_array = constraints_to_bool_array(*constraints);
+        let orig_positions = arm.joint_positions();
+        let mut num_try = 0;
+        loop {
+            if let Err(error) = self
+                .solver
+                .solve_with_constraints(arm, target_pose, constraints)
+            {
+                arm.set_joint_positions(&orig_positions)?;
+                return Err(error);
+            }
+            let collisions = match self.robot {
+                Some(ref robot) => self.checker.collisions(robot),
+                None => self.checker.collisions(arm),
+            };
+            if collisions.is_empty() {
+                return Ok(());
+            }
+            if num_try >= self.num_avoidance_try || !self.push_away(arm, constraints_array) {
+                arm.set_joint_positions(&orig_positions)?;
+                return Err(IKError::CollisionError { collisions });
+            }
+            num_try += 1;
+        }
+    }
+}
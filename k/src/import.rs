@@ -0,0 +1,290 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Common types of the importers of robot descriptions other than URDF (`sdf`, `mjcf`)
+//!
+//! # Examples
+//!
+//! ```
+//! let imported = k::import::from_str::<f64>(
+//!     r#"<mujoco><worldbody><body name="b"><joint name="j"/></body></worldbody></mujoco>"#,
+//! )
+//! .unwrap();
+//! assert!(imported.chain.find("j").is_some());
+//! ```
+use na::{self, Isometry3, Matrix3, Real, Translation3, UnitQuaternion, Vector3};
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use xml::{Element, Xml};
+
+use chain::*;
+use errors::*;
+use link::*;
+use mjcf;
+use sdf;
+
+/// The element which is ignored or approximated by the importer
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsupportedElement {
+    /// Path of the element, like `model[arm]/link[base]/sensor[camera]`
+    pub path: String,
+    /// What happened to the element
+    pub message: String,
+}
+
+impl Display for UnsupportedElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Imported chain and the elements which are not represented in it
+#[derive(Debug)]
+pub struct Imported<T: Real> {
+    /// The imported chain
+    pub chain: Chain<T>,
+    /// The elements which are ignored or approximated
+    pub unsupported: Vec<UnsupportedElement>,
+}
+
+/// Load SDF or MJCF string, the format is detected by the root element
+pub fn from_str<T: Real>(text: &str) -> Result<Imported<T>, ImportError> {
+    let root = parse_xml(text)?;
+    match root.name.as_str() {
+        "sdf" => sdf::from_element(&root),
+        "mujoco" => mjcf::from_element(root),
+        name => Err(ImportError::InvalidModelError {
+            error: format!("root element must be sdf or mujoco, but {}", name),
+        }),
+    }
+}
+
+/// Load SDF or MJCF file, the format is detected by the root element
+pub fn from_file<T: Real, P: AsRef<Path>>(path: P) -> Result<Imported<T>, ImportError> {
+    from_str(&read_file(path)?)
+}
+
+pub(crate) fn parse_xml(text: &str) -> Result<Element, ImportError> {
+    text.parse().map_err(|e| ImportError::ParseError {
+        error: format!("{:?}", e),
+    })
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(path: P) -> Result<String, ImportError> {
+    fs::read_to_string(path).map_err(|e| ImportError::ParseError {
+        error: e.to_string(),
+    })
+}
+
+/// Child elements
+pub(crate) fn children(element: &Element) -> impl Iterator<Item = &Element> {
+    element.children.iter().filter_map(|child| match *child {
+        Xml::ElementNode(ref e) => Some(e),
+        _ => None,
+    })
+}
+
+/// Path of the child element, `{parent}/{tag}[{name}]`
+pub(crate) fn child_path(parent_path: &str, element: &Element) -> String {
+    let tag = match element.get_attribute("name", None) {
+        Some(name) => format!("{}[{}]", element.name, name),
+        None => element.name.clone(),
+    };
+    if parent_path.is_empty() {
+        tag
+    } else {
+        format!("{}/{}", parent_path, tag)
+    }
+}
+
+pub(crate) fn parse_floats(text: &str, path: &str) -> Result<Vec<f64>, ImportError> {
+    text.split_whitespace()
+        .map(|v| {
+            v.parse::<f64>()
+                .map_err(|_| ImportError::InvalidModelError {
+                    error: format!("{}: {:?} is not a number", path, text),
+                })
+        })
+        .collect()
+}
+
+/// Parse exactly `size` numbers
+pub(crate) fn parse_floats_n(text: &str, size: usize, path: &str) -> Result<Vec<f64>, ImportError> {
+    let values = parse_floats(text, path)?;
+    if values.len() != size {
+        return Err(ImportError::InvalidModelError {
+            error: format!("{}: {} numbers are required, but {:?}", path, size, text),
+        });
+    }
+    Ok(values)
+}
+
+pub(crate) fn vector3(values: &[f64]) -> Vector3<f64> {
+    Vector3::new(values[0], values[1], values[2])
+}
+
+pub(crate) fn translation(values: &[f64]) -> Isometry3<f64> {
+    Isometry3::from_parts(
+        Translation3::from(vector3(values)),
+        UnitQuaternion::identity(),
+    )
+}
+
+/// Collect unsupported elements
+#[derive(Debug, Default)]
+pub(crate) struct Reporter {
+    pub(crate) unsupported: Vec<UnsupportedElement>,
+}
+
+impl Reporter {
+    pub(crate) fn report(&mut self, path: String, message: &str) {
+        warn!("{}: {}", path, message);
+        self.unsupported.push(UnsupportedElement {
+            path,
+            message: message.to_owned(),
+        });
+    }
+    /// Report the child elements which are not in `known`
+    pub(crate) fn report_unknown_children(
+        &mut self,
+        element: &Element,
+        path: &str,
+        known: &[&str],
+    ) {
+        for child in children(element) {
+            if !known.contains(&child.name.as_str()) {
+                self.report(child_path(path, child), "ignored");
+            }
+        }
+    }
+}
+
+/// Shape of visual or collision, in f64
+pub(crate) struct Shape {
+    pub(crate) name: String,
+    pub(crate) origin: Isometry3<f64>,
+    pub(crate) geometry: Geometry<f64>,
+    pub(crate) color: Option<[f64; 4]>,
+}
+
+/// Origin, mass and inertia in f64
+pub(crate) type InertialF64 = (Isometry3<f64>, f64, Matrix3<f64>);
+
+/// Build `Link` from the shapes and the inertial in f64
+///
+/// `to_node` is the transform from the frame of the node to the frame of the shapes.
+pub(crate) fn build_link<T: Real>(
+    name: &str,
+    to_node: &Isometry3<f64>,
+    inertial: Option<InertialF64>,
+    visuals: &[Shape],
+    collisions: &[Shape],
+) -> Link<T> {
+    let convert_geometry = |geometry: &Geometry<f64>| match *geometry {
+        Geometry::Box {
+            depth,
+            width,
+            height,
+        } => Geometry::Box {
+            depth: na::convert(depth),
+            width: na::convert(width),
+            height: na::convert(height),
+        },
+        Geometry::Cylinder { radius, length } => Geometry::Cylinder {
+            radius: na::convert(radius),
+            length: na::convert(length),
+        },
+        Geometry::Sphere { radius } => Geometry::Sphere {
+            radius: na::convert(radius),
+        },
+        Geometry::Mesh {
+            ref filename,
+            ref scale,
+        } => Geometry::Mesh {
+            filename: filename.clone(),
+            scale: na::convert(*scale),
+        },
+    };
+    let mut builder = LinkBuilder::new().name(name);
+    if let Some((origin, mass, inertia)) = inertial {
+        builder = builder.inertial(Inertial::new(
+            na::convert(to_node * origin),
+            na::convert(mass),
+            na::convert(inertia),
+        ));
+    }
+    for shape in visuals {
+        let rgba = shape.color.unwrap_or([0.0; 4]);
+        let material = Material {
+            name: String::new(),
+            color: Color {
+                r: na::convert(rgba[0]),
+                g: na::convert(rgba[1]),
+                b: na::convert(rgba[2]),
+                a: na::convert(rgba[3]),
+            },
+            texture: Texture::default(),
+        };
+        builder = builder.add_visual(Visual::new(
+            shape.name.clone(),
+            na::convert(to_node * shape.origin),
+            convert_geometry(&shape.geometry),
+            material,
+        ));
+    }
+    for shape in collisions {
+        builder = builder.add_collision(Collision::new(
+            shape.name.clone(),
+            na::convert(to_node * shape.origin),
+            convert_geometry(&shape.geometry),
+        ));
+    }
+    builder.finalize()
+}
+
+#[test]
+fn test_import_from_str() {
+    let sdf = r#"<sdf version="1.6"><model name="m">
+        <link name="base"/><link name="arm"/>
+        <joint name="j" type="prismatic"><parent>base</parent><child>arm</child></joint>
+        <plugin name="p"/>
+        </model></sdf>"#;
+    let imported = from_str::<f64>(sdf).unwrap();
+    assert!(imported.chain.find("j").is_some());
+    assert_eq!(imported.unsupported[0].path, "model[m]/plugin[p]");
+
+    let mjcf = r#"<mujoco><worldbody><body name="b"><joint name="j" type="slide"/></body></worldbody>
+        <actuator/></mujoco>"#;
+    let imported = from_str::<f64>(mjcf).unwrap();
+    assert!(imported.chain.find("j").is_some());
+    assert_eq!(imported.unsupported[0].path, "actuator");
+
+    let dir = ::std::env::temp_dir().join("k_test_import");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("model.xml");
+    fs::write(&path, mjcf).unwrap();
+    assert!(from_file::<f64, _>(&path).unwrap().chain.find("j").is_some());
+
+    match from_str::<f64>(r#"<robot name="r"><link name="l"/></robot>"#) {
+        Err(ImportError::InvalidModelError { .. }) => {}
+        other => panic!("{:?}", other.map(|i| i.unsupported)),
+    }
+    match from_str::<f64>("<sdf>") {
+        Err(ImportError::ParseError { .. }) => {}
+        other => panic!("{:?}", other.map(|i| i.unsupported)),
+    }
+    assert!(from_file::<f64, _>(dir.join("not_found.xml")).is_err());
+}
@@ -15,9 +15,11 @@
 */
 //! Joint related structs
 use errors::*;
-use na::{Isometry3, Real, Translation3, Unit, UnitQuaternion, Vector3};
+use na::{self, Isometry3, Real, Translation3, Unit, UnitQuaternion, Vector3};
 use std::cell::RefCell;
 use std::fmt::{self, Display};
+use std::ops::Deref;
+use std::slice;
 
 #[derive(Clone, Debug, Copy)]
 pub struct Velocity<T: Real> {
@@ -55,7 +57,50 @@ where
     }
 }
 
-/// Type of Joint, `Fixed`, `Rotational`, `Linear` is supported now
+/// Linear and angular acceleration in the world frame
+#[derive(Clone, Debug, Copy)]
+pub struct Acceleration<T: Real> {
+    pub translation: Vector3<T>,
+    pub rotation: Vector3<T>,
+}
+
+impl<T> Acceleration<T>
+where
+    T: Real,
+{
+    pub fn new() -> Self {
+        Self::zero()
+    }
+    pub fn from_parts(translation: Vector3<T>, rotation: Vector3<T>) -> Self {
+        Self {
+            translation,
+            rotation,
+        }
+    }
+    pub fn zero() -> Self {
+        Self {
+            translation: Vector3::zeros(),
+            rotation: Vector3::zeros(),
+        }
+    }
+}
+
+impl<T> Default for Acceleration<T>
+where
+    T: Real,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Max degree of freedom of a joint
+const MAX_JOINT_DOF: usize = 6;
+
+/// Type of Joint
+///
+/// `Planar`, `Spherical` and `Floating` have multiple positions, use
+/// `Joint::joint_positions()` for them. The motions of them are listed by `motions()`.
 #[derive(Copy, Debug, Clone)]
 pub enum JointType<T: Real> {
     /// Fixed joint. It has no `joint_position` and axis.
@@ -65,11 +110,193 @@ pub enum JointType<T: Real> {
         /// axis of the joint
         axis: Unit<Vector3<T>>,
     },
+    /// Rotational joint which rotates endlessly.
+    /// The position [rad] is normalized to (-pi, pi].
+    Continuous {
+        /// axis of the joint
+        axis: Unit<Vector3<T>>,
+    },
     /// Linear joint. position is length
     Linear {
         /// axis of the joint
         axis: Unit<Vector3<T>>,
     },
+    /// Joint which moves in the plane perpendicular to `normal`.
+    /// The positions are `[x, y, angle]`.
+    /// `x` and `y` are along `planar_axes(normal)`, then it rotates around `normal`.
+    Planar {
+        /// normal of the plane
+        normal: Unit<Vector3<T>>,
+    },
+    /// Joint which rotates freely around the origin.
+    /// The positions are the angles `[rx, ry, rz]` around X, Y and Z axes,
+    /// applied in this order to the rotated frame.
+    ///
+    /// These Euler angles have a singularity (gimbal lock) at `ry = ±pi/2`,
+    /// where `rx` and `rz` rotate around the same axis. The Jacobian loses rank
+    /// there, so IK and planning can be unstable near it.
+    Spherical,
+    /// Free joint. The positions are `[x, y, z, rx, ry, rz]`,
+    /// the translation and then the same rotation as `Spherical`
+    /// (with the same gimbal lock).
+    Floating,
+}
+
+/// Elementary motion of a joint along or around the axis
+///
+/// Multi DOF joints are sequences of them. The translations are always before
+/// the rotations, so the rotations do not move the origin of the child frame.
+#[derive(Copy, Debug, Clone)]
+pub enum Motion<T: Real> {
+    /// Rotation around the axis
+    Rotation(Unit<Vector3<T>>),
+    /// Translation along the axis
+    Translation(Unit<Vector3<T>>),
+}
+
+impl<T> Motion<T>
+where
+    T: Real,
+{
+    /// Transform of the motion for the position
+    pub fn transform(&self, position: T) -> Isometry3<T> {
+        match *self {
+            Motion::Rotation(axis) => Isometry3::from_parts(
+                Translation3::new(T::zero(), T::zero(), T::zero()),
+                UnitQuaternion::from_axis_angle(&axis, position),
+            ),
+            Motion::Translation(axis) => Isometry3::from_parts(
+                Translation3::from(axis.into_inner() * position),
+                UnitQuaternion::identity(),
+            ),
+        }
+    }
+}
+
+/// Motions of a joint, stored without heap allocation
+///
+/// It is used as a slice of `Motion` with `JointType::dof()` elements.
+#[derive(Copy, Debug, Clone)]
+pub struct Motions<T: Real> {
+    motions: [Motion<T>; MAX_JOINT_DOF],
+    len: usize,
+}
+
+impl<T> Motions<T>
+where
+    T: Real,
+{
+    fn new(list: &[Motion<T>]) -> Self {
+        let mut motions = [Motion::Rotation(Vector3::z_axis()); MAX_JOINT_DOF];
+        motions[..list.len()].copy_from_slice(list);
+        Motions {
+            motions,
+            len: list.len(),
+        }
+    }
+}
+
+impl<T> Deref for Motions<T>
+where
+    T: Real,
+{
+    type Target = [Motion<T>];
+    fn deref(&self) -> &[Motion<T>] {
+        &self.motions[..self.len]
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Motions<T>
+where
+    T: Real,
+{
+    type Item = &'a Motion<T>;
+    type IntoIter = slice::Iter<'a, Motion<T>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Two axes of the plane perpendicular to `normal`, used by `JointType::Planar`
+///
+/// They are X and Y for the normal Z.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let (x, y) = k::joint::planar_axes(&Vector3::<f64>::z_axis());
+/// assert_eq!(x, Vector3::x_axis());
+/// assert_eq!(y, Vector3::y_axis());
+/// ```
+pub fn planar_axes<T: Real>(normal: &Unit<Vector3<T>>) -> (Unit<Vector3<T>>, Unit<Vector3<T>>) {
+    let reference = if normal.x.abs() < na::convert(0.9) {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    let x = Unit::new_normalize(reference - normal.into_inner() * normal.dot(&reference));
+    let y = Unit::new_normalize(normal.cross(&x));
+    (x, y)
+}
+
+impl<T> JointType<T>
+where
+    T: Real,
+{
+    /// Degree of freedom of the joint
+    pub fn dof(&self) -> usize {
+        match *self {
+            JointType::Fixed => 0,
+            JointType::Rotational { .. }
+            | JointType::Continuous { .. }
+            | JointType::Linear { .. } => 1,
+            JointType::Planar { .. } | JointType::Spherical => 3,
+            JointType::Floating => 6,
+        }
+    }
+    /// Motions for each position of the joint, in the order of applying
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let planar = JointType::<f64>::Planar { normal: Vector3::z_axis() };
+    /// assert_eq!(planar.motions().len(), planar.dof());
+    /// ```
+    pub fn motions(&self) -> Motions<T> {
+        let (x, y, z) = (Vector3::x_axis(), Vector3::y_axis(), Vector3::z_axis());
+        match *self {
+            JointType::Fixed => Motions::new(&[]),
+            JointType::Rotational { axis } | JointType::Continuous { axis } => {
+                Motions::new(&[Motion::Rotation(axis)])
+            }
+            JointType::Linear { axis } => Motions::new(&[Motion::Translation(axis)]),
+            JointType::Planar { normal } => {
+                let (u, v) = planar_axes(&normal);
+                Motions::new(&[
+                    Motion::Translation(u),
+                    Motion::Translation(v),
+                    Motion::Rotation(normal),
+                ])
+            }
+            JointType::Spherical => Motions::new(&[
+                Motion::Rotation(x),
+                Motion::Rotation(y),
+                Motion::Rotation(z),
+            ]),
+            JointType::Floating => Motions::new(&[
+                Motion::Translation(x),
+                Motion::Translation(y),
+                Motion::Translation(z),
+                Motion::Rotation(x),
+                Motion::Rotation(y),
+                Motion::Rotation(z),
+            ]),
+        }
+    }
 }
 
 fn axis_to_string<T: Real>(axis: &Unit<Vector3<T>>) -> &str {
@@ -95,11 +322,39 @@ impl<T: Real> Display for JointType<T> {
         match self {
             JointType::Fixed => write!(f, "[⚓]"),
             JointType::Rotational { axis } => write!(f, "[⚙{}]", axis_to_string(axis)),
+            JointType::Continuous { axis } => write!(f, "[↻{}]", axis_to_string(axis)),
             JointType::Linear { axis } => write!(f, "[↕{}]", axis_to_string(axis)),
+            JointType::Planar { normal } => write!(f, "[▱{}]", axis_to_string(normal)),
+            JointType::Spherical => write!(f, "[◎]"),
+            JointType::Floating => write!(f, "[✥]"),
         }
     }
 }
 
+/// Normalize the angle to (-pi, pi]
+///
+/// # Examples
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use k::joint::normalize_angle;
+///
+/// assert!((normalize_angle(3.0 * PI / 2.0) + PI / 2.0).abs() < 1e-10);
+/// assert_eq!(normalize_angle(PI), PI);
+/// assert_eq!(normalize_angle(-PI), PI);
+/// assert!((normalize_angle(37.0) - (37.0 - 12.0 * PI)).abs() < 1e-10);
+/// ```
+pub fn normalize_angle<T: Real>(angle: T) -> T {
+    let two_pi = T::two_pi();
+    let wrapped = angle - two_pi * ((angle + T::pi()) / two_pi).floor();
+    // wrapped is in [-pi, pi)
+    if wrapped <= -T::pi() {
+        wrapped + two_pi
+    } else {
+        wrapped
+    }
+}
+
 /// min/max range to check the joint position
 #[derive(Copy, Debug, Clone)]
 pub struct Range<T: Real> {
@@ -157,6 +412,63 @@ where
     }
 }
 
+/// Soft limits and gains of the safety controller
+///
+/// It is the same as `<safety_controller>` of URDF. The controller
+/// starts to push the joint back before it reaches `Joint::limits`.
+///
+/// # Examples
+///
+/// ```
+/// let safety = k::joint::SafetyController::new((-0.9f64..=0.9).into(), 10.0, 1.0);
+/// assert!(safety.soft_limits.is_valid(0.5));
+/// assert!(!safety.soft_limits.is_valid(0.95));
+/// ```
+#[derive(Copy, Debug, Clone)]
+pub struct SafetyController<T: Real> {
+    /// Soft limits of the position
+    pub soft_limits: Range<T>,
+    /// Gain of the position for the velocity limit
+    pub k_position: T,
+    /// Gain of the velocity for the effort limit
+    pub k_velocity: T,
+}
+
+impl<T> SafetyController<T>
+where
+    T: Real,
+{
+    /// Create new instance of SafetyController
+    pub fn new(soft_limits: Range<T>, k_position: T, k_velocity: T) -> Self {
+        SafetyController {
+            soft_limits,
+            k_position,
+            k_velocity,
+        }
+    }
+}
+
+/// Physical damping and friction of the joint
+///
+/// It is the same as `<dynamics>` of URDF.
+#[derive(Copy, Debug, Clone)]
+pub struct Dynamics<T: Real> {
+    /// Damping coefficient [N s/m] or [N m s/rad]
+    pub damping: T,
+    /// Static friction [N] or [N m]
+    pub friction: T,
+}
+
+impl<T> Dynamics<T>
+where
+    T: Real,
+{
+    /// Create new instance of Dynamics
+    pub fn new(damping: T, friction: T) -> Self {
+        Dynamics { damping, friction }
+    }
+}
+
 /// Information for copying joint state of other joint
 ///
 /// For example, `Mimic` is used to calculate the position of the gripper(R) from
@@ -213,18 +525,33 @@ pub struct Joint<T: Real> {
     pub name: String,
     /// Type of this joint
     pub joint_type: JointType<T>,
-    /// position (angle) of this joint
-    position: T,
-    /// velocity of this joint
-    velocity: T,
-    /// Limits of this joint
+    /// positions (angles) of this joint, only the first `dof()` elements are used
+    positions: [T; MAX_JOINT_DOF],
+    /// velocities of this joint
+    velocities: [T; MAX_JOINT_DOF],
+    /// accelerations of this joint
+    accelerations: [T; MAX_JOINT_DOF],
+    /// Limits of this joint, applied to all the positions of multi DOF joint
+    ///
+    /// The same range is used for every component, e.g. both translations and
+    /// rotations of `Floating`. Use `None` if they need different ranges.
     pub limits: Option<Range<T>>,
+    /// Max absolute velocity of this joint, applied to all the velocities of multi DOF joint
+    pub velocity_limit: Option<T>,
+    /// Max absolute effort (torque or force) of this joint
+    pub effort_limit: Option<T>,
+    /// Soft limits of this joint
+    pub safety_controller: Option<SafetyController<T>>,
+    /// Damping and friction of this joint
+    pub dynamics: Option<Dynamics<T>>,
     /// local origin transform of joint
     origin: Isometry3<T>,
     /// cache of world transform
     world_transform_cache: RefCell<Option<Isometry3<T>>>,
     /// cache of world velocity
     world_velocity_cache: RefCell<Option<Velocity<T>>>,
+    /// cache of world acceleration
+    world_acceleration_cache: RefCell<Option<Acceleration<T>>>,
 }
 
 impl<T> Joint<T>
@@ -252,12 +579,18 @@ where
         Joint {
             name: name.to_string(),
             joint_type,
-            position: T::zero(),
-            velocity: T::zero(),
+            positions: [T::zero(); MAX_JOINT_DOF],
+            velocities: [T::zero(); MAX_JOINT_DOF],
+            accelerations: [T::zero(); MAX_JOINT_DOF],
             limits: None,
+            velocity_limit: None,
+            effort_limit: None,
+            safety_controller: None,
+            dynamics: None,
             origin: Isometry3::identity(),
             world_transform_cache: RefCell::new(None),
             world_velocity_cache: RefCell::new(None),
+            world_acceleration_cache: RefCell::new(None),
         }
     }
     /// Set the position of the joint
@@ -287,12 +620,95 @@ where
     /// ```
     ///
     pub fn set_joint_position(&mut self, position: T) -> Result<(), JointError> {
-        if let JointType::Fixed = self.joint_type {
-            return Err(JointError::OutOfLimitError {
+        self.check_single_dof()?;
+        let position = self.normalize_position(position);
+        self.check_position_limits(position)?;
+        self.set_joint_position_unchecked(position);
+        Ok(())
+    }
+    pub fn set_joint_position_unchecked(&mut self, position: T) {
+        self.positions[0] = self.normalize_position(position);
+        self.clear_caches();
+    }
+    /// Set all the positions of the joint
+    ///
+    /// The length of `positions` must be `dof()`. It returns Err if any of them is out of the limits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let mut planar = Joint::<f64>::new("base", JointType::Planar { normal: Vector3::z_axis() });
+    /// planar.set_joint_positions(&[1.0, 2.0, 0.5]).unwrap();
+    /// assert_eq!(planar.joint_positions(), &[1.0, 2.0, 0.5]);
+    /// assert!(planar.joint_position().is_none());
+    /// let trans = planar.local_transform();
+    /// assert_eq!(trans.translation.vector, Vector3::new(1.0, 2.0, 0.0));
+    /// assert!((trans.rotation.angle() - 0.5).abs() < 1e-10);
+    /// assert!(planar.set_joint_positions(&[1.0]).is_err());
+    /// ```
+    pub fn set_joint_positions(&mut self, positions: &[T]) -> Result<(), JointError> {
+        self.check_size(positions.len())?;
+        let positions = positions
+            .iter()
+            .map(|p| self.normalize_position(*p))
+            .collect::<Vec<_>>();
+        for position in &positions {
+            self.check_position_limits(*position)?;
+        }
+        self.set_joint_positions_unchecked(&positions);
+        Ok(())
+    }
+    pub fn set_joint_positions_unchecked(&mut self, positions: &[T]) {
+        for (dst, src) in self.positions.iter_mut().zip(positions.iter()) {
+            *dst = *src;
+        }
+        if let JointType::Continuous { .. } = self.joint_type {
+            self.positions[0] = normalize_angle(self.positions[0]);
+        }
+        self.clear_caches();
+    }
+    /// Returns all the positions, the length is `dof()`
+    #[inline]
+    pub fn joint_positions(&self) -> &[T] {
+        &self.positions[..self.dof()]
+    }
+    /// Degree of freedom of this joint
+    #[inline]
+    pub fn dof(&self) -> usize {
+        self.joint_type.dof()
+    }
+    fn clear_caches(&self) {
+        // TODO: have to reset descendent `world_transform_cache`
+        self.world_transform_cache.replace(None);
+        self.world_velocity_cache.replace(None);
+        self.world_acceleration_cache.replace(None);
+    }
+    /// Returns Err if this is not single DOF joint
+    fn check_single_dof(&self) -> Result<(), JointError> {
+        match self.dof() {
+            1 => Ok(()),
+            0 => Err(JointError::OutOfLimitError {
                 joint_name: self.name.to_string(),
                 message: "Joint is Fixed".to_owned(),
+            }),
+            dof => Err(JointError::OutOfLimitError {
+                joint_name: self.name.to_string(),
+                message: format!("Joint has {} DOF, use the multi DOF setter", dof),
+            }),
+        }
+    }
+    fn check_size(&self, input: usize) -> Result<(), JointError> {
+        if input != self.dof() {
+            return Err(JointError::SizeMismatchError {
+                input,
+                required: self.dof(),
             });
         }
+        Ok(())
+    }
+    fn check_position_limits(&self, position: T) -> Result<(), JointError> {
         if let Some(ref range) = self.limits {
             if !range.is_valid(position) {
                 return Err(JointError::OutOfLimitError {
@@ -304,24 +720,68 @@ where
                 });
             }
         }
-        self.position = position;
-        // TODO: have to reset descendent `world_transform_cache`
-        self.world_transform_cache.replace(None);
-        self.world_velocity_cache.replace(None);
         Ok(())
     }
-    pub fn set_joint_position_unchecked(&mut self, position: T) {
-        self.position = position;
-        // TODO: have to reset descendent `world_transform_cache`
-        self.world_transform_cache.replace(None);
-        self.world_velocity_cache.replace(None);
+    fn check_velocity_limit(&self, velocity: T) -> Result<(), JointError> {
+        if let Some(limit) = self.velocity_limit {
+            if velocity.abs() > limit {
+                return Err(JointError::OutOfLimitError {
+                    joint_name: self.name.to_string(),
+                    message: format!(
+                        "Joint velocity is out of limit: input={}, limit={}",
+                        velocity, limit
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+    /// Normalize the position to (-pi, pi] if this is continuous joint
+    #[inline]
+    fn normalize_position(&self, position: T) -> T {
+        match self.joint_type {
+            JointType::Continuous { .. } => normalize_angle(position),
+            _ => position,
+        }
+    }
+    /// Difference of the positions from `from` to `to`
+    ///
+    /// It takes the shorter way around for continuous joint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    /// extern crate k;
+    ///
+    /// let rot = k::Joint::<f64>::new("r0", k::JointType::Rotational { axis: na::Vector3::z_axis() });
+    /// assert!((rot.position_difference(3.0, -3.0) + 6.0).abs() < 1e-10);
+    /// let cont = k::Joint::<f64>::new("c0", k::JointType::Continuous { axis: na::Vector3::z_axis() });
+    /// assert!((cont.position_difference(3.0, -3.0) - (2.0 * std::f64::consts::PI - 6.0)).abs() < 1e-10);
+    /// ```
+    pub fn position_difference(&self, from: T, to: T) -> T {
+        match self.joint_type {
+            JointType::Continuous { .. } => normalize_angle(to - from),
+            _ => to - from,
+        }
+    }
+    /// Returns true if this is continuous joint
+    #[inline]
+    #[allow(clippy::match_like_matches_macro)]
+    pub fn is_continuous(&self) -> bool {
+        match self.joint_type {
+            JointType::Continuous { .. } => true,
+            _ => false,
+        }
     }
     /// Returns the position (angle)
+    ///
+    /// It is `None` for fixed and multi DOF joints.
     #[inline]
     pub fn joint_position(&self) -> Option<T> {
-        match self.joint_type {
-            JointType::Fixed => None,
-            _ => Some(self.position),
+        match self.dof() {
+            1 => Some(self.positions[0]),
+            _ => None,
         }
     }
 
@@ -334,29 +794,126 @@ where
     pub fn set_origin(&mut self, origin: Isometry3<T>) {
         self.origin = origin;
         self.world_transform_cache.replace(None);
+        self.world_velocity_cache.replace(None);
+        self.world_acceleration_cache.replace(None);
     }
 
+    /// Set the velocity of the joint
+    ///
+    /// It returns Err if it exceeds `velocity_limit`, or this is fixed joint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    /// extern crate k;
+    ///
+    /// let mut rot = k::Joint::<f64>::new("r0", k::JointType::Rotational { axis: na::Vector3::y_axis() });
+    /// rot.velocity_limit = Some(1.0);
+    /// assert!(rot.set_joint_velocity(-1.0).is_ok());
+    /// assert!(rot.set_joint_velocity(1.5).is_err());
+    /// assert_eq!(rot.joint_velocity().unwrap(), -1.0);
+    /// ```
     pub fn set_joint_velocity(&mut self, velocity: T) -> Result<(), JointError> {
-        if let JointType::Fixed = self.joint_type {
-            return Err(JointError::OutOfLimitError {
-                joint_name: self.name.to_string(),
-                message: "Joint is Fixed".to_owned(),
-            });
-        }
-        self.velocity = velocity;
-        self.world_velocity_cache.replace(None);
+        self.check_single_dof()?;
+        self.check_velocity_limit(velocity)?;
+        self.set_joint_velocity_unchecked(velocity);
         Ok(())
     }
 
+    pub fn set_joint_velocity_unchecked(&mut self, velocity: T) {
+        self.velocities[0] = velocity;
+        self.world_velocity_cache.replace(None);
+        self.world_acceleration_cache.replace(None);
+    }
+
     /// Returns the velocity
+    ///
+    /// It is `None` for fixed and multi DOF joints.
     #[inline]
     pub fn joint_velocity(&self) -> Option<T> {
-        match self.joint_type {
-            JointType::Fixed => None,
-            _ => Some(self.velocity),
+        match self.dof() {
+            1 => Some(self.velocities[0]),
+            _ => None,
+        }
+    }
+
+    /// Set all the velocities of the joint
+    ///
+    /// The length of `velocities` must be `dof()`.
+    pub fn set_joint_velocities(&mut self, velocities: &[T]) -> Result<(), JointError> {
+        self.check_size(velocities.len())?;
+        for velocity in velocities {
+            self.check_velocity_limit(*velocity)?;
+        }
+        self.set_joint_velocities_unchecked(velocities);
+        Ok(())
+    }
+
+    pub fn set_joint_velocities_unchecked(&mut self, velocities: &[T]) {
+        for (dst, src) in self.velocities.iter_mut().zip(velocities.iter()) {
+            *dst = *src;
+        }
+        self.world_velocity_cache.replace(None);
+        self.world_acceleration_cache.replace(None);
+    }
+
+    /// Returns all the velocities, the length is `dof()`
+    #[inline]
+    pub fn joint_velocities(&self) -> &[T] {
+        &self.velocities[..self.dof()]
+    }
+
+    /// Set the acceleration of the joint
+    ///
+    /// It returns Err if this is fixed joint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate nalgebra as na;
+    /// extern crate k;
+    ///
+    /// let mut rot = k::Joint::<f64>::new("r0", k::JointType::Rotational { axis: na::Vector3::y_axis() });
+    /// rot.set_joint_acceleration(0.3).unwrap();
+    /// assert_eq!(rot.joint_acceleration().unwrap(), 0.3);
+    /// ```
+    pub fn set_joint_acceleration(&mut self, acceleration: T) -> Result<(), JointError> {
+        self.check_single_dof()?;
+        self.accelerations[0] = acceleration;
+        self.world_acceleration_cache.replace(None);
+        Ok(())
+    }
+
+    /// Returns the acceleration
+    ///
+    /// It is `None` for fixed and multi DOF joints.
+    #[inline]
+    pub fn joint_acceleration(&self) -> Option<T> {
+        match self.dof() {
+            1 => Some(self.accelerations[0]),
+            _ => None,
         }
     }
 
+    /// Set all the accelerations of the joint
+    ///
+    /// The length of `accelerations` must be `dof()`.
+    pub fn set_joint_accelerations(&mut self, accelerations: &[T]) -> Result<(), JointError> {
+        self.check_size(accelerations.len())?;
+        for (dst, src) in self.accelerations.iter_mut().zip(accelerations.iter()) {
+            *dst = *src;
+        }
+        self.world_acceleration_cache.replace(None);
+        Ok(())
+    }
+
+    /// Returns all the accelerations, the length is `dof()`
+    #[inline]
+    pub fn joint_accelerations(&self) -> &[T] {
+        &self.accelerations[..self.dof()]
+    }
+
     /// Calculate and returns the transform of the end of this joint
     ///
     /// # Examples
@@ -373,18 +930,34 @@ where
     /// ```
     ///
     pub fn local_transform(&self) -> Isometry3<T> {
-        let joint_transform = match self.joint_type {
-            JointType::Fixed => Isometry3::identity(),
-            JointType::Rotational { axis } => Isometry3::from_parts(
-                Translation3::new(T::zero(), T::zero(), T::zero()),
-                UnitQuaternion::from_axis_angle(&axis, self.position),
-            ),
-            JointType::Linear { axis } => Isometry3::from_parts(
-                Translation3::from(axis.into_inner() * self.position),
-                UnitQuaternion::identity(),
-            ),
-        };
-        self.origin * joint_transform
+        self.joint_type
+            .motions()
+            .iter()
+            .zip(self.positions.iter())
+            .fold(self.origin, |trans, (motion, position)| {
+                trans * motion.transform(*position)
+            })
+    }
+
+    /// Motions of this joint with the world frames where they are applied
+    ///
+    /// The frames are calculated from the cache of `world_transform()`.
+    pub(crate) fn world_motions<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (Motion<T>, Isometry3<T>)> + 'a {
+        let motions = self.joint_type.motions();
+        let motion_transform = motions
+            .iter()
+            .zip(self.positions.iter())
+            .fold(Isometry3::identity(), |trans, (motion, position)| {
+                trans * motion.transform(*position)
+            });
+        let frame = self.world_transform().expect("cache must exist") * motion_transform.inverse();
+        (0..motions.len()).scan(frame, move |frame, i| {
+            let current = *frame;
+            *frame *= motions[i].transform(self.positions[i]);
+            Some((motions[i], current))
+        })
     }
 
     #[inline]
@@ -396,6 +969,11 @@ where
     pub(crate) fn set_world_velocity(&self, world_velocity: Velocity<T>) {
         self.world_velocity_cache.replace(Some(world_velocity));
     }
+
+    #[inline]
+    pub(crate) fn set_world_acceleration(&self, world_acceleration: Acceleration<T>) {
+        self.world_acceleration_cache.replace(Some(world_acceleration));
+    }
     /// Get the result of forward kinematics
     ///
     /// The value is updated by `Chain::update_transforms`
@@ -404,17 +982,25 @@ where
         *self.world_transform_cache.borrow()
     }
 
+    /// Get the velocity of the joint origin in the world frame
+    ///
+    /// The value is updated by `Chain::update_velocities`
     #[inline]
     pub fn world_velocity(&self) -> Option<Velocity<T>> {
         *self.world_velocity_cache.borrow()
     }
 
+    /// Get the acceleration of the joint origin in the world frame
+    ///
+    /// The value is updated by `Chain::update_accelerations`
+    #[inline]
+    pub fn world_acceleration(&self) -> Option<Acceleration<T>> {
+        *self.world_acceleration_cache.borrow()
+    }
+
     #[inline]
     pub fn is_movable(&self) -> bool {
-        match self.joint_type {
-            JointType::Fixed => false,
-            _ => true,
-        }
+        self.dof() > 0
     }
 }
 
@@ -29,18 +29,34 @@ extern crate failure;
 extern crate log;
 extern crate nalgebra as na;
 extern crate urdf_rs;
+extern crate xml;
 
 mod chain;
 mod errors;
 mod funcs;
 mod ik;
 
+pub mod balance;
+pub mod cartesian;
+pub mod collision;
+pub mod dh;
+pub mod import;
 pub mod iterator;
 pub mod joint;
 pub mod link;
+pub mod mesh;
+pub mod mjcf;
 pub mod node;
+pub mod planning;
+pub mod poe;
 pub mod prelude;
+pub mod resource;
+pub mod sdf;
+pub mod topp;
+pub mod trajectory;
 pub mod urdf;
+pub mod world;
+pub mod xacro;
 
 pub use self::chain::*;
 pub use self::errors::*;
@@ -48,7 +64,7 @@ pub use self::funcs::*;
 pub use self::ik::*;
 pub use self::joint::{Joint, JointType};
 pub use self::link::Link;
-pub use self::node::{JointBuilder, Node};
+pub use self::node::{Frame, JointBuilder, Node};
 
 // re-export
 pub use na::{Isometry3, Real, Translation3, UnitQuaternion, Vector3};
@@ -17,7 +17,10 @@
 //!
 //! `link` module is optional for `k`.
 //!
-use na::{Isometry3, Matrix3, Real, Vector3};
+use na::{self, Isometry3, Matrix3, Real, Vector3};
+use std::sync::Arc;
+
+use mesh::TriangleMesh;
 
 #[derive(Debug, Clone)]
 pub enum Geometry<T: Real> {
@@ -27,6 +30,82 @@ pub enum Geometry<T: Real> {
     Mesh { filename: String, scale: Vector3<T> },
 }
 
+impl<T> Geometry<T>
+where
+    T: Real,
+{
+    /// Volume of the shape. `Mesh` is not supported and returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::link::Geometry;
+    ///
+    /// let b = Geometry::Box { depth: 1.0, width: 2.0, height: 3.0 };
+    /// assert_eq!(b.volume().unwrap(), 6.0);
+    /// ```
+    pub fn volume(&self) -> Option<T> {
+        let pi = T::pi();
+        match *self {
+            Geometry::Box {
+                depth,
+                width,
+                height,
+            } => Some(depth * width * height),
+            Geometry::Cylinder { radius, length } => Some(pi * radius * radius * length),
+            Geometry::Sphere { radius } => {
+                Some(na::convert::<f64, T>(4.0 / 3.0) * pi * radius * radius * radius)
+            }
+            Geometry::Mesh { .. } => None,
+        }
+    }
+    /// Inertia tensor of the solid shape with `mass` around its center
+    ///
+    /// The axes are the same as the shape (the cylinder is along Z axis, as URDF).
+    /// `Mesh` is not supported and returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::link::Geometry;
+    ///
+    /// let s = Geometry::Sphere { radius: 0.5f64 };
+    /// let inertia = s.inertia(2.0).unwrap();
+    /// assert!((inertia[(0, 0)] - 0.2).abs() < 1e-10); // 2/5 * m * r^2
+    /// assert_eq!(inertia[(0, 1)], 0.0);
+    /// ```
+    pub fn inertia(&self, mass: T) -> Option<Matrix3<T>> {
+        let twelfth = mass / na::convert(12.0);
+        match *self {
+            Geometry::Box {
+                depth,
+                width,
+                height,
+            } => {
+                let (x2, y2, z2) = (depth * depth, width * width, height * height);
+                Some(Matrix3::from_diagonal(&Vector3::new(
+                    twelfth * (y2 + z2),
+                    twelfth * (x2 + z2),
+                    twelfth * (x2 + y2),
+                )))
+            }
+            Geometry::Cylinder { radius, length } => {
+                let r2 = radius * radius;
+                let side = twelfth * (na::convert::<f64, T>(3.0) * r2 + length * length);
+                Some(Matrix3::from_diagonal(&Vector3::new(
+                    side,
+                    side,
+                    mass * r2 / na::convert(2.0),
+                )))
+            }
+            Geometry::Sphere { radius } => Some(Matrix3::from_diagonal_element(
+                mass * radius * radius * na::convert(0.4),
+            )),
+            Geometry::Mesh { .. } => None,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Color<T: Real> {
     pub r: T,
@@ -63,19 +142,95 @@ pub struct Inertial<T: Real> {
     world_transform_cache: Option<Isometry3<T>>,
 }
 
-// TODO
 impl<T> Inertial<T>
 where
     T: Real,
 {
+    /// Create point mass
     pub fn from_mass(mass: T) -> Self {
         Self {
             origin: Isometry3::identity(),
             mass,
-            inertia: Matrix3::identity(),
+            inertia: Matrix3::zeros(),
             world_transform_cache: None,
         }
     }
+    /// Create the inertial of the solid `geometry` with uniform `density`
+    ///
+    /// `origin` is the center of the shape. `Mesh` is not supported and returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let geometry = Geometry::<f64>::Box { depth: 0.1, width: 0.2, height: 0.5 };
+    /// let inertial = Inertial::from_geometry(&geometry, Isometry3::identity(), 1000.0).unwrap();
+    /// assert!((inertial.mass - 10.0).abs() < 1e-10);
+    /// ```
+    pub fn from_geometry(geometry: &Geometry<T>, origin: Isometry3<T>, density: T) -> Option<Self> {
+        let mass = geometry.volume()? * density;
+        Some(Self::new(origin, mass, geometry.inertia(mass)?))
+    }
+    /// Create the inertial of the solid closed `mesh` with uniform `density`
+    ///
+    /// `origin` is the frame of the mesh. The origin of the output is moved to
+    /// the center of mass. Returns `None` if the volume is zero.
+    pub fn from_mesh(mesh: &TriangleMesh<T>, origin: Isometry3<T>, density: T) -> Option<Self> {
+        let mass = mesh.volume() * density;
+        let inertia = mesh.inertia(mass)?;
+        let com = mesh.center_of_mass()?;
+        let origin = Isometry3::from_parts((origin * com).coords.into(), origin.rotation);
+        Some(Self::new(origin, mass, inertia))
+    }
+    /// Merge the inertials which are defined in the same frame into one
+    ///
+    /// The origin of the output is the center of mass without rotation, and
+    /// `inertia` is around it. Returns `None` if the total mass is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let mut i0 = Inertial::from_mass(1.0);
+    /// i0.set_origin(Isometry3::translation(-1.0, 0.0, 0.0));
+    /// let mut i1 = Inertial::from_mass(1.0);
+    /// i1.set_origin(Isometry3::translation(1.0, 0.0, 0.0));
+    /// let merged = Inertial::merge(&[i0, i1]).unwrap();
+    /// assert_eq!(merged.mass, 2.0);
+    /// assert_eq!(merged.origin().translation.vector.x, 0.0);
+    /// assert_eq!(merged.inertia[(0, 0)], 0.0);
+    /// assert_eq!(merged.inertia[(1, 1)], 2.0);
+    /// ```
+    pub fn merge(inertials: &[Inertial<T>]) -> Option<Self> {
+        let mass = inertials.iter().fold(T::zero(), |sum, i| sum + i.mass);
+        if mass <= T::zero() {
+            return None;
+        }
+        let com = inertials.iter().fold(Vector3::zeros(), |sum, i| {
+            sum + i.origin.translation.vector * i.mass
+        }) / mass;
+        let inertia = inertials
+            .iter()
+            .fold(Matrix3::zeros(), |sum, i| sum + i.inertia_at(&com));
+        Some(Self::new(
+            Isometry3::translation(com.x, com.y, com.z),
+            mass,
+            inertia,
+        ))
+    }
+    /// Inertia tensor around `point` in the frame where `origin` is defined
+    ///
+    /// `inertia` is rotated by `origin` and moved by the parallel axis theorem.
+    pub fn inertia_at(&self, point: &Vector3<T>) -> Matrix3<T> {
+        let rot = self.origin.rotation.to_rotation_matrix();
+        let d = self.origin.translation.vector - point;
+        rot * self.inertia * rot.transpose()
+            + (Matrix3::from_diagonal_element(d.norm_squared()) - d * d.transpose()) * self.mass
+    }
     pub fn new(origin: Isometry3<T>, mass: T, inertia: Matrix3<T>) -> Self {
         Self {
             origin,
@@ -108,6 +263,8 @@ pub struct Visual<T: Real> {
     origin: Isometry3<T>,
     pub geometry: Geometry<T>,
     pub material: Material<T>,
+    /// Loaded mesh of `Geometry::Mesh` with the scale applied, see `Chain::load_meshes()`
+    pub mesh: Option<Arc<TriangleMesh<T>>>,
     world_transform_cache: Option<Isometry3<T>>,
 }
 
@@ -126,6 +283,7 @@ where
             origin,
             geometry,
             material,
+            mesh: None,
             world_transform_cache: None,
         }
     }
@@ -152,6 +310,8 @@ pub struct Collision<T: Real> {
     pub name: String,
     origin: Isometry3<T>,
     pub geometry: Geometry<T>,
+    /// Loaded mesh of `Geometry::Mesh` with the scale applied, see `Chain::load_meshes()`
+    pub mesh: Option<Arc<TriangleMesh<T>>>,
     world_transform_cache: Option<Isometry3<T>>,
 }
 
@@ -164,6 +324,7 @@ where
             name,
             origin,
             geometry,
+            mesh: None,
             world_transform_cache: None,
         }
     }
@@ -193,6 +354,55 @@ pub struct Link<T: Real> {
     pub collisions: Vec<Collision<T>>,
 }
 
+impl<T> Link<T>
+where
+    T: Real,
+{
+    /// Estimate the inertial from the shapes of the link
+    ///
+    /// Collision shapes are used if any, otherwise visual shapes are used.
+    /// `Mesh` is used only if it is loaded by `Chain::load_meshes()`.
+    /// Returns `None` if there are no shapes, or any of the meshes is not loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let link = LinkBuilder::<f64>::new()
+    ///     .add_collision(Collision::new(
+    ///         "ball".to_owned(),
+    ///         Isometry3::translation(0.0, 0.0, 0.5),
+    ///         Geometry::Sphere { radius: 0.1 }))
+    ///     .finalize();
+    /// let inertial = link.estimate_inertial(1000.0).unwrap();
+    /// assert!((inertial.mass - 4.18879).abs() < 1e-4);
+    /// assert_eq!(inertial.origin().translation.vector.z, 0.5);
+    /// ```
+    pub fn estimate_inertial(&self, density: T) -> Option<Inertial<T>> {
+        let shapes = if self.collisions.is_empty() {
+            self.visuals
+                .iter()
+                .map(|v| (v.origin, &v.geometry, &v.mesh))
+                .collect::<Vec<_>>()
+        } else {
+            self.collisions
+                .iter()
+                .map(|c| (c.origin, &c.geometry, &c.mesh))
+                .collect::<Vec<_>>()
+        };
+        let inertials = shapes
+            .into_iter()
+            .map(|(origin, geometry, mesh)| match *mesh {
+                Some(ref mesh) => Inertial::from_mesh(mesh, origin, density),
+                None => Inertial::from_geometry(geometry, origin, density),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Inertial::merge(&inertials)
+    }
+}
+
 impl<T> Default for Link<T>
 where
     T: Real,
@@ -200,7 +410,7 @@ where
     fn default() -> Self {
         Self {
             name: "".to_owned(),
-            inertial: Inertial::new(Isometry3::identity(), T::zero(), Matrix3::identity()),
+            inertial: Inertial::from_mass(T::zero()),
             visuals: Vec::new(),
             collisions: Vec::new(),
         }
@@ -224,7 +434,7 @@ where
     pub fn new() -> Self {
         Self {
             name: "".to_owned(),
-            inertial: Inertial::new(Isometry3::identity(), T::zero(), Matrix3::identity()),
+            inertial: Inertial::from_mass(T::zero()),
             visuals: Vec::new(),
             collisions: Vec::new(),
         }
@@ -0,0 +1,619 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Triangle mesh loaded from STL (binary / ASCII) or Wavefront OBJ
+//!
+//! `Chain::load_meshes()` loads the meshes of `Geometry::Mesh` into `Visual::mesh` and
+//! `Collision::mesh`. The loaded meshes are used by `Link::estimate_inertial()`.
+//!
+//! # Examples
+//!
+//! ```
+//! use k::mesh::TriangleMesh;
+//!
+//! let tetrahedron = TriangleMesh::<f64>::from_obj_str(
+//!     "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 3 2\nf 1 2 4\nf 1 4 3\nf 2 3 4\n",
+//! )
+//! .unwrap();
+//! assert!((tetrahedron.volume() - 1.0 / 6.0).abs() < 1e-10);
+//! assert_eq!(tetrahedron.aabb().unwrap().max.x, 1.0);
+//! ```
+use na::{self, Isometry3, Matrix3, Point3, Real, Vector3};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chain::*;
+use errors::*;
+use link::*;
+use resource::ResourceResolver;
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aabb<T: Real> {
+    pub min: Point3<T>,
+    pub max: Point3<T>,
+}
+
+impl<T> Aabb<T>
+where
+    T: Real,
+{
+    /// Smallest box which contains all the points, `None` if empty
+    pub fn from_points<'a, I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Point3<T>>,
+    {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(
+            Self {
+                min: *first,
+                max: *first,
+            },
+            |aabb, p| Self {
+                min: Point3::from(aabb.min.coords.zip_map(&p.coords, |a, b| a.min(b))),
+                max: Point3::from(aabb.max.coords.zip_map(&p.coords, |a, b| a.max(b))),
+            },
+        ))
+    }
+    pub fn center(&self) -> Point3<T> {
+        na::center(&self.min, &self.max)
+    }
+    pub fn half_extents(&self) -> Vector3<T> {
+        (self.max - self.min) * na::convert::<f64, T>(0.5)
+    }
+    /// Bounding box of this box moved by `transform`
+    pub fn transformed(&self, transform: &Isometry3<T>) -> Self {
+        let center = transform * self.center();
+        let rotation = transform.rotation.to_rotation_matrix();
+        let half = rotation.matrix().abs() * self.half_extents();
+        Self {
+            min: center - half,
+            max: center + half,
+        }
+    }
+    pub fn contains(&self, point: &Point3<T>) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+}
+
+/// Sphere which contains all the vertices
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundingSphere<T: Real> {
+    pub center: Point3<T>,
+    pub radius: T,
+}
+
+/// Triangle mesh
+///
+/// Volume and inertia are valid only for closed meshes. The direction of
+/// the faces may be inward or outward, but must be consistent.
+#[derive(Debug, Clone)]
+pub struct TriangleMesh<T: Real> {
+    pub vertices: Vec<Point3<T>>,
+    /// Indices of `vertices`
+    pub triangles: Vec<[usize; 3]>,
+}
+
+/// Collect the vertices without duplication
+#[derive(Default)]
+struct VertexSet {
+    vertices: Vec<[f64; 3]>,
+    indices: HashMap<[u64; 3], usize>,
+}
+
+impl VertexSet {
+    fn insert(&mut self, v: [f64; 3]) -> usize {
+        let vertices = &mut self.vertices;
+        *self
+            .indices
+            .entry([v[0].to_bits(), v[1].to_bits(), v[2].to_bits()])
+            .or_insert_with(|| {
+                vertices.push(v);
+                vertices.len() - 1
+            })
+    }
+    fn into_mesh<T: Real>(self, triangles: Vec<[usize; 3]>) -> TriangleMesh<T> {
+        TriangleMesh::new(
+            self.vertices
+                .into_iter()
+                .map(|v| Point3::new(na::convert(v[0]), na::convert(v[1]), na::convert(v[2])))
+                .collect(),
+            triangles,
+        )
+    }
+}
+
+fn parse_error(error: String) -> MeshError {
+    MeshError::ParseError { error }
+}
+
+fn parse_f64(text: Option<&str>) -> Result<f64, MeshError> {
+    let text = text.ok_or_else(|| parse_error("number is missing".to_owned()))?;
+    text.parse()
+        .map_err(|_| parse_error(format!("{:?} is not a number", text)))
+}
+
+impl<T> TriangleMesh<T>
+where
+    T: Real,
+{
+    pub fn new(vertices: Vec<Point3<T>>, triangles: Vec<[usize; 3]>) -> Self {
+        Self {
+            vertices,
+            triangles,
+        }
+    }
+    /// Load STL, binary or ASCII
+    pub fn from_stl_bytes(bytes: &[u8]) -> Result<Self, MeshError> {
+        // ASCII STL may start with "solid" too, so check the size. Some exporters
+        // write extra bytes after the facets, so the file may be longer. The count of
+        // ASCII STL is made of printable characters and is too large for the size.
+        let num_facets = if bytes.len() >= 84 {
+            let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]);
+            if (bytes.len() as u64 - 84) / 50 >= u64::from(count) {
+                Some(count as usize)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        let mut set = VertexSet::default();
+        let mut triangles = Vec::new();
+        if let Some(num_facets) = num_facets {
+            let read = |offset: usize| {
+                let b = &bytes[offset..offset + 4];
+                f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            };
+            for facet in 0..num_facets {
+                // skip the normal
+                let offset = 84 + facet * 50 + 12;
+                let mut triangle = [0; 3];
+                for (i, index) in triangle.iter_mut().enumerate() {
+                    let v = offset + i * 12;
+                    *index = set.insert([read(v), read(v + 4), read(v + 8)]);
+                }
+                triangles.push(triangle);
+            }
+        } else {
+            let text = ::std::str::from_utf8(bytes)
+                .map_err(|_| parse_error("neither binary nor ASCII STL".to_owned()))?;
+            let mut words = text.split_whitespace();
+            let mut facet = Vec::new();
+            while let Some(word) = words.next() {
+                match word {
+                    "vertex" => {
+                        let v = [
+                            parse_f64(words.next())?,
+                            parse_f64(words.next())?,
+                            parse_f64(words.next())?,
+                        ];
+                        facet.push(set.insert(v));
+                    }
+                    "endfacet" => {
+                        if facet.len() != 3 {
+                            return Err(parse_error(format!("facet has {} vertices", facet.len())));
+                        }
+                        triangles.push([facet[0], facet[1], facet[2]]);
+                        facet.clear();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(set.into_mesh(triangles))
+    }
+    /// Load Wavefront OBJ, only `v` and `f` are used
+    ///
+    /// Polygons are split into triangles as fans.
+    pub fn from_obj_str(text: &str) -> Result<Self, MeshError> {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("v") => vertices.push([
+                    parse_f64(words.next())?,
+                    parse_f64(words.next())?,
+                    parse_f64(words.next())?,
+                ]),
+                Some("f") => {
+                    let face = words
+                        .map(|word| {
+                            // v/vt/vn, negative index is relative to the end
+                            let index = word.split('/').next().unwrap_or("");
+                            let index = index
+                                .parse::<i64>()
+                                .map_err(|_| parse_error(format!("invalid face {:?}", line)))?;
+                            let index = if index < 0 {
+                                vertices.len() as i64 + index
+                            } else {
+                                index - 1
+                            };
+                            if index < 0 || index as usize >= vertices.len() {
+                                return Err(parse_error(format!("invalid face {:?}", line)));
+                            }
+                            Ok(index as usize)
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if face.len() < 3 {
+                        return Err(parse_error(format!("invalid face {:?}", line)));
+                    }
+                    for i in 1..face.len() - 1 {
+                        triangles.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let set = VertexSet {
+            vertices,
+            indices: HashMap::new(),
+        };
+        Ok(set.into_mesh(triangles))
+    }
+    /// Load `.stl` or `.obj` file (case insensitive)
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, MeshError> {
+        let path = path.as_ref();
+        let filename = path.display().to_string();
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let read_error = |e: ::std::io::Error| MeshError::ReadError {
+            filename: filename.clone(),
+            error: e.to_string(),
+        };
+        match extension.as_str() {
+            "stl" => Self::from_stl_bytes(&fs::read(path).map_err(read_error)?),
+            "obj" => Self::from_obj_str(&fs::read_to_string(path).map_err(read_error)?),
+            _ => Err(MeshError::UnsupportedFormatError { filename }),
+        }
+    }
+    /// Mesh scaled along each axis, like the `scale` of URDF
+    pub fn scaled(&self, scale: &Vector3<T>) -> Self {
+        Self::new(
+            self.vertices
+                .iter()
+                .map(|v| Point3::from(v.coords.component_mul(scale)))
+                .collect(),
+            self.triangles.clone(),
+        )
+    }
+    /// Vertices of each triangle
+    pub fn triangle_vertices<'a>(&'a self) -> impl Iterator<Item = [&'a Point3<T>; 3]> + 'a {
+        self.triangles.iter().map(move |t| {
+            [
+                &self.vertices[t[0]],
+                &self.vertices[t[1]],
+                &self.vertices[t[2]],
+            ]
+        })
+    }
+    /// Bounding box of the vertices, `None` if there are no vertices
+    pub fn aabb(&self) -> Option<Aabb<T>> {
+        Aabb::from_points(&self.vertices)
+    }
+    /// Bounding sphere around the center of the AABB, `None` if there are no vertices
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere<T>> {
+        let center = self.aabb()?.center();
+        let radius = self
+            .vertices
+            .iter()
+            .fold(T::zero(), |r, v| r.max(na::distance(&center, v)));
+        Some(BoundingSphere { center, radius })
+    }
+    /// Volume, first moment and second moment (`∫xxᵀdV`) with unit density
+    fn moments(&self) -> (T, Vector3<T>, Matrix3<T>) {
+        let mut volume = T::zero();
+        let mut first = Vector3::zeros();
+        let mut second = Matrix3::zeros();
+        // sum of the tetrahedra between the origin and the triangles
+        for [a, b, c] in self.triangle_vertices() {
+            let (a, b, c) = (a.coords, b.coords, c.coords);
+            let v = a.dot(&b.cross(&c)) / na::convert(6.0);
+            let sum = a + b + c;
+            volume += v;
+            first += sum * (v / na::convert(4.0));
+            second +=
+                (a * a.transpose() + b * b.transpose() + c * c.transpose() + sum * sum.transpose())
+                    * (v / na::convert(20.0));
+        }
+        // inward faces
+        if volume < T::zero() {
+            (-volume, -first, -second)
+        } else {
+            (volume, first, second)
+        }
+    }
+    /// Volume of the closed mesh
+    pub fn volume(&self) -> T {
+        self.moments().0
+    }
+    /// Center of mass of the solid with uniform density, `None` if the volume is zero
+    pub fn center_of_mass(&self) -> Option<Point3<T>> {
+        let (volume, first, _) = self.moments();
+        if volume <= T::zero() {
+            return None;
+        }
+        Some(Point3::from(first / volume))
+    }
+    /// Inertia tensor of the solid with `mass` around the center of mass
+    ///
+    /// Returns `None` if the volume is zero.
+    pub fn inertia(&self, mass: T) -> Option<Matrix3<T>> {
+        let (volume, first, second) = self.moments();
+        if volume <= T::zero() {
+            return None;
+        }
+        let density = mass / volume;
+        let com = first / volume;
+        let around_origin = Matrix3::from_diagonal_element(second.trace()) - second;
+        let shift = Matrix3::from_diagonal_element(com.norm_squared()) - com * com.transpose();
+        Some(around_origin * density - shift * mass)
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: Real,
+{
+    /// Load the meshes of visuals and collisions into `mesh`
+    ///
+    /// The filenames are resolved by `resolver`, and the scales are applied.
+    /// The same file is loaded only once, and the geometries with the same file and
+    /// scale share the mesh. Returns the errors of the meshes which are not loaded.
+    pub fn load_meshes(&self, resolver: &ResourceResolver) -> Vec<MeshError> {
+        let mut cache = HashMap::<PathBuf, Option<TriangleMesh<T>>>::new();
+        // scale is not hashable, but a few scales are used for a file
+        let mut scaled_cache = HashMap::<PathBuf, Vec<(Vector3<T>, Arc<TriangleMesh<T>>)>>::new();
+        let mut errors = Vec::new();
+        let mut load = |geometry: &Geometry<T>| -> Option<Arc<TriangleMesh<T>>> {
+            let (filename, scale) = match *geometry {
+                Geometry::Mesh {
+                    ref filename,
+                    ref scale,
+                } => (filename, scale),
+                _ => return None,
+            };
+            let path = match resolver.resolve(filename) {
+                Some(path) => path,
+                None => {
+                    errors.push(MeshError::ReadError {
+                        filename: filename.clone(),
+                        error: "not found".to_owned(),
+                    });
+                    return None;
+                }
+            };
+            let scaled_meshes = scaled_cache.entry(path.clone()).or_default();
+            if let Some((_, mesh)) = scaled_meshes.iter().find(|&&(s, _)| s == *scale) {
+                return Some(mesh.clone());
+            }
+            let mesh =
+                cache
+                    .entry(path.clone())
+                    .or_insert_with(|| match TriangleMesh::from_file(&path) {
+                        Ok(mesh) => Some(mesh),
+                        Err(error) => {
+                            errors.push(error);
+                            None
+                        }
+                    });
+            let mesh = Arc::new(mesh.as_ref()?.scaled(scale));
+            scaled_meshes.push((*scale, mesh.clone()));
+            Some(mesh)
+        };
+        for node in self.iter() {
+            if let Some(ref mut link) = node.0.borrow_mut().link {
+                for visual in &mut link.visuals {
+                    if let Some(mesh) = load(&visual.geometry) {
+                        visual.mesh = Some(mesh);
+                    }
+                }
+                for collision in &mut link.collisions {
+                    if let Some(mesh) = load(&collision.geometry) {
+                        collision.mesh = Some(mesh);
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+fn cube_stl_ascii() -> String {
+    // unit cube at [0, 1]^3 with outward faces
+    let corners = |i: usize| [(i & 1) as f64, ((i >> 1) & 1) as f64, ((i >> 2) & 1) as f64];
+    let faces = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+    let mut stl = "solid cube\n".to_owned();
+    for face in &faces {
+        for triangle in &[[face[0], face[1], face[2]], [face[0], face[2], face[3]]] {
+            stl.push_str("facet normal 0 0 0\nouter loop\n");
+            for &i in triangle {
+                let c = corners(i);
+                stl.push_str(&format!("vertex {} {} {}\n", c[0], c[1], c[2]));
+            }
+            stl.push_str("endloop\nendfacet\n");
+        }
+    }
+    stl + "endsolid cube\n"
+}
+
+#[test]
+fn test_stl() {
+    let ascii = TriangleMesh::<f64>::from_stl_bytes(cube_stl_ascii().as_bytes()).unwrap();
+    assert_eq!(ascii.vertices.len(), 8);
+    assert_eq!(ascii.triangles.len(), 12);
+    assert!((ascii.volume() - 1.0).abs() < 1e-10);
+    let com = ascii.center_of_mass().unwrap();
+    assert!((com - Point3::new(0.5, 0.5, 0.5)).norm() < 1e-10);
+    // solid cube: m (a^2 + a^2) / 12
+    let inertia = ascii.inertia(6.0).unwrap();
+    assert!((inertia - Matrix3::identity()).norm() < 1e-10);
+
+    // binary STL with "solid" in the header and reversed faces
+    let mut binary = b"solid binary".to_vec();
+    binary.resize(80, 0);
+    binary.extend_from_slice(&(ascii.triangles.len() as u32).to_le_bytes());
+    for [a, b, c] in ascii.triangle_vertices() {
+        binary.extend_from_slice(&[0; 12]);
+        for v in &[a, c, b] {
+            for i in 0..3 {
+                binary.extend_from_slice(&(v[i] as f32).to_le_bytes());
+            }
+        }
+        binary.extend_from_slice(&[0; 2]);
+    }
+    let mesh = TriangleMesh::<f32>::from_stl_bytes(&binary).unwrap();
+    assert_eq!(mesh.vertices.len(), 8);
+    // trailing bytes after the facets are ignored
+    let mut padded = binary.clone();
+    padded.extend_from_slice(b"\n\0\0");
+    let padded = TriangleMesh::<f32>::from_stl_bytes(&padded).unwrap();
+    assert_eq!(padded.triangles, mesh.triangles);
+    assert!((mesh.volume() - 1.0).abs() < 1e-5);
+    let scaled = mesh.scaled(&Vector3::new(2.0, 3.0, 4.0));
+    assert!((scaled.volume() - 24.0).abs() < 1e-4);
+    let aabb = scaled.aabb().unwrap();
+    assert_eq!(aabb.max, Point3::new(2.0, 3.0, 4.0));
+    assert_eq!(aabb.center(), Point3::new(1.0, 1.5, 2.0));
+    let sphere = scaled.bounding_sphere().unwrap();
+    assert_eq!(sphere.center, aabb.center());
+    assert!((sphere.radius - 29.0f32.sqrt() / 2.0).abs() < 1e-5);
+
+    assert!(TriangleMesh::<f64>::from_stl_bytes(b"solid broken\nfacet\nvertex 0 0\n").is_err());
+}
+
+#[test]
+fn test_obj() {
+    let mesh = TriangleMesh::<f64>::from_obj_str(
+        "# square pyramid\n\
+         o pyramid\n\
+         v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\nv 0 0 3\n\
+         vn 0 0 1\n\
+         f 4//1 3//1 2//1 1//1\n\
+         f 1/1 2/1 5/1\nf 2 3 5\nf 3 4 5\nf -2 -5 -1\n",
+    )
+    .unwrap();
+    assert_eq!(mesh.triangles.len(), 6);
+    assert!((mesh.volume() - 4.0).abs() < 1e-10);
+    let com = mesh.center_of_mass().unwrap();
+    assert!((com.coords - Vector3::new(0.0, 0.0, 0.75)).norm() < 1e-10);
+    // square pyramid with base 2a and height h: Izz = m (2a)^2 / 10
+    let inertia = mesh.inertia(5.0).unwrap();
+    assert!((inertia[(2, 2)] - 2.0).abs() < 1e-10);
+    assert!(inertia[(0, 1)].abs() < 1e-10);
+    assert!(TriangleMesh::<f64>::from_obj_str("v 0 0 0\nf 1 2 3\n").is_err());
+}
+
+#[test]
+fn test_load_meshes() {
+    use link::*;
+    use node::*;
+    use std::env;
+    let dir = env::temp_dir().join("k_test_load_meshes");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("cube.stl"), cube_stl_ascii()).unwrap();
+    let mesh = |filename: &str| Geometry::Mesh {
+        filename: filename.to_owned(),
+        scale: Vector3::new(0.1, 0.2, 0.5),
+    };
+    let node = JointBuilder::<f64>::new().name("j").into_node();
+    node.set_link(Some(
+        LinkBuilder::new()
+            .add_visual(Visual::new(
+                "v".to_owned(),
+                Isometry3::identity(),
+                mesh("cube.stl"),
+                Material::default(),
+            ))
+            .add_collision(Collision::new(
+                "c".to_owned(),
+                Isometry3::translation(0.0, 0.0, 1.0),
+                mesh("package://k_test/cube.stl"),
+            ))
+            .add_collision(Collision::new(
+                "same".to_owned(),
+                Isometry3::identity(),
+                mesh("cube.stl"),
+            ))
+            .add_collision(Collision::new(
+                "large".to_owned(),
+                Isometry3::identity(),
+                Geometry::Mesh {
+                    filename: "cube.stl".to_owned(),
+                    scale: Vector3::new(1.0, 1.0, 1.0),
+                },
+            ))
+            .add_collision(Collision::new(
+                "missing".to_owned(),
+                Isometry3::identity(),
+                mesh("missing.obj"),
+            ))
+            .finalize(),
+    ));
+    let chain = Chain::from_root(node.clone());
+    let link = node.link();
+    assert!(link.as_ref().unwrap().estimate_inertial(1000.0).is_none());
+    drop(link);
+    let errors = chain.load_meshes(
+        &ResourceResolver::new()
+            .package("k_test", &dir)
+            .base_dir(&dir),
+    );
+    assert_eq!(errors.len(), 1);
+    {
+        let link = node.link();
+        let link = link.as_ref().unwrap();
+        let visual_mesh = link.visuals[0].mesh.as_ref().unwrap();
+        assert_eq!(visual_mesh.aabb().unwrap().max, Point3::new(0.1, 0.2, 0.5));
+        assert!(link.collisions[0].mesh.is_some());
+        // the same file and scale share the mesh
+        let same_mesh = link.collisions[1].mesh.as_ref().unwrap();
+        assert!(Arc::ptr_eq(visual_mesh, same_mesh));
+        let large_mesh = link.collisions[2].mesh.as_ref().unwrap();
+        assert!(!Arc::ptr_eq(visual_mesh, large_mesh));
+        assert_eq!(large_mesh.aabb().unwrap().max, Point3::new(1.0, 1.0, 1.0));
+        assert!(link.collisions[3].mesh.is_none());
+        // the missing mesh prevents the estimation
+        assert!(link.estimate_inertial(1000.0).is_none());
+    }
+    node.0
+        .borrow_mut()
+        .link
+        .as_mut()
+        .unwrap()
+        .collisions
+        .truncate(1);
+    assert_eq!(chain.fill_missing_inertials(1000.0), 1);
+    let link = node.link();
+    let inertial = &link.as_ref().unwrap().inertial;
+    assert!((inertial.mass - 10.0).abs() < 1e-10);
+    let com = inertial.origin().translation.vector;
+    assert!((com - Vector3::new(0.05, 0.1, 1.25)).norm() < 1e-10);
+    assert!((inertial.inertia[(2, 2)] - 10.0 * (0.01 + 0.04) / 12.0).abs() < 1e-10);
+}
@@ -0,0 +1,1006 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Load [MuJoCo MJCF](http://www.mujoco.org/book/XMLreference.html) model and create `k::Chain`
+//!
+//! Each `<body>` becomes a link. A body without joints is connected by a fixed joint,
+//! a body with joints gets one node per joint (only the last one has the link).
+//! `hinge`, `slide`, `ball` and `free` become `Rotational`, `Linear`, `Spherical` and
+//! `Floating`. Geoms are loaded as visuals, and also as collisions unless both
+//! `contype` and `conaffinity` are zero. Joint equalities with linear `polycoef` become
+//! mimic joints.
+//!
+//! `<default>` classes are applied to the elements with `class`, or `childclass` of
+//! the ancestor body. The nested classes inherit the attributes of the parent class.
+//!
+//! Capsules are approximated by cylinders. Those and the elements which can not be
+//! represented (actuators, sensors, sites, ...) are listed in `Imported::unsupported`.
+//!
+//! # Examples
+//!
+//! ```
+//! let imported = k::mjcf::from_str::<f64>(
+//!     r#"<mujoco model="arm">
+//!     <worldbody>
+//!       <body name="upper_arm" pos="0 0 1">
+//!         <joint name="shoulder" type="hinge" axis="0 1 0" range="-90 90"/>
+//!         <geom type="capsule" size="0.05 0.2"/>
+//!       </body>
+//!     </worldbody>
+//!     <actuator><motor joint="shoulder"/></actuator>
+//!     </mujoco>"#,
+//! )
+//! .unwrap();
+//! let shoulder = imported.chain.find("shoulder").unwrap();
+//! let limits = shoulder.joint().limits.unwrap();
+//! assert!((limits.max - std::f64::consts::FRAC_PI_2).abs() < 1e-10);
+//! assert_eq!(imported.unsupported.len(), 2);
+//! ```
+use na::{self, Isometry3, Matrix3, Real, Rotation3, Translation3, Unit, UnitQuaternion, Vector3};
+use std::collections::HashMap;
+use std::path::Path;
+use xml::{Element, Xml};
+
+use chain::*;
+use errors::*;
+use import::*;
+use joint::*;
+use link::*;
+use node::*;
+use urdf::ROOT_JOINT_NAME;
+
+/// Name of the link of `<worldbody>`
+pub const WORLD_LINK_NAME: &str = "world";
+
+/// Name of the top level `<default>` class
+const MAIN_CLASS: &str = "main";
+
+/// Attributes which specify the orientation, only one of them is used
+const ORIENTATIONS: [&str; 5] = ["quat", "axisangle", "euler", "xyaxes", "zaxis"];
+
+/// Default attributes of the elements (`joint`, `geom`, ...) in a class
+type DefaultClass = HashMap<String, HashMap<String, String>>;
+
+/// Load `<default>` and the nested classes, they inherit the attributes of `parent`
+fn load_defaults(
+    element: &Element,
+    parent: &DefaultClass,
+    classes: &mut HashMap<String, DefaultClass>,
+) -> Result<(), ImportError> {
+    let name = element.get_attribute("class", None).unwrap_or(MAIN_CLASS);
+    let mut class = parent.clone();
+    for child in children(element).filter(|child| child.name != "default") {
+        let attributes = class.entry(child.name.clone()).or_default();
+        if child
+            .attributes
+            .keys()
+            .any(|(a, _)| ORIENTATIONS.contains(&a.as_str()))
+        {
+            attributes.retain(|a, _| !ORIENTATIONS.contains(&a.as_str()));
+        }
+        for ((attribute, _), value) in &child.attributes {
+            attributes.insert(attribute.clone(), value.clone());
+        }
+    }
+    for child in children(element).filter(|child| child.name == "default") {
+        load_defaults(child, &class, classes)?;
+    }
+    if classes.insert(name.to_owned(), class).is_some() {
+        return Err(ImportError::InvalidModelError {
+            error: format!("default class {} is defined twice", name),
+        });
+    }
+    Ok(())
+}
+
+/// Fill the attributes which are not set in the element and its descendants
+///
+/// The class of the element is `class`, or `child_class` inherited from the ancestors.
+/// `kind` is the name of the element in the class, `equality` for all the equalities.
+fn apply_defaults(
+    element: &mut Element,
+    kind: &str,
+    classes: &HashMap<String, DefaultClass>,
+    child_class: &str,
+) -> Result<(), ImportError> {
+    let class_name = element
+        .get_attribute("class", None)
+        .unwrap_or(child_class)
+        .to_owned();
+    let class = classes
+        .get(&class_name)
+        .ok_or_else(|| ImportError::InvalidModelError {
+            error: format!(
+                "{}: default class {} is not found",
+                element.name, class_name
+            ),
+        })?;
+    if let Some(defaults) = class.get(kind) {
+        let has_orientation = element
+            .attributes
+            .keys()
+            .any(|(a, _)| ORIENTATIONS.contains(&a.as_str()));
+        for (attribute, value) in defaults {
+            if !(has_orientation && ORIENTATIONS.contains(&attribute.as_str())) {
+                element
+                    .attributes
+                    .entry((attribute.clone(), None))
+                    .or_insert_with(|| value.clone());
+            }
+        }
+    }
+    let child_class = element
+        .get_attribute("childclass", None)
+        .unwrap_or(child_class)
+        .to_owned();
+    let is_equality = element.name == "equality";
+    for child in &mut element.children {
+        if let Xml::ElementNode(ref mut child) = *child {
+            if child.name == "default" {
+                continue;
+            }
+            let kind = if is_equality {
+                "equality".to_owned()
+            } else {
+                child.name.clone()
+            };
+            apply_defaults(child, &kind, classes, &child_class)?;
+        }
+    }
+    Ok(())
+}
+
+struct Loader {
+    /// true if the angles are in degrees (default)
+    degree: bool,
+    euler_sequence: String,
+    mesh_dir: String,
+    /// name -> (file, scale)
+    meshes: HashMap<String, (String, Vector3<f64>)>,
+    reporter: Reporter,
+}
+
+fn attribute_floats(
+    element: &Element,
+    name: &str,
+    size: usize,
+    path: &str,
+) -> Result<Option<Vec<f64>>, ImportError> {
+    match element.get_attribute(name, None) {
+        Some(value) => Ok(Some(parse_floats_n(
+            value,
+            size,
+            &format!("{}@{}", path, name),
+        )?)),
+        None => Ok(None),
+    }
+}
+
+fn rotation_between_z(direction: &Vector3<f64>) -> UnitQuaternion<f64> {
+    UnitQuaternion::rotation_between(&Vector3::z(), direction).unwrap_or_else(|| {
+        // opposite direction
+        UnitQuaternion::from_axis_angle(&Vector3::x_axis(), ::std::f64::consts::PI)
+    })
+}
+
+impl Loader {
+    fn angle(&self, value: f64) -> f64 {
+        if self.degree {
+            value.to_radians()
+        } else {
+            value
+        }
+    }
+
+    /// Position and orientation of the element
+    fn frame(&self, element: &Element, path: &str) -> Result<Isometry3<f64>, ImportError> {
+        let position = attribute_floats(element, "pos", 3, path)?.unwrap_or_else(|| vec![0.0; 3]);
+        let rotation = if let Some(q) = attribute_floats(element, "quat", 4, path)? {
+            UnitQuaternion::from_quaternion(na::Quaternion::new(q[0], q[1], q[2], q[3]))
+        } else if let Some(a) = attribute_floats(element, "axisangle", 4, path)? {
+            UnitQuaternion::from_axis_angle(&Unit::new_normalize(vector3(&a)), self.angle(a[3]))
+        } else if let Some(e) = attribute_floats(element, "euler", 3, path)? {
+            let mut rotation = UnitQuaternion::identity();
+            for (c, angle) in self.euler_sequence.chars().zip(e.iter()) {
+                let axis = match c.to_ascii_lowercase() {
+                    'x' => Vector3::x_axis(),
+                    'y' => Vector3::y_axis(),
+                    _ => Vector3::z_axis(),
+                };
+                let r = UnitQuaternion::from_axis_angle(&axis, self.angle(*angle));
+                // lower case rotates around the moving axes
+                rotation = if c.is_lowercase() {
+                    rotation * r
+                } else {
+                    r * rotation
+                };
+            }
+            rotation
+        } else if let Some(a) = attribute_floats(element, "xyaxes", 6, path)? {
+            let x = vector3(&a).normalize();
+            let y = (vector3(&a[3..]) - x * x.dot(&vector3(&a[3..]))).normalize();
+            UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(
+                Matrix3::from_columns(&[x, y, x.cross(&y)]),
+            ))
+        } else if let Some(z) = attribute_floats(element, "zaxis", 3, path)? {
+            rotation_between_z(&vector3(&z))
+        } else {
+            UnitQuaternion::identity()
+        };
+        Ok(Isometry3::from_parts(
+            Translation3::from(vector3(&position)),
+            rotation,
+        ))
+    }
+
+    fn geom(&mut self, element: &Element, path: &str) -> Result<Option<Shape>, ImportError> {
+        let geom_type = element.get_attribute("type", None).unwrap_or("sphere");
+        let size = match element.get_attribute("size", None) {
+            Some(size) => parse_floats(size, &format!("{}@size", path))?,
+            None => Vec::new(),
+        };
+        let size_at = |i: usize| -> Result<f64, ImportError> {
+            size.get(i)
+                .cloned()
+                .ok_or_else(|| ImportError::InvalidModelError {
+                    error: format!("{}: size[{}] is required for {}", path, i, geom_type),
+                })
+        };
+        let mut origin = self.frame(element, path)?;
+        // fromto overrides the frame and the length
+        let mut half_length = None;
+        if let Some(ft) = attribute_floats(element, "fromto", 6, path)? {
+            let (from, to) = (vector3(&ft), vector3(&ft[3..]));
+            origin = Isometry3::from_parts(
+                Translation3::from((from + to) * 0.5),
+                rotation_between_z(&(to - from)),
+            );
+            half_length = Some((to - from).norm() * 0.5);
+        }
+        let geometry = match geom_type {
+            "sphere" => Geometry::Sphere {
+                radius: size_at(0)?,
+            },
+            "box" => Geometry::Box {
+                depth: size_at(0)? * 2.0,
+                width: size_at(1)? * 2.0,
+                height: size_at(2)? * 2.0,
+            },
+            "cylinder" => Geometry::Cylinder {
+                radius: size_at(0)?,
+                length: half_length.map_or_else(|| size_at(1), Ok)? * 2.0,
+            },
+            "capsule" => {
+                self.reporter
+                    .report(path.to_owned(), "capsule is approximated by cylinder");
+                let radius = size_at(0)?;
+                Geometry::Cylinder {
+                    radius,
+                    length: (half_length.map_or_else(|| size_at(1), Ok)? + radius) * 2.0,
+                }
+            }
+            "mesh" => {
+                let mesh_name = element.get_attribute("mesh", None).unwrap_or("");
+                match self.meshes.get(mesh_name) {
+                    Some((file, scale)) => Geometry::Mesh {
+                        filename: file.clone(),
+                        scale: *scale,
+                    },
+                    None => {
+                        self.reporter
+                            .report(path.to_owned(), &format!("mesh {} not found", mesh_name));
+                        return Ok(None);
+                    }
+                }
+            }
+            _ => {
+                self.reporter
+                    .report(path.to_owned(), &format!("{} geom is ignored", geom_type));
+                return Ok(None);
+            }
+        };
+        if element.get_attribute("material", None).is_some() {
+            self.reporter
+                .report(format!("{}@material", path), "material is ignored");
+        }
+        let color = attribute_floats(element, "rgba", 4, path)?.map(|c| [c[0], c[1], c[2], c[3]]);
+        Ok(Some(Shape {
+            name: element.get_attribute("name", None).unwrap_or("").to_owned(),
+            origin,
+            geometry,
+            color,
+        }))
+    }
+
+    fn inertial(&self, element: &Element, path: &str) -> Result<InertialF64, ImportError> {
+        let origin = self.frame(element, path)?;
+        let mass = attribute_floats(element, "mass", 1, path)?.map_or(0.0, |m| m[0]);
+        let inertia = if let Some(d) = attribute_floats(element, "diaginertia", 3, path)? {
+            Matrix3::from_diagonal(&vector3(&d))
+        } else if let Some(f) = attribute_floats(element, "fullinertia", 6, path)? {
+            Matrix3::new(f[0], f[3], f[4], f[3], f[1], f[5], f[4], f[5], f[2])
+        } else {
+            Matrix3::zeros()
+        };
+        Ok((origin, mass, inertia))
+    }
+
+    /// Load the link contents (geoms and inertial) of the body
+    fn link<T: Real>(
+        &mut self,
+        name: &str,
+        element: &Element,
+        path: &str,
+        to_node: &Isometry3<f64>,
+    ) -> Result<Link<T>, ImportError> {
+        let mut shapes = Vec::new();
+        let mut collision_flags = Vec::new();
+        for geom in element.get_children("geom", None) {
+            let geom_path = child_path(path, geom);
+            if let Some(shape) = self.geom(geom, &geom_path)? {
+                let is_collision = geom.get_attribute("contype", None) != Some("0")
+                    || geom.get_attribute("conaffinity", None) != Some("0");
+                collision_flags.push(is_collision);
+                shapes.push(shape);
+            }
+        }
+        let inertial = match element.get_child("inertial", None) {
+            Some(inertial) => Some(self.inertial(inertial, &format!("{}/inertial", path))?),
+            None => None,
+        };
+        let (collisions, visuals): (Vec<_>, Vec<_>) = shapes
+            .into_iter()
+            .zip(collision_flags)
+            .map(|(shape, is_collision)| {
+                let collision = if is_collision {
+                    Some(Shape {
+                        name: shape.name.clone(),
+                        origin: shape.origin,
+                        geometry: shape.geometry.clone(),
+                        color: None,
+                    })
+                } else {
+                    None
+                };
+                (collision, shape)
+            })
+            .unzip();
+        let collisions = collisions.into_iter().flatten().collect::<Vec<_>>();
+        Ok(build_link(name, to_node, inertial, &visuals, &collisions))
+    }
+
+    /// Create the nodes of the body and its children, returns the first node
+    fn body<T: Real>(
+        &mut self,
+        element: &Element,
+        path: &str,
+        index: usize,
+        parent_offset: &Isometry3<f64>,
+    ) -> Result<Node<T>, ImportError> {
+        self.reporter.report_unknown_children(
+            element,
+            path,
+            &["inertial", "joint", "freejoint", "geom", "body"],
+        );
+        let name = element
+            .get_attribute("name", None)
+            .map_or_else(|| format!("body{}", index), |n| n.to_owned());
+        let body_frame = parent_offset * self.frame(element, path)?;
+        let mut nodes = Vec::new();
+        // from the body frame to the frame of the last node
+        let mut position = Vector3::zeros();
+        let joints = element.children.iter().filter_map(|child| match *child {
+            ::xml::Xml::ElementNode(ref e) if e.name == "joint" || e.name == "freejoint" => Some(e),
+            _ => None,
+        });
+        for (i, joint) in joints.enumerate() {
+            let joint_path = child_path(path, joint);
+            let joint_name = joint
+                .get_attribute("name", None)
+                .map_or_else(|| format!("{}_joint{}", name, i), |n| n.to_owned());
+            let joint_type = if joint.name == "freejoint" {
+                "free"
+            } else {
+                joint.get_attribute("type", None).unwrap_or("hinge")
+            };
+            let joint_position = if joint_type == "free" {
+                Vector3::zeros()
+            } else {
+                attribute_floats(joint, "pos", 3, &joint_path)?
+                    .map_or_else(Vector3::zeros, |p| vector3(&p))
+            };
+            let axis = attribute_floats(joint, "axis", 3, &joint_path)?
+                .map_or_else(Vector3::z, |a| vector3(&a));
+            if axis.norm() == 0.0 {
+                return Err(ImportError::InvalidModelError {
+                    error: format!("{}: zero length axis", joint_path),
+                });
+            }
+            let axis = Unit::new_normalize(na::convert::<_, Vector3<T>>(axis));
+            let range = attribute_floats(joint, "range", 2, &joint_path)?;
+            let limited = match joint.get_attribute("limited", None) {
+                Some("true") => true,
+                Some("false") => false,
+                _ => range.is_some(),
+            };
+            let limits = |scale: &dyn Fn(f64) -> f64| match range {
+                Some(ref r) if limited => Some(Range::new(
+                    na::convert(scale(r[0])),
+                    na::convert(scale(r[1])),
+                )),
+                _ => None,
+            };
+            let (joint_type, limits) = match joint_type {
+                "hinge" => (JointType::Rotational { axis }, limits(&|v| self.angle(v))),
+                "slide" => (JointType::Linear { axis }, limits(&|v| v)),
+                "ball" => (JointType::Spherical, None),
+                "free" => (JointType::Floating, None),
+                _ => {
+                    return Err(ImportError::InvalidModelError {
+                        error: format!("{}: unknown joint type {}", joint_path, joint_type),
+                    })
+                }
+            };
+            let damping = attribute_floats(joint, "damping", 1, &joint_path)?.map_or(0.0, |v| v[0]);
+            let friction =
+                attribute_floats(joint, "frictionloss", 1, &joint_path)?.map_or(0.0, |v| v[0]);
+            let dynamics = if damping == 0.0 && friction == 0.0 {
+                None
+            } else {
+                Some(Dynamics::new(na::convert(damping), na::convert(friction)))
+            };
+            let origin = if i == 0 {
+                body_frame * translation(joint_position.as_slice())
+            } else {
+                translation((joint_position - position).as_slice())
+            };
+            position = joint_position;
+            let node = JointBuilder::<T>::new()
+                .name(&joint_name)
+                .joint_type(joint_type)
+                .limits(limits)
+                .dynamics(dynamics)
+                .origin(na::convert(origin))
+                .into_node();
+            if let Some(last) = nodes.last() {
+                node.set_parent(last);
+            }
+            nodes.push(node);
+        }
+        if nodes.is_empty() {
+            nodes.push(
+                JointBuilder::<T>::new()
+                    .name(&name)
+                    .origin(na::convert(body_frame))
+                    .into_node(),
+            );
+        }
+        let offset = translation((-position).as_slice());
+        let last = nodes.last().unwrap().clone();
+        last.set_link(Some(self.link(&name, element, path, &offset)?));
+        for (i, child) in element.get_children("body", None).enumerate() {
+            let child_node =
+                self.body::<T>(child, &child_path(path, child), index * 100 + i, &offset)?;
+            child_node.set_parent(&last);
+        }
+        Ok(nodes[0].clone())
+    }
+}
+
+/// Load MJCF string
+///
+/// The filenames of the meshes are `meshdir` of `<compiler>` joined with `file` of `<mesh>`.
+pub fn from_str<T: Real>(mjcf: &str) -> Result<Imported<T>, ImportError> {
+    from_element(parse_xml(mjcf)?)
+}
+
+/// Load the parsed `<mujoco>` element
+pub(crate) fn from_element<T: Real>(mut mujoco: Element) -> Result<Imported<T>, ImportError> {
+    if mujoco.name != "mujoco" {
+        return Err(ImportError::InvalidModelError {
+            error: format!("root element must be mujoco, but {}", mujoco.name),
+        });
+    }
+    let mut classes = HashMap::new();
+    for default in children(&mujoco).filter(|child| child.name == "default") {
+        load_defaults(default, &DefaultClass::new(), &mut classes)?;
+    }
+    classes
+        .entry(MAIN_CLASS.to_owned())
+        .or_insert_with(DefaultClass::new);
+    apply_defaults(&mut mujoco, "mujoco", &classes, MAIN_CLASS)?;
+    let mut loader = Loader {
+        degree: true,
+        euler_sequence: "xyz".to_owned(),
+        mesh_dir: String::new(),
+        meshes: HashMap::new(),
+        reporter: Reporter::default(),
+    };
+    if let Some(compiler) = mujoco.get_child("compiler", None) {
+        loader.degree = compiler.get_attribute("angle", None) != Some("radian");
+        if let Some(sequence) = compiler.get_attribute("eulerseq", None) {
+            loader.euler_sequence = sequence.to_owned();
+        }
+        if let Some(dir) = compiler
+            .get_attribute("meshdir", None)
+            .or_else(|| compiler.get_attribute("assetdir", None))
+        {
+            loader.mesh_dir = dir.to_owned();
+        }
+    }
+    let mut equalities = Vec::new();
+    for element in children(&mujoco) {
+        let path = child_path("", element);
+        match element.name.as_str() {
+            "compiler" | "worldbody" | "default" => {}
+            "asset" => {
+                for asset in children(element) {
+                    let asset_path = child_path(&path, asset);
+                    if asset.name != "mesh" {
+                        loader.reporter.report(asset_path, "ignored");
+                        continue;
+                    }
+                    let file = asset.get_attribute("file", None).unwrap_or("");
+                    let name = asset.get_attribute("name", None).map_or_else(
+                        || {
+                            // the file name without the extension
+                            Path::new(file)
+                                .file_stem()
+                                .map_or_else(String::new, |s| s.to_string_lossy().into_owned())
+                        },
+                        |n| n.to_owned(),
+                    );
+                    let scale = attribute_floats(asset, "scale", 3, &asset_path)?
+                        .map_or_else(|| Vector3::new(1.0, 1.0, 1.0), |s| vector3(&s));
+                    let filename = if loader.mesh_dir.is_empty() {
+                        file.to_owned()
+                    } else {
+                        Path::new(&loader.mesh_dir)
+                            .join(file)
+                            .to_string_lossy()
+                            .into_owned()
+                    };
+                    loader.meshes.insert(name, (filename, scale));
+                }
+            }
+            "equality" => {
+                for equality in children(element) {
+                    equalities.push((equality, child_path(&path, equality)));
+                }
+            }
+            _ => loader.reporter.report(path, "ignored"),
+        }
+    }
+    let root = JointBuilder::<T>::new().name(ROOT_JOINT_NAME).into_node();
+    if let Some(world) = mujoco.get_child("worldbody", None) {
+        let path = "worldbody";
+        loader
+            .reporter
+            .report_unknown_children(world, path, &["geom", "body"]);
+        root.set_link(Some(loader.link(
+            WORLD_LINK_NAME,
+            world,
+            path,
+            &Isometry3::identity(),
+        )?));
+        for (i, body) in world.get_children("body", None).enumerate() {
+            let node =
+                loader.body::<T>(body, &child_path(path, body), i, &Isometry3::identity())?;
+            node.set_parent(&root);
+        }
+    }
+    let chain = Chain::from_root(root);
+    for (equality, path) in equalities {
+        let polycoef = attribute_floats(equality, "polycoef", 5, &path)?
+            .unwrap_or_else(|| vec![0.0, 1.0, 0.0, 0.0, 0.0]);
+        let joints = (
+            equality.get_attribute("joint1", None),
+            equality.get_attribute("joint2", None),
+        );
+        match (equality.name.as_str(), joints) {
+            ("joint", (Some(joint1), Some(joint2))) if polycoef[2..].iter().all(|c| *c == 0.0) => {
+                match (chain.find(joint1), chain.find(joint2)) {
+                    (Some(child), Some(parent)) => child.set_mimic_parent(
+                        parent,
+                        Mimic::new(na::convert(polycoef[1]), na::convert(polycoef[0])),
+                    ),
+                    _ => {
+                        return Err(ImportError::InvalidModelError {
+                            error: format!("{}: joint is not found", path),
+                        })
+                    }
+                }
+            }
+            _ => loader.reporter.report(path, "ignored"),
+        }
+    }
+    Ok(Imported {
+        chain,
+        unsupported: loader.reporter.unsupported,
+    })
+}
+
+/// Load MJCF file
+pub fn from_file<T: Real, P: AsRef<Path>>(path: P) -> Result<Imported<T>, ImportError> {
+    from_str(&read_file(path)?)
+}
+
+#[test]
+fn test_mjcf() {
+    use std::f64::consts::FRAC_PI_2;
+    let imported = from_str::<f64>(
+        r#"<mujoco model="robot">
+  <compiler angle="radian" meshdir="meshes"/>
+  <option timestep="0.001"/>
+  <asset>
+    <mesh name="hand" file="hand.stl" scale="0.001 0.001 0.001"/>
+    <texture name="grid" type="2d" builtin="checker"/>
+  </asset>
+  <worldbody>
+    <light pos="0 0 3"/>
+    <geom name="floor" type="plane" size="1 1 0.1"/>
+    <geom name="table" type="box" size="1 0.5 0.1" pos="0 0 -0.1"/>
+    <body name="base" pos="0 0 0.5">
+      <inertial pos="0 0 0.1" mass="3" fullinertia="1 2 3 0.1 0.2 0.3"/>
+      <geom type="cylinder" size="0.1 0.2" rgba="0 1 0 1"/>
+      <body name="arm" pos="0 0 0.4" euler="0 0 1.5707963267948966">
+        <joint name="j1" axis="1 0 0" pos="0 0 0.1" range="-2 2" damping="0.5"/>
+        <joint name="j2" type="slide" axis="0 0 1" pos="0 0 0.2"/>
+        <geom name="arm_geom" type="capsule" fromto="0 0 0 0 0 0.3" size="0.05"
+              contype="0" conaffinity="0"/>
+        <site name="tip" pos="0 0 0.3"/>
+        <body name="hand" pos="0 0 0.3">
+          <joint name="j3" axis="0 1 0"/>
+          <geom type="mesh" mesh="hand"/>
+        </body>
+      </body>
+    </body>
+    <body name="ball" pos="1 0 0">
+      <freejoint name="free"/>
+      <geom size="0.1"/>
+    </body>
+  </worldbody>
+  <equality>
+    <joint joint1="j3" joint2="j1" polycoef="0.1 2 0 0 0"/>
+    <weld body1="ball" body2="base"/>
+  </equality>
+</mujoco>"#,
+    )
+    .unwrap();
+    let paths = imported
+        .unsupported
+        .iter()
+        .map(|u| u.path.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "option",
+            "asset/texture[grid]",
+            "worldbody/light",
+            "worldbody/geom[floor]",
+            "worldbody/body[base]/body[arm]/site[tip]",
+            "worldbody/body[base]/body[arm]/geom[arm_geom]",
+            "equality/weld",
+        ]
+    );
+    let chain = imported.chain;
+    assert_eq!(chain.dof(), 3 + 6);
+    let j1 = chain.find("j1").unwrap();
+    assert_eq!(j1.joint().limits.unwrap().max, 2.0);
+    assert_eq!(j1.joint().dynamics.unwrap().damping, 0.5);
+    assert!(chain.find("j2").unwrap().joint().limits.is_none());
+    assert!(chain.find("free").is_some());
+    assert!(chain.find("base").is_some());
+
+    j1.set_joint_position(FRAC_PI_2).unwrap();
+    chain.find("j2").unwrap().set_joint_position(0.05).unwrap();
+    // j3 = 2 * j1 + 0.1
+    assert_eq!(
+        chain.find("j3").unwrap().joint_position().unwrap(),
+        FRAC_PI_2 * 2.0 + 0.1
+    );
+    chain.update_transforms();
+    let link_transform = |joint_name: &str| {
+        let node = chain.find(joint_name).unwrap();
+        let link = node.link();
+        let link = link.as_ref().unwrap();
+        (
+            link.name.clone(),
+            node.world_transform().unwrap() * link.visuals[0].origin(),
+        )
+    };
+    // j1 is at z = 1.0, and rotates around the world y axis
+    let (name, hand) = link_transform("j3");
+    assert_eq!(name, "hand");
+    assert!((hand.translation.vector - Vector3::new(0.25, 0.0, 1.0)).norm() < 1e-10);
+    // the center of the capsule
+    let (name, arm) = link_transform("j2");
+    assert_eq!(name, "arm");
+    assert!((arm.translation.vector - Vector3::new(0.1, 0.0, 1.0)).norm() < 1e-10);
+
+    let node = chain.find("j2").unwrap();
+    let link = node.link();
+    let arm = link.as_ref().unwrap();
+    match arm.visuals[0].geometry {
+        Geometry::Cylinder { radius, length } => {
+            assert_eq!(radius, 0.05);
+            assert!((length - 0.4).abs() < 1e-10);
+        }
+        _ => panic!("capsule must be cylinder"),
+    }
+    assert!(arm.collisions.is_empty());
+
+    let node = chain.find("j3").unwrap();
+    let link = node.link();
+    match link.as_ref().unwrap().collisions[0].geometry {
+        Geometry::Mesh {
+            ref filename,
+            ref scale,
+        } => {
+            assert_eq!(filename, "meshes/hand.stl");
+            assert_eq!(scale.z, 0.001);
+        }
+        _ => panic!("hand must be mesh"),
+    }
+
+    let node = chain.find("base").unwrap();
+    let link = node.link();
+    let base = link.as_ref().unwrap();
+    assert_eq!(base.visuals[0].material.color.g, 1.0);
+    assert_eq!(base.inertial.mass, 3.0);
+    assert_eq!(base.inertial.inertia[(2, 1)], 0.3);
+    assert_eq!(base.inertial.origin().translation.vector.z, 0.1);
+
+    let root = chain.find(ROOT_JOINT_NAME).unwrap();
+    let link = root.link();
+    let world = link.as_ref().unwrap();
+    assert_eq!(world.name, WORLD_LINK_NAME);
+    assert_eq!(world.visuals.len(), 1);
+    assert_eq!(world.visuals[0].name, "table");
+}
+
+#[test]
+fn test_mjcf_multiple_joints() {
+    use std::f64::consts::{FRAC_PI_2, PI};
+    let imported = from_str::<f64>(
+        r#"<mujoco><compiler angle="radian"/><worldbody>
+        <body name="arm" pos="0 0 1">
+          <joint name="a" axis="0 0 1" pos="0 0 0.1"/>
+          <joint name="b" type="slide" axis="1 0 0" pos="0 0 0.3"/>
+          <joint name="c" type="ball" pos="0 0 0.2"/>
+          <geom size="0.1"/>
+        </body>
+        </worldbody></mujoco>"#,
+    )
+    .unwrap();
+    let chain = imported.chain;
+    assert_eq!(chain.dof(), 5);
+    let parent_name = |name: &str| {
+        chain
+            .find(name)
+            .unwrap()
+            .parent()
+            .unwrap()
+            .joint()
+            .name
+            .clone()
+    };
+    assert_eq!(parent_name("a"), ROOT_JOINT_NAME);
+    assert_eq!(parent_name("b"), "a");
+    assert_eq!(parent_name("c"), "b");
+    // only the last node has the link
+    assert!(chain.find("a").unwrap().link().is_none());
+    assert!(chain.find("b").unwrap().link().is_none());
+    assert_eq!(chain.find_link("arm").unwrap().joint().name, "c");
+    let geom_position = |positions: &[f64]| {
+        chain.set_joint_positions(positions).unwrap();
+        chain.update_transforms();
+        let node = chain.find("c").unwrap();
+        let link = node.link();
+        (node.world_transform().unwrap() * link.as_ref().unwrap().visuals[0].origin())
+            .translation
+            .vector
+    };
+    assert!((geom_position(&[0.0; 5]) - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-10);
+    // the slide is rotated by the hinge
+    let moved = geom_position(&[FRAC_PI_2, 0.5, 0.0, 0.0, 0.0]);
+    assert!((moved - Vector3::new(0.0, 0.5, 1.0)).norm() < 1e-10);
+    // the ball rotates around its position
+    let moved = geom_position(&[FRAC_PI_2, 0.5, PI, 0.0, 0.0]);
+    assert!((moved - Vector3::new(0.0, 0.5, 1.4)).norm() < 1e-10);
+}
+
+#[test]
+fn test_mjcf_euler_sequence() {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+    let rotation = |sequence: &str| {
+        let imported = from_str::<f64>(&format!(
+            r#"<mujoco><compiler eulerseq="{}"/><worldbody>
+            <body name="b" euler="90 45 0"/>
+            </worldbody></mujoco>"#,
+            sequence
+        ))
+        .unwrap();
+        let node = imported.chain.find("b").unwrap();
+        let rotation = node.joint().origin().rotation;
+        rotation
+    };
+    let rz = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2);
+    let ry = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), FRAC_PI_4);
+    let rx = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), FRAC_PI_2);
+    // lower case rotates around the moving axes, upper case around the fixed axes
+    assert!(rotation("zyx").angle_to(&(rz * ry)) < 1e-10);
+    assert!(rotation("ZYX").angle_to(&(ry * rz)) < 1e-10);
+    assert!(rotation("xyz").angle_to(&(rx * ry)) < 1e-10);
+}
+
+#[test]
+fn test_mjcf_equality() {
+    let mjcf = |equality: &str| {
+        from_str::<f64>(&format!(
+            r#"<mujoco><worldbody>
+            <body name="b0"><joint name="a"/>
+              <body name="b1"><joint name="b"/>
+                <body name="b2"><joint name="c"/></body>
+              </body>
+            </body>
+            </worldbody><equality>{}</equality></mujoco>"#,
+            equality
+        ))
+    };
+    let imported = mjcf(
+        r#"<joint joint1="b" joint2="a"/>
+        <joint name="quadratic" joint1="c" joint2="a" polycoef="0 1 0.5 0 0"/>"#,
+    )
+    .unwrap();
+    assert_eq!(imported.unsupported.len(), 1);
+    assert_eq!(imported.unsupported[0].path, "equality/joint[quadratic]");
+    let chain = imported.chain;
+    let mimic = |name: &str| chain.find(name).unwrap().0.borrow().mimic.clone();
+    // the default polycoef is "0 1 0 0 0"
+    let b = mimic("b").unwrap();
+    assert_eq!((b.multiplier, b.origin), (1.0, 0.0));
+    assert!(mimic("c").is_none());
+    chain.find("a").unwrap().set_joint_position(0.3).unwrap();
+    assert_eq!(chain.find("b").unwrap().joint_position().unwrap(), 0.3);
+    assert_eq!(chain.find("c").unwrap().joint_position().unwrap(), 0.0);
+
+    let imported = mjcf(r#"<joint joint1="c" joint2="b" polycoef="-0.5 3 0 0 0"/>"#).unwrap();
+    let c = imported
+        .chain
+        .find("c")
+        .unwrap()
+        .0
+        .borrow()
+        .mimic
+        .clone()
+        .unwrap();
+    assert_eq!((c.multiplier, c.origin), (3.0, -0.5));
+
+    match mjcf(r#"<joint joint1="x" joint2="a"/>"#) {
+        Err(ImportError::InvalidModelError { .. }) => {}
+        other => panic!("{:?}", other.map(|i| i.unsupported)),
+    }
+}
+
+#[test]
+fn test_mjcf_defaults() {
+    use std::f64::consts::FRAC_PI_2;
+    let mjcf = |body: &str| {
+        from_str::<f64>(&format!(
+            r#"<mujoco>
+            <default>
+              <joint damping="0.1" axis="0 1 0"/>
+              <geom type="box" size="0.1 0.2 0.3" rgba="1 0 0 1"/>
+              <default class="limited">
+                <joint range="-30 30"/>
+                <geom euler="0 0 90"/>
+                <default class="finger">
+                  <joint range="-10 10" type="slide"/>
+                  <geom type="sphere" size="0.05"/>
+                </default>
+              </default>
+            </default>
+            <worldbody>{}</worldbody></mujoco>"#,
+            body
+        ))
+    };
+    let imported = mjcf(
+        r#"<body name="base">
+          <joint name="j0" axis="1 0 0"/>
+          <geom name="g0"/>
+          <body name="arm" childclass="limited">
+            <joint name="j1"/>
+            <geom name="g1" quat="1 0 0 0"/>
+            <body name="hand">
+              <joint name="j2" class="finger"/>
+              <geom name="g2" class="finger" rgba="0 0 1 1"/>
+            </body>
+          </body>
+        </body>"#,
+    )
+    .unwrap();
+    assert!(imported.unsupported.is_empty());
+    let chain = imported.chain;
+    let joint_of = |name: &str| chain.find(name).unwrap().joint().clone();
+
+    // main class, the attribute of the element is prior
+    let j0 = joint_of("j0");
+    assert_eq!(j0.dynamics.unwrap().damping, 0.1);
+    assert!(j0.limits.is_none());
+    match j0.joint_type {
+        JointType::Rotational { axis } => assert_eq!(axis, Vector3::x_axis()),
+        _ => panic!("j0 must be rotational"),
+    }
+    // childclass of the body
+    let j1 = joint_of("j1");
+    assert_eq!(j1.dynamics.unwrap().damping, 0.1);
+    assert!((j1.limits.unwrap().max - 30.0f64.to_radians()).abs() < 1e-10);
+    match j1.joint_type {
+        JointType::Rotational { axis } => assert_eq!(axis, Vector3::y_axis()),
+        _ => panic!("j1 must be rotational"),
+    }
+    // the nested class inherits the parent class
+    let j2 = joint_of("j2");
+    assert_eq!(j2.dynamics.unwrap().damping, 0.1);
+    assert_eq!(j2.limits.unwrap().max, 10.0);
+    match j2.joint_type {
+        JointType::Linear { axis } => assert_eq!(axis, Vector3::y_axis()),
+        _ => panic!("j2 must be linear"),
+    }
+
+    let visual = |joint_name: &str| {
+        let node = chain.find(joint_name).unwrap();
+        let link = node.link();
+        link.as_ref().unwrap().visuals[0].clone()
+    };
+    let g0 = visual("j0");
+    match g0.geometry {
+        Geometry::Box { depth, height, .. } => assert_eq!((depth, height), (0.2, 0.6)),
+        _ => panic!("g0 must be box"),
+    }
+    assert_eq!(g0.material.color.r, 1.0);
+    // quat of the element overrides euler of the class
+    let g1 = visual("j1");
+    match g1.geometry {
+        Geometry::Box { .. } => {}
+        _ => panic!("g1 must be box"),
+    }
+    assert!(g1.origin().rotation.angle() < 1e-10);
+    let g2 = visual("j2");
+    match g2.geometry {
+        Geometry::Sphere { radius } => assert_eq!(radius, 0.05),
+        _ => panic!("g2 must be sphere"),
+    }
+    assert_eq!((g2.material.color.r, g2.material.color.b), (0.0, 1.0));
+    assert!((g2.origin().rotation.angle() - FRAC_PI_2).abs() < 1e-10);
+
+    for body in &[
+        r#"<body><geom class="undefined"/></body>"#,
+        r#"<body childclass="undefined"><geom/></body>"#,
+    ] {
+        match mjcf(body) {
+            Err(ImportError::InvalidModelError { .. }) => {}
+            other => panic!("{:?}", other.map(|i| i.unsupported)),
+        }
+    }
+    match from_str::<f64>(
+        r#"<mujoco><default><default class="a"/><default class="a"/></default></mujoco>"#,
+    ) {
+        Err(ImportError::InvalidModelError { .. }) => {}
+        other => panic!("{:?}", other.map(|i| i.unsupported)),
+    }
+}
@@ -27,6 +27,24 @@ use link::*;
 
 type WeakNode<T> = Weak<RefCell<NodeImpl<T>>>;
 
+/// Mimic of the child, which must have the same DOF as the parent
+fn mimic_of<T: Real>(parent: &Joint<T>, child: &NodeImpl<T>) -> Result<Mimic<T>, JointError> {
+    match child.mimic {
+        Some(ref mimic) if child.joint.dof() == parent.dof() => Ok(mimic.clone()),
+        _ => Err(JointError::MimicError {
+            from: parent.name.clone(),
+            to: child.joint.name.clone(),
+            message: format!(
+                "mimic of {} (dof = {}) -> {} (dof = {}) is not available",
+                parent.name,
+                parent.dof(),
+                child.joint.name,
+                child.joint.dof()
+            ),
+        }),
+    }
+}
+
 #[derive(Debug)]
 /// Node for joint tree struct
 pub struct NodeImpl<T>
@@ -40,6 +58,17 @@ where
     pub mimic_children: Vec<Node<T>>,
     pub mimic: Option<Mimic<T>>,
     pub link: Option<Link<T>>,
+    pub frames: Vec<Frame<T>>,
+}
+
+/// Named frame fixed to a `Node`
+///
+/// Use `Chain::add_frame()` to attach it.
+#[derive(Debug, Clone)]
+pub struct Frame<T: Real> {
+    pub name: String,
+    /// Transform from the node
+    pub offset: Isometry3<T>,
 }
 
 /// Parts of `Chain`
@@ -65,6 +94,7 @@ where
             mimic_children: Vec::new(),
             mimic: None,
             link: None,
+            frames: Vec::new(),
         })))
     }
 
@@ -229,6 +259,128 @@ where
         }
         Ok(())
     }
+    /// Set the velocity of the joint
+    ///
+    /// Mimic children get `velocity * multiplier`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// let j0 = JointBuilder::new()
+    ///     .joint_type(JointType::Linear{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// let j1 = JointBuilder::new()
+    ///     .joint_type(JointType::Linear{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// j1.set_mimic_parent(&j0, k::joint::Mimic::new(1.5, 0.1));
+    /// j0.set_joint_velocity(2.0).unwrap();
+    /// assert_eq!(j0.joint().joint_velocity().unwrap(), 2.0);
+    /// assert_eq!(j1.joint().joint_velocity().unwrap(), 3.0);
+    /// ```
+    pub fn set_joint_velocity(&self, velocity: T) -> Result<(), JointError> {
+        let mut node = self.0.borrow_mut();
+        if node.mimic_parent.is_some() {
+            return Ok(());
+        }
+        node.joint.set_joint_velocity(velocity)?;
+        for child in &node.mimic_children {
+            let mut child_node = child.0.borrow_mut();
+            let multiplier = child_node
+                .mimic
+                .as_ref()
+                .map(|m| m.multiplier)
+                .unwrap_or_else(T::one);
+            child_node.joint.set_joint_velocity(velocity * multiplier)?;
+        }
+        Ok(())
+    }
+
+    /// Set the acceleration of the joint
+    ///
+    /// Mimic children get `acceleration * multiplier`.
+    pub fn set_joint_acceleration(&self, acceleration: T) -> Result<(), JointError> {
+        let mut node = self.0.borrow_mut();
+        if node.mimic_parent.is_some() {
+            return Ok(());
+        }
+        node.joint.set_joint_acceleration(acceleration)?;
+        for child in &node.mimic_children {
+            let mut child_node = child.0.borrow_mut();
+            let multiplier = child_node
+                .mimic
+                .as_ref()
+                .map(|m| m.multiplier)
+                .unwrap_or_else(T::one);
+            child_node
+                .joint
+                .set_joint_acceleration(acceleration * multiplier)?;
+        }
+        Ok(())
+    }
+
+    /// Set all the positions of the joint, for multi DOF joints too
+    ///
+    /// Mimic children get `Mimic::mimic_position()` of each position, so they must
+    /// have the same DOF. Nothing happens if this joint is a mimic child.
+    pub fn set_joint_positions(&self, positions: &[T]) -> Result<(), JointError> {
+        let mut node = self.0.borrow_mut();
+        if node.mimic_parent.is_some() {
+            return Ok(());
+        }
+        node.joint.set_joint_positions(positions)?;
+        for child in &node.mimic_children {
+            let mut child_node = child.0.borrow_mut();
+            let mimic = mimic_of(&node.joint, &child_node)?;
+            let positions = positions
+                .iter()
+                .map(|p| mimic.mimic_position(*p))
+                .collect::<Vec<_>>();
+            child_node.joint.set_joint_positions(&positions)?;
+        }
+        Ok(())
+    }
+    /// Set all the velocities of the joint, for multi DOF joints too
+    ///
+    /// Mimic children get `velocity * multiplier` of each velocity.
+    pub fn set_joint_velocities(&self, velocities: &[T]) -> Result<(), JointError> {
+        let mut node = self.0.borrow_mut();
+        if node.mimic_parent.is_some() {
+            return Ok(());
+        }
+        node.joint.set_joint_velocities(velocities)?;
+        for child in &node.mimic_children {
+            let mut child_node = child.0.borrow_mut();
+            let multiplier = mimic_of(&node.joint, &child_node)?.multiplier;
+            let velocities = velocities
+                .iter()
+                .map(|v| *v * multiplier)
+                .collect::<Vec<_>>();
+            child_node.joint.set_joint_velocities(&velocities)?;
+        }
+        Ok(())
+    }
+    /// Set all the accelerations of the joint, for multi DOF joints too
+    ///
+    /// Mimic children get `acceleration * multiplier` of each acceleration.
+    pub fn set_joint_accelerations(&self, accelerations: &[T]) -> Result<(), JointError> {
+        let mut node = self.0.borrow_mut();
+        if node.mimic_parent.is_some() {
+            return Ok(());
+        }
+        node.joint.set_joint_accelerations(accelerations)?;
+        for child in &node.mimic_children {
+            let mut child_node = child.0.borrow_mut();
+            let multiplier = mimic_of(&node.joint, &child_node)?.multiplier;
+            let accelerations = accelerations
+                .iter()
+                .map(|a| *a * multiplier)
+                .collect::<Vec<_>>();
+            child_node.joint.set_joint_accelerations(&accelerations)?;
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn set_joint_position_unchecked(&self, position: T) {
         self.0
@@ -237,6 +389,14 @@ where
             .set_joint_position_unchecked(position);
     }
 
+    #[inline]
+    pub fn set_joint_velocity_unchecked(&self, velocity: T) {
+        self.0
+            .borrow_mut()
+            .joint
+            .set_joint_velocity_unchecked(velocity);
+    }
+
     pub(crate) fn parent_world_transform(&self) -> Option<Isometry3<T>> {
         //match self.0.borrow().parent {
         match self.parent() {
@@ -252,6 +412,13 @@ where
         }
     }
 
+    pub(crate) fn parent_world_acceleration(&self) -> Option<Acceleration<T>> {
+        match self.parent() {
+            Some(ref parent) => parent.world_acceleration(),
+            None => Some(Acceleration::zero()),
+        }
+    }
+
     /// Get the calculated world transform.
     /// Call `Chain::update_transforms()` before using this method.
     ///
@@ -284,10 +451,34 @@ where
     pub fn world_transform(&self) -> Option<Isometry3<T>> {
         self.0.borrow().joint.world_transform()
     }
+    /// World transform calculated from the root, ignoring the caches
+    ///
+    /// The cache of a node is not cleared when its ancestors move, so it is not trusted.
+    /// The caches of the ancestors and this node are updated with the results.
+    pub(crate) fn updated_world_transform(&self) -> Isometry3<T> {
+        let mut ancestors = self.iter_ancestors().collect::<Vec<_>>();
+        ancestors.reverse();
+        ancestors
+            .iter()
+            .fold(Isometry3::identity(), |transform, node| {
+                let joint = node.joint();
+                let trans = transform * joint.local_transform();
+                joint.set_world_transform(trans);
+                trans
+            })
+    }
+    /// Get the calculated world velocity.
+    /// Call `Chain::update_velocities()` before using this method.
     #[inline]
     pub fn world_velocity(&self) -> Option<Velocity<T>> {
         self.0.borrow().joint.world_velocity()
     }
+    /// Get the calculated world acceleration.
+    /// Call `Chain::update_accelerations()` before using this method.
+    #[inline]
+    pub fn world_acceleration(&self) -> Option<Acceleration<T>> {
+        self.0.borrow().joint.world_acceleration()
+    }
 
     pub fn set_mimic_parent(&self, parent: &Node<T>, mimic: Mimic<T>) {
         self.0.borrow_mut().mimic_parent = Some(Rc::downgrade(&parent.0));
@@ -475,6 +666,10 @@ pub struct JointBuilder<T: Real> {
     name: String,
     joint_type: JointType<T>,
     limits: Option<Range<T>>,
+    velocity_limit: Option<T>,
+    effort_limit: Option<T>,
+    safety_controller: Option<SafetyController<T>>,
+    dynamics: Option<Dynamics<T>>,
     origin: Isometry3<T>,
 }
 
@@ -496,6 +691,10 @@ where
             name: "".to_string(),
             joint_type: JointType::Fixed,
             limits: None,
+            velocity_limit: None,
+            effort_limit: None,
+            safety_controller: None,
+            dynamics: None,
             origin: Isometry3::identity(),
         }
     }
@@ -514,6 +713,29 @@ where
         self.limits = limits;
         self
     }
+    /// Set max absolute velocity of the joint
+    pub fn velocity_limit(mut self, velocity_limit: Option<T>) -> JointBuilder<T> {
+        self.velocity_limit = velocity_limit;
+        self
+    }
+    /// Set max absolute effort (torque or force) of the joint
+    pub fn effort_limit(mut self, effort_limit: Option<T>) -> JointBuilder<T> {
+        self.effort_limit = effort_limit;
+        self
+    }
+    /// Set soft limits of the joint
+    pub fn safety_controller(
+        mut self,
+        safety_controller: Option<SafetyController<T>>,
+    ) -> JointBuilder<T> {
+        self.safety_controller = safety_controller;
+        self
+    }
+    /// Set damping and friction of the joint
+    pub fn dynamics(mut self, dynamics: Option<Dynamics<T>>) -> JointBuilder<T> {
+        self.dynamics = dynamics;
+        self
+    }
     /// Set the origin transform of this joint
     pub fn origin(mut self, origin: Isometry3<T>) -> JointBuilder<T> {
         self.origin = origin;
@@ -534,6 +756,10 @@ where
         let mut joint = Joint::new(&self.name, self.joint_type);
         joint.set_origin(self.origin);
         joint.limits = self.limits;
+        joint.velocity_limit = self.velocity_limit;
+        joint.effort_limit = self.effort_limit;
+        joint.safety_controller = self.safety_controller;
+        joint.dynamics = self.dynamics;
         joint
     }
     /// Create `Node` instead of `Joint` as output
@@ -0,0 +1,626 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Sampling-based motion planning in joint space
+//!
+//! `RrtConnect` grows two trees from the start and the goal positions and tries to
+//! connect them (Kuffner and LaValle, RRT-Connect). The validity of the positions is
+//! given by `ValidityChecker`, such as `ChainValidityChecker` for the collisions.
+//! The found path is shortcut and smoothed, and the distance between its waypoints
+//! is within `max_step`, so it can be passed to `TimeParameterizer::parameterize()`.
+//!
+//! # Examples
+//!
+//! ```
+//! use k::joint::Range;
+//! use k::planning::*;
+//! use k::topp::TimeParameterizer;
+//!
+//! // a disk in the middle of the 2D joint space is invalid
+//! let is_valid = |q: &[f64]| q[0] * q[0] + q[1] * q[1] > 0.25;
+//! let mut planner = RrtConnect::new(vec![Range::new(-2.0, 2.0); 2]);
+//! planner.seed = 1;
+//! let path = planner.plan(&[-1.0, 0.0], &[1.0, 0.0], &is_valid).unwrap();
+//! assert_eq!(path[0], vec![-1.0, 0.0]);
+//! assert_eq!(path[path.len() - 1], vec![1.0, 0.0]);
+//! assert!(path.iter().all(|q| is_valid(q)));
+//!
+//! let mut parameterizer = TimeParameterizer::new(2);
+//! parameterizer.velocity_limits = vec![Some(1.0), Some(1.0)];
+//! parameterizer.acceleration_limits = vec![Some(2.0), Some(2.0)];
+//! let timing = parameterizer.parameterize(&path).unwrap();
+//! assert!(timing.duration() > 2.0);
+//! ```
+use na::{self, Real};
+
+use chain::*;
+use collision::*;
+use errors::*;
+use joint::*;
+
+/// Check if the joint positions are valid, such as collision-free
+pub trait ValidityChecker<T>
+where
+    T: Real,
+{
+    fn is_valid(&self, positions: &[T]) -> bool;
+}
+
+impl<T, F> ValidityChecker<T> for F
+where
+    T: Real,
+    F: Fn(&[T]) -> bool,
+{
+    fn is_valid(&self, positions: &[T]) -> bool {
+        self(positions)
+    }
+}
+
+/// Validity checker which sets the positions to the chain and checks the collisions
+///
+/// The positions out of the joint limits are invalid. The joint positions of
+/// the chain are changed by the check.
+#[derive(Debug)]
+pub struct ChainValidityChecker<'a, T: Real> {
+    chain: &'a Chain<T>,
+    checker: &'a CollisionChecker<T>,
+}
+
+impl<'a, T> ChainValidityChecker<'a, T>
+where
+    T: Real,
+{
+    pub fn new(chain: &'a Chain<T>, checker: &'a CollisionChecker<T>) -> Self {
+        ChainValidityChecker { chain, checker }
+    }
+}
+
+impl<'a, T> ValidityChecker<T> for ChainValidityChecker<'a, T>
+where
+    T: Real,
+{
+    fn is_valid(&self, positions: &[T]) -> bool {
+        self.chain.set_joint_positions(positions).is_ok() && !self.checker.is_colliding(self.chain)
+    }
+}
+
+/// Pseudo random numbers (SplitMix64) for the reproducible planning
+#[derive(Debug, Clone)]
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Uniform in `[min, max)`
+    fn uniform<T: Real>(&mut self, min: T, max: T) -> T {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        min + (max - min) * na::convert(unit)
+    }
+    /// Uniform in `0..n`
+    fn index(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// `b - a`, the shortest angle for the continuous joints
+fn difference<T: Real>(a: T, b: T, is_continuous: bool) -> T {
+    if is_continuous {
+        normalize_angle(b - a)
+    } else {
+        b - a
+    }
+}
+
+/// Euclidean distance. The joints marked in `continuous` are measured the short way around.
+fn distance<T: Real>(a: &[T], b: &[T], continuous: &[bool]) -> T {
+    a.iter()
+        .zip(b)
+        .enumerate()
+        .fold(T::zero(), |sum, (i, (a, b))| {
+            let d = difference(*a, *b, is_continuous(continuous, i));
+            sum + d * d
+        })
+        .sqrt()
+}
+
+/// Linear interpolation. The continuous joints take the short way and are normalized.
+fn interpolate<T: Real>(a: &[T], b: &[T], t: T, continuous: &[bool]) -> Vec<T> {
+    a.iter()
+        .zip(b)
+        .enumerate()
+        .map(|(i, (a, b))| {
+            if is_continuous(continuous, i) {
+                normalize_angle(*a + difference(*a, *b, true) * t)
+            } else {
+                *a + (*b - *a) * t
+            }
+        })
+        .collect()
+}
+
+fn is_continuous(continuous: &[bool], index: usize) -> bool {
+    continuous.get(index).cloned().unwrap_or(false)
+}
+
+/// Number of the steps to divide `length` by `step`, at least one
+fn num_steps<T: Real>(length: T, step: T) -> usize {
+    na::try_convert::<T, f64>((length / step).ceil())
+        .map(|n| n as usize)
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Insert the waypoints so that the distance between them is within `max_step`
+///
+/// # Examples
+///
+/// ```
+/// let path = k::planning::interpolate_path(&[vec![0.0, 0.0], vec![1.0, 0.0]], 0.3);
+/// assert_eq!(path.len(), 5);
+/// assert_eq!(path[1], vec![0.25, 0.0]);
+/// ```
+pub fn interpolate_path<T: Real>(path: &[Vec<T>], max_step: T) -> Vec<Vec<T>> {
+    interpolate_path_continuous(path, max_step, &[])
+}
+
+/// Insert the waypoints like `interpolate_path()`, taking the short way around
+/// for the joints marked in `continuous`
+///
+/// # Examples
+///
+/// ```
+/// let path = k::planning::interpolate_path_continuous(&[vec![3.0], vec![-3.0]], 0.2, &[true]);
+/// assert_eq!(path.len(), 3);
+/// assert!((path[1][0] - std::f64::consts::PI).abs() < 1e-10);
+/// ```
+pub fn interpolate_path_continuous<T: Real>(
+    path: &[Vec<T>],
+    max_step: T,
+    continuous: &[bool],
+) -> Vec<Vec<T>> {
+    let mut dense = Vec::new();
+    for pair in path.windows(2) {
+        let n = num_steps(distance(&pair[0], &pair[1], continuous), max_step);
+        let n_real: T = na::convert(n as f64);
+        for i in 0..n {
+            let t: T = na::convert::<f64, T>(i as f64) / n_real;
+            dense.push(interpolate(&pair[0], &pair[1], t, continuous));
+        }
+    }
+    dense.extend(path.last().cloned());
+    dense
+}
+
+/// Tree of the positions, the root has no parent
+type Tree<T> = Vec<(Vec<T>, Option<usize>)>;
+
+/// Positions from the root to the node
+fn trace<T: Real>(tree: &Tree<T>, mut index: usize) -> Vec<Vec<T>> {
+    let mut path = vec![tree[index].0.clone()];
+    while let Some(parent) = tree[index].1 {
+        path.push(tree[parent].0.clone());
+        index = parent;
+    }
+    path.reverse();
+    path
+}
+
+/// RRT-Connect planner in joint space
+#[derive(Debug, Clone)]
+pub struct RrtConnect<T: Real> {
+    /// Ranges of the joint positions to sample
+    pub limits: Vec<Range<T>>,
+    /// Flags of the continuous joints. Their distances and interpolations take
+    /// the short way around, and the positions are normalized to (-pi, pi].
+    /// Missing flags are `false`.
+    pub continuous: Vec<bool>,
+    /// Max distance to extend the trees, and between the output waypoints
+    pub max_step: T,
+    /// Max distance between the positions checked along an edge
+    pub check_resolution: T,
+    /// Max number of the extensions of the trees
+    pub max_iterations: usize,
+    /// Number of the tries to shortcut the path
+    pub num_shortcuts: usize,
+    /// Number of the iterations to smooth the path
+    pub num_smoothing: usize,
+    /// Seed of the random numbers, the same seed gives the same path
+    pub seed: u64,
+}
+
+impl<T> RrtConnect<T>
+where
+    T: Real,
+{
+    /// Create planner in the joint space bounded by `limits`
+    pub fn new(limits: Vec<Range<T>>) -> Self {
+        Self {
+            limits,
+            continuous: Vec::new(),
+            max_step: na::convert(0.1),
+            check_resolution: na::convert(0.02),
+            max_iterations: 5000,
+            num_shortcuts: 100,
+            num_smoothing: 20,
+            seed: 0,
+        }
+    }
+    /// Create planner bounded by `Joint::limits`
+    ///
+    /// The rotations without limits are bounded by `[-pi, pi]`, and `Continuous` joints
+    /// are marked in `continuous`. The translations without limits can not be sampled,
+    /// so it returns `InvalidArgumentsError` for them. Set the limits of such joints,
+    /// or create the planner by `new()` with explicit bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = JointBuilder::new()
+    ///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+    ///     .limits(Some(k::joint::Range::new(-1.0, 1.0)))
+    ///     .into_node();
+    /// let l1 = JointBuilder::<f64>::new()
+    ///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let l2 = JointBuilder::<f64>::new()
+    ///     .joint_type(JointType::Continuous{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// l2.set_parent(&l1);
+    /// let chain = Chain::from_root(l0);
+    /// let planner = k::planning::RrtConnect::from_chain(&chain).unwrap();
+    /// assert_eq!(planner.limits[0].max, 1.0);
+    /// assert_eq!(planner.limits[1].max, std::f64::consts::PI);
+    /// assert_eq!(planner.continuous, vec![false, false, true]);
+    ///
+    /// let l3 = JointBuilder::<f64>::new()
+    ///     .joint_type(JointType::Linear{axis: Vector3::x_axis()})
+    ///     .into_node();
+    /// l3.set_parent(&l2);
+    /// assert!(k::planning::RrtConnect::from_chain(&Chain::from_root(l3.clone())).is_err());
+    /// ```
+    pub fn from_chain(chain: &Chain<T>) -> Result<Self, PlanningError> {
+        let mut limits = Vec::new();
+        let mut continuous = Vec::new();
+        for joint in chain.iter_joints() {
+            for motion in joint.joint_type.motions().iter() {
+                let range = match (joint.limits, *motion) {
+                    (Some(range), _) => range,
+                    (None, Motion::Rotation(_)) => Range::new(-T::pi(), T::pi()),
+                    (None, Motion::Translation(_)) => {
+                        return Err(PlanningError::InvalidArgumentsError {
+                            error: format!("joint {} has no limits of the translation", joint.name),
+                        });
+                    }
+                };
+                limits.push(range);
+                continuous.push(joint.is_continuous());
+            }
+        }
+        let mut planner = Self::new(limits);
+        planner.continuous = continuous;
+        Ok(planner)
+    }
+    /// Find a valid path from `start` to `goal`
+    ///
+    /// The output starts with `start` and ends with `goal`, and the distance
+    /// between the waypoints is within `max_step`. If there are continuous joints,
+    /// create the trajectory by `Trajectory::new_continuous()` to keep the short way.
+    pub fn plan<V>(
+        &self,
+        start: &[T],
+        goal: &[T],
+        checker: &V,
+    ) -> Result<Vec<Vec<T>>, PlanningError>
+    where
+        V: ValidityChecker<T> + ?Sized,
+    {
+        if self.max_step <= T::zero() || self.check_resolution <= T::zero() {
+            return Err(PlanningError::InvalidArgumentsError {
+                error: "max_step and check_resolution must be positive".to_owned(),
+            });
+        }
+        for &(name, positions) in &[("start", start), ("goal", goal)] {
+            if positions.len() != self.limits.len() {
+                return Err(PlanningError::InvalidArgumentsError {
+                    error: format!(
+                        "{} has {} positions, must be {}",
+                        name,
+                        positions.len(),
+                        self.limits.len()
+                    ),
+                });
+            }
+            let in_limits = positions
+                .iter()
+                .zip(&self.limits)
+                .all(|(position, range)| range.is_valid(*position));
+            if !in_limits || !checker.is_valid(positions) {
+                return Err(PlanningError::InvalidPositionsError {
+                    name: name.to_owned(),
+                });
+            }
+        }
+        let mut random = Random::new(self.seed);
+        let path = if self.is_edge_valid(start, goal, checker) {
+            vec![start.to_vec(), goal.to_vec()]
+        } else {
+            self.connect_trees(start, goal, checker, &mut random)?
+        };
+        let path = self.shortcut(path, checker, &mut random);
+        Ok(self.smooth(path, checker))
+    }
+
+    fn is_edge_valid<V>(&self, a: &[T], b: &[T], checker: &V) -> bool
+    where
+        V: ValidityChecker<T> + ?Sized,
+    {
+        let n = num_steps(distance(a, b, &self.continuous), self.check_resolution);
+        let n_real: T = na::convert(n as f64);
+        (1..=n).all(|i| {
+            let t: T = na::convert::<f64, T>(i as f64) / n_real;
+            checker.is_valid(&interpolate(a, b, t, &self.continuous))
+        })
+    }
+
+    /// Extend the tree to `target` by `max_step`, returns the index of the new node
+    fn extend<V>(&self, tree: &mut Tree<T>, target: &[T], checker: &V) -> Option<usize>
+    where
+        V: ValidityChecker<T> + ?Sized,
+    {
+        let (nearest, nearest_distance) = tree
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (i, distance(&node.0, target, &self.continuous)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(::std::cmp::Ordering::Equal))?;
+        if nearest_distance <= T::default_epsilon() {
+            return Some(nearest);
+        }
+        let new_positions = if nearest_distance <= self.max_step {
+            target.to_vec()
+        } else {
+            interpolate(
+                &tree[nearest].0,
+                target,
+                self.max_step / nearest_distance,
+                &self.continuous,
+            )
+        };
+        if !self.is_edge_valid(&tree[nearest].0, &new_positions, checker) {
+            return None;
+        }
+        tree.push((new_positions, Some(nearest)));
+        Some(tree.len() - 1)
+    }
+
+    /// Extend the tree until it reaches `target`
+    fn connect<V>(&self, tree: &mut Tree<T>, target: &[T], checker: &V) -> Option<usize>
+    where
+        V: ValidityChecker<T> + ?Sized,
+    {
+        loop {
+            let index = self.extend(tree, target, checker)?;
+            if distance(&tree[index].0, target, &self.continuous) <= T::default_epsilon() {
+                return Some(index);
+            }
+        }
+    }
+
+    fn connect_trees<V>(
+        &self,
+        start: &[T],
+        goal: &[T],
+        checker: &V,
+        random: &mut Random,
+    ) -> Result<Vec<Vec<T>>, PlanningError>
+    where
+        V: ValidityChecker<T> + ?Sized,
+    {
+        let mut tree_a = vec![(start.to_vec(), None)];
+        let mut tree_b = vec![(goal.to_vec(), None)];
+        let mut a_is_start = true;
+        for _ in 0..self.max_iterations {
+            let sample = self
+                .limits
+                .iter()
+                .map(|range| random.uniform(range.min, range.max))
+                .collect::<Vec<_>>();
+            if let Some(new_a) = self.extend(&mut tree_a, &sample, checker) {
+                let new_positions = tree_a[new_a].0.clone();
+                if let Some(new_b) = self.connect(&mut tree_b, &new_positions, checker) {
+                    let (mut from_start, mut from_goal) = if a_is_start {
+                        (trace(&tree_a, new_a), trace(&tree_b, new_b))
+                    } else {
+                        (trace(&tree_b, new_b), trace(&tree_a, new_a))
+                    };
+                    // both end with the connecting positions
+                    from_goal.pop();
+                    from_goal.reverse();
+                    from_start.extend(from_goal);
+                    return Ok(from_start);
+                }
+            }
+            ::std::mem::swap(&mut tree_a, &mut tree_b);
+            a_is_start = !a_is_start;
+        }
+        Err(PlanningError::PathNotFoundError {
+            iterations: self.max_iterations,
+        })
+    }
+
+    /// Remove the waypoints between the random pairs which can be connected directly
+    fn shortcut<V>(&self, mut path: Vec<Vec<T>>, checker: &V, random: &mut Random) -> Vec<Vec<T>>
+    where
+        V: ValidityChecker<T> + ?Sized,
+    {
+        for _ in 0..self.num_shortcuts {
+            if path.len() <= 2 {
+                break;
+            }
+            let i = random.index(path.len());
+            let j = random.index(path.len());
+            let (i, j) = if i < j { (i, j) } else { (j, i) };
+            if j > i + 1 && self.is_edge_valid(&path[i], &path[j], checker) {
+                path.drain(i + 1..j);
+            }
+        }
+        path
+    }
+
+    /// Interpolate the path by `max_step` and move the waypoints toward their neighbors
+    fn smooth<V>(&self, path: Vec<Vec<T>>, checker: &V) -> Vec<Vec<T>>
+    where
+        V: ValidityChecker<T> + ?Sized,
+    {
+        let mut path = interpolate_path_continuous(&path, self.max_step, &self.continuous);
+        let half = na::convert::<f64, T>(0.5);
+        for _ in 0..self.num_smoothing {
+            for i in 1..path.len().saturating_sub(1) {
+                let middle = interpolate(&path[i - 1], &path[i + 1], half, &self.continuous);
+                let moved = interpolate(&path[i], &middle, half, &self.continuous);
+                if self.is_edge_valid(&path[i - 1], &moved, checker)
+                    && self.is_edge_valid(&moved, &path[i + 1], checker)
+                {
+                    path[i] = moved;
+                }
+            }
+        }
+        path
+    }
+}
+
+#[test]
+fn test_rrt_connect() {
+    // a wall with a narrow gap at y = 1.5
+    let is_valid = |q: &[f64]| q[0].abs() > 0.2 || (q[1] - 1.5).abs() < 0.2;
+    let mut planner = RrtConnect::new(vec![Range::new(-2.0, 2.0); 2]);
+    let start = [-1.0, 0.0];
+    let goal = [1.0, 0.0];
+    planner.seed = 3;
+    let path = planner.plan(&start, &goal, &is_valid).unwrap();
+    assert_eq!(path[0], start.to_vec());
+    assert_eq!(path[path.len() - 1], goal.to_vec());
+    for pair in path.windows(2) {
+        assert!(distance(&pair[0], &pair[1], &[]) <= planner.max_step + 1e-9);
+        assert!(planner.is_edge_valid(&pair[0], &pair[1], &is_valid));
+    }
+    // reproducible
+    assert_eq!(planner.plan(&start, &goal, &is_valid).unwrap(), path);
+    // shorter than the path without shortcut
+    let length = |path: &[Vec<f64>]| {
+        path.windows(2)
+            .map(|pair| distance(&pair[0], &pair[1], &[]))
+            .sum::<f64>()
+    };
+    planner.num_shortcuts = 0;
+    planner.num_smoothing = 0;
+    assert!(length(&planner.plan(&start, &goal, &is_valid).unwrap()) > length(&path));
+
+    assert!(planner.plan(&[0.0, 0.0], &goal, &is_valid).is_err());
+    assert!(planner.plan(&[-3.0, 0.0], &goal, &is_valid).is_err());
+    assert!(planner.plan(&[0.0], &goal, &is_valid).is_err());
+    let closed = |q: &[f64]| q[0].abs() > 0.2;
+    planner.max_iterations = 100;
+    match planner.plan(&start, &goal, &closed) {
+        Err(PlanningError::PathNotFoundError { iterations }) => assert_eq!(iterations, 100),
+        _ => panic!("must not be found"),
+    }
+}
+
+#[test]
+fn test_chain_validity_checker() {
+    use link::*;
+    use na::{Isometry3, Translation3, Vector3};
+    use node::*;
+    let j0 = JointBuilder::new()
+        .joint_type(JointType::Rotational {
+            axis: Vector3::z_axis(),
+        })
+        .limits(Some(Range::new(-3.0, 3.0)))
+        .into_node();
+    let j1 = JointBuilder::new()
+        .joint_type(JointType::Linear {
+            axis: Vector3::x_axis(),
+        })
+        .limits(Some(Range::new(-0.7, 0.7)))
+        .translation(Translation3::new(1.0, 0.0, 0.0))
+        .into_node();
+    j1.set_link(Some(
+        LinkBuilder::new()
+            .name("hand")
+            .add_collision(Collision::new(
+                "hand".to_owned(),
+                Isometry3::identity(),
+                Geometry::Sphere { radius: 0.1 },
+            ))
+            .finalize(),
+    ));
+    j1.set_parent(&j0);
+    let chain = Chain::<f64>::from_root(j0);
+    // a pole at +y blocks the hand unless the arm is shortened or extended
+    let collision_checker = CollisionChecker::new().obstacle(Obstacle::Shape {
+        shape: Shape::Cylinder {
+            radius: 0.2,
+            half_length: 1.0,
+        },
+        transform: Isometry3::translation(0.0, 1.0, 0.0),
+    });
+    let checker = ChainValidityChecker::new(&chain, &collision_checker);
+    assert!(!checker.is_valid(&[::std::f64::consts::FRAC_PI_2, 0.0]));
+    assert!(checker.is_valid(&[::std::f64::consts::FRAC_PI_2, -0.5]));
+    assert!(!checker.is_valid(&[3.1, 0.0]));
+    let planner = RrtConnect::from_chain(&chain).unwrap();
+    let path = planner.plan(&[2.8, 0.0], &[0.5, 0.0], &checker).unwrap();
+    assert!(path.iter().any(|q| q[1].abs() > 0.29));
+}
+
+#[test]
+fn test_rrt_connect_continuous() {
+    use std::f64::consts::PI;
+    // the way through zero is blocked, but the continuous joint can go around through pi
+    let is_valid = |q: &[f64]| q[0].abs() > 1.0;
+    let mut planner = RrtConnect::new(vec![Range::new(-PI, PI); 2]);
+    planner.continuous = vec![true, false];
+    let start = [-2.0, 0.0];
+    let goal = [2.0, 0.0];
+    assert!(planner.is_edge_valid(&start, &goal, &is_valid));
+    assert!((distance(&start, &goal, &planner.continuous) - (2.0 * PI - 4.0)).abs() < 1e-10);
+    let path = planner.plan(&start, &goal, &is_valid).unwrap();
+    assert_eq!(path[0], start.to_vec());
+    assert_eq!(path[path.len() - 1], goal.to_vec());
+    for q in &path {
+        assert!(is_valid(q));
+        assert!(q[0] > -PI && q[0] <= PI);
+    }
+    assert!(path.windows(2).all(|pair| {
+        distance(&pair[0], &pair[1], &planner.continuous) <= planner.max_step + 1e-9
+    }));
+    // without the flag, it is a wall
+    planner.continuous = vec![];
+    planner.max_iterations = 100;
+    assert!(planner.plan(&start, &goal, &is_valid).is_err());
+}
@@ -0,0 +1,336 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Product of exponentials (PoE) representation of `SerialChain`
+//!
+//! The screw axes are the ones of "Modern Robotics" (Lynch and Park),
+//! `(omega, v)` with unit `omega` for rotations and zero `omega` with unit `v` for translations.
+//!
+//! # Examples
+//!
+//! ```
+//! use k::*;
+//! use k::poe::*;
+//!
+//! let j0 = JointBuilder::new()
+//!     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+//!     .into_node();
+//! let j1 = JointBuilder::new()
+//!     .translation(Translation3::new(1.0f64, 0.0, 0.0))
+//!     .into_node();
+//! j1.set_parent(&j0);
+//! let arm = SerialChain::from_end(&j1);
+//!
+//! let poe = ProductOfExponentials::from_serial_chain(&arm);
+//! assert_eq!(poe.home.translation.vector, Vector3::new(1.0, 0.0, 0.0));
+//! let end = poe.end_transform(&[std::f64::consts::FRAC_PI_2]).unwrap();
+//! assert!((end.translation.vector - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-10);
+//! ```
+use na::{Isometry3, Real, Translation3, UnitQuaternion, Vector3, Vector6};
+
+use chain::*;
+use errors::*;
+use joint::*;
+use node::*;
+
+/// Screw axis (unit twist)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Screw<T: Real> {
+    /// Angular part, unit vector or zero
+    pub omega: Vector3<T>,
+    /// Linear part
+    pub v: Vector3<T>,
+}
+
+impl<T> Screw<T>
+where
+    T: Real,
+{
+    /// Create screw from the angular and linear parts
+    pub fn new(omega: Vector3<T>, v: Vector3<T>) -> Self {
+        Screw { omega, v }
+    }
+    /// Screw of the rotation around `axis` which passes through `point`
+    pub fn revolute(axis: Vector3<T>, point: Vector3<T>) -> Self {
+        let omega = axis.normalize();
+        Screw {
+            omega,
+            v: -omega.cross(&point),
+        }
+    }
+    /// Screw of the translation along `axis`
+    pub fn prismatic(axis: Vector3<T>) -> Self {
+        Screw {
+            omega: Vector3::zeros(),
+            v: axis.normalize(),
+        }
+    }
+    /// Screw of the motion of the joint which is at `frame`
+    pub fn from_motion(motion: &Motion<T>, frame: &Isometry3<T>) -> Self {
+        match *motion {
+            Motion::Rotation(axis) => {
+                Screw::revolute(frame.rotation * axis.into_inner(), frame.translation.vector)
+            }
+            Motion::Translation(axis) => Screw::prismatic(frame.rotation * axis.into_inner()),
+        }
+    }
+    /// True if it is a rotation
+    pub fn is_revolute(&self) -> bool {
+        self.omega.norm() > T::default_epsilon()
+    }
+    /// `(omega, v)` as a vector
+    pub fn to_vector(&self) -> Vector6<T> {
+        Vector6::new(
+            self.omega[0],
+            self.omega[1],
+            self.omega[2],
+            self.v[0],
+            self.v[1],
+            self.v[2],
+        )
+    }
+    /// The screw in the frame which is transformed by `trans` (the adjoint map `Ad_trans`)
+    pub fn transformed(&self, trans: &Isometry3<T>) -> Self {
+        let omega = trans.rotation * self.omega;
+        Screw {
+            omega,
+            v: trans.translation.vector.cross(&omega) + trans.rotation * self.v,
+        }
+    }
+    /// Matrix exponential of the twist `self * theta`
+    pub fn exp(&self, theta: T) -> Isometry3<T> {
+        if !self.is_revolute() {
+            return Isometry3::from_parts(
+                Translation3::from(self.v * theta),
+                UnitQuaternion::identity(),
+            );
+        }
+        // Rodrigues' formula for the translation part
+        let w_v = self.omega.cross(&self.v);
+        let translation = self.v * theta
+            + w_v * (T::one() - theta.cos())
+            + self.omega.cross(&w_v) * (theta - theta.sin());
+        Isometry3::from_parts(
+            Translation3::from(translation),
+            UnitQuaternion::from_scaled_axis(self.omega * theta),
+        )
+    }
+}
+
+/// Product of exponentials representation of the serial chain
+///
+/// The joint with `n` DOF has `n` screws, in the order of `Chain::joint_positions()`.
+/// Mimic relations and the limits of the joints are not included.
+#[derive(Debug, Clone)]
+pub struct ProductOfExponentials<T: Real> {
+    /// Screw axes in the space (root) frame
+    pub space_screws: Vec<Screw<T>>,
+    /// End transform at the zero position
+    pub home: Isometry3<T>,
+}
+
+impl<T> ProductOfExponentials<T>
+where
+    T: Real,
+{
+    /// Create from the space screws and the home configuration `M`
+    pub fn new(space_screws: Vec<Screw<T>>, home: Isometry3<T>) -> Self {
+        ProductOfExponentials { space_screws, home }
+    }
+    /// Create from the body screws and the home configuration `M`
+    pub fn from_body_screws(body_screws: &[Screw<T>], home: Isometry3<T>) -> Self {
+        ProductOfExponentials {
+            space_screws: body_screws.iter().map(|s| s.transformed(&home)).collect(),
+            home,
+        }
+    }
+    /// Convert the `Joint` origin representation to PoE
+    ///
+    /// The current joint positions are not used. The home pose includes the TCP offset.
+    pub fn from_serial_chain(chain: &SerialChain<T>) -> Self {
+        let mut space_screws = Vec::with_capacity(chain.dof());
+        let mut home = Isometry3::identity();
+        for node in chain.iter() {
+            let joint = node.joint();
+            home *= *joint.origin();
+            // all motions are identity at zero, so they share the frame
+            space_screws.extend(
+                joint
+                    .joint_type
+                    .motions()
+                    .iter()
+                    .map(|motion| Screw::from_motion(motion, &home)),
+            );
+        }
+        home *= *chain.tcp_offset();
+        ProductOfExponentials { space_screws, home }
+    }
+    /// Convert to the `Joint` origin representation
+    ///
+    /// The joints are named `joint1`, `joint2`, ... and the end is a fixed joint named `end`.
+    pub fn to_serial_chain(&self) -> SerialChain<T> {
+        let root = JointBuilder::new().name("root").into_node();
+        let mut parent = root.clone();
+        // joint frames are not rotated from the root
+        let mut parent_frame = Isometry3::identity();
+        for (i, screw) in self.space_screws.iter().enumerate() {
+            let (joint_type, frame) = if screw.is_revolute() {
+                // the nearest point on the axis from the origin
+                (
+                    JointType::Rotational {
+                        axis: na::Unit::new_normalize(screw.omega),
+                    },
+                    Isometry3::from_parts(
+                        Translation3::from(screw.omega.cross(&screw.v)),
+                        UnitQuaternion::identity(),
+                    ),
+                )
+            } else {
+                (
+                    JointType::Linear {
+                        axis: na::Unit::new_normalize(screw.v),
+                    },
+                    parent_frame,
+                )
+            };
+            let node = JointBuilder::new()
+                .name(&format!("joint{}", i + 1))
+                .joint_type(joint_type)
+                .origin(parent_frame.inverse() * frame)
+                .into_node();
+            node.set_parent(&parent);
+            parent = node;
+            parent_frame = frame;
+        }
+        let end = JointBuilder::new()
+            .name("end")
+            .origin(parent_frame.inverse() * self.home)
+            .into_node();
+        end.set_parent(&parent);
+        SerialChain::from_end(&end)
+    }
+    /// Screw axes in the end (body) frame
+    pub fn body_screws(&self) -> Vec<Screw<T>> {
+        let home_inv = self.home.inverse();
+        self.space_screws
+            .iter()
+            .map(|s| s.transformed(&home_inv))
+            .collect()
+    }
+    /// Number of the screws
+    pub fn dof(&self) -> usize {
+        self.space_screws.len()
+    }
+    fn check_size(&self, positions: &[T]) -> Result<(), JointError> {
+        if positions.len() != self.dof() {
+            return Err(JointError::SizeMismatchError {
+                input: positions.len(),
+                required: self.dof(),
+            });
+        }
+        Ok(())
+    }
+    /// Forward kinematics with the space screws, `e^[S1]q1 ... e^[Sn]qn M`
+    pub fn end_transform(&self, positions: &[T]) -> Result<Isometry3<T>, JointError> {
+        self.check_size(positions)?;
+        Ok(self
+            .space_screws
+            .iter()
+            .zip(positions)
+            .fold(Isometry3::identity(), |trans, (screw, &q)| {
+                trans * screw.exp(q)
+            })
+            * self.home)
+    }
+    /// Forward kinematics with the body screws, `M e^[B1]q1 ... e^[Bn]qn`
+    pub fn end_transform_body(&self, positions: &[T]) -> Result<Isometry3<T>, JointError> {
+        self.check_size(positions)?;
+        Ok(self
+            .body_screws()
+            .iter()
+            .zip(positions)
+            .fold(self.home, |trans, (screw, &q)| trans * screw.exp(q)))
+    }
+}
+
+#[cfg(test)]
+fn assert_same_transform(a: &Isometry3<f64>, b: &Isometry3<f64>) {
+    assert!(
+        (a.translation.vector - b.translation.vector).norm() < 1e-10,
+        "{} != {}",
+        a,
+        b
+    );
+    assert!(a.rotation.angle_to(&b.rotation) < 1e-10, "{} != {}", a, b);
+}
+
+#[test]
+fn test_poe_fk() {
+    let l0 = JointBuilder::new()
+        .joint_type(JointType::Rotational {
+            axis: Vector3::y_axis(),
+        })
+        .translation(Translation3::new(0.0, 0.1, 0.2))
+        .into_node();
+    let l1 = JointBuilder::new()
+        .joint_type(JointType::Linear {
+            axis: Vector3::x_axis(),
+        })
+        .origin(Isometry3::new(
+            Vector3::new(0.3, 0.0, 0.1),
+            Vector3::new(0.2, 0.0, 0.5),
+        ))
+        .into_node();
+    let l2 = JointBuilder::new()
+        .joint_type(JointType::Spherical)
+        .translation(Translation3::new(0.0, 0.0, 0.4))
+        .into_node();
+    let l3 = JointBuilder::new()
+        .origin(Isometry3::new(
+            Vector3::new(0.1, 0.2, 0.3),
+            Vector3::new(0.0, 0.4, 0.0),
+        ))
+        .into_node();
+    let l4 = JointBuilder::new()
+        .joint_type(JointType::Continuous {
+            axis: Vector3::z_axis(),
+        })
+        .translation(Translation3::new(0.0, 0.0, 0.4))
+        .into_node();
+    l1.set_parent(&l0);
+    l2.set_parent(&l1);
+    l3.set_parent(&l2);
+    l4.set_parent(&l3);
+    let arm = SerialChain::from_end(&l4);
+    let poe = ProductOfExponentials::from_serial_chain(&arm);
+    assert_eq!(poe.dof(), 6);
+    assert!(poe.end_transform(&[0.0; 5]).is_err());
+
+    let rebuilt = poe.to_serial_chain();
+    let rebuilt_poe = ProductOfExponentials::from_body_screws(&poe.body_screws(), poe.home);
+    for q in &[
+        [0.0; 6],
+        [0.3, -0.2, 0.5, 1.2, -0.7, 2.0],
+        [-1.0, 0.4, -0.3, 0.1, 0.9, -2.5],
+    ] {
+        arm.set_joint_positions(q).unwrap();
+        rebuilt.set_joint_positions(q).unwrap();
+        let expected = arm.end_transform();
+        assert_same_transform(&poe.end_transform(q).unwrap(), &expected);
+        assert_same_transform(&poe.end_transform_body(q).unwrap(), &expected);
+        assert_same_transform(&rebuilt.end_transform(), &expected);
+        assert_same_transform(&rebuilt_poe.end_transform(q).unwrap(), &expected);
+    }
+}
@@ -0,0 +1,256 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Resolve resource filenames like `package://pkg/meshes/arm.stl`
+//!
+//! # Examples
+//!
+//! ```
+//! use k::resource::ResourceResolver;
+//!
+//! let resolver = ResourceResolver::new()
+//!     .package("my_robot", "/opt/my_robot")
+//!     .base_dir("/opt/my_robot/urdf");
+//! assert_eq!(
+//!     resolver.resolve_path("package://my_robot/meshes/arm.stl").unwrap(),
+//!     std::path::Path::new("/opt/my_robot/meshes/arm.stl")
+//! );
+//! assert_eq!(
+//!     resolver.resolve_path("../meshes/base.stl").unwrap(),
+//!     std::path::Path::new("/opt/my_robot/urdf/../meshes/base.stl")
+//! );
+//! ```
+use na::Real;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use chain::*;
+use link::*;
+
+/// Environment variable used by `ResourceResolver::from_env()`
+pub const ROS_PACKAGE_PATH: &str = "ROS_PACKAGE_PATH";
+
+/// Maps `package://`, `file://` and relative filenames to the paths on the filesystem
+///
+/// `package://pkg/rest` is resolved to `{path}/rest` if the package is registered by
+/// `package()`, otherwise to the first existing `{search path}/pkg/rest`.
+/// Relative filenames are resolved from `base_dir()`, then from the search paths.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceResolver {
+    packages: HashMap<String, PathBuf>,
+    search_paths: Vec<PathBuf>,
+    base_dir: Option<PathBuf>,
+}
+
+impl ResourceResolver {
+    /// Create resolver without any paths
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create resolver which uses the directories in `ROS_PACKAGE_PATH` as search paths
+    pub fn from_env() -> Self {
+        Self::new().env_search_paths(ROS_PACKAGE_PATH)
+    }
+    /// Register the directory of the package
+    pub fn package<P: AsRef<Path>>(mut self, name: &str, path: P) -> Self {
+        self.packages
+            .insert(name.to_owned(), path.as_ref().to_path_buf());
+        self
+    }
+    /// Add the directory which contains packages or resources
+    pub fn search_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.search_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+    /// Add the search paths in the environment variable (separated by `:` on unix)
+    ///
+    /// Nothing is added if the variable is not set.
+    pub fn env_search_paths(mut self, key: &str) -> Self {
+        if let Some(paths) = env::var_os(key) {
+            self.search_paths.extend(env::split_paths(&paths));
+        }
+        self
+    }
+    /// Set the directory for the relative filenames, usually the directory of the URDF file
+    pub fn base_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.base_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+    /// Returns the directory for the relative filenames
+    #[allow(clippy::option_as_ref_deref)]
+    pub fn get_base_dir(&self) -> Option<&Path> {
+        self.base_dir.as_ref().map(|dir| dir.as_path())
+    }
+    /// Resolve the filename without checking existence of the file
+    ///
+    /// Only the candidates in the search paths are checked for existence, because
+    /// there are many of them. Returns `None` if no candidate is found.
+    pub fn resolve_path(&self, filename: &str) -> Option<PathBuf> {
+        let (scheme, rest) = match filename.find("://") {
+            Some(index) => (&filename[..index], &filename[index + 3..]),
+            None => ("", filename),
+        };
+        if scheme == "package" {
+            let (package, relative) = match rest.find('/') {
+                Some(index) => (&rest[..index], &rest[index + 1..]),
+                None => (rest, ""),
+            };
+            if let Some(dir) = self.packages.get(package) {
+                return Some(dir.join(relative));
+            }
+            return self
+                .search_paths
+                .iter()
+                .flat_map(|dir| {
+                    // the search path may be the package itself
+                    let itself = if dir.file_name() == Some(OsStr::new(package)) {
+                        Some(dir.join(relative))
+                    } else {
+                        None
+                    };
+                    itself
+                        .into_iter()
+                        .chain(Some(dir.join(package).join(relative)))
+                })
+                .find(|path| path.exists());
+        }
+        let path = Path::new(if scheme == "file" { rest } else { filename });
+        if path.is_absolute() {
+            return Some(path.to_path_buf());
+        }
+        if let Some(ref base_dir) = self.base_dir {
+            return Some(base_dir.join(path));
+        }
+        self.search_paths
+            .iter()
+            .map(|dir| dir.join(path))
+            .find(|path| path.exists())
+    }
+    /// Resolve the filename to the existing file
+    pub fn resolve(&self, filename: &str) -> Option<PathBuf> {
+        self.resolve_path(filename).filter(|path| path.exists())
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: Real,
+{
+    /// Replace the filenames of the meshes of visuals and collisions with the resolved paths
+    ///
+    /// Returns the filenames which are not resolved to existing files.
+    /// They are not changed.
+    pub fn resolve_resources(&self, resolver: &ResourceResolver) -> Vec<String> {
+        let mut unresolved = Vec::new();
+        let mut resolve = |geometry: &mut Geometry<T>| {
+            if let Geometry::Mesh {
+                ref mut filename, ..
+            } = *geometry
+            {
+                match resolver.resolve(filename) {
+                    Some(path) => *filename = path.to_string_lossy().into_owned(),
+                    None => {
+                        warn!("failed to resolve {}", filename);
+                        unresolved.push(filename.clone());
+                    }
+                }
+            }
+        };
+        for node in self.iter() {
+            if let Some(ref mut link) = node.0.borrow_mut().link {
+                for visual in &mut link.visuals {
+                    resolve(&mut visual.geometry);
+                }
+                for collision in &mut link.collisions {
+                    resolve(&mut collision.geometry);
+                }
+            }
+        }
+        unresolved
+    }
+}
+
+#[cfg(test)]
+fn create_files(root: &Path, files: &[&str]) {
+    use std::fs;
+    let _ = fs::remove_dir_all(root);
+    for file in files {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+}
+
+#[test]
+fn test_resolve() {
+    let root = env::temp_dir().join("k_test_resolve");
+    create_files(
+        &root,
+        &[
+            "ws1/pkg_a/meshes/a.stl",
+            "ws2/pkg_b/meshes/b.stl",
+            "ws2/pkg_a/meshes/other.stl",
+            "pkg_c/c.obj",
+            "robot/urdf/robot.urdf",
+            "robot/meshes/base.stl",
+        ],
+    );
+    // the variable is not set, so nothing is added
+    let resolver = ResourceResolver::new()
+        .env_search_paths("K_TEST_RESOLVE_PATH_NOT_SET")
+        .search_path(root.join("ws1"))
+        .search_path(root.join("ws2"))
+        .search_path(root.join("pkg_c"))
+        .base_dir(root.join("robot/urdf"));
+    assert_eq!(
+        resolver.resolve("package://pkg_a/meshes/a.stl").unwrap(),
+        root.join("ws1/pkg_a/meshes/a.stl")
+    );
+    // the package is found in the second search path
+    assert_eq!(
+        resolver.resolve("package://pkg_b/meshes/b.stl").unwrap(),
+        root.join("ws2/pkg_b/meshes/b.stl")
+    );
+    assert_eq!(
+        resolver
+            .resolve("package://pkg_a/meshes/other.stl")
+            .unwrap(),
+        root.join("ws2/pkg_a/meshes/other.stl")
+    );
+    // the search path is the package
+    assert_eq!(
+        resolver.resolve("package://pkg_c/c.obj").unwrap(),
+        root.join("pkg_c/c.obj")
+    );
+    assert!(resolver.resolve("package://pkg_d/d.stl").is_none());
+    assert_eq!(
+        resolver.resolve("../meshes/base.stl").unwrap(),
+        root.join("robot/urdf/../meshes/base.stl")
+    );
+    let absolute = root.join("robot/meshes/base.stl");
+    assert_eq!(
+        resolver
+            .resolve(&format!("file://{}", absolute.display()))
+            .unwrap(),
+        absolute
+    );
+    // registered package is used even if it is in the search path
+    let resolver = resolver.package("pkg_a", root.join("ws2/pkg_a"));
+    assert!(resolver.resolve("package://pkg_a/meshes/a.stl").is_none());
+    // unknown schemes are not resolved as packages
+    assert!(resolver.resolve("http://pkg_c/c.obj").is_none());
+}
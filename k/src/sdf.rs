@@ -0,0 +1,798 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Load [SDFormat](http://sdformat.org) model and create `k::Chain`
+//!
+//! The first `<model>` (directly under `<sdf>` or in `<world>`) is loaded.
+//! Poses are interpreted as SDFormat 1.6: links are in the model frame,
+//! joints are in the child link frame, axes are in the joint frame unless
+//! `use_parent_model_frame` is set. The chain is in the model frame.
+//!
+//! `ball` joints become `JointType::Spherical`, root links other than the first one are
+//! connected to the root by `JointType::Floating`. The elements which can not be
+//! represented (sensors, plugins, universal joints, ...) are listed in
+//! `Imported::unsupported`.
+//!
+//! # Examples
+//!
+//! ```
+//! let imported = k::sdf::from_str::<f64>(
+//!     r#"<sdf version="1.6"><model name="arm">
+//!     <link name="base"/>
+//!     <link name="arm"><pose>0 0 1 0 0 0</pose></link>
+//!     <joint name="shoulder" type="revolute">
+//!       <parent>base</parent><child>arm</child><axis><xyz>0 1 0</xyz></axis>
+//!     </joint>
+//!     <plugin name="controller" filename="libcontroller.so"/>
+//!     </model></sdf>"#,
+//! )
+//! .unwrap();
+//! assert_eq!(imported.chain.dof(), 1);
+//! assert_eq!(imported.unsupported[0].path, "model[arm]/plugin[controller]");
+//! ```
+use na::{self, Isometry3, Matrix3, Real, Translation3, UnitQuaternion, Vector3};
+use std::collections::HashMap;
+use std::path::Path;
+use xml::Element;
+
+use chain::*;
+use errors::*;
+use import::*;
+use joint::*;
+use link::*;
+use node::*;
+use urdf::ROOT_JOINT_NAME;
+
+/// Limits whose absolute values are larger than this are treated as no limits
+const UNLIMITED: f64 = 1e15;
+
+fn text(element: &Element, tag: &str) -> Option<String> {
+    element.get_child(tag, None).map(|e| e.content_str())
+}
+
+fn float(element: &Element, tag: &str, default: f64, path: &str) -> Result<f64, ImportError> {
+    match text(element, tag) {
+        Some(value) => Ok(parse_floats_n(&value, 1, &format!("{}/{}", path, tag))?[0]),
+        None => Ok(default),
+    }
+}
+
+/// `<pose>` of the element, reports `relative_to`
+fn pose(
+    element: &Element,
+    path: &str,
+    reporter: &mut Reporter,
+) -> Result<Isometry3<f64>, ImportError> {
+    let pose = match element.get_child("pose", None) {
+        Some(pose) => pose,
+        None => return Ok(Isometry3::identity()),
+    };
+    let pose_path = format!("{}/pose", path);
+    if pose.get_attribute("relative_to", None).is_some() {
+        reporter.report(pose_path.clone(), "relative_to is ignored");
+    }
+    let mut values = parse_floats_n(&pose.content_str(), 6, &pose_path)?;
+    if pose.get_attribute("degrees", None) == Some("true") {
+        for value in &mut values[3..] {
+            *value = value.to_radians();
+        }
+    }
+    Ok(Isometry3::from_parts(
+        Translation3::from(vector3(&values)),
+        UnitQuaternion::from_euler_angles(values[3], values[4], values[5]),
+    ))
+}
+
+fn geometry(
+    element: &Element,
+    path: &str,
+    reporter: &mut Reporter,
+) -> Result<Option<Geometry<f64>>, ImportError> {
+    let geometry = match element.get_child("geometry", None) {
+        Some(geometry) => geometry,
+        None => {
+            reporter.report(path.to_owned(), "no geometry");
+            return Ok(None);
+        }
+    };
+    let geometry_path = format!("{}/geometry", path);
+    let shape = match children(geometry).next() {
+        Some(shape) => shape,
+        None => return Ok(None),
+    };
+    let shape_path = child_path(&geometry_path, shape);
+    Ok(Some(match shape.name.as_str() {
+        "box" => {
+            let size = parse_floats_n(
+                &text(shape, "size").unwrap_or_else(|| "1 1 1".to_owned()),
+                3,
+                &shape_path,
+            )?;
+            Geometry::Box {
+                depth: size[0],
+                width: size[1],
+                height: size[2],
+            }
+        }
+        "cylinder" => Geometry::Cylinder {
+            radius: float(shape, "radius", 1.0, &shape_path)?,
+            length: float(shape, "length", 1.0, &shape_path)?,
+        },
+        "sphere" => Geometry::Sphere {
+            radius: float(shape, "radius", 1.0, &shape_path)?,
+        },
+        "capsule" => {
+            reporter.report(shape_path.clone(), "approximated by cylinder");
+            let radius = float(shape, "radius", 0.5, &shape_path)?;
+            Geometry::Cylinder {
+                radius,
+                length: float(shape, "length", 1.0, &shape_path)? + radius * 2.0,
+            }
+        }
+        "mesh" => {
+            reporter.report_unknown_children(shape, &shape_path, &["uri", "scale"]);
+            let scale = parse_floats_n(
+                &text(shape, "scale").unwrap_or_else(|| "1 1 1".to_owned()),
+                3,
+                &shape_path,
+            )?;
+            Geometry::Mesh {
+                filename: text(shape, "uri").unwrap_or_default().trim().to_owned(),
+                scale: vector3(&scale),
+            }
+        }
+        _ => {
+            reporter.report(shape_path, "ignored");
+            return Ok(None);
+        }
+    }))
+}
+
+fn shapes(
+    link: &Element,
+    link_path: &str,
+    tag: &str,
+    reporter: &mut Reporter,
+) -> Result<Vec<Shape>, ImportError> {
+    let mut shapes = Vec::new();
+    for element in link.get_children(tag, None) {
+        let path = child_path(link_path, element);
+        let known = if tag == "visual" {
+            vec!["pose", "geometry", "material"]
+        } else {
+            vec!["pose", "geometry", "surface"]
+        };
+        reporter.report_unknown_children(element, &path, &known);
+        let color = match element.get_child("material", None) {
+            Some(material) => {
+                let material_path = format!("{}/material", path);
+                reporter.report_unknown_children(
+                    material,
+                    &material_path,
+                    &["ambient", "diffuse", "specular", "emissive"],
+                );
+                match text(material, "diffuse").or_else(|| text(material, "ambient")) {
+                    Some(rgba) => {
+                        let rgba = parse_floats_n(&rgba, 4, &material_path)?;
+                        Some([rgba[0], rgba[1], rgba[2], rgba[3]])
+                    }
+                    None => None,
+                }
+            }
+            None => None,
+        };
+        let origin = pose(element, &path, reporter)?;
+        if let Some(geometry) = geometry(element, &path, reporter)? {
+            shapes.push(Shape {
+                name: element.get_attribute("name", None).unwrap_or("").to_owned(),
+                origin,
+                geometry,
+                color,
+            });
+        }
+    }
+    Ok(shapes)
+}
+
+fn inertial(
+    link: &Element,
+    link_path: &str,
+    reporter: &mut Reporter,
+) -> Result<Option<InertialF64>, ImportError> {
+    let inertial = match link.get_child("inertial", None) {
+        Some(inertial) => inertial,
+        None => return Ok(None),
+    };
+    let path = format!("{}/inertial", link_path);
+    reporter.report_unknown_children(inertial, &path, &["pose", "mass", "inertia"]);
+    let origin = pose(inertial, &path, reporter)?;
+    let mass = float(inertial, "mass", 1.0, &path)?;
+    let inertia = match inertial.get_child("inertia", None) {
+        Some(i) => {
+            let value = |tag: &str, default: f64| float(i, tag, default, &path);
+            let (ixy, ixz, iyz) = (value("ixy", 0.0)?, value("ixz", 0.0)?, value("iyz", 0.0)?);
+            Matrix3::new(
+                value("ixx", 1.0)?,
+                ixy,
+                ixz,
+                ixy,
+                value("iyy", 1.0)?,
+                iyz,
+                ixz,
+                iyz,
+                value("izz", 1.0)?,
+            )
+        }
+        None => Matrix3::identity(),
+    };
+    Ok(Some((origin, mass, inertia)))
+}
+
+struct SdfLink<'a> {
+    element: &'a Element,
+    path: String,
+    pose: Isometry3<f64>,
+}
+
+struct SdfJoint<'a> {
+    element: &'a Element,
+    path: String,
+    name: String,
+    parent: String,
+    child: String,
+}
+
+struct Loader<'a> {
+    links: HashMap<String, SdfLink<'a>>,
+    joints: Vec<SdfJoint<'a>>,
+    built_joints: usize,
+    reporter: Reporter,
+}
+
+impl<'a> Loader<'a> {
+    fn build_link<T: Real>(
+        &mut self,
+        link_name: &str,
+        node_frame: &Isometry3<f64>,
+    ) -> Result<Link<T>, ImportError> {
+        let link = &self.links[link_name];
+        let (element, path) = (link.element, link.path.clone());
+        let to_node = node_frame.inverse() * link.pose;
+        self.reporter.report_unknown_children(
+            element,
+            &path,
+            &["pose", "inertial", "visual", "collision"],
+        );
+        let inertial = inertial(element, &path, &mut self.reporter)?;
+        let visuals = shapes(element, &path, "visual", &mut self.reporter)?;
+        let collisions = shapes(element, &path, "collision", &mut self.reporter)?;
+        Ok(build_link(
+            link_name,
+            &to_node,
+            inertial,
+            &visuals,
+            &collisions,
+        ))
+    }
+
+    /// Create the node of the joint, returns the node and the frame of it in the model
+    fn build_joint<T: Real>(
+        &mut self,
+        joint_index: usize,
+        parent_frame: &Isometry3<f64>,
+    ) -> Result<(Node<T>, Isometry3<f64>), ImportError> {
+        let (element, path, name, child) = {
+            let joint = &self.joints[joint_index];
+            (
+                joint.element,
+                joint.path.clone(),
+                joint.name.clone(),
+                joint.child.clone(),
+            )
+        };
+        self.reporter
+            .report_unknown_children(element, &path, &["pose", "parent", "child", "axis"]);
+        let frame = self.links[&child].pose * pose(element, &path, &mut self.reporter)?;
+        let axis_element = element.get_child("axis", None);
+        let axis_path = format!("{}/axis", path);
+        let axis = match axis_element {
+            Some(axis) => {
+                self.reporter.report_unknown_children(
+                    axis,
+                    &axis_path,
+                    &["xyz", "limit", "dynamics", "use_parent_model_frame"],
+                );
+                let xyz = parse_floats_n(
+                    &text(axis, "xyz").unwrap_or_else(|| "0 0 1".to_owned()),
+                    3,
+                    &axis_path,
+                )?;
+                let mut xyz = vector3(&xyz);
+                if text(axis, "use_parent_model_frame").map(|t| t.trim().to_owned())
+                    == Some("true".to_owned())
+                {
+                    xyz = frame.rotation.inverse() * xyz;
+                }
+                if xyz.norm() == 0.0 {
+                    return Err(ImportError::InvalidModelError {
+                        error: format!("{}: zero length axis", axis_path),
+                    });
+                }
+                na::Unit::new_normalize(na::convert::<_, Vector3<T>>(xyz))
+            }
+            None => Vector3::z_axis(),
+        };
+        let limit = axis_element.and_then(|axis| axis.get_child("limit", None));
+        let limit_value = |tag: &str| -> Result<Option<f64>, ImportError> {
+            match limit.and_then(|limit| text(limit, tag)) {
+                Some(value) => Ok(Some(
+                    parse_floats_n(&value, 1, &format!("{}/limit/{}", axis_path, tag))?[0],
+                )),
+                None => Ok(None),
+            }
+        };
+        let limits = match (limit_value("lower")?, limit_value("upper")?) {
+            (Some(lower), Some(upper)) if lower.abs() < UNLIMITED && upper.abs() < UNLIMITED => {
+                Some(Range::new(na::convert(lower), na::convert(upper)))
+            }
+            _ => None,
+        };
+        let positive = |value: Option<f64>| match value {
+            Some(value) if value > 0.0 && value < UNLIMITED => Some(na::convert(value)),
+            _ => None,
+        };
+        let velocity_limit = positive(limit_value("velocity")?);
+        let effort_limit = positive(limit_value("effort")?);
+        let dynamics = match axis_element.and_then(|axis| axis.get_child("dynamics", None)) {
+            Some(dynamics) => Some(Dynamics::new(
+                na::convert(float(dynamics, "damping", 0.0, &axis_path)?),
+                na::convert(float(dynamics, "friction", 0.0, &axis_path)?),
+            )),
+            None => None,
+        };
+        let joint_type = match element.get_attribute("type", None).unwrap_or("") {
+            "revolute" if limits.is_some() => JointType::Rotational { axis },
+            "revolute" | "continuous" => JointType::Continuous { axis },
+            "prismatic" => JointType::Linear { axis },
+            "ball" => JointType::Spherical,
+            "fixed" => JointType::Fixed,
+            joint_type => {
+                self.reporter.report(
+                    path.clone(),
+                    &format!("{} joint is loaded as fixed", joint_type),
+                );
+                JointType::Fixed
+            }
+        };
+        let limits = match joint_type {
+            JointType::Rotational { .. } | JointType::Linear { .. } => limits,
+            _ => None,
+        };
+        let node = JointBuilder::new()
+            .name(&name)
+            .joint_type(joint_type)
+            .limits(limits)
+            .velocity_limit(velocity_limit)
+            .effort_limit(effort_limit)
+            .dynamics(dynamics)
+            .origin(na::convert(parent_frame.inverse() * frame))
+            .into_node();
+        let link = self.build_link(&child, &frame)?;
+        node.set_link(Some(link));
+        self.built_joints += 1;
+        Ok((node, frame))
+    }
+
+    /// Create the nodes of the joints whose parent is `link_name` recursively
+    fn build_children<T: Real>(
+        &mut self,
+        link_name: &str,
+        node: &Node<T>,
+        node_frame: &Isometry3<f64>,
+        depth: usize,
+    ) -> Result<(), ImportError> {
+        if depth > self.joints.len() {
+            return Err(ImportError::InvalidModelError {
+                error: format!("joints make a cycle around {}", link_name),
+            });
+        }
+        let child_joints = (0..self.joints.len())
+            .filter(|i| self.joints[*i].parent == link_name)
+            .collect::<Vec<_>>();
+        for i in child_joints {
+            let (child_node, frame) = self.build_joint(i, node_frame)?;
+            child_node.set_parent(node);
+            let child_link = self.joints[i].child.clone();
+            self.build_children(&child_link, &child_node, &frame, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn find_model<'a>(
+    sdf: &'a Element,
+    reporter: &mut Reporter,
+) -> Result<(&'a Element, String), ImportError> {
+    if sdf.name != "sdf" {
+        return Err(ImportError::InvalidModelError {
+            error: format!("root element must be sdf, but {}", sdf.name),
+        });
+    }
+    let mut models = Vec::new();
+    for element in children(sdf) {
+        match element.name.as_str() {
+            "model" => models.push((element, String::new())),
+            "world" => {
+                let world_path = child_path("", element);
+                for world_child in children(element) {
+                    if world_child.name == "model" {
+                        models.push((world_child, world_path.clone()));
+                    } else {
+                        reporter.report(child_path(&world_path, world_child), "ignored");
+                    }
+                }
+            }
+            _ => reporter.report(child_path("", element), "ignored"),
+        }
+    }
+    let mut models = models.into_iter();
+    let model = models
+        .next()
+        .ok_or_else(|| ImportError::InvalidModelError {
+            error: "no model found".to_owned(),
+        })?;
+    for (other, parent_path) in models {
+        reporter.report(
+            child_path(&parent_path, other),
+            "only the first model is loaded",
+        );
+    }
+    Ok(model)
+}
+
+/// Load SDF string
+pub fn from_str<T: Real>(sdf: &str) -> Result<Imported<T>, ImportError> {
+    from_element(&parse_xml(sdf)?)
+}
+
+/// Load the parsed `<sdf>` element
+pub(crate) fn from_element<T: Real>(sdf: &Element) -> Result<Imported<T>, ImportError> {
+    let mut reporter = Reporter::default();
+    let (model, parent_path) = find_model(sdf, &mut reporter)?;
+    let model_path = child_path(&parent_path, model);
+    let mut loader = Loader {
+        links: HashMap::new(),
+        joints: Vec::new(),
+        built_joints: 0,
+        reporter,
+    };
+    let mut link_names = Vec::new();
+    for element in children(model) {
+        let path = child_path(&model_path, element);
+        let name = element.get_attribute("name", None).unwrap_or("").to_owned();
+        match element.name.as_str() {
+            "link" => {
+                let link_pose = pose(element, &path, &mut loader.reporter)?;
+                if loader.links.contains_key(&name) {
+                    return Err(ImportError::InvalidModelError {
+                        error: format!("{}: duplicated link", path),
+                    });
+                }
+                link_names.push(name.clone());
+                loader.links.insert(
+                    name,
+                    SdfLink {
+                        element,
+                        path,
+                        pose: link_pose,
+                    },
+                );
+            }
+            "joint" => {
+                let parent = text(element, "parent")
+                    .unwrap_or_default()
+                    .trim()
+                    .to_owned();
+                let child = text(element, "child").unwrap_or_default().trim().to_owned();
+                loader.joints.push(SdfJoint {
+                    element,
+                    path,
+                    name,
+                    parent,
+                    child,
+                });
+            }
+            "pose" | "static" => {}
+            _ => loader.reporter.report(path, "ignored"),
+        }
+    }
+    for (i, joint) in loader.joints.iter().enumerate() {
+        let error = if joint.parent != "world" && !loader.links.contains_key(&joint.parent) {
+            format!("parent link {} is not found", joint.parent)
+        } else if !loader.links.contains_key(&joint.child) {
+            format!("child link {} is not found", joint.child)
+        } else if loader.joints[..i].iter().any(|j| j.child == joint.child) {
+            format!("link {} has multiple parent joints", joint.child)
+        } else {
+            continue;
+        };
+        return Err(ImportError::InvalidModelError {
+            error: format!("{}: {}", joint.path, error),
+        });
+    }
+    let root = JointBuilder::new().name(ROOT_JOINT_NAME).into_node();
+    let identity = Isometry3::identity();
+    loader.build_children("world", &root, &identity, 0)?;
+    let root_link_names = link_names
+        .into_iter()
+        .filter(|name| !loader.joints.iter().any(|j| j.child == *name))
+        .collect::<Vec<_>>();
+    for (i, link_name) in root_link_names.iter().enumerate() {
+        let node = if i == 0 {
+            root.clone()
+        } else {
+            let frame = loader.links[link_name].pose;
+            let node = JointBuilder::new()
+                .name(&format!("{}_floating", link_name))
+                .joint_type(JointType::Floating)
+                .origin(na::convert(frame))
+                .into_node();
+            node.set_parent(&root);
+            node
+        };
+        let frame = if i == 0 {
+            identity
+        } else {
+            loader.links[link_name].pose
+        };
+        let link = loader.build_link(link_name, &frame)?;
+        node.set_link(Some(link));
+        loader.build_children(link_name, &node, &frame, 0)?;
+    }
+    if loader.built_joints != loader.joints.len() {
+        return Err(ImportError::InvalidModelError {
+            error: "joints make a cycle".to_owned(),
+        });
+    }
+    Ok(Imported {
+        chain: Chain::from_root(root),
+        unsupported: loader.reporter.unsupported,
+    })
+}
+
+/// Load SDF file
+pub fn from_file<T: Real, P: AsRef<Path>>(path: P) -> Result<Imported<T>, ImportError> {
+    from_str(&read_file(path)?)
+}
+
+#[test]
+fn test_sdf() {
+    use std::f64::consts::FRAC_PI_2;
+    let imported = from_str::<f64>(
+        r#"<?xml version="1.0"?>
+<sdf version="1.6">
+  <world name="default">
+    <model name="arm">
+      <link name="base">
+        <inertial>
+          <pose>0 0 0.1 0 0 0</pose>
+          <mass>2.0</mass>
+          <inertia><ixx>1</ixx><iyy>2</iyy><izz>3</izz><ixy>0.1</ixy></inertia>
+        </inertial>
+        <visual name="base_visual">
+          <geometry><box><size>0.2 0.3 0.4</size></box></geometry>
+          <material><diffuse>1 0 0 1</diffuse></material>
+        </visual>
+        <collision name="base_collision">
+          <geometry><cylinder><radius>0.1</radius><length>0.5</length></cylinder></geometry>
+        </collision>
+      </link>
+      <link name="upper">
+        <pose>0 0 1 0 0 1.5707963267948966</pose>
+        <visual name="upper_visual">
+          <pose>0.1 0 0 0 0 0</pose>
+          <geometry><sphere><radius>0.05</radius></sphere></geometry>
+        </visual>
+        <sensor name="imu" type="imu"/>
+      </link>
+      <link name="lower">
+        <pose>0 0 2 0 0 0</pose>
+        <visual>
+          <geometry><mesh><uri>model://arm/lower.stl</uri><scale>2 2 2</scale></mesh></geometry>
+        </visual>
+      </link>
+      <joint name="shoulder" type="revolute">
+        <parent>base</parent>
+        <child>upper</child>
+        <axis><xyz>1 0 0</xyz><limit><lower>-2</lower><upper>2</upper></limit></axis>
+      </joint>
+      <joint name="elbow" type="prismatic">
+        <pose>0 0 -0.5 0 0 0</pose>
+        <parent>upper</parent>
+        <child>lower</child>
+        <axis><xyz>0 0 1</xyz><use_parent_model_frame>true</use_parent_model_frame></axis>
+      </joint>
+      <joint name="wrist" type="universal">
+        <parent>lower</parent>
+        <child>hand</child>
+      </joint>
+      <link name="hand"><pose>0 0 2.5 0 0 0</pose></link>
+    </model>
+    <model name="other"/>
+  </world>
+</sdf>"#,
+    )
+    .unwrap();
+    let chain = imported.chain;
+    assert_eq!(chain.dof(), 2);
+    let paths = imported
+        .unsupported
+        .iter()
+        .map(|u| u.path.as_str())
+        .collect::<Vec<_>>();
+    assert!(paths.contains(&"world[default]/model[other]"));
+    assert!(paths.contains(&"world[default]/model[arm]/link[upper]/sensor[imu]"));
+    assert!(paths.contains(&"world[default]/model[arm]/joint[wrist]"));
+
+    let shoulder = chain.find("shoulder").unwrap();
+    let limits = shoulder.joint().limits.unwrap();
+    assert_eq!((limits.min, limits.max), (-2.0, 2.0));
+    // the axis of the revolute joint is in the joint frame, rotated by the link pose
+    match shoulder.joint().joint_type {
+        JointType::Rotational { axis } => {
+            assert!((axis.into_inner() - Vector3::x()).norm() < 1e-10)
+        }
+        _ => panic!("shoulder must be rotational"),
+    }
+    // the axis of the prismatic joint is in the model frame
+    match chain.find("elbow").unwrap().joint().joint_type {
+        JointType::Linear { axis } => assert!((axis.into_inner() - Vector3::z()).norm() < 1e-10),
+        _ => panic!("elbow must be linear"),
+    }
+
+    chain.set_joint_positions(&[FRAC_PI_2, 0.3]).unwrap();
+    chain.update_transforms();
+    let visual_transform = |joint_name: &str| {
+        let node = chain.find(joint_name).unwrap();
+        let link = node.link();
+        node.world_transform().unwrap() * link.as_ref().unwrap().visuals[0].origin()
+    };
+    // upper link rotates around its own x axis, which is the model y axis
+    let upper = visual_transform("shoulder");
+    assert!((upper.translation.vector - Vector3::new(0.0, 0.1, 1.0)).norm() < 1e-10);
+    // elbow is at z = 1.5 in the initial pose, and its axis is rotated with the upper link
+    let lower = visual_transform("elbow");
+    assert!((lower.translation.vector - Vector3::new(0.5 + 0.3 + 0.5, 0.0, 1.0)).norm() < 1e-10);
+
+    let root = chain.find(ROOT_JOINT_NAME).unwrap();
+    let link = root.link();
+    let base = link.as_ref().unwrap();
+    assert_eq!(base.name, "base");
+    assert_eq!(base.visuals[0].material.color.r, 1.0);
+    match base.visuals[0].geometry {
+        Geometry::Box { depth, width, .. } => assert_eq!((depth, width), (0.2, 0.3)),
+        _ => panic!("base visual must be box"),
+    }
+    assert_eq!(base.collisions.len(), 1);
+    assert_eq!(base.inertial.mass, 2.0);
+    assert_eq!(base.inertial.inertia[(0, 1)], 0.1);
+    assert_eq!(base.inertial.inertia[(1, 0)], 0.1);
+    assert_eq!(base.inertial.origin().translation.vector.z, 0.1);
+    let node = chain.find("elbow").unwrap();
+    let link = node.link();
+    match link.as_ref().unwrap().visuals[0].geometry {
+        Geometry::Mesh {
+            ref filename,
+            ref scale,
+        } => {
+            assert_eq!(filename, "model://arm/lower.stl");
+            assert_eq!(scale.x, 2.0);
+        }
+        _ => panic!("lower visual must be mesh"),
+    }
+}
+
+#[test]
+fn test_sdf_use_parent_model_frame() {
+    let imported = from_str::<f64>(
+        r#"<sdf version="1.6"><model name="m">
+        <link name="base"/>
+        <link name="a"><pose>0 0 1 0 0 1.5707963267948966</pose></link>
+        <link name="b"><pose>0 0 1 0 0 1.5707963267948966</pose></link>
+        <joint name="in_joint_frame" type="revolute">
+          <parent>base</parent><child>a</child>
+          <axis><xyz>1 0 0</xyz><use_parent_model_frame>false</use_parent_model_frame></axis>
+        </joint>
+        <joint name="in_model_frame" type="revolute">
+          <parent>base</parent><child>b</child>
+          <axis><xyz>1 0 0</xyz><use_parent_model_frame>true</use_parent_model_frame></axis>
+        </joint>
+        </model></sdf>"#,
+    )
+    .unwrap();
+    let chain = imported.chain;
+    chain.update_transforms();
+    let axis_in_model = |name: &str| {
+        let node = chain.find(name).unwrap();
+        let axis = match node.joint().joint_type {
+            JointType::Rotational { axis } | JointType::Continuous { axis } => axis,
+            _ => panic!("{} must be rotational", name),
+        };
+        node.world_transform().unwrap().rotation * axis.into_inner()
+    };
+    // x of the joint frame is y of the model
+    assert!((axis_in_model("in_joint_frame") - Vector3::y()).norm() < 1e-10);
+    assert!((axis_in_model("in_model_frame") - Vector3::x()).norm() < 1e-10);
+}
+
+#[test]
+fn test_sdf_multiple_root_links() {
+    use std::f64::consts::FRAC_PI_2;
+    let imported = from_str::<f64>(
+        r#"<sdf version="1.6"><model name="m">
+        <link name="base"/>
+        <link name="box"><pose>1 0 0 0 0 0</pose></link>
+        <link name="case"><pose>0 2 0 0 0 1.5707963267948966</pose></link>
+        <link name="lid"><pose>0 2 0.5 0 0 1.5707963267948966</pose></link>
+        <joint name="hinge" type="revolute">
+          <parent>case</parent><child>lid</child>
+          <axis><xyz>0 1 0</xyz><limit><lower>0</lower><upper>1</upper></limit></axis>
+        </joint>
+        </model></sdf>"#,
+    )
+    .unwrap();
+    let chain = imported.chain;
+    assert_eq!(chain.dof(), 6 + 6 + 1);
+    // the first root link is the link of the root
+    let root = chain.find(ROOT_JOINT_NAME).unwrap();
+    assert_eq!(root.link().as_ref().unwrap().name, "base");
+    for &(name, link_name) in &[("box_floating", "box"), ("case_floating", "case")] {
+        let node = chain.find(name).unwrap();
+        match node.joint().joint_type {
+            JointType::Floating => {}
+            _ => panic!("{} must be floating", name),
+        }
+        assert_eq!(node.parent().unwrap().joint().name, ROOT_JOINT_NAME);
+        assert_eq!(node.link().as_ref().unwrap().name, link_name);
+    }
+    let hinge = chain.find("hinge").unwrap();
+    assert_eq!(hinge.parent().unwrap().joint().name, "case_floating");
+
+    chain.update_transforms();
+    let position = |name: &str| {
+        chain
+            .find(name)
+            .unwrap()
+            .world_transform()
+            .unwrap()
+            .translation
+            .vector
+    };
+    assert!((position("box_floating") - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-10);
+    assert!((position("hinge") - Vector3::new(0.0, 2.0, 0.5)).norm() < 1e-10);
+    // move the case by its floating joint, the lid follows it
+    let offset: usize = chain
+        .iter_joints()
+        .take_while(|joint| joint.name != "case_floating")
+        .map(|joint| joint.dof())
+        .sum();
+    let mut positions = chain.joint_positions();
+    positions[offset..offset + 6].copy_from_slice(&[0.0, 0.0, 1.0, 0.0, 0.0, FRAC_PI_2]);
+    chain.set_joint_positions(&positions).unwrap();
+    chain.update_transforms();
+    assert!((position("case_floating") - Vector3::new(0.0, 2.0, 1.0)).norm() < 1e-10);
+    assert!((position("hinge") - Vector3::new(0.0, 2.0, 1.5)).norm() < 1e-10);
+    assert!((position("box_floating") - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-10);
+}
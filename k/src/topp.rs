@@ -0,0 +1,479 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Time-optimal path parameterization (TOPP-RA)
+//!
+//! Find the fastest timing along a geometric path in joint space which respects
+//! the velocity, acceleration and effort limits of the joints.
+//!
+//! The path is parameterized by the length `s` in joint space. With `u = s''` and
+//! `x = s'^2`, all the limits are linear constraints on `(u, x)` at each waypoint.
+//! The sets of the controllable `x` are computed from the end to the start by
+//! small linear programs (reachability analysis), then the path is followed
+//! greedily with the max acceleration from the start.
+//! The motion starts and stops at rest.
+//!
+//! # Examples
+//!
+//! ```
+//! use k::topp::*;
+//!
+//! let path = (0..=50).map(|i| vec![i as f64 / 50.0, 0.0]).collect::<Vec<_>>();
+//! let mut parameterizer = TimeParameterizer::new(2);
+//! parameterizer.velocity_limits = vec![Some(0.5), None];
+//! parameterizer.acceleration_limits = vec![Some(1.0), Some(1.0)];
+//! let timing = parameterizer.parameterize(&path).unwrap();
+//! // accelerate 0.5s, move 1.5s at the max velocity and decelerate 0.5s
+//! assert!((timing.duration() - 2.5).abs() < 0.1);
+//! ```
+use na::{self, Real};
+
+use chain::*;
+use errors::*;
+use trajectory::*;
+
+/// Bound of `x = s'^2` and `|u| = |s''|` used when the joints have no limits
+const UNBOUNDED: f64 = 1.0e6;
+
+/// Function to calculate the efforts from the positions, velocities and accelerations
+type InverseDynamics<'a, T> = &'a dyn Fn(&[T], &[T], &[T]) -> Vec<T>;
+
+/// Linear constraint `a * u + b * x <= r`
+#[derive(Debug, Clone, Copy)]
+struct LinearConstraint<T: Real> {
+    a: T,
+    b: T,
+    r: T,
+}
+
+impl<T> LinearConstraint<T>
+where
+    T: Real,
+{
+    fn new(a: T, b: T, r: T) -> Self {
+        Self { a, b, r }
+    }
+    fn is_satisfied(&self, u: T, x: T) -> bool {
+        self.a * u + self.b * x <= self.r + T::default_epsilon().sqrt() * (T::one() + self.r.abs())
+    }
+}
+
+/// Maximize `cu * u + cx * x` by checking all the vertices of the feasible polygon
+fn solve_lp<T>(constraints: &[LinearConstraint<T>], cu: T, cx: T) -> Option<(T, T)>
+where
+    T: Real,
+{
+    let mut best: Option<(T, T, T)> = None;
+    for (i, c0) in constraints.iter().enumerate() {
+        for c1 in &constraints[i + 1..] {
+            let det = c0.a * c1.b - c0.b * c1.a;
+            let scale = (c0.a.abs() + c0.b.abs()) * (c1.a.abs() + c1.b.abs());
+            if det.abs() <= T::default_epsilon() * scale {
+                continue;
+            }
+            let u = (c0.r * c1.b - c0.b * c1.r) / det;
+            let x = (c0.a * c1.r - c0.r * c1.a) / det;
+            if constraints.iter().all(|c| c.is_satisfied(u, x)) {
+                let value = cu * u + cx * x;
+                match best {
+                    Some((_, _, best_value)) if value <= best_value => {}
+                    _ => best = Some((u, x, value)),
+                }
+            }
+        }
+    }
+    best.map(|(u, x, _)| (u, x))
+}
+
+/// Result of the time parameterization
+#[derive(Debug, Clone)]
+pub struct PathTiming<T: Real> {
+    /// Time to pass each waypoint, starting from zero
+    pub times: Vec<T>,
+    /// Joint positions (the input waypoints)
+    pub positions: Vec<Vec<T>>,
+    /// Joint velocities at the waypoints
+    pub velocities: Vec<Vec<T>>,
+    /// Joint accelerations at the waypoints
+    pub accelerations: Vec<Vec<T>>,
+}
+
+impl<T> PathTiming<T>
+where
+    T: Real,
+{
+    /// Total time to follow the path
+    pub fn duration(&self) -> T {
+        *self.times.last().unwrap()
+    }
+    /// Create `Trajectory` which passes the waypoints at the times
+    ///
+    /// The limits are satisfied at the waypoints, the motion between them
+    /// depends on `profile`. Dense waypoints keep it close to the limits.
+    pub fn to_trajectory(&self, profile: Profile) -> Result<Trajectory<T>, TrajectoryError> {
+        Trajectory::new(&self.positions, &self.times, profile)
+    }
+}
+
+/// Compute the time-optimal timing of joint paths
+///
+/// `None` means that the joint has no limit.
+#[derive(Debug, Clone)]
+pub struct TimeParameterizer<T: Real> {
+    /// Max absolute velocities of the joints
+    pub velocity_limits: Vec<Option<T>>,
+    /// Max absolute accelerations of the joints
+    pub acceleration_limits: Vec<Option<T>>,
+    /// Max absolute efforts of the joints, used by `parameterize_with_dynamics()`
+    pub effort_limits: Vec<Option<T>>,
+}
+
+impl<T> TimeParameterizer<T>
+where
+    T: Real,
+{
+    /// Create parameterizer for `dof` joints without any limits
+    pub fn new(dof: usize) -> Self {
+        Self {
+            velocity_limits: vec![None; dof],
+            acceleration_limits: vec![None; dof],
+            effort_limits: vec![None; dof],
+        }
+    }
+    /// Create parameterizer with the velocity and effort limits of the joints
+    ///
+    /// Accelerations are not limited, set `acceleration_limits` if needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::topp::TimeParameterizer;
+    ///
+    /// let l0 = JointBuilder::new()
+    ///     .joint_type(JointType::Rotational{axis: Vector3::z_axis()})
+    ///     .velocity_limit(Some(1.0f64))
+    ///     .effort_limit(Some(10.0))
+    ///     .into_node();
+    /// let l1 = JointBuilder::new()
+    ///     .translation(Translation3::new(0.5, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::from_end(&l1);
+    /// let parameterizer = TimeParameterizer::from_chain(&arm);
+    /// assert_eq!(parameterizer.velocity_limits, vec![Some(1.0)]);
+    /// assert_eq!(parameterizer.acceleration_limits, vec![None]);
+    /// ```
+    pub fn from_chain(chain: &Chain<T>) -> Self {
+        Self {
+            velocity_limits: chain
+                .iter_joints()
+                .flat_map(|j| vec![j.velocity_limit; j.dof()])
+                .collect(),
+            acceleration_limits: vec![None; chain.dof()],
+            effort_limits: chain
+                .iter_joints()
+                .flat_map(|j| vec![j.effort_limit; j.dof()])
+                .collect(),
+        }
+    }
+
+    /// Number of the joints
+    pub fn dof(&self) -> usize {
+        self.velocity_limits.len()
+    }
+
+    /// Compute the timing with the velocity and acceleration limits
+    ///
+    /// `path` is the joint positions sampled densely enough along the path.
+    pub fn parameterize(&self, path: &[Vec<T>]) -> Result<PathTiming<T>, TrajectoryError> {
+        self.solve(path, None)
+    }
+
+    /// Compute the timing with the effort limits in addition
+    ///
+    /// `inverse_dynamics(positions, velocities, accelerations)` returns the efforts
+    /// of the joints.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::topp::*;
+    ///
+    /// // a mass of 2.0 on a linear joint against the gravity
+    /// let path = (0..=40).map(|i| vec![i as f64 / 40.0]).collect::<Vec<_>>();
+    /// let mut parameterizer = TimeParameterizer::new(1);
+    /// parameterizer.effort_limits = vec![Some(30.0)];
+    /// let timing = parameterizer
+    ///     .parameterize_with_dynamics(&path, |_, _, acc| vec![2.0 * (acc[0] + 9.8)])
+    ///     .unwrap();
+    /// for acc in &timing.accelerations {
+    ///     assert!(2.0 * (acc[0] + 9.8) < 30.0 + 1e-6);
+    /// }
+    /// ```
+    pub fn parameterize_with_dynamics<F>(
+        &self,
+        path: &[Vec<T>],
+        inverse_dynamics: F,
+    ) -> Result<PathTiming<T>, TrajectoryError>
+    where
+        F: Fn(&[T], &[T], &[T]) -> Vec<T>,
+    {
+        self.solve(path, Some(&inverse_dynamics))
+    }
+
+    fn check_arguments(&self, path: &[Vec<T>]) -> Result<(), TrajectoryError> {
+        let dof = self.dof();
+        if self.acceleration_limits.len() != dof || self.effort_limits.len() != dof {
+            return Err(TrajectoryError::InvalidArgumentsError {
+                error: format!(
+                    "limits length: velocity = {}, acceleration = {}, effort = {}",
+                    dof,
+                    self.acceleration_limits.len(),
+                    self.effort_limits.len()
+                ),
+            });
+        }
+        if self
+            .velocity_limits
+            .iter()
+            .chain(self.acceleration_limits.iter())
+            .chain(self.effort_limits.iter())
+            .filter_map(|limit| *limit)
+            .any(|limit| limit <= T::zero())
+        {
+            return Err(TrajectoryError::InvalidArgumentsError {
+                error: "limits must be positive".to_owned(),
+            });
+        }
+        if path.len() < 2 {
+            return Err(TrajectoryError::InvalidArgumentsError {
+                error: format!("path needs two or more waypoints, got {}", path.len()),
+            });
+        }
+        if let Some(index) = path.iter().position(|q| q.len() != dof) {
+            return Err(TrajectoryError::InvalidArgumentsError {
+                error: format!(
+                    "waypoints[{}] has {} joints, expected {}",
+                    index,
+                    path[index].len(),
+                    dof
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Linear constraints of `(u, x)` at the waypoint
+    fn constraints(
+        &self,
+        position: &[T],
+        derivative: &[T],
+        second_derivative: &[T],
+        inverse_dynamics: Option<InverseDynamics<T>>,
+    ) -> Result<Vec<LinearConstraint<T>>, String> {
+        let unbounded: T = na::convert(UNBOUNDED);
+        let mut constraints = vec![
+            LinearConstraint::new(T::zero(), -T::one(), T::zero()),
+            LinearConstraint::new(T::zero(), T::one(), unbounded),
+            LinearConstraint::new(T::one(), T::zero(), unbounded),
+            LinearConstraint::new(-T::one(), T::zero(), unbounded),
+        ];
+        for (i, limit) in self.velocity_limits.iter().enumerate() {
+            if let Some(limit) = *limit {
+                if derivative[i] != T::zero() {
+                    let max_x = limit / derivative[i];
+                    constraints.push(LinearConstraint::new(T::zero(), T::one(), max_x * max_x));
+                }
+            }
+        }
+        // q'' = q' * u + q'' * x
+        for (i, limit) in self.acceleration_limits.iter().enumerate() {
+            if let Some(limit) = *limit {
+                let (a, b) = (derivative[i], second_derivative[i]);
+                constraints.push(LinearConstraint::new(a, b, limit));
+                constraints.push(LinearConstraint::new(-a, -b, limit));
+            }
+        }
+        // The efforts are affine in (u, x): a * u + b * x + c
+        if let Some(inverse_dynamics) = inverse_dynamics {
+            if self.effort_limits.iter().any(|l| l.is_some()) {
+                let zeros = vec![T::zero(); self.dof()];
+                let c = inverse_dynamics(position, &zeros, &zeros);
+                let a = inverse_dynamics(position, &zeros, derivative);
+                let b = inverse_dynamics(position, derivative, second_derivative);
+                if [&c, &a, &b].iter().any(|e| e.len() != self.dof()) {
+                    return Err(format!(
+                        "inverse dynamics returned {} efforts, expected {}",
+                        c.len(),
+                        self.dof()
+                    ));
+                }
+                for (i, limit) in self.effort_limits.iter().enumerate() {
+                    if let Some(limit) = *limit {
+                        let (a, b) = (a[i] - c[i], b[i] - c[i]);
+                        constraints.push(LinearConstraint::new(a, b, limit - c[i]));
+                        constraints.push(LinearConstraint::new(-a, -b, limit + c[i]));
+                    }
+                }
+            }
+        }
+        Ok(constraints)
+    }
+
+    fn solve(
+        &self,
+        path: &[Vec<T>],
+        inverse_dynamics: Option<InverseDynamics<T>>,
+    ) -> Result<PathTiming<T>, TrajectoryError> {
+        self.check_arguments(path)?;
+        let dof = self.dof();
+        let n = path.len() - 1;
+        let two: T = na::convert(2.0);
+
+        // path parameter is the length in joint space
+        let mut s = vec![T::zero(); n + 1];
+        for i in 0..n {
+            let length = path[i]
+                .iter()
+                .zip(path[i + 1].iter())
+                .fold(T::zero(), |sum, (a, b)| sum + (*b - *a) * (*b - *a))
+                .sqrt();
+            if length == T::zero() {
+                return Err(TrajectoryError::InvalidArgumentsError {
+                    error: format!("waypoints[{}] and waypoints[{}] are the same", i, i + 1),
+                });
+            }
+            s[i + 1] = s[i] + length;
+        }
+
+        // derivatives by (non-uniform) finite differences
+        let mut derivatives = vec![vec![T::zero(); dof]; n + 1];
+        let mut second_derivatives = vec![vec![T::zero(); dof]; n + 1];
+        for i in 0..=n {
+            let (prev, next) = (if i == 0 { 0 } else { i - 1 }, (i + 1).min(n));
+            for j in 0..dof {
+                derivatives[i][j] = (path[next][j] - path[prev][j]) / (s[next] - s[prev]);
+            }
+            if i > 0 && i < n {
+                let (h0, h1) = (s[i] - s[i - 1], s[i + 1] - s[i]);
+                for j in 0..dof {
+                    second_derivatives[i][j] = ((path[i + 1][j] - path[i][j]) / h1
+                        - (path[i][j] - path[i - 1][j]) / h0)
+                        * two
+                        / (h0 + h1);
+                }
+            }
+        }
+        if n > 1 {
+            second_derivatives[0] = second_derivatives[1].clone();
+            second_derivatives[n] = second_derivatives[n - 1].clone();
+        }
+
+        let constraints = (0..=n)
+            .map(|i| {
+                self.constraints(
+                    &path[i],
+                    &derivatives[i],
+                    &second_derivatives[i],
+                    inverse_dynamics,
+                )
+                .map_err(|error| TrajectoryError::InvalidArgumentsError { error })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // backward pass: controllable range of x at each waypoint (stop at the end)
+        let mut controllable = vec![(T::zero(), T::zero()); n + 1];
+        for i in (0..n).rev() {
+            let delta = s[i + 1] - s[i];
+            let (min_next, max_next) = controllable[i + 1];
+            let mut cs = constraints[i].clone();
+            cs.push(LinearConstraint::new(two * delta, T::one(), max_next));
+            cs.push(LinearConstraint::new(-two * delta, -T::one(), -min_next));
+            let max_x = solve_lp(&cs, T::zero(), T::one());
+            let min_x = solve_lp(&cs, T::zero(), -T::one());
+            match (min_x, max_x) {
+                (Some((_, min_x)), Some((_, max_x))) => {
+                    controllable[i] = (min_x.max(T::zero()), max_x.max(T::zero()))
+                }
+                _ => {
+                    return Err(TrajectoryError::PathInfeasibleError {
+                        index: i,
+                        message: "no feasible velocity to reach the end".to_owned(),
+                    })
+                }
+            }
+        }
+        if controllable[0].0 > T::default_epsilon().sqrt() {
+            return Err(TrajectoryError::PathInfeasibleError {
+                index: 0,
+                message: format!("can not start at rest, min s'^2 = {}", controllable[0].0),
+            });
+        }
+
+        // forward pass: accelerate as much as possible staying controllable
+        let unbounded: T = na::convert(UNBOUNDED);
+        let mut xs = vec![T::zero(); n + 1];
+        let mut us = vec![T::zero(); n + 1];
+        for i in 0..n {
+            let delta = s[i + 1] - s[i];
+            let x = xs[i];
+            let mut u = unbounded;
+            for c in &constraints[i] {
+                if c.a > T::zero() {
+                    u = u.min((c.r - c.b * x) / c.a);
+                }
+            }
+            let (min_next, max_next) = controllable[i + 1];
+            let next_x = (x + two * delta * u).min(max_next).max(min_next);
+            us[i] = (next_x - x) / (two * delta);
+            xs[i + 1] = next_x;
+        }
+        us[n] = us[n - 1];
+
+        let mut times = vec![T::zero(); n + 1];
+        for i in 0..n {
+            let speed = xs[i].sqrt() + xs[i + 1].sqrt();
+            if speed <= T::zero() {
+                return Err(TrajectoryError::PathInfeasibleError {
+                    index: i,
+                    message: "can not move forward".to_owned(),
+                });
+            }
+            times[i + 1] = times[i] + two * (s[i + 1] - s[i]) / speed;
+        }
+        let velocities = (0..=n)
+            .map(|i| {
+                let ds = xs[i].sqrt();
+                derivatives[i].iter().map(|d| *d * ds).collect()
+            })
+            .collect();
+        let accelerations = (0..=n)
+            .map(|i| {
+                derivatives[i]
+                    .iter()
+                    .zip(second_derivatives[i].iter())
+                    .map(|(d, dd)| *d * us[i] + *dd * xs[i])
+                    .collect()
+            })
+            .collect();
+        Ok(PathTiming {
+            times,
+            positions: path.to_vec(),
+            velocities,
+            accelerations,
+        })
+    }
+}
@@ -0,0 +1,619 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Time-parameterized joint space trajectory
+//!
+//! # Examples
+//!
+//! ```
+//! use k::trajectory::*;
+//!
+//! let waypoints = vec![vec![0.0f64, 0.0], vec![1.0, -0.5], vec![0.5, 0.5]];
+//! let trajectory = Trajectory::new(&waypoints, &[0.0, 1.0, 3.0], Profile::Quintic).unwrap();
+//! let point = trajectory.sample(1.0);
+//! assert!((point.position[0] - 1.0).abs() < 1e-10);
+//! assert!((point.position[1] + 0.5).abs() < 1e-10);
+//! assert_eq!(trajectory.end_time(), 3.0);
+//! ```
+use na::{self, Real};
+use std::cmp::Ordering;
+
+use errors::*;
+use joint::normalize_angle;
+
+/// Number of the samples per segment to check the limits
+const VALIDATION_SAMPLES: usize = 100;
+
+/// Shape of the motion between waypoints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    /// Cubic polynomial. Velocity is continuous.
+    Cubic,
+    /// Quintic polynomial. Velocity and acceleration are continuous.
+    Quintic,
+    /// Constant acceleration, constant velocity and constant deceleration.
+    /// It stops at every waypoint.
+    Trapezoidal,
+}
+
+/// Position, velocity and acceleration of the joints at a time
+#[derive(Debug, Clone)]
+pub struct TrajectoryPoint<T: Real> {
+    pub position: Vec<T>,
+    pub velocity: Vec<T>,
+    pub acceleration: Vec<T>,
+}
+
+#[derive(Debug, Clone)]
+enum JointProfile<T: Real> {
+    /// coefficients of `c0 + c1 t + ... + c5 t^5`
+    Polynomial([T; 6]),
+    /// `blend` is the duration of the acceleration (and deceleration)
+    Trapezoidal { start: T, distance: T, blend: T },
+}
+
+impl<T> JointProfile<T>
+where
+    T: Real,
+{
+    fn sample(&self, t: T, duration: T) -> (T, T, T) {
+        match *self {
+            JointProfile::Polynomial(c) => {
+                let two: T = na::convert(2.0);
+                let three: T = na::convert(3.0);
+                let four: T = na::convert(4.0);
+                let five: T = na::convert(5.0);
+                let six: T = na::convert(6.0);
+                let twelve: T = na::convert(12.0);
+                let twenty: T = na::convert(20.0);
+                let p = c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * (c[4] + t * c[5]))));
+                let v = c[1]
+                    + t * (two * c[2] + t * (three * c[3] + t * (four * c[4] + t * five * c[5])));
+                let a = two * c[2] + t * (six * c[3] + t * (twelve * c[4] + t * twenty * c[5]));
+                (p, v, a)
+            }
+            JointProfile::Trapezoidal {
+                start,
+                distance,
+                blend,
+            } => {
+                if blend <= T::zero() || distance == T::zero() {
+                    return (start, T::zero(), T::zero());
+                }
+                let half: T = na::convert(0.5);
+                let acc = distance / (blend * (duration - blend));
+                if t < blend {
+                    (start + half * acc * t * t, acc * t, acc)
+                } else if t <= duration - blend {
+                    (
+                        start + half * acc * blend * blend + acc * blend * (t - blend),
+                        acc * blend,
+                        T::zero(),
+                    )
+                } else {
+                    let rest = duration - t;
+                    (
+                        start + distance - half * acc * rest * rest,
+                        acc * rest,
+                        -acc,
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Segment<T: Real> {
+    start_time: T,
+    duration: T,
+    joints: Vec<JointProfile<T>>,
+}
+
+fn cubic_coefficients<T: Real>(q0: T, q1: T, v0: T, v1: T, duration: T) -> [T; 6] {
+    let h = q1 - q0;
+    let t = duration;
+    let two: T = na::convert(2.0);
+    let three: T = na::convert(3.0);
+    [
+        q0,
+        v0,
+        (three * h - (two * v0 + v1) * t) / (t * t),
+        (-two * h + (v0 + v1) * t) / (t * t * t),
+        T::zero(),
+        T::zero(),
+    ]
+}
+
+/// Quintic polynomial with zero accelerations at the both ends
+fn quintic_coefficients<T: Real>(q0: T, q1: T, v0: T, v1: T, duration: T) -> [T; 6] {
+    let h = q1 - q0;
+    let t = duration;
+    let t3 = t * t * t;
+    let c = |x: f64| -> T { na::convert(x) };
+    [
+        q0,
+        v0,
+        T::zero(),
+        (c(20.0) * h - (c(8.0) * v1 + c(12.0) * v0) * t) / (c(2.0) * t3),
+        (c(-30.0) * h + (c(14.0) * v1 + c(16.0) * v0) * t) / (c(2.0) * t3 * t),
+        (c(12.0) * h - c(6.0) * (v1 + v0) * t) / (c(2.0) * t3 * t * t),
+    ]
+}
+
+/// Shortest duration of rest-to-rest motion of `distance`
+fn minimum_duration<T: Real>(
+    profile: Profile,
+    distance: T,
+    max_velocity: T,
+    max_acceleration: T,
+) -> T {
+    let d = distance.abs();
+    match profile {
+        Profile::Cubic => (d * na::convert(1.5) / max_velocity)
+            .max((d * na::convert(6.0) / max_acceleration).sqrt()),
+        Profile::Quintic => (d * na::convert(1.875) / max_velocity)
+            .max((d * na::convert(10.0 / 3.0f64.sqrt()) / max_acceleration).sqrt()),
+        Profile::Trapezoidal => {
+            if d * max_acceleration >= max_velocity * max_velocity {
+                d / max_velocity + max_velocity / max_acceleration
+            } else {
+                (d / max_acceleration).sqrt() * na::convert(2.0)
+            }
+        }
+    }
+}
+
+/// Rewrite the positions of the continuous joints to take the short way around
+///
+/// The positions of the joints marked in `continuous` are shifted by `2 pi * n`
+/// so that every step between waypoints is in (-pi, pi]. The trajectory of the
+/// result may go out of (-pi, pi], but `Joint::set_joint_position()` normalizes it.
+///
+/// # Examples
+///
+/// ```
+/// use k::trajectory::*;
+///
+/// let waypoints = vec![vec![3.0f64, 3.0], vec![-3.0, -3.0]];
+/// let unwrapped = unwrap_continuous_waypoints(&waypoints, &[true, false]);
+/// assert!((unwrapped[1][0] - (2.0 * std::f64::consts::PI - 3.0)).abs() < 1e-10);
+/// assert_eq!(unwrapped[1][1], -3.0);
+/// ```
+pub fn unwrap_continuous_waypoints<T>(waypoints: &[Vec<T>], continuous: &[bool]) -> Vec<Vec<T>>
+where
+    T: Real,
+{
+    let mut unwrapped = waypoints.to_vec();
+    for k in 1..unwrapped.len() {
+        for (i, is_continuous) in continuous.iter().enumerate() {
+            if *is_continuous && i < unwrapped[k].len() {
+                let step = normalize_angle(waypoints[k][i] - waypoints[k - 1][i]);
+                unwrapped[k][i] = unwrapped[k - 1][i] + step;
+            }
+        }
+    }
+    unwrapped
+}
+
+/// Time-parameterized path through joint waypoints
+#[derive(Debug, Clone)]
+pub struct Trajectory<T: Real> {
+    dof: usize,
+    times: Vec<T>,
+    segments: Vec<Segment<T>>,
+}
+
+impl<T> Trajectory<T>
+where
+    T: Real,
+{
+    /// Create the trajectory which passes `waypoints` at `times`
+    ///
+    /// `times` must be increasing. The velocities at the start and the end are zero.
+    /// For polynomial profiles, the velocity at a middle waypoint is the average of
+    /// the slopes before and after it, or zero if the direction changes there.
+    /// For `Trapezoidal`, one third of each segment is used to accelerate and decelerate.
+    pub fn new(
+        waypoints: &[Vec<T>],
+        times: &[T],
+        profile: Profile,
+    ) -> Result<Self, TrajectoryError> {
+        Self::build(waypoints, times, profile, None)
+    }
+
+    /// Create the trajectory like `new()`, taking the short way around for continuous joints
+    ///
+    /// The joints marked in `continuous` are unwrapped by `unwrap_continuous_waypoints()`,
+    /// so the positions in the trajectory may go out of (-pi, pi].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::trajectory::*;
+    ///
+    /// let waypoints = vec![vec![3.0f64], vec![-3.0]];
+    /// let trajectory =
+    ///     Trajectory::new_continuous(&waypoints, &[0.0, 1.0], Profile::Cubic, &[true]).unwrap();
+    /// let middle = trajectory.sample(0.5).position[0];
+    /// assert!((middle - std::f64::consts::PI).abs() < 1e-10);
+    /// ```
+    pub fn new_continuous(
+        waypoints: &[Vec<T>],
+        times: &[T],
+        profile: Profile,
+        continuous: &[bool],
+    ) -> Result<Self, TrajectoryError> {
+        let waypoints = unwrap_continuous_waypoints(waypoints, continuous);
+        Self::build(&waypoints, times, profile, None)
+    }
+
+    /// Create the fastest trajectory of `profile` in the limits
+    ///
+    /// The durations of the segments are calculated as rest-to-rest motions,
+    /// then the whole trajectory is slowed down uniformly if it still exceeds the limits.
+    /// Consecutive duplicated waypoints are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::trajectory::*;
+    ///
+    /// let waypoints = vec![vec![0.0f64, 0.0], vec![1.0, 0.2], vec![2.0, 0.0]];
+    /// let trajectory = Trajectory::with_limits(
+    ///     &waypoints, &[1.0, 1.0], &[2.0, 2.0], Profile::Trapezoidal).unwrap();
+    /// assert!(trajectory.validate(&[1.0, 1.0], &[2.0, 2.0]).is_ok());
+    /// assert!(trajectory.validate(&[0.5, 1.0], &[2.0, 2.0]).is_err());
+    /// ```
+    pub fn with_limits(
+        waypoints: &[Vec<T>],
+        velocity_limits: &[T],
+        acceleration_limits: &[T],
+        profile: Profile,
+    ) -> Result<Self, TrajectoryError> {
+        Self::with_limits_continuous(
+            waypoints,
+            velocity_limits,
+            acceleration_limits,
+            profile,
+            &[],
+        )
+    }
+
+    /// Create the fastest trajectory like `with_limits()`, taking the short way around
+    /// for the joints marked in `continuous`
+    pub fn with_limits_continuous(
+        waypoints: &[Vec<T>],
+        velocity_limits: &[T],
+        acceleration_limits: &[T],
+        profile: Profile,
+        continuous: &[bool],
+    ) -> Result<Self, TrajectoryError> {
+        let mut waypoints = unwrap_continuous_waypoints(waypoints, continuous);
+        waypoints.dedup();
+        let dof = check_waypoints(&waypoints)?;
+        check_limits(dof, velocity_limits, acceleration_limits)?;
+        let mut times = vec![T::zero()];
+        for pair in waypoints.windows(2) {
+            let duration = (0..dof).fold(T::zero(), |max, i| {
+                max.max(minimum_duration(
+                    profile,
+                    pair[1][i] - pair[0][i],
+                    velocity_limits[i],
+                    acceleration_limits[i],
+                ))
+            });
+            let last = times[times.len() - 1];
+            times.push(last + duration);
+        }
+        let trajectory = Self::build(&waypoints, &times, profile, Some(acceleration_limits))?;
+        let (velocity_ratio, acceleration_ratio) =
+            trajectory.max_limit_ratios(velocity_limits, acceleration_limits);
+        let scale = velocity_ratio.max(acceleration_ratio.sqrt());
+        if scale <= T::one() {
+            return Ok(trajectory);
+        }
+        let times = times.iter().map(|t| *t * scale).collect::<Vec<_>>();
+        Self::build(&waypoints, &times, profile, Some(acceleration_limits))
+    }
+
+    fn build(
+        waypoints: &[Vec<T>],
+        times: &[T],
+        profile: Profile,
+        acceleration_limits: Option<&[T]>,
+    ) -> Result<Self, TrajectoryError> {
+        let dof = check_waypoints(waypoints)?;
+        if times.len() != waypoints.len() {
+            return Err(TrajectoryError::InvalidArgumentsError {
+                error: format!(
+                    "times length = {}, waypoints length = {}",
+                    times.len(),
+                    waypoints.len()
+                ),
+            });
+        }
+        if times.windows(2).any(|t| t[1] <= t[0]) {
+            return Err(TrajectoryError::InvalidArgumentsError {
+                error: "times must be increasing".to_owned(),
+            });
+        }
+        let velocities = waypoint_velocities(waypoints, times);
+        let third: T = na::convert(1.0 / 3.0);
+        let half: T = na::convert(0.5);
+        let segments = (0..waypoints.len() - 1)
+            .map(|k| {
+                let duration = times[k + 1] - times[k];
+                let joints = (0..dof)
+                    .map(|i| {
+                        let (q0, q1) = (waypoints[k][i], waypoints[k + 1][i]);
+                        let (v0, v1) = (velocities[k][i], velocities[k + 1][i]);
+                        match profile {
+                            Profile::Cubic => JointProfile::Polynomial(cubic_coefficients(
+                                q0, q1, v0, v1, duration,
+                            )),
+                            Profile::Quintic => JointProfile::Polynomial(quintic_coefficients(
+                                q0, q1, v0, v1, duration,
+                            )),
+                            Profile::Trapezoidal => {
+                                let distance = q1 - q0;
+                                let blend = match acceleration_limits {
+                                    // use the max acceleration and the minimum velocity
+                                    Some(limits) => {
+                                        let d = duration * duration
+                                            - distance.abs() * na::convert(4.0) / limits[i];
+                                        (duration - d.max(T::zero()).sqrt()) * half
+                                    }
+                                    None => duration * third,
+                                };
+                                JointProfile::Trapezoidal {
+                                    start: q0,
+                                    distance,
+                                    blend,
+                                }
+                            }
+                        }
+                    })
+                    .collect();
+                Segment {
+                    start_time: times[k],
+                    duration,
+                    joints,
+                }
+            })
+            .collect();
+        Ok(Self {
+            dof,
+            times: times.to_vec(),
+            segments,
+        })
+    }
+
+    /// Number of the joints
+    pub fn dof(&self) -> usize {
+        self.dof
+    }
+    /// Times of the waypoints
+    pub fn times(&self) -> &[T] {
+        &self.times
+    }
+    /// Time of the first waypoint
+    pub fn start_time(&self) -> T {
+        self.times[0]
+    }
+    /// Time of the last waypoint
+    pub fn end_time(&self) -> T {
+        self.times[self.times.len() - 1]
+    }
+    /// Duration of the whole trajectory
+    pub fn duration(&self) -> T {
+        self.end_time() - self.start_time()
+    }
+
+    /// Get (q, dq, ddq) at time `t`
+    ///
+    /// `t` is clamped into [`start_time()`, `end_time()`].
+    pub fn sample(&self, t: T) -> TrajectoryPoint<T> {
+        let t = t.max(self.start_time()).min(self.end_time());
+        let segment = self
+            .segments
+            .iter()
+            .find(|s| t <= s.start_time + s.duration)
+            .unwrap_or_else(|| &self.segments[self.segments.len() - 1]);
+        let local_t = t - segment.start_time;
+        let mut point = TrajectoryPoint {
+            position: Vec::with_capacity(self.dof),
+            velocity: Vec::with_capacity(self.dof),
+            acceleration: Vec::with_capacity(self.dof),
+        };
+        for joint in &segment.joints {
+            let (p, v, a) = joint.sample(local_t, segment.duration);
+            point.position.push(p);
+            point.velocity.push(v);
+            point.acceleration.push(a);
+        }
+        point
+    }
+
+    /// Sample the trajectory at every `dt` from the start to the end (included)
+    ///
+    /// Returns `InvalidArgumentsError` if `dt` is not positive, or too small to
+    /// advance the time.
+    pub fn sample_all(&self, dt: T) -> Result<Vec<(T, TrajectoryPoint<T>)>, TrajectoryError> {
+        let start = self.start_time();
+        let end = self.end_time();
+        // zero, negative or too small dt never reaches the end
+        if dt.partial_cmp(&T::zero()) != Some(Ordering::Greater) || end + dt == end {
+            return Err(TrajectoryError::InvalidArgumentsError {
+                error: format!("dt must be positive and large enough, but {}", dt),
+            });
+        }
+        let num_steps = match na::try_convert::<T, f64>(((end - start) / dt).ceil()) {
+            Some(steps) if steps.is_finite() => steps as usize,
+            _ => {
+                return Err(TrajectoryError::InvalidArgumentsError {
+                    error: format!("invalid dt {} for duration {}", dt, end - start),
+                })
+            }
+        };
+        let mut samples = Vec::with_capacity(num_steps + 1);
+        for i in 0..num_steps {
+            let t = start + dt * na::convert(i as f64);
+            if t >= end {
+                break;
+            }
+            samples.push((t, self.sample(t)));
+        }
+        samples.push((end, self.sample(end)));
+        Ok(samples)
+    }
+
+    fn for_each_check_sample<F>(&self, mut f: F)
+    where
+        F: FnMut(T, &TrajectoryPoint<T>),
+    {
+        for segment in &self.segments {
+            for j in 0..=VALIDATION_SAMPLES {
+                let ratio: T = na::convert(j as f64 / VALIDATION_SAMPLES as f64);
+                let t = segment.start_time + segment.duration * ratio;
+                f(t, &self.sample(t));
+            }
+        }
+    }
+
+    /// Max of |velocity| / limit and |acceleration| / limit
+    fn max_limit_ratios(&self, velocity_limits: &[T], acceleration_limits: &[T]) -> (T, T) {
+        let mut velocity_ratio = T::zero();
+        let mut acceleration_ratio = T::zero();
+        self.for_each_check_sample(|_, point| {
+            for i in 0..self.dof {
+                velocity_ratio = velocity_ratio.max(point.velocity[i].abs() / velocity_limits[i]);
+                acceleration_ratio =
+                    acceleration_ratio.max(point.acceleration[i].abs() / acceleration_limits[i]);
+            }
+        });
+        (velocity_ratio, acceleration_ratio)
+    }
+
+    /// Check the velocities and accelerations are in the limits
+    ///
+    /// Limits are the absolute values for each joint. The trajectory is checked at
+    /// sampled points in each segment.
+    pub fn validate(
+        &self,
+        velocity_limits: &[T],
+        acceleration_limits: &[T],
+    ) -> Result<(), TrajectoryError> {
+        check_limits(self.dof, velocity_limits, acceleration_limits)?;
+        let tolerance: T = na::convert(1e-9);
+        let mut result = Ok(());
+        self.for_each_check_sample(|t, point| {
+            if result.is_err() {
+                return;
+            }
+            for i in 0..self.dof {
+                if point.velocity[i].abs() > velocity_limits[i] * (T::one() + tolerance) {
+                    result = Err(TrajectoryError::VelocityLimitError {
+                        joint_index: i,
+                        message: format!(
+                            "velocity = {} at t = {}, limit = {}",
+                            point.velocity[i], t, velocity_limits[i]
+                        ),
+                    });
+                    return;
+                }
+                if point.acceleration[i].abs() > acceleration_limits[i] * (T::one() + tolerance) {
+                    result = Err(TrajectoryError::AccelerationLimitError {
+                        joint_index: i,
+                        message: format!(
+                            "acceleration = {} at t = {}, limit = {}",
+                            point.acceleration[i], t, acceleration_limits[i]
+                        ),
+                    });
+                    return;
+                }
+            }
+        });
+        result
+    }
+}
+
+fn check_waypoints<T: Real>(waypoints: &[Vec<T>]) -> Result<usize, TrajectoryError> {
+    if waypoints.len() < 2 {
+        return Err(TrajectoryError::InvalidArgumentsError {
+            error: format!("at least 2 waypoints are required, got {}", waypoints.len()),
+        });
+    }
+    let dof = waypoints[0].len();
+    if waypoints.iter().any(|w| w.len() != dof) {
+        return Err(TrajectoryError::InvalidArgumentsError {
+            error: "all waypoints must have the same length".to_owned(),
+        });
+    }
+    Ok(dof)
+}
+
+fn check_limits<T: Real>(
+    dof: usize,
+    velocity_limits: &[T],
+    acceleration_limits: &[T],
+) -> Result<(), TrajectoryError> {
+    if velocity_limits.len() != dof || acceleration_limits.len() != dof {
+        return Err(TrajectoryError::InvalidArgumentsError {
+            error: format!(
+                "limits length = ({}, {}), dof = {}",
+                velocity_limits.len(),
+                acceleration_limits.len(),
+                dof
+            ),
+        });
+    }
+    if velocity_limits
+        .iter()
+        .chain(acceleration_limits.iter())
+        .any(|l| *l <= T::zero())
+    {
+        return Err(TrajectoryError::InvalidArgumentsError {
+            error: "limits must be positive".to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Velocities at the waypoints for the polynomial profiles
+fn waypoint_velocities<T: Real>(waypoints: &[Vec<T>], times: &[T]) -> Vec<Vec<T>> {
+    let n = waypoints.len();
+    let dof = waypoints[0].len();
+    let half: T = na::convert(0.5);
+    (0..n)
+        .map(|k| {
+            (0..dof)
+                .map(|i| {
+                    if k == 0 || k == n - 1 {
+                        return T::zero();
+                    }
+                    let before =
+                        (waypoints[k][i] - waypoints[k - 1][i]) / (times[k] - times[k - 1]);
+                    let after = (waypoints[k + 1][i] - waypoints[k][i]) / (times[k + 1] - times[k]);
+                    if before * after <= T::zero() {
+                        T::zero()
+                    } else {
+                        (before + after) * half
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -13,18 +13,21 @@
   See the License for the specific language governing permissions and
   limitations under the License.
 */
-//! Load [URDF](http://wiki.ros.org/urdf) format and create `k::Chain`
+//! Load [URDF](http://wiki.ros.org/urdf) format and create `k::Chain`, or export `k::Chain` as URDF
 //!
 use urdf_rs;
 
-use na::{self, Isometry3, Matrix3, Real};
-use std::collections::HashMap;
+use na::{self, Isometry3, Matrix3, Real, Vector3};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::path::Path;
 
 use chain::*;
+use errors::*;
 use joint::*;
 use link::*;
 use node::*;
+use resource::*;
 
 pub const ROOT_JOINT_NAME: &str = "root";
 
@@ -216,33 +219,94 @@ where
     na::convert(na::Translation3::new(array3[0], array3[1], array3[2]))
 }
 
+/// Returns true if the limit is [0, 0], which is the default of urdf_rs without `<limit>`
+fn is_locked_by_default(joint: &urdf_rs::Joint) -> bool {
+    joint.limit.lower == 0.0 && joint.limit.upper == 0.0
+}
+
 impl<'a, T> From<&'a urdf_rs::Joint> for Joint<T>
 where
     T: Real,
 {
     fn from(joint: &urdf_rs::Joint) -> Joint<T> {
-        let limit = if (joint.limit.upper - joint.limit.lower) == 0.0 {
+        // Only revolute and prismatic joints have limits. Zero width range is
+        // a valid (locked) limit, not "no limit", but infinite limits are.
+        let limit = match joint.joint_type {
+            urdf_rs::JointType::Revolute | urdf_rs::JointType::Prismatic
+                if joint.limit.lower == f64::NEG_INFINITY && joint.limit.upper == f64::INFINITY =>
+            {
+                None
+            }
+            urdf_rs::JointType::Revolute | urdf_rs::JointType::Prismatic => {
+                if is_locked_by_default(joint) {
+                    warn!(
+                        "joint {} has limit [0, 0] and can not move, <limit> may be missing",
+                        joint.name
+                    );
+                }
+                Some(Range::new(
+                    na::convert(joint.limit.lower),
+                    na::convert(joint.limit.upper),
+                ))
+            }
+            _ => None,
+        };
+        // zero means "not specified" in urdf_rs
+        let positive = |value: f64| {
+            if value > 0.0 {
+                Some(na::convert(value))
+            } else {
+                None
+            }
+        };
+        let safety = &joint.safety_controller;
+        let safety_controller = if safety.soft_lower_limit == 0.0
+            && safety.soft_upper_limit == 0.0
+            && safety.k_position == 0.0
+            && safety.k_velocity == 0.0
+        {
             None
         } else {
-            Some(Range::new(
-                na::convert(joint.limit.lower),
-                na::convert(joint.limit.upper),
+            Some(SafetyController::new(
+                Range::new(
+                    na::convert(safety.soft_lower_limit),
+                    na::convert(safety.soft_upper_limit),
+                ),
+                na::convert(safety.k_position),
+                na::convert(safety.k_velocity),
+            ))
+        };
+        let dynamics = if joint.dynamics.damping == 0.0 && joint.dynamics.friction == 0.0 {
+            None
+        } else {
+            Some(Dynamics::new(
+                na::convert(joint.dynamics.damping),
+                na::convert(joint.dynamics.friction),
             ))
         };
         JointBuilder::<T>::new()
             .name(&joint.name)
             .joint_type(match joint.joint_type {
-                urdf_rs::JointType::Revolute | urdf_rs::JointType::Continuous => {
-                    JointType::Rotational {
-                        axis: axis_from(joint.axis.xyz),
-                    }
-                }
+                urdf_rs::JointType::Revolute => JointType::Rotational {
+                    axis: axis_from(joint.axis.xyz),
+                },
+                urdf_rs::JointType::Continuous => JointType::Continuous {
+                    axis: axis_from(joint.axis.xyz),
+                },
                 urdf_rs::JointType::Prismatic => JointType::Linear {
                     axis: axis_from(joint.axis.xyz),
                 },
+                urdf_rs::JointType::Planar => JointType::Planar {
+                    normal: axis_from(joint.axis.xyz),
+                },
+                urdf_rs::JointType::Floating => JointType::Floating,
                 _ => JointType::Fixed,
             })
             .limits(limit)
+            .velocity_limit(positive(joint.limit.velocity))
+            .effort_limit(positive(joint.limit.effort))
+            .safety_controller(safety_controller)
+            .dynamics(dynamics)
             .rotation(quaternion_from(&joint.origin.rpy))
             .translation(translation_from(&joint.origin.xyz))
             .finalize()
@@ -293,10 +357,13 @@ where
             if j.mimic.joint != "" {
                 debug!("mimic found for {}", j.mimic.joint);
                 let mut child = joint_name_to_node[&j.name].clone();
-                let parent = joint_name_to_node
-                    .get(&j.mimic.joint)
-                    .unwrap_or_else(|| panic!("{} not found, mimic not found", &j.mimic.joint));
-                child.set_mimic_parent(parent, (&j.mimic).into());
+                match joint_name_to_node.get(&j.mimic.joint) {
+                    Some(parent) => child.set_mimic_parent(parent, (&j.mimic).into()),
+                    None => warn!(
+                        "{} not found, mimic of {} is ignored",
+                        j.mimic.joint, j.name
+                    ),
+                }
             }
         }
         // set root as parent of root joint nodes
@@ -320,18 +387,848 @@ where
     }
 }
 
+/// Returns names which are used more than once, with the counts
+fn duplicated_names<'a, I>(names: I) -> Vec<(String, usize)>
+where
+    I: Iterator<Item = &'a String>,
+{
+    let mut counts = Vec::<(String, usize)>::new();
+    for name in names {
+        match counts.iter_mut().find(|c| c.0 == *name) {
+            Some(count) => count.1 += 1,
+            None => counts.push((name.clone(), 1)),
+        }
+    }
+    counts.retain(|c| c.1 > 1);
+    counts
+}
+
+/// Check the URDF and returns all the problems found
+///
+/// `Chain::try_from_urdf()` fails if this returns any problems.
+/// `Chain::from()` accepts them, but the result may be broken
+/// (e.g. NaN axis, missing mimic, ignored links).
+///
+/// # Examples
+///
+/// ```
+/// extern crate urdf_rs;
+/// extern crate k;
+///
+/// let mut robot = urdf_rs::Robot::default();
+/// robot.links.push(urdf_rs::Link {
+///     name: "base".to_owned(),
+///     ..Default::default()
+/// });
+/// robot.links.push(urdf_rs::Link {
+///     name: "base".to_owned(),
+///     ..Default::default()
+/// });
+/// let problems = k::urdf::validate(&robot);
+/// assert_eq!(problems.len(), 1);
+/// assert_eq!(
+///     problems[0],
+///     k::UrdfProblem::DuplicateLinkName {
+///         link_name: "base".to_owned(),
+///         count: 2
+///     }
+/// );
+/// ```
+pub fn validate(robot: &urdf_rs::Robot) -> Vec<UrdfProblem> {
+    let mut problems = Vec::new();
+    for (joint_name, count) in duplicated_names(robot.joints.iter().map(|j| &j.name)) {
+        problems.push(UrdfProblem::DuplicateJointName { joint_name, count });
+    }
+    for (link_name, count) in duplicated_names(robot.links.iter().map(|l| &l.name)) {
+        problems.push(UrdfProblem::DuplicateLinkName { link_name, count });
+    }
+    let link_names = robot.links.iter().map(|l| &l.name).collect::<HashSet<_>>();
+    let joint_names = robot.joints.iter().map(|j| &j.name).collect::<HashSet<_>>();
+    let mut parent_joints = HashMap::<&String, Vec<&urdf_rs::Joint>>::new();
+    for j in &robot.joints {
+        for link_name in &[&j.parent.link, &j.child.link] {
+            if !link_names.contains(link_name) {
+                problems.push(UrdfProblem::LinkNotFound {
+                    joint_name: j.name.clone(),
+                    link_name: link_name.to_string(),
+                });
+            }
+        }
+        #[allow(clippy::match_like_matches_macro)]
+        let has_axis = match j.joint_type {
+            urdf_rs::JointType::Revolute
+            | urdf_rs::JointType::Continuous
+            | urdf_rs::JointType::Prismatic
+            | urdf_rs::JointType::Planar => true,
+            _ => false,
+        };
+        if has_axis && j.axis.xyz.iter().all(|v| *v == 0.0) {
+            problems.push(UrdfProblem::ZeroLengthAxis {
+                joint_name: j.name.clone(),
+            });
+        }
+        if !j.mimic.joint.is_empty() && !joint_names.contains(&j.mimic.joint) {
+            problems.push(UrdfProblem::MimicJointNotFound {
+                joint_name: j.name.clone(),
+                mimic_joint_name: j.mimic.joint.clone(),
+            });
+        }
+        parent_joints.entry(&j.child.link).or_default().push(j);
+    }
+    let mut root_link_names = Vec::new();
+    let mut checked_link_names = HashSet::new();
+    for l in &robot.links {
+        if !checked_link_names.insert(&l.name) {
+            continue;
+        }
+        match parent_joints.get(&l.name) {
+            Some(joints) if joints.len() > 1 => {
+                problems.push(UrdfProblem::MultipleParentJoints {
+                    link_name: l.name.clone(),
+                    joint_names: joints.iter().map(|j| j.name.clone()).collect(),
+                });
+            }
+            Some(_) => {}
+            None => root_link_names.push(l.name.clone()),
+        }
+    }
+    if root_link_names.len() > 1 {
+        problems.push(UrdfProblem::MultipleRootLinks {
+            link_names: root_link_names,
+        });
+    } else if root_link_names.is_empty() && !robot.links.is_empty() {
+        problems.push(UrdfProblem::NoRootLink);
+    }
+    // go up from every link through all the parent joints and check if it comes back
+    let mut cycles = Vec::<Vec<String>>::new();
+    let mut finished_links = HashSet::new();
+    for l in &robot.links {
+        find_cycles(
+            &l.name,
+            &parent_joints,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            &mut finished_links,
+            &mut cycles,
+        );
+    }
+    for joint_names in cycles {
+        problems.push(UrdfProblem::Cycle { joint_names });
+    }
+    problems
+}
+
+/// Depth first search to the parents, a cycle is found if the link is already in the path
+fn find_cycles<'a>(
+    link_name: &'a String,
+    parent_joints: &HashMap<&'a String, Vec<&'a urdf_rs::Joint>>,
+    path_links: &mut Vec<&'a String>,
+    path_joints: &mut Vec<String>,
+    finished_links: &mut HashSet<&'a String>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    if finished_links.contains(link_name) {
+        return;
+    }
+    if let Some(index) = path_links.iter().position(|n| *n == link_name) {
+        let mut cycle = path_joints[index..].to_vec();
+        cycle.sort();
+        if !cycles.contains(&cycle) {
+            cycles.push(cycle);
+        }
+        return;
+    }
+    path_links.push(link_name);
+    if let Some(joints) = parent_joints.get(link_name) {
+        for joint in joints {
+            path_joints.push(joint.name.clone());
+            find_cycles(
+                &joint.parent.link,
+                parent_joints,
+                path_links,
+                path_joints,
+                finished_links,
+                cycles,
+            );
+            path_joints.pop();
+        }
+    }
+    path_links.pop();
+    finished_links.insert(link_name);
+}
+
 impl<T> Chain<T>
 where
     T: na::Real,
 {
-    pub fn from_urdf_file<P>(path: P) -> Result<Self, urdf_rs::UrdfError>
+    pub fn from_urdf_file<P>(path: P) -> Result<Self, UrdfLoadError>
     where
         P: AsRef<Path>,
     {
-        Ok(urdf_rs::read_file(path)?.into())
+        Ok(read_urdf_file(path)?.into())
+    }
+    /// Create `Chain` from URDF string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let chain = k::Chain::<f64>::from_urdf_str(
+    ///     r#"<robot name="r"><link name="base"/><link name="arm"/>
+    ///     <joint name="j" type="continuous">
+    ///     <parent link="base"/><child link="arm"/><axis xyz="0 0 1"/></joint>
+    ///     </robot>"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(chain.dof(), 1);
+    /// ```
+    pub fn from_urdf_str(urdf: &str) -> Result<Self, UrdfLoadError> {
+        let robot = urdf_rs::read_from_string(urdf).map_err(|e| UrdfLoadError::ReadError {
+            error: e.to_string(),
+        })?;
+        Ok(robot.into())
+    }
+    /// Create `Chain` from the reader of URDF
+    pub fn from_urdf_reader<R>(mut reader: R) -> Result<Self, UrdfLoadError>
+    where
+        R: Read,
+    {
+        let mut urdf = String::new();
+        reader
+            .read_to_string(&mut urdf)
+            .map_err(|e| UrdfLoadError::ReadError {
+                error: e.to_string(),
+            })?;
+        Self::from_urdf_str(&urdf)
+    }
+    /// Load URDF file and resolve the filenames of the meshes by `resolver`
+    ///
+    /// The relative filenames are resolved from the directory of the file
+    /// if `resolver` does not have the base directory.
+    /// The meshes which can not be resolved are kept as they are.
+    pub fn from_urdf_file_with_resolver<P>(
+        path: P,
+        resolver: &ResourceResolver,
+    ) -> Result<Self, UrdfLoadError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let chain = Self::from(read_urdf_file(path)?);
+        match (resolver.get_base_dir(), path.parent()) {
+            (None, Some(dir)) => chain.resolve_resources(&resolver.clone().base_dir(dir)),
+            _ => chain.resolve_resources(resolver),
+        };
+        Ok(chain)
+    }
+    /// Create `Chain` from the robot, or returns all the problems found by `validate()`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate urdf_rs;
+    /// extern crate k;
+    ///
+    /// let mut robot = urdf_rs::Robot::default();
+    /// robot.links.push(urdf_rs::Link {
+    ///     name: "base".to_owned(),
+    ///     ..Default::default()
+    /// });
+    /// let chain = k::Chain::<f64>::try_from_urdf(&robot).unwrap();
+    /// assert_eq!(chain.iter().count(), 1);
+    /// ```
+    pub fn try_from_urdf(robot: &urdf_rs::Robot) -> Result<Self, UrdfLoadError> {
+        let problems = validate(robot);
+        if problems.is_empty() {
+            Ok(robot.into())
+        } else {
+            Err(UrdfLoadError::InvalidUrdfError { problems })
+        }
+    }
+    /// Load the file and create `Chain` by `try_from_urdf()`
+    pub fn try_from_urdf_file<P>(path: P) -> Result<Self, UrdfLoadError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::try_from_urdf(&read_urdf_file(path)?)
+    }
+}
+
+fn read_urdf_file<P: AsRef<Path>>(path: P) -> Result<urdf_rs::Robot, UrdfLoadError> {
+    urdf_rs::read_file(path).map_err(|e| UrdfLoadError::ReadError {
+        error: e.to_string(),
+    })
+}
+
+fn to_f64<T: Real>(value: T) -> f64 {
+    na::try_convert(value).unwrap_or(f64::NAN)
+}
+
+fn to_f64_array3<T: Real>(vector: &na::Vector3<T>) -> [f64; 3] {
+    [to_f64(vector[0]), to_f64(vector[1]), to_f64(vector[2])]
+}
+
+/// Returns urdf_rs::Pose from nalgebra::Isometry3
+pub fn pose_from<T: Real>(origin: &Isometry3<T>) -> urdf_rs::Pose {
+    let (roll, pitch, yaw) = origin.rotation.euler_angles();
+    urdf_rs::Pose {
+        xyz: to_f64_array3(&origin.translation.vector),
+        rpy: [to_f64(roll), to_f64(pitch), to_f64(yaw)],
+    }
+}
+
+impl<'a, T> From<&'a Color<T>> for urdf_rs::Color
+where
+    T: Real,
+{
+    fn from(color: &Color<T>) -> Self {
+        urdf_rs::Color {
+            rgba: [
+                to_f64(color.r),
+                to_f64(color.g),
+                to_f64(color.b),
+                to_f64(color.a),
+            ],
+        }
+    }
+}
+
+impl<'a, T> From<&'a Material<T>> for urdf_rs::Material
+where
+    T: Real,
+{
+    fn from(material: &Material<T>) -> Self {
+        urdf_rs::Material {
+            name: material.name.clone(),
+            color: (&material.color).into(),
+            texture: urdf_rs::Texture {
+                filename: material.texture.filename.clone(),
+            },
+        }
+    }
+}
+
+impl<'a, T> From<&'a Inertial<T>> for urdf_rs::Inertial
+where
+    T: Real,
+{
+    fn from(inertial: &Inertial<T>) -> Self {
+        let i = &inertial.inertia;
+        urdf_rs::Inertial {
+            origin: pose_from(inertial.origin()),
+            mass: urdf_rs::Mass {
+                value: to_f64(inertial.mass),
+            },
+            inertia: urdf_rs::Inertia {
+                ixx: to_f64(i[(0, 0)]),
+                ixy: to_f64(i[(0, 1)]),
+                ixz: to_f64(i[(0, 2)]),
+                iyy: to_f64(i[(1, 1)]),
+                iyz: to_f64(i[(1, 2)]),
+                izz: to_f64(i[(2, 2)]),
+            },
+        }
+    }
+}
+
+impl<'a, T> From<&'a Geometry<T>> for urdf_rs::Geometry
+where
+    T: Real,
+{
+    fn from(geometry: &Geometry<T>) -> Self {
+        match *geometry {
+            Geometry::Box {
+                depth,
+                width,
+                height,
+            } => urdf_rs::Geometry::Box {
+                size: [to_f64(depth), to_f64(width), to_f64(height)],
+            },
+            Geometry::Cylinder { radius, length } => urdf_rs::Geometry::Cylinder {
+                radius: to_f64(radius),
+                length: to_f64(length),
+            },
+            Geometry::Sphere { radius } => urdf_rs::Geometry::Sphere {
+                radius: to_f64(radius),
+            },
+            Geometry::Mesh {
+                ref filename,
+                ref scale,
+            } => urdf_rs::Geometry::Mesh {
+                filename: filename.clone(),
+                scale: to_f64_array3(scale),
+            },
+        }
+    }
+}
+
+impl<'a, T> From<&'a Visual<T>> for urdf_rs::Visual
+where
+    T: Real,
+{
+    fn from(visual: &Visual<T>) -> Self {
+        urdf_rs::Visual {
+            name: visual.name.clone(),
+            origin: pose_from(visual.origin()),
+            geometry: (&visual.geometry).into(),
+            material: (&visual.material).into(),
+        }
+    }
+}
+
+impl<'a, T> From<&'a Collision<T>> for urdf_rs::Collision
+where
+    T: Real,
+{
+    fn from(collision: &Collision<T>) -> Self {
+        urdf_rs::Collision {
+            name: collision.name.clone(),
+            origin: pose_from(collision.origin()),
+            geometry: (&collision.geometry).into(),
+        }
+    }
+}
+
+impl<'a, T> From<&'a Link<T>> for urdf_rs::Link
+where
+    T: Real,
+{
+    fn from(link: &Link<T>) -> Self {
+        urdf_rs::Link {
+            name: link.name.clone(),
+            inertial: (&link.inertial).into(),
+            visual: link.visuals.iter().map(|v| v.into()).collect(),
+            collision: link.collisions.iter().map(|c| c.into()).collect(),
+        }
     }
 }
 
+/// Name of the link of the node in URDF, `{joint name}_link` if it has no named link
+fn urdf_link_name<T: Real>(node: &Node<T>) -> String {
+    match *node.link() {
+        Some(ref link) if !link.name.is_empty() => link.name.clone(),
+        _ => format!("{}_link", node.joint().name),
+    }
+}
+
+/// Create urdf_rs::Joint from the joint of `node`, without the mimic
+fn urdf_joint_from<T: Real>(
+    joint: &Joint<T>,
+    parent_link: String,
+    child_link: String,
+) -> Result<urdf_rs::Joint, UrdfExportError> {
+    let limits = joint
+        .limits
+        .as_ref()
+        .map(|l| (to_f64(l.min), to_f64(l.max)));
+    let (joint_type, axis, limits) = match joint.joint_type {
+        JointType::Fixed => (urdf_rs::JointType::Fixed, Vector3::x_axis(), None),
+        JointType::Rotational { axis } => match limits {
+            Some(limits) => (urdf_rs::JointType::Revolute, axis, Some(limits)),
+            None => (urdf_rs::JointType::Continuous, axis, None),
+        },
+        JointType::Continuous { axis } => (urdf_rs::JointType::Continuous, axis, None),
+        JointType::Linear { axis } => match limits {
+            Some(limits) => (urdf_rs::JointType::Prismatic, axis, Some(limits)),
+            None => {
+                return Err(UrdfExportError::UnsupportedJointError {
+                    joint_name: joint.name.clone(),
+                    message: "URDF prismatic joint requires limits".to_owned(),
+                })
+            }
+        },
+        JointType::Planar { normal } => (urdf_rs::JointType::Planar, normal, None),
+        JointType::Floating => (urdf_rs::JointType::Floating, Vector3::x_axis(), None),
+        JointType::Spherical => {
+            return Err(UrdfExportError::UnsupportedJointError {
+                joint_name: joint.name.clone(),
+                message: "URDF does not have spherical joint".to_owned(),
+            })
+        }
+    };
+    let (lower, upper) = limits.unwrap_or((0.0, 0.0));
+    let safety_controller = match joint.safety_controller {
+        Some(ref safety) => urdf_rs::SafetyController {
+            soft_lower_limit: to_f64(safety.soft_limits.min),
+            soft_upper_limit: to_f64(safety.soft_limits.max),
+            k_position: to_f64(safety.k_position),
+            k_velocity: to_f64(safety.k_velocity),
+        },
+        None => urdf_rs::SafetyController::default(),
+    };
+    let dynamics = match joint.dynamics {
+        Some(ref dynamics) => urdf_rs::Dynamics {
+            damping: to_f64(dynamics.damping),
+            friction: to_f64(dynamics.friction),
+        },
+        None => urdf_rs::Dynamics::default(),
+    };
+    Ok(urdf_rs::Joint {
+        name: joint.name.clone(),
+        joint_type,
+        origin: pose_from(joint.origin()),
+        parent: urdf_rs::LinkName { link: parent_link },
+        child: urdf_rs::LinkName { link: child_link },
+        axis: urdf_rs::Axis {
+            xyz: to_f64_array3(&axis),
+        },
+        limit: urdf_rs::JointLimit {
+            lower,
+            upper,
+            effort: joint.effort_limit.map_or(0.0, to_f64),
+            velocity: joint.velocity_limit.map_or(0.0, to_f64),
+        },
+        dynamics,
+        mimic: urdf_rs::Mimic::default(),
+        safety_controller,
+    })
+}
+
+impl<T> Chain<T>
+where
+    T: na::Real,
+{
+    /// Export the chain as URDF
+    ///
+    /// If the root joint is named `ROOT_JOINT_NAME`, fixed and has no offset (like the
+    /// chain loaded from URDF), its link becomes the root link. Otherwise a root link
+    /// named `ROOT_JOINT_NAME` is added, and the root joint is exported as a joint.
+    /// Nodes without links get empty links named `{joint name}_link`.
+    /// `Rotational` joints without limits are exported as `continuous`.
+    /// `Linear` joints without limits and `Spherical` joints can not be exported.
+    ///
+    /// Use `write_string()` or `write_file()` to get URDF XML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let j0 = JointBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+    ///     .limits(Some((-1.0f64..=1.0).into()))
+    ///     .into_node();
+    /// let j1 = JointBuilder::new()
+    ///     .name("j1")
+    ///     .translation(Translation3::new(0.0, 0.0, 0.5))
+    ///     .into_node();
+    /// j1.set_parent(&j0);
+    /// let robot = Chain::from_root(j0).to_urdf().unwrap();
+    /// assert_eq!(robot.links.len(), 3);
+    /// assert_eq!(robot.joints.len(), 2);
+    /// assert_eq!(robot.joints[0].parent.link, k::urdf::ROOT_JOINT_NAME);
+    /// assert_eq!(robot.joints[0].child.link, "j0_link");
+    /// assert_eq!(robot.joints[0].limit.upper, 1.0);
+    /// let xml = k::urdf::write_string(&robot);
+    /// assert!(xml.contains("<joint name=\"j1\" type=\"fixed\">"));
+    /// ```
+    pub fn to_urdf(&self) -> Result<urdf_rs::Robot, UrdfExportError> {
+        let mut links = Vec::new();
+        let mut joints = Vec::new();
+        let mut nodes = self.iter();
+        let root = match nodes.next() {
+            Some(root) => root,
+            None => return Ok(urdf_rs::Robot::default()),
+        };
+        let is_virtual_root = {
+            let joint = root.joint();
+            joint.name == ROOT_JOINT_NAME
+                && !joint.is_movable()
+                && *joint.origin() == Isometry3::identity()
+        };
+        let root_link_name = if is_virtual_root {
+            urdf_link_name(root)
+        } else {
+            ROOT_JOINT_NAME.to_owned()
+        };
+        let to_urdf_link = |node: &Node<T>| {
+            let mut link = match *node.link() {
+                Some(ref link) => link.into(),
+                None => urdf_rs::Link::default(),
+            };
+            link.name = urdf_link_name(node);
+            link
+        };
+        if is_virtual_root {
+            links.push(to_urdf_link(root));
+        } else {
+            links.push(urdf_rs::Link {
+                name: root_link_name.clone(),
+                ..Default::default()
+            });
+        }
+        let joint_nodes = if is_virtual_root {
+            nodes.collect::<Vec<_>>()
+        } else {
+            self.iter().collect()
+        };
+        for node in joint_nodes {
+            let parent_link = match node.parent() {
+                Some(ref parent) => urdf_link_name(parent),
+                None => root_link_name.clone(),
+            };
+            let mut urdf_joint = urdf_joint_from(&node.joint(), parent_link, urdf_link_name(node))?;
+            let node_impl = node.0.borrow();
+            if let (Some(mimic_parent), Some(mimic)) = (
+                node_impl.mimic_parent.as_ref().and_then(|p| p.upgrade()),
+                node_impl.mimic.as_ref(),
+            ) {
+                urdf_joint.mimic = urdf_rs::Mimic {
+                    joint: mimic_parent.borrow().joint.name.clone(),
+                    multiplier: to_f64(mimic.multiplier),
+                    offset: to_f64(mimic.origin),
+                };
+            }
+            joints.push(urdf_joint);
+            links.push(to_urdf_link(node));
+        }
+        Ok(urdf_rs::Robot {
+            name: root_link_name,
+            links,
+            joints,
+            materials: Vec::new(),
+        })
+    }
+}
+
+/// Writer of URDF XML
+struct XmlWriter {
+    buffer: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    fn escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+    fn attributes(attributes: &[(&str, String)]) -> String {
+        attributes
+            .iter()
+            .map(|&(key, ref value)| format!(" {}=\"{}\"", key, Self::escape(value)))
+            .collect()
+    }
+    fn empty(&mut self, tag: &str, attributes: &[(&str, String)]) {
+        self.line(&format!("<{}{}/>", tag, Self::attributes(attributes)));
+    }
+    fn start(&mut self, tag: &str, attributes: &[(&str, String)]) {
+        self.line(&format!("<{}{}>", tag, Self::attributes(attributes)));
+        self.depth += 1;
+    }
+    fn end(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.buffer.push_str("  ");
+        }
+        self.buffer.push_str(text);
+        self.buffer.push('\n');
+    }
+    fn origin(&mut self, pose: &urdf_rs::Pose) {
+        self.empty(
+            "origin",
+            &[
+                ("xyz", array_string(&pose.xyz)),
+                ("rpy", array_string(&pose.rpy)),
+            ],
+        );
+    }
+    fn geometry(&mut self, geometry: &urdf_rs::Geometry) {
+        self.start("geometry", &[]);
+        match *geometry {
+            urdf_rs::Geometry::Box { ref size } => {
+                self.empty("box", &[("size", array_string(size))])
+            }
+            urdf_rs::Geometry::Cylinder { radius, length } => self.empty(
+                "cylinder",
+                &[
+                    ("radius", radius.to_string()),
+                    ("length", length.to_string()),
+                ],
+            ),
+            urdf_rs::Geometry::Sphere { radius } => {
+                self.empty("sphere", &[("radius", radius.to_string())])
+            }
+            urdf_rs::Geometry::Mesh {
+                ref filename,
+                ref scale,
+            } => self.empty(
+                "mesh",
+                &[
+                    ("filename", filename.clone()),
+                    ("scale", array_string(scale)),
+                ],
+            ),
+        }
+        self.end("geometry");
+    }
+    fn material(&mut self, material: &urdf_rs::Material) {
+        let has_color = material.color.rgba.iter().any(|c| *c != 0.0);
+        let has_texture = !material.texture.filename.is_empty();
+        if material.name.is_empty() && !has_color && !has_texture {
+            return;
+        }
+        self.start("material", &[("name", material.name.clone())]);
+        if has_color {
+            self.empty("color", &[("rgba", array_string(&material.color.rgba))]);
+        }
+        if has_texture {
+            self.empty(
+                "texture",
+                &[("filename", material.texture.filename.clone())],
+            );
+        }
+        self.end("material");
+    }
+    fn link(&mut self, link: &urdf_rs::Link) {
+        self.start("link", &[("name", link.name.clone())]);
+        let inertial = &link.inertial;
+        self.start("inertial", &[]);
+        self.origin(&inertial.origin);
+        self.empty("mass", &[("value", inertial.mass.value.to_string())]);
+        let i = &inertial.inertia;
+        self.empty(
+            "inertia",
+            &[
+                ("ixx", i.ixx.to_string()),
+                ("ixy", i.ixy.to_string()),
+                ("ixz", i.ixz.to_string()),
+                ("iyy", i.iyy.to_string()),
+                ("iyz", i.iyz.to_string()),
+                ("izz", i.izz.to_string()),
+            ],
+        );
+        self.end("inertial");
+        for visual in &link.visual {
+            self.start("visual", &[("name", visual.name.clone())]);
+            self.origin(&visual.origin);
+            self.geometry(&visual.geometry);
+            self.material(&visual.material);
+            self.end("visual");
+        }
+        for collision in &link.collision {
+            self.start("collision", &[("name", collision.name.clone())]);
+            self.origin(&collision.origin);
+            self.geometry(&collision.geometry);
+            self.end("collision");
+        }
+        self.end("link");
+    }
+    fn joint(&mut self, joint: &urdf_rs::Joint) {
+        let joint_type = match joint.joint_type {
+            urdf_rs::JointType::Revolute => "revolute",
+            urdf_rs::JointType::Continuous => "continuous",
+            urdf_rs::JointType::Prismatic => "prismatic",
+            urdf_rs::JointType::Fixed => "fixed",
+            urdf_rs::JointType::Floating => "floating",
+            urdf_rs::JointType::Planar => "planar",
+        };
+        self.start(
+            "joint",
+            &[
+                ("name", joint.name.clone()),
+                ("type", joint_type.to_owned()),
+            ],
+        );
+        self.origin(&joint.origin);
+        self.empty("parent", &[("link", joint.parent.link.clone())]);
+        self.empty("child", &[("link", joint.child.link.clone())]);
+        self.empty("axis", &[("xyz", array_string(&joint.axis.xyz))]);
+        let limit = &joint.limit;
+        self.empty(
+            "limit",
+            &[
+                ("lower", limit.lower.to_string()),
+                ("upper", limit.upper.to_string()),
+                ("effort", limit.effort.to_string()),
+                ("velocity", limit.velocity.to_string()),
+            ],
+        );
+        let dynamics = &joint.dynamics;
+        if dynamics.damping != 0.0 || dynamics.friction != 0.0 {
+            self.empty(
+                "dynamics",
+                &[
+                    ("damping", dynamics.damping.to_string()),
+                    ("friction", dynamics.friction.to_string()),
+                ],
+            );
+        }
+        let mimic = &joint.mimic;
+        if !mimic.joint.is_empty() {
+            self.empty(
+                "mimic",
+                &[
+                    ("joint", mimic.joint.clone()),
+                    ("multiplier", mimic.multiplier.to_string()),
+                    ("offset", mimic.offset.to_string()),
+                ],
+            );
+        }
+        let safety = &joint.safety_controller;
+        if safety.soft_lower_limit != 0.0
+            || safety.soft_upper_limit != 0.0
+            || safety.k_position != 0.0
+            || safety.k_velocity != 0.0
+        {
+            self.empty(
+                "safety_controller",
+                &[
+                    ("soft_lower_limit", safety.soft_lower_limit.to_string()),
+                    ("soft_upper_limit", safety.soft_upper_limit.to_string()),
+                    ("k_position", safety.k_position.to_string()),
+                    ("k_velocity", safety.k_velocity.to_string()),
+                ],
+            );
+        }
+        self.end("joint");
+    }
+}
+
+fn array_string(values: &[f64]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Write URDF XML of the robot to string
+///
+/// The numbers are written in the shortest form that is parsed to the same value,
+/// so the robot is restored exactly by `urdf_rs::read_from_string()`.
+pub fn write_string(robot: &urdf_rs::Robot) -> String {
+    let mut writer = XmlWriter {
+        buffer: "<?xml version=\"1.0\"?>\n".to_owned(),
+        depth: 0,
+    };
+    writer.start("robot", &[("name", robot.name.clone())]);
+    for material in &robot.materials {
+        writer.material(material);
+    }
+    for link in &robot.links {
+        writer.link(link);
+    }
+    for joint in &robot.joints {
+        writer.joint(joint);
+    }
+    writer.end("robot");
+    writer.buffer
+}
+
+/// Write URDF XML of the robot to the file
+pub fn write_file<P>(robot: &urdf_rs::Robot, path: P) -> ::std::io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    ::std::fs::write(path, write_string(robot))
+}
+
 /// Useful function to deal about 'Links' of URDF
 ///
 /// `k` deals only `Joint`s of URDF. But links is connected
@@ -399,3 +1296,519 @@ fn test_tree_from_file() {
     assert_eq!(names[0], "root");
     assert_eq!(names[1], "r_shoulder_yaw");
 }
+
+#[test]
+fn test_joint_limits_from_urdf() {
+    let mut urdf_joint = urdf_rs::Joint {
+        name: "j0".to_owned(),
+        joint_type: urdf_rs::JointType::Revolute,
+        origin: urdf_rs::Pose::default(),
+        parent: urdf_rs::LinkName::default(),
+        child: urdf_rs::LinkName::default(),
+        axis: urdf_rs::Axis {
+            xyz: [0.0, 0.0, 1.0],
+        },
+        limit: urdf_rs::JointLimit {
+            lower: 0.0,
+            upper: 0.0,
+            effort: 10.0,
+            velocity: 2.0,
+        },
+        dynamics: urdf_rs::Dynamics {
+            damping: 0.5,
+            friction: 0.1,
+        },
+        mimic: urdf_rs::Mimic::default(),
+        safety_controller: urdf_rs::SafetyController {
+            soft_lower_limit: -0.1,
+            soft_upper_limit: 0.1,
+            k_position: 100.0,
+            k_velocity: 10.0,
+        },
+    };
+    let mut joint = Joint::<f64>::from(&urdf_joint);
+    // zero width range is a locked joint
+    let limits = joint.limits.unwrap();
+    assert_eq!(limits.min, 0.0);
+    assert_eq!(limits.max, 0.0);
+    assert!(joint.set_joint_position(0.1).is_err());
+    assert_eq!(joint.velocity_limit, Some(2.0));
+    assert_eq!(joint.effort_limit, Some(10.0));
+    assert!(joint.set_joint_velocity(2.5).is_err());
+    let safety = joint.safety_controller.unwrap();
+    assert_eq!(safety.soft_limits.max, 0.1);
+    assert_eq!(safety.k_velocity, 10.0);
+    let dynamics = joint.dynamics.unwrap();
+    assert_eq!(dynamics.damping, 0.5);
+    assert_eq!(dynamics.friction, 0.1);
+
+    urdf_joint.joint_type = urdf_rs::JointType::Continuous;
+    urdf_joint.dynamics = urdf_rs::Dynamics::default();
+    urdf_joint.safety_controller = urdf_rs::SafetyController::default();
+    let mut joint = Joint::<f64>::from(&urdf_joint);
+    assert!(joint.is_continuous());
+    assert!(joint.limits.is_none());
+    joint.set_joint_position(7.0).unwrap();
+    assert!((joint.joint_position().unwrap() - (7.0 - 2.0 * ::std::f64::consts::PI)).abs() < 1e-10);
+    assert!(joint.safety_controller.is_none());
+    assert!(joint.dynamics.is_none());
+
+    urdf_joint.joint_type = urdf_rs::JointType::Planar;
+    assert_eq!(Joint::<f64>::from(&urdf_joint).dof(), 3);
+    urdf_joint.joint_type = urdf_rs::JointType::Floating;
+    assert_eq!(Joint::<f64>::from(&urdf_joint).dof(), 6);
+}
+
+#[test]
+fn test_joint_without_limit_is_locked() {
+    let robot = urdf_rs::read_from_string(
+        r#"
+<robot name="r">
+  <link name="base"/>
+  <link name="l1"/>
+  <link name="l2"/>
+  <joint name="no_limit" type="revolute">
+    <parent link="base"/>
+    <child link="l1"/>
+    <axis xyz="0 0 1"/>
+  </joint>
+  <joint name="with_limit" type="prismatic">
+    <parent link="l1"/>
+    <child link="l2"/>
+    <axis xyz="1 0 0"/>
+    <limit lower="-0.5" upper="0.5" effort="1" velocity="1"/>
+  </joint>
+</robot>
+"#,
+    )
+    .unwrap();
+    // the warning is shown only for the joint without <limit>
+    assert!(is_locked_by_default(&robot.joints[0]));
+    assert!(!is_locked_by_default(&robot.joints[1]));
+    let chain = Chain::<f64>::from(&robot);
+    let node = chain.find("no_limit").unwrap();
+    let limits = node.joint().limits.unwrap();
+    assert_eq!((limits.min, limits.max), (0.0, 0.0));
+    assert!(node.set_joint_position(0.1).is_err());
+    assert!(chain
+        .find("with_limit")
+        .unwrap()
+        .set_joint_position(0.4)
+        .is_ok());
+}
+
+#[test]
+fn test_to_urdf_round_trip() {
+    let root = JointBuilder::<f64>::new().name(ROOT_JOINT_NAME).into_node();
+    root.set_link(Some(LinkBuilder::new().name("base").finalize()));
+    let j0 = JointBuilder::new()
+        .name("j0")
+        .joint_type(JointType::Rotational {
+            axis: Vector3::y_axis(),
+        })
+        .limits(Some((-1.0..=1.5).into()))
+        .velocity_limit(Some(2.0))
+        .effort_limit(Some(30.0))
+        .dynamics(Some(Dynamics::new(0.5, 0.1)))
+        .safety_controller(Some(SafetyController::new((-0.9..=1.4).into(), 10.0, 1.0)))
+        .origin(Isometry3::new(
+            Vector3::new(0.1, 0.2, 0.3),
+            Vector3::new(0.3, -0.2, 0.1),
+        ))
+        .into_node();
+    let material = Material {
+        name: "red & \"bright\"".to_owned(),
+        color: Color {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        },
+        texture: Texture::default(),
+    };
+    j0.set_link(Some(
+        LinkBuilder::new()
+            .name("l0")
+            .inertial(Inertial::new(
+                Isometry3::translation(0.0, 0.0, 0.1),
+                2.0,
+                Matrix3::new(0.1, 0.01, 0.02, 0.01, 0.2, 0.03, 0.02, 0.03, 0.3),
+            ))
+            .add_visual(Visual::new(
+                "v0".to_owned(),
+                Isometry3::translation(0.0, 0.0, 0.2),
+                Geometry::Box {
+                    depth: 0.1,
+                    width: 0.2,
+                    height: 0.3,
+                },
+                material,
+            ))
+            .add_collision(Collision::new(
+                "c0".to_owned(),
+                Isometry3::identity(),
+                Geometry::Mesh {
+                    filename: "package://robot/l0.stl".to_owned(),
+                    scale: Vector3::new(1.0, 2.0, 3.0),
+                },
+            ))
+            .finalize(),
+    ));
+    let j1 = JointBuilder::new()
+        .name("j1")
+        .joint_type(JointType::Linear {
+            axis: Vector3::z_axis(),
+        })
+        .limits(Some((-0.1..=0.2).into()))
+        .into_node();
+    let j2 = JointBuilder::new()
+        .name("j2")
+        .joint_type(JointType::Continuous {
+            axis: Vector3::x_axis(),
+        })
+        .into_node();
+    let j3 = JointBuilder::new()
+        .name("j3")
+        .joint_type(JointType::Rotational {
+            axis: Vector3::x_axis(),
+        })
+        .into_node();
+    j0.set_parent(&root);
+    j1.set_parent(&j0);
+    j2.set_parent(&root);
+    j3.set_parent(&j1);
+    j2.set_mimic_parent(&j0, Mimic::new(2.0, 0.1));
+    let chain = Chain::from_root(root);
+
+    let robot = chain.to_urdf().unwrap();
+    assert_eq!(robot.links.len(), 5);
+    assert_eq!(robot.joints.len(), 4);
+    assert_eq!(robot.links[0].name, "base");
+    let urdf_joint = |name: &str| robot.joints.iter().find(|j| j.name == name).unwrap();
+    assert_eq!(urdf_joint("j1").parent.link, "l0");
+    assert_eq!(urdf_joint("j1").child.link, "j1_link");
+    assert_eq!(urdf_joint("j2").parent.link, "base");
+    assert_eq!(urdf_joint("j2").mimic.joint, "j0");
+    // unlimited rotational joint is continuous
+    match urdf_joint("j3").joint_type {
+        urdf_rs::JointType::Continuous => {}
+        _ => panic!("must be continuous"),
+    }
+    assert!(write_string(&robot).contains("<joint name=\"j3\" type=\"continuous\">"));
+    assert!(!write_string(&robot).contains("inf"));
+
+    let path = ::std::env::temp_dir().join("k_test_to_urdf_round_trip.urdf");
+    write_file(&robot, &path).unwrap();
+    let loaded = Chain::<f64>::from_urdf_file(&path).unwrap();
+    let _ = ::std::fs::remove_file(&path);
+    let mut names = loaded
+        .iter()
+        .map(|node| node.joint().name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["j0", "j1", "j2", "j3", "root"]);
+
+    let j0 = loaded.find("j0").unwrap().joint();
+    let origin_error = chain.find("j0").unwrap().joint().origin().inverse() * j0.origin();
+    assert!(origin_error.translation.vector.norm() < 1e-10);
+    assert!(origin_error.rotation.angle() < 1e-10);
+    assert_eq!(j0.limits.unwrap().max, 1.5);
+    assert_eq!(j0.velocity_limit, Some(2.0));
+    assert_eq!(j0.effort_limit, Some(30.0));
+    assert_eq!(j0.dynamics.unwrap().friction, 0.1);
+    assert_eq!(j0.safety_controller.unwrap().soft_limits.min, -0.9);
+    drop(j0);
+    assert_eq!(loaded.find("j1").unwrap().joint().limits.unwrap().min, -0.1);
+    assert!(loaded.find("j2").unwrap().joint().is_continuous());
+    assert!(loaded.find("j3").unwrap().joint().is_continuous());
+
+    {
+        let link = loaded.find_link("l0").unwrap().link();
+        let link = link.as_ref().unwrap();
+        assert_eq!(link.inertial.mass, 2.0);
+        assert_eq!(link.inertial.inertia[(1, 2)], 0.03);
+        assert_eq!(link.visuals[0].material.name, "red & \"bright\"");
+        assert_eq!(link.visuals[0].material.color.r, 1.0);
+        match link.visuals[0].geometry {
+            Geometry::Box { width, .. } => assert_eq!(width, 0.2),
+            _ => panic!("must be box"),
+        }
+        match link.collisions[0].geometry {
+            Geometry::Mesh {
+                ref filename,
+                ref scale,
+            } => {
+                assert_eq!(filename, "package://robot/l0.stl");
+                assert_eq!(scale.z, 3.0);
+            }
+            _ => panic!("must be mesh"),
+        }
+    }
+
+    // mimic is restored
+    loaded.find("j0").unwrap().set_joint_position(0.5).unwrap();
+    let j2_position = loaded.find("j2").unwrap().joint().joint_position().unwrap();
+    assert!((j2_position - 1.1).abs() < 1e-10);
+
+    // spherical joint can not be exported
+    let spherical = JointBuilder::<f64>::new()
+        .joint_type(JointType::Spherical)
+        .into_node();
+    assert!(Chain::from_root(spherical).to_urdf().is_err());
+    // prismatic joint requires limits
+    let linear = JointBuilder::<f64>::new()
+        .joint_type(JointType::Linear {
+            axis: Vector3::x_axis(),
+        })
+        .into_node();
+    assert!(Chain::from_root(linear).to_urdf().is_err());
+    // infinite limits of the old exports are no limits
+    let mut urdf_joint = urdf_joint("j0").clone();
+    urdf_joint.limit.lower = f64::NEG_INFINITY;
+    urdf_joint.limit.upper = f64::INFINITY;
+    assert!(Joint::<f64>::from(&urdf_joint).limits.is_none());
+
+    // the name of the fixed root joint is kept
+    let base = JointBuilder::<f64>::new().name("base").into_node();
+    let arm = JointBuilder::new()
+        .name("arm")
+        .joint_type(JointType::Continuous {
+            axis: Vector3::z_axis(),
+        })
+        .into_node();
+    arm.set_parent(&base);
+    let robot = Chain::from_root(base).to_urdf().unwrap();
+    assert_eq!(robot.joints[0].name, "base");
+    assert_eq!(robot.joints[0].parent.link, ROOT_JOINT_NAME);
+    let loaded = Chain::<f64>::from(&robot);
+    assert!(loaded.find("base").is_some());
+    assert!(loaded.find("arm").is_some());
+}
+
+#[test]
+fn test_validate() {
+    let robot = urdf_rs::read_from_string(
+        r#"
+<robot name="broken">
+  <link name="base"/>
+  <link name="l1"/>
+  <link name="l2"/>
+  <link name="l2"/>
+  <link name="l3"/>
+  <link name="l4"/>
+  <link name="orphan"/>
+  <joint name="j1" type="revolute">
+    <parent link="base"/>
+    <child link="l1"/>
+    <axis xyz="0 0 0"/>
+    <limit lower="-1" upper="1" effort="1" velocity="1"/>
+  </joint>
+  <joint name="j2" type="fixed">
+    <parent link="l1"/>
+    <child link="l2"/>
+    <mimic joint="no_joint"/>
+  </joint>
+  <joint name="j2" type="fixed">
+    <parent link="l1"/>
+    <child link="missing"/>
+  </joint>
+  <joint name="j3" type="fixed">
+    <parent link="l4"/>
+    <child link="l3"/>
+  </joint>
+  <joint name="j4" type="fixed">
+    <parent link="l3"/>
+    <child link="l4"/>
+  </joint>
+  <joint name="j5" type="fixed">
+    <parent link="base"/>
+    <child link="l1"/>
+  </joint>
+</robot>
+"#,
+    )
+    .unwrap();
+    let problems = validate(&robot);
+    let expected = vec![
+        UrdfProblem::DuplicateJointName {
+            joint_name: "j2".to_owned(),
+            count: 2,
+        },
+        UrdfProblem::DuplicateLinkName {
+            link_name: "l2".to_owned(),
+            count: 2,
+        },
+        UrdfProblem::ZeroLengthAxis {
+            joint_name: "j1".to_owned(),
+        },
+        UrdfProblem::MimicJointNotFound {
+            joint_name: "j2".to_owned(),
+            mimic_joint_name: "no_joint".to_owned(),
+        },
+        UrdfProblem::LinkNotFound {
+            joint_name: "j2".to_owned(),
+            link_name: "missing".to_owned(),
+        },
+        UrdfProblem::MultipleParentJoints {
+            link_name: "l1".to_owned(),
+            joint_names: vec!["j1".to_owned(), "j5".to_owned()],
+        },
+        UrdfProblem::MultipleRootLinks {
+            link_names: vec!["base".to_owned(), "orphan".to_owned()],
+        },
+        UrdfProblem::Cycle {
+            joint_names: vec!["j3".to_owned(), "j4".to_owned()],
+        },
+    ];
+    assert_eq!(problems, expected);
+    match Chain::<f64>::try_from_urdf(&robot) {
+        Err(UrdfLoadError::InvalidUrdfError { problems }) => assert_eq!(problems, expected),
+        _ => panic!("must fail"),
+    }
+    // `From` does not panic
+    let _ = Chain::<f64>::from(&robot);
+
+    let mut robot = urdf_rs::Robot::default();
+    robot.links.push(urdf_rs::Link {
+        name: "l0".to_owned(),
+        ..Default::default()
+    });
+    assert!(validate(&robot).is_empty());
+    assert!(Chain::<f64>::try_from_urdf_file("no_such_file.urdf").is_err());
+}
+
+#[test]
+fn test_validate_cycle_through_second_parent() {
+    // l1 has two parents, the cycle l1 -> l2 -> l1 is through the second one
+    let robot = urdf_rs::read_from_string(
+        r#"
+<robot name="cycle">
+  <link name="base"/>
+  <link name="l1"/>
+  <link name="l2"/>
+  <link name="l3"/>
+  <joint name="j1" type="fixed">
+    <parent link="base"/>
+    <child link="l1"/>
+  </joint>
+  <joint name="j2" type="fixed">
+    <parent link="l2"/>
+    <child link="l1"/>
+  </joint>
+  <joint name="j3" type="fixed">
+    <parent link="l1"/>
+    <child link="l2"/>
+  </joint>
+  <joint name="j4" type="fixed">
+    <parent link="l2"/>
+    <child link="l3"/>
+  </joint>
+  <joint name="j5" type="fixed">
+    <parent link="l3"/>
+    <child link="l2"/>
+  </joint>
+</robot>
+"#,
+    )
+    .unwrap();
+    let cycles = validate(&robot)
+        .into_iter()
+        .filter_map(|problem| match problem {
+            UrdfProblem::Cycle { joint_names } => Some(joint_names),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        cycles,
+        vec![
+            vec!["j2".to_owned(), "j3".to_owned()],
+            vec!["j4".to_owned(), "j5".to_owned()],
+        ]
+    );
+}
+
+#[test]
+fn test_load_with_resolver() {
+    use std::fs;
+    let root = ::std::env::temp_dir().join("k_test_load_with_resolver");
+    let _ = fs::remove_dir_all(&root);
+    for dir in &["share/my_robot/meshes", "my_robot/urdf", "my_robot/meshes"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    fs::write(root.join("share/my_robot/meshes/base.stl"), "").unwrap();
+    fs::write(root.join("my_robot/meshes/arm.stl"), "").unwrap();
+    let urdf = r#"<robot name="my_robot">
+  <link name="base">
+    <visual>
+      <geometry><mesh filename="package://my_robot/meshes/base.stl"/></geometry>
+    </visual>
+    <collision>
+      <geometry><mesh filename="package://unknown/meshes/base.stl"/></geometry>
+    </collision>
+  </link>
+  <link name="arm">
+    <visual>
+      <geometry><mesh filename="../meshes/arm.stl"/></geometry>
+    </visual>
+  </link>
+  <joint name="j" type="fixed">
+    <parent link="base"/>
+    <child link="arm"/>
+  </joint>
+</robot>"#;
+    let urdf_path = root.join("my_robot/urdf/my_robot.urdf");
+    fs::write(&urdf_path, urdf).unwrap();
+
+    let mesh_filename = |chain: &Chain<f64>, link_name: &str, visual: bool| {
+        let node = chain.find_link(link_name).unwrap();
+        let link = node.link();
+        let link = link.as_ref().unwrap();
+        let geometry = if visual {
+            &link.visuals[0].geometry
+        } else {
+            &link.collisions[0].geometry
+        };
+        match *geometry {
+            Geometry::Mesh { ref filename, .. } => filename.clone(),
+            _ => panic!("must be mesh"),
+        }
+    };
+    // not resolved
+    let chain = Chain::<f64>::from_urdf_str(urdf).unwrap();
+    assert_eq!(
+        mesh_filename(&chain, "base", true),
+        "package://my_robot/meshes/base.stl"
+    );
+    let chain = Chain::<f64>::from_urdf_reader(fs::File::open(&urdf_path).unwrap()).unwrap();
+    assert_eq!(mesh_filename(&chain, "arm", true), "../meshes/arm.stl");
+    assert!(Chain::<f64>::from_urdf_str("<robot").is_err());
+    // all the loaders return UrdfLoadError
+    let missing = root.join("my_robot/urdf/missing.urdf");
+    match Chain::<f64>::from_urdf_file(&missing) {
+        Err(UrdfLoadError::ReadError { .. }) => {}
+        other => panic!("{:?}", other.map(|chain| chain.dof())),
+    }
+    match Chain::<f64>::from_urdf_file_with_resolver(&missing, &ResourceResolver::new()) {
+        Err(UrdfLoadError::ReadError { .. }) => {}
+        other => panic!("{:?}", other.map(|chain| chain.dof())),
+    }
+
+    let resolver = ResourceResolver::new().search_path(root.join("share"));
+    let chain = Chain::<f64>::from_urdf_file_with_resolver(&urdf_path, &resolver).unwrap();
+    assert_eq!(
+        Path::new(&mesh_filename(&chain, "base", true)),
+        root.join("share/my_robot/meshes/base.stl")
+    );
+    assert_eq!(
+        Path::new(&mesh_filename(&chain, "arm", true)),
+        root.join("my_robot/urdf/../meshes/arm.stl")
+    );
+    assert_eq!(
+        mesh_filename(&chain, "base", false),
+        "package://unknown/meshes/base.stl"
+    );
+    assert_eq!(
+        chain.resolve_resources(&resolver),
+        vec!["package://unknown/meshes/base.stl".to_owned()]
+    );
+}
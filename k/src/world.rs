@@ -0,0 +1,819 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Planning scene with multiple robots and objects
+//!
+//! `World` holds named chains at their base poses and named objects. The base poses
+//! are kept in `World`, the chains are not modified, so the transforms of the nodes
+//! are still relative to the base of the chain. An object is static in the world, or
+//! attached to a node of a robot and follows it. The attached object is added to the
+//! collisions of the link of the node, so it is checked as a part of the robot.
+//!
+//! The frames are named by
+//!
+//! * `"world"`
+//! * the name of a robot (its base) or an object
+//! * `"robot/joint"`, `"robot/link"` or `"robot/frame"` for a joint, a link or a named
+//!   frame (see `Chain::add_frame()`) of a robot
+//!
+//! # Examples
+//!
+//! ```
+//! use k::*;
+//! use k::link::Geometry;
+//! use k::world::World;
+//!
+//! let base = JointBuilder::new().name("base").into_node();
+//! let wrist = JointBuilder::new()
+//!     .name("wrist_yaw")
+//!     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+//!     .translation(Translation3::new(0.5, 0.0, 0.0))
+//!     .into_node();
+//! wrist.set_parent(&base);
+//! let mut world = World::new();
+//! world.add_robot("robot1", Chain::from_root(base), Isometry3::translation(0.0, 1.0, 0.0)).unwrap();
+//! world.add_object(
+//!     "table",
+//!     Geometry::Box { depth: 1.0, width: 1.0, height: 0.1 },
+//!     Isometry3::translation(0.5, 1.0, -0.5)).unwrap();
+//! let wrist_to_table = world.transform("robot1/wrist_yaw", "table").unwrap();
+//! assert_eq!(wrist_to_table.translation.vector, Vector3::new(0.0, 0.0, -0.5));
+//! ```
+use na::{Isometry3, Real};
+
+use chain::*;
+use collision::*;
+use errors::*;
+use link::*;
+use node::*;
+
+/// Name of the world frame
+pub const WORLD_FRAME_NAME: &str = "world";
+
+#[derive(Debug)]
+struct Robot<T: Real> {
+    name: String,
+    chain: Chain<T>,
+    base: Isometry3<T>,
+}
+
+#[derive(Debug, Clone)]
+enum Placement<T: Real> {
+    Static(Isometry3<T>),
+    Attached {
+        robot: String,
+        node: Node<T>,
+        offset: Isometry3<T>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct WorldObject<T: Real> {
+    name: String,
+    geometry: Geometry<T>,
+    placement: Placement<T>,
+}
+
+/// Geometry in the world to be drawn or checked
+#[derive(Debug, Clone)]
+pub struct WorldGeometry<T: Real> {
+    /// Name of the object, or `"robot/link"` for the links
+    pub name: String,
+    pub geometry: Geometry<T>,
+    pub transform: Isometry3<T>,
+}
+
+/// Named robots and objects in the world
+#[derive(Debug)]
+pub struct World<T: Real> {
+    robots: Vec<Robot<T>>,
+    objects: Vec<WorldObject<T>>,
+    /// Nodes whose links are created by `attach_object()`
+    created_links: Vec<Node<T>>,
+}
+
+fn not_found<T>(name: &str) -> Result<T, WorldError> {
+    Err(WorldError::NotFoundError {
+        name: name.to_owned(),
+    })
+}
+
+impl<T> World<T>
+where
+    T: Real,
+{
+    /// Create empty world
+    pub fn new() -> Self {
+        World {
+            robots: Vec::new(),
+            objects: Vec::new(),
+            created_links: Vec::new(),
+        }
+    }
+
+    fn check_new_name(&self, name: &str) -> Result<(), WorldError> {
+        if name == WORLD_FRAME_NAME
+            || self.robots.iter().any(|r| r.name == name)
+            || self.objects.iter().any(|o| o.name == name)
+        {
+            return Err(WorldError::DuplicateNameError {
+                name: name.to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    fn find_robot(&self, name: &str) -> Result<&Robot<T>, WorldError> {
+        match self.robots.iter().find(|r| r.name == name) {
+            Some(robot) => Ok(robot),
+            None => not_found(name),
+        }
+    }
+
+    fn find_object(&self, name: &str) -> Result<usize, WorldError> {
+        match self.objects.iter().position(|o| o.name == name) {
+            Some(index) => Ok(index),
+            None => not_found(name),
+        }
+    }
+
+    /// Add the chain with the root at `base` in the world
+    ///
+    /// The frame of the chain (the parent of the root joint) is placed on `base`.
+    /// The chain itself is not modified.
+    pub fn add_robot(
+        &mut self,
+        name: &str,
+        chain: Chain<T>,
+        base: Isometry3<T>,
+    ) -> Result<(), WorldError> {
+        self.check_new_name(name)?;
+        self.robots.push(Robot {
+            name: name.to_owned(),
+            chain,
+            base,
+        });
+        Ok(())
+    }
+    /// Get the chain of the robot
+    pub fn robot(&self, name: &str) -> Option<&Chain<T>> {
+        self.find_robot(name).ok().map(|robot| &robot.chain)
+    }
+    pub fn robot_names(&self) -> Vec<&str> {
+        self.robots.iter().map(|r| r.name.as_str()).collect()
+    }
+    /// Move the base of the robot
+    pub fn set_base_pose(&mut self, name: &str, base: Isometry3<T>) -> Result<(), WorldError> {
+        let robot = match self.robots.iter_mut().find(|r| r.name == name) {
+            Some(robot) => robot,
+            None => return not_found(name),
+        };
+        robot.base = base;
+        Ok(())
+    }
+
+    /// Add the static object at `pose` in the world
+    pub fn add_object(
+        &mut self,
+        name: &str,
+        geometry: Geometry<T>,
+        pose: Isometry3<T>,
+    ) -> Result<(), WorldError> {
+        self.check_new_name(name)?;
+        self.objects.push(WorldObject {
+            name: name.to_owned(),
+            geometry,
+            placement: Placement::Static(pose),
+        });
+        Ok(())
+    }
+    /// Remove the object, it is detached if attached
+    pub fn remove_object(&mut self, name: &str) -> Result<(), WorldError> {
+        self.detach_object(name)?;
+        let index = self.find_object(name)?;
+        self.objects.remove(index);
+        Ok(())
+    }
+    pub fn object_names(&self) -> Vec<&str> {
+        self.objects.iter().map(|o| o.name.as_str()).collect()
+    }
+    /// Attach the object to the node of the robot, such as `"robot1/gripper"`
+    ///
+    /// The object keeps the current pose and follows the node after that.
+    /// The object is added to the collisions of the link of the node (a link named
+    /// by the object is created if the node has no link, and removed when it becomes
+    /// empty by `detach_object()`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::Geometry;
+    /// use k::world::World;
+    ///
+    /// let hand = JointBuilder::new()
+    ///     .name("hand")
+    ///     .joint_type(JointType::Linear { axis: Vector3::x_axis() })
+    ///     .into_node();
+    /// let mut world = World::new();
+    /// world.add_robot("robot", Chain::from_root(hand.clone()), Isometry3::identity()).unwrap();
+    /// world.add_object(
+    ///     "box", Geometry::Sphere { radius: 0.1 }, Isometry3::translation(0.0, 0.0, 0.2)).unwrap();
+    /// world.attach_object("box", "robot/hand").unwrap();
+    /// hand.set_joint_position(1.0).unwrap();
+    /// assert_eq!(
+    ///     world.transform("world", "box").unwrap().translation.vector,
+    ///     Vector3::new(1.0, 0.0, 0.2));
+    /// world.detach_object("box").unwrap();
+    /// hand.set_joint_position(0.0).unwrap();
+    /// assert_eq!(world.transform("world", "box").unwrap().translation.vector.x, 1.0);
+    /// ```
+    pub fn attach_object(&mut self, name: &str, frame: &str) -> Result<(), WorldError> {
+        self.detach_object(name)?;
+        let index = self.find_object(name)?;
+        let (robot_index, node, _) = self.find_node(frame)?;
+        let robot = self.robots[robot_index].name.clone();
+        let pose = self.object_pose(index);
+        let offset = self.node_pose(&robot, &node).inverse() * pose;
+        let geometry = self.objects[index].geometry.clone();
+        let collision = Collision::new(name.to_owned(), offset, geometry);
+        let is_created = {
+            let mut node_mut = node.0.borrow_mut();
+            match node_mut.link {
+                Some(ref mut link) => {
+                    link.collisions.push(collision);
+                    false
+                }
+                None => {
+                    node_mut.link = Some(
+                        LinkBuilder::new()
+                            .name(name)
+                            .add_collision(collision)
+                            .finalize(),
+                    );
+                    true
+                }
+            }
+        };
+        if is_created {
+            self.created_links.push(node.clone());
+        }
+        self.objects[index].placement = Placement::Attached {
+            robot,
+            node,
+            offset,
+        };
+        Ok(())
+    }
+    /// Detach the object at the current pose, nothing happens if it is static
+    pub fn detach_object(&mut self, name: &str) -> Result<(), WorldError> {
+        let index = self.find_object(name)?;
+        let pose = self.object_pose(index);
+        if let Placement::Attached { ref node, .. } = self.objects[index].placement {
+            let is_empty = match node.0.borrow_mut().link {
+                Some(ref mut link) => {
+                    link.collisions.retain(|collision| collision.name != name);
+                    link.collisions.is_empty()
+                }
+                None => false,
+            };
+            if let Some(created) = self.created_links.iter().position(|n| n == node) {
+                if is_empty {
+                    node.set_link(None);
+                    self.created_links.remove(created);
+                }
+            }
+        }
+        self.objects[index].placement = Placement::Static(pose);
+        Ok(())
+    }
+
+    fn object_pose(&self, index: usize) -> Isometry3<T> {
+        match self.objects[index].placement {
+            Placement::Static(pose) => pose,
+            Placement::Attached {
+                ref robot,
+                ref node,
+                ref offset,
+            } => self.node_pose(robot, node) * offset,
+        }
+    }
+
+    /// Pose of the node of the robot in the world
+    fn node_pose(&self, robot: &str, node: &Node<T>) -> Isometry3<T> {
+        let base = self
+            .robots
+            .iter()
+            .find(|r| r.name == robot)
+            .map(|r| r.base)
+            .unwrap_or_else(Isometry3::identity);
+        base * node.updated_world_transform()
+    }
+
+    /// Find the index of the robot, the node and the offset from it by `"robot/joint"`,
+    /// `"robot/link"` or `"robot/frame"`
+    fn find_node(&self, frame: &str) -> Result<(usize, Node<T>, Isometry3<T>), WorldError> {
+        let mut split = frame.splitn(2, '/');
+        let robot_name = split.next().unwrap_or("");
+        let node_name = match split.next() {
+            Some(node_name) => node_name,
+            None => return not_found(frame),
+        };
+        let robot_index = match self.robots.iter().position(|r| r.name == robot_name) {
+            Some(index) => index,
+            None => return not_found(robot_name),
+        };
+        match self.robots[robot_index].chain.find_frame(node_name) {
+            Ok((node, offset)) => Ok((robot_index, node, offset)),
+            Err(_) => not_found(frame),
+        }
+    }
+
+    /// Pose of the frame in the world
+    pub fn frame_transform(&self, frame: &str) -> Result<Isometry3<T>, WorldError> {
+        if frame == WORLD_FRAME_NAME {
+            return Ok(Isometry3::identity());
+        }
+        if let Ok(index) = self.find_object(frame) {
+            return Ok(self.object_pose(index));
+        }
+        if let Ok(robot) = self.find_robot(frame) {
+            return Ok(robot.base);
+        }
+        let (robot_index, node, offset) = self.find_node(frame)?;
+        Ok(self.robots[robot_index].base * node.updated_world_transform() * offset)
+    }
+    /// Pose of the frame `to` seen from the frame `from`
+    ///
+    /// The positions in `to` are converted to `from` by multiplying it.
+    pub fn transform(&self, from: &str, to: &str) -> Result<Isometry3<T>, WorldError> {
+        Ok(self.frame_transform(from)?.inverse() * self.frame_transform(to)?)
+    }
+
+    /// Update the transforms of all the robots
+    pub fn update_transforms(&self) {
+        for robot in &self.robots {
+            robot.chain.update_link_transforms();
+        }
+    }
+
+    /// Collision checker of the robot, the static objects and the other robots are obstacles
+    ///
+    /// The obstacles are placed at the current poses in the frame of the chain (relative
+    /// to the base of the robot), because the checker uses the transforms of the chain.
+    /// The objects attached to the robot are checked as its links.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::Geometry;
+    /// use k::world::World;
+    ///
+    /// let hand = JointBuilder::new()
+    ///     .name("hand")
+    ///     .joint_type(JointType::Linear { axis: Vector3::x_axis() })
+    ///     .into_node();
+    /// let mut world = World::new();
+    /// world.add_robot("robot", Chain::from_root(hand.clone()), Isometry3::identity()).unwrap();
+    /// world.add_object(
+    ///     "box", Geometry::Sphere { radius: 0.1 }, Isometry3::translation(0.0, 0.0, 0.2)).unwrap();
+    /// world.add_object(
+    ///     "wall", Geometry::Box { depth: 0.1, width: 1.0, height: 1.0 },
+    ///     Isometry3::translation(1.0, 0.0, 0.0)).unwrap();
+    /// world.attach_object("box", "robot/hand").unwrap();
+    /// let checker = world.collision_checker("robot").unwrap();
+    /// assert_eq!(checker.obstacles.len(), 1);
+    /// let robot = world.robot("robot").unwrap();
+    /// assert!(!checker.is_colliding(robot));
+    /// hand.set_joint_position(0.9).unwrap();
+    /// assert!(checker.is_colliding(robot));
+    /// ```
+    pub fn collision_checker(&self, name: &str) -> Result<CollisionChecker<T>, WorldError> {
+        let world_to_chain = self.find_robot(name)?.base.inverse();
+        let mut checker = CollisionChecker::new();
+        for object in &self.objects {
+            if let Placement::Static(pose) = object.placement {
+                let collision = Collision::new(object.name.clone(), pose, object.geometry.clone());
+                if let Some(shape) = Shape::from_collision(&collision) {
+                    checker = checker.obstacle(Obstacle::Shape {
+                        shape,
+                        transform: world_to_chain * pose,
+                    });
+                }
+            }
+        }
+        for robot in self.robots.iter().filter(|r| r.name != name) {
+            let other_to_chain = world_to_chain * robot.base;
+            for (_, shapes) in robot.chain.link_collision_shapes() {
+                for (shape, transform) in shapes {
+                    checker = checker.obstacle(Obstacle::Shape {
+                        shape,
+                        transform: other_to_chain * transform,
+                    });
+                }
+            }
+        }
+        Ok(checker)
+    }
+
+    /// All the geometries with the current poses, for drawing
+    ///
+    /// The visuals of the links of the robots and the objects are listed.
+    pub fn geometries(&self) -> Vec<WorldGeometry<T>> {
+        self.update_transforms();
+        let mut geometries = Vec::new();
+        for robot in &self.robots {
+            for link in robot.chain.iter_links() {
+                for visual in &link.visuals {
+                    if let Some(transform) = *visual.world_transform() {
+                        geometries.push(WorldGeometry {
+                            name: format!("{}/{}", robot.name, link.name),
+                            geometry: visual.geometry.clone(),
+                            transform: robot.base * transform,
+                        });
+                    }
+                }
+            }
+        }
+        for (index, object) in self.objects.iter().enumerate() {
+            geometries.push(WorldGeometry {
+                name: object.name.clone(),
+                geometry: object.geometry.clone(),
+                transform: self.object_pose(index),
+            });
+        }
+        geometries
+    }
+}
+
+impl<T> Default for World<T>
+where
+    T: Real,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_world() {
+    use joint::*;
+    use na::{Translation3, UnitQuaternion, Vector3};
+    let make_robot = || {
+        let base = JointBuilder::new().name("base").into_node();
+        let wrist = JointBuilder::new()
+            .name("wrist_yaw")
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .translation(Translation3::new(1.0, 0.0, 0.0))
+            .into_node();
+        wrist.set_link(Some(
+            LinkBuilder::new()
+                .name("hand")
+                .add_collision(Collision::new(
+                    "hand".to_owned(),
+                    Isometry3::identity(),
+                    Geometry::Sphere { radius: 0.1 },
+                ))
+                .finalize(),
+        ));
+        wrist.set_parent(&base);
+        Chain::<f64>::from_root(base)
+    };
+    let mut world = World::new();
+    world
+        .add_robot("robot1", make_robot(), Isometry3::identity())
+        .unwrap();
+    let base2 = Isometry3::from_parts(
+        Translation3::new(2.0, 0.0, 0.0),
+        UnitQuaternion::from_euler_angles(0.0, 0.0, ::std::f64::consts::PI),
+    );
+    world.add_robot("robot2", make_robot(), base2).unwrap();
+    assert!(world
+        .add_robot("robot2", make_robot(), Isometry3::identity())
+        .is_err());
+    assert!(world
+        .add_object(
+            "world",
+            Geometry::Sphere { radius: 1.0 },
+            Isometry3::identity()
+        )
+        .is_err());
+    assert_eq!(world.robot_names(), vec!["robot1", "robot2"]);
+
+    // both hands are at (1, 0, 0)
+    let t = world.transform("robot1/wrist_yaw", "robot2/hand").unwrap();
+    assert!(t.translation.vector.norm() < 1e-10);
+    assert!((t.rotation.angle() - ::std::f64::consts::PI).abs() < 1e-10);
+    assert_eq!(
+        world
+            .transform("robot1", "robot2")
+            .unwrap()
+            .translation
+            .vector,
+        Vector3::new(2.0, 0.0, 0.0)
+    );
+    assert!(world.transform("robot1/elbow", "world").is_err());
+    world
+        .robot("robot1")
+        .unwrap()
+        .add_frame("tool", "hand", Isometry3::translation(0.1, 0.0, 0.0))
+        .unwrap();
+    assert!(
+        (world
+            .transform("robot2/hand", "robot1/tool")
+            .unwrap()
+            .translation
+            .vector
+            - Vector3::new(-0.1, 0.0, 0.0))
+        .norm()
+            < 1e-10
+    );
+    assert!(world.transform("robot3/base", "world").is_err());
+    assert!(world.transform("table", "world").is_err());
+    assert!(world
+        .collision_checker("robot1")
+        .unwrap()
+        .is_colliding(world.robot("robot1").unwrap()));
+
+    world
+        .set_base_pose("robot2", Isometry3::translation(3.0, 0.0, 0.0))
+        .unwrap();
+    assert_eq!(
+        world
+            .frame_transform("robot2/hand")
+            .unwrap()
+            .translation
+            .vector,
+        Vector3::new(4.0, 0.0, 0.0)
+    );
+    let checker = world.collision_checker("robot1").unwrap();
+    assert!(!checker.is_colliding(world.robot("robot1").unwrap()));
+
+    // attach the object to the node without link
+    world
+        .add_object(
+            "cup",
+            Geometry::Cylinder {
+                radius: 0.05,
+                length: 0.1,
+            },
+            Isometry3::translation(0.0, 0.0, 0.5),
+        )
+        .unwrap();
+    world.attach_object("cup", "robot1/base").unwrap();
+    assert_eq!(
+        world
+            .robot("robot1")
+            .unwrap()
+            .find("base")
+            .unwrap()
+            .link()
+            .as_ref()
+            .unwrap()
+            .collisions
+            .len(),
+        1
+    );
+    world
+        .set_base_pose("robot1", Isometry3::translation(0.0, 1.0, 0.0))
+        .unwrap();
+    assert_eq!(
+        world.frame_transform("cup").unwrap().translation.vector,
+        Vector3::new(0.0, 1.0, 0.5)
+    );
+    assert_eq!(world.geometries().len(), 1);
+    world.remove_object("cup").unwrap();
+    assert!(world.object_names().is_empty());
+    // the link created by attach_object is removed
+    assert!(world
+        .robot("robot1")
+        .unwrap()
+        .find("base")
+        .unwrap()
+        .link()
+        .is_none());
+}
+
+#[cfg(test)]
+fn make_test_arm() -> Chain<f64> {
+    use joint::*;
+    use na::{Translation3, Vector3};
+    let base = JointBuilder::new()
+        .name("base")
+        .translation(Translation3::new(0.0, 0.0, 0.5))
+        .into_node();
+    let arm = JointBuilder::new()
+        .name("arm")
+        .joint_type(JointType::Linear {
+            axis: Vector3::x_axis(),
+        })
+        .translation(Translation3::new(1.0, 0.0, 0.0))
+        .into_node();
+    arm.set_link(Some(
+        LinkBuilder::new()
+            .name("arm_link")
+            .add_collision(Collision::new(
+                "arm_link".to_owned(),
+                Isometry3::identity(),
+                Geometry::Sphere { radius: 0.1 },
+            ))
+            .finalize(),
+    ));
+    arm.set_parent(&base);
+    Chain::from_root(base)
+}
+
+#[test]
+fn test_world_add_robot_keeps_chain() {
+    use na::Vector3;
+    let chain = make_test_arm();
+    let root = chain.find("base").unwrap();
+    let mut world = World::new();
+    world
+        .add_robot(
+            "robot1",
+            Chain::from_root(root.clone()),
+            Isometry3::translation(0.0, 1.0, 0.0),
+        )
+        .unwrap();
+    world
+        .add_robot(
+            "robot2",
+            Chain::from_root(root.clone()),
+            Isometry3::translation(0.0, 2.0, 0.0),
+        )
+        .unwrap();
+    // the chain of the caller is not moved
+    assert_eq!(
+        chain
+            .find("base")
+            .unwrap()
+            .joint()
+            .origin()
+            .translation
+            .vector,
+        Vector3::new(0.0, 0.0, 0.5)
+    );
+    assert_eq!(
+        chain
+            .find("arm")
+            .unwrap()
+            .updated_world_transform()
+            .translation
+            .vector,
+        Vector3::new(1.0, 0.0, 0.5)
+    );
+    // the bases are not stacked even if the nodes are shared
+    assert_eq!(
+        world
+            .frame_transform("robot1/arm")
+            .unwrap()
+            .translation
+            .vector,
+        Vector3::new(1.0, 1.0, 0.5)
+    );
+    assert_eq!(
+        world
+            .frame_transform("robot2/arm")
+            .unwrap()
+            .translation
+            .vector,
+        Vector3::new(1.0, 2.0, 0.5)
+    );
+    world
+        .set_base_pose("robot2", Isometry3::translation(0.0, 3.0, 0.0))
+        .unwrap();
+    assert_eq!(
+        world
+            .frame_transform("robot2/arm")
+            .unwrap()
+            .translation
+            .vector,
+        Vector3::new(1.0, 3.0, 0.5)
+    );
+    assert_eq!(world.geometries().len(), 0, "the links have no visuals");
+}
+
+#[test]
+fn test_world_collision_checker_in_chain_frame() {
+    use na::{Translation3, UnitQuaternion};
+    let mut world = World::new();
+    let base = Isometry3::from_parts(
+        Translation3::new(0.0, 2.0, 0.0),
+        UnitQuaternion::from_euler_angles(0.0, 0.0, ::std::f64::consts::FRAC_PI_2),
+    );
+    world.add_robot("robot", make_test_arm(), base).unwrap();
+    // the arm is at (0, 3, 0.5) in the world
+    world
+        .add_object(
+            "ball",
+            Geometry::Sphere { radius: 0.1 },
+            Isometry3::translation(0.0, 3.15, 0.5),
+        )
+        .unwrap();
+    assert!(world
+        .collision_checker("robot")
+        .unwrap()
+        .is_colliding(world.robot("robot").unwrap()));
+    world
+        .add_robot(
+            "other",
+            make_test_arm(),
+            Isometry3::translation(-2.0, 0.0, 0.0),
+        )
+        .unwrap();
+    world.remove_object("ball").unwrap();
+    assert!(!world
+        .collision_checker("robot")
+        .unwrap()
+        .is_colliding(world.robot("robot").unwrap()));
+    // move the arm of the other robot to (0, 3, 0.5)
+    world
+        .robot("other")
+        .unwrap()
+        .find("arm")
+        .unwrap()
+        .set_joint_position(1.0)
+        .unwrap();
+    world
+        .set_base_pose("other", Isometry3::translation(-2.0, 3.0, 0.0))
+        .unwrap();
+    assert!(world
+        .collision_checker("robot")
+        .unwrap()
+        .is_colliding(world.robot("robot").unwrap()));
+}
+
+#[test]
+fn test_world_detach_object() {
+    use na::Vector3;
+    let mut world = World::new();
+    world
+        .add_robot(
+            "robot",
+            make_test_arm(),
+            Isometry3::translation(0.0, 1.0, 0.0),
+        )
+        .unwrap();
+    for &(name, z) in &[("cup", 1.0), ("plate", 2.0)] {
+        world
+            .add_object(
+                name,
+                Geometry::Sphere { radius: 0.1 },
+                Isometry3::translation(0.0, 0.0, z),
+            )
+            .unwrap();
+    }
+    let num_collisions = |world: &World<f64>, node: &str| {
+        world
+            .robot("robot")
+            .unwrap()
+            .find(node)
+            .unwrap()
+            .link()
+            .as_ref()
+            .map(|link| link.collisions.len())
+    };
+    // the link of the node is kept
+    world.attach_object("cup", "robot/arm").unwrap();
+    assert_eq!(num_collisions(&world, "arm"), Some(2));
+    world.detach_object("cup").unwrap();
+    assert_eq!(num_collisions(&world, "arm"), Some(1));
+
+    // the created link is kept until all the objects are detached
+    world.attach_object("cup", "robot/base").unwrap();
+    world.attach_object("plate", "robot/base").unwrap();
+    assert_eq!(num_collisions(&world, "base"), Some(2));
+    world
+        .set_base_pose("robot", Isometry3::translation(0.0, 2.0, 0.0))
+        .unwrap();
+    assert_eq!(
+        world.frame_transform("plate").unwrap().translation.vector,
+        Vector3::new(0.0, 1.0, 2.0)
+    );
+    world.detach_object("cup").unwrap();
+    assert_eq!(num_collisions(&world, "base"), Some(1));
+    world.detach_object("plate").unwrap();
+    assert_eq!(num_collisions(&world, "base"), None);
+    // detached objects stay at the last pose
+    assert_eq!(
+        world.frame_transform("cup").unwrap().translation.vector,
+        Vector3::new(0.0, 1.0, 1.0)
+    );
+    assert!(world.detach_object("glass").is_err());
+}
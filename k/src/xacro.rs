@@ -0,0 +1,1613 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Expand [xacro](http://wiki.ros.org/xacro) into URDF string
+//!
+//! The common subset of xacro is supported.
+//!
+//! * `<xacro:property>` with `value` or block contents, `<xacro:insert_block>`
+//! * `<xacro:arg>` and `$(arg name)`, `$(find pkg)`, `$(env VAR)`, `$(optenv VAR default)`
+//! * `<xacro:macro>` with `name:=default`, `name:=^|default`, `*block` and `**block` params
+//! * `<xacro:include>`, the filenames are resolved by `ResourceResolver`
+//! * `<xacro:if>` and `<xacro:unless>`
+//! * `${...}` with arithmetic, comparison, `and`/`or`/`not`, `a if c else b`
+//!   and the functions of python `math`
+//!
+//! # Examples
+//!
+//! ```
+//! let urdf = k::xacro::Expander::new()
+//!     .arg("arm_length", "0.5")
+//!     .expand_str(
+//!         r#"<robot name="arm" xmlns:xacro="http://www.ros.org/wiki/xacro">
+//!         <xacro:arg name="arm_length" default="1.0"/>
+//!         <xacro:property name="length" value="$(arg arm_length)"/>
+//!         <xacro:macro name="link" params="name">
+//!           <link name="${name}"/>
+//!         </xacro:macro>
+//!         <xacro:link name="base"/>
+//!         <xacro:link name="arm"/>
+//!         <joint name="shoulder" type="revolute">
+//!           <origin xyz="0 0 ${length / 2}"/>
+//!           <parent link="base"/>
+//!           <child link="arm"/>
+//!           <limit lower="${-pi / 2}" upper="${pi / 2}" effort="1" velocity="1"/>
+//!         </joint>
+//!         </robot>"#,
+//!     )
+//!     .unwrap();
+//! let chain = k::Chain::<f64>::from_urdf_str(&urdf).unwrap();
+//! let shoulder = chain.find("shoulder").unwrap();
+//! assert_eq!(shoulder.joint().origin().translation.vector.z, 0.25);
+//! ```
+use std::collections::HashMap;
+use std::env;
+use std::f64::consts;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use xml::{Element, Xml};
+
+use errors::*;
+use resource::ResourceResolver;
+
+const XACRO_NAMESPACES: [&str; 2] = ["http://www.ros.org/wiki/xacro", "http://ros.org/wiki/xacro"];
+const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+/// Limit of the nesting of the macros and the includes
+const MAX_DEPTH: usize = 100;
+
+/// Value of the expression
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Value {
+    /// Interpret the text of the property like python literals
+    fn from_text(text: &str) -> Self {
+        match text.trim() {
+            "True" => Value::Bool(true),
+            "False" => Value::Bool(false),
+            trimmed => match trimmed.parse() {
+                Ok(number) => Value::Number(number),
+                Err(_) => Value::Str(text.to_owned()),
+            },
+        }
+    }
+    fn is_truthy(&self) -> bool {
+        match *self {
+            Value::Number(number) => number != 0.0,
+            Value::Bool(b) => b,
+            Value::Str(ref s) => !s.is_empty(),
+        }
+    }
+    fn number(&self) -> Result<f64, String> {
+        match *self {
+            Value::Number(number) => Ok(number),
+            Value::Bool(b) => Ok(if b { 1.0 } else { 0.0 }),
+            Value::Str(ref s) => Err(format!("{:?} is not a number", s)),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Bool(true) => write!(f, "True"),
+            Value::Bool(false) => write!(f, "False"),
+            Value::Str(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Name(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 16] = [
+    "**", "//", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "(", ")", ",",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let bytes = expression.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && i + 1 < bytes.len() && bytes[i + 1].is_ascii_digit())
+        {
+            while i < bytes.len() {
+                let c = bytes[i] as char;
+                let is_exponent_sign =
+                    (c == '+' || c == '-') && (bytes[i - 1] == b'e' || bytes[i - 1] == b'E');
+                if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || is_exponent_sign) {
+                    break;
+                }
+                i += 1;
+            }
+            let number = &expression[start..i];
+            tokens.push(Token::Number(
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid number {}", number))?,
+            ));
+        } else if c == '\'' || c == '"' {
+            let end = expression[i + 1..]
+                .find(c)
+                .ok_or_else(|| "unterminated string".to_owned())?;
+            tokens.push(Token::Str(expression[i + 1..i + 1 + end].to_owned()));
+            i += end + 2;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || b"_.".contains(&bytes[i]))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Name(expression[start..i].to_owned()));
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| expression[i..].starts_with(*op))
+                .ok_or_else(|| format!("unexpected {:?}", &expression[i..]))?;
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+fn call_function(name: &str, args: &[Value]) -> Result<Value, String> {
+    let numbers = args
+        .iter()
+        .map(Value::number)
+        .collect::<Result<Vec<_>, _>>()?;
+    let unary = |f: fn(f64) -> f64| match numbers.len() {
+        1 => Ok(Value::Number(f(numbers[0]))),
+        n => Err(format!("{}() takes 1 argument, but {} given", name, n)),
+    };
+    match name {
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "sqrt" => unary(f64::sqrt),
+        "exp" => unary(f64::exp),
+        "log" => unary(f64::ln),
+        "log10" => unary(f64::log10),
+        "abs" | "fabs" => unary(f64::abs),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "int" => unary(f64::trunc),
+        "float" => unary(|v| v),
+        "radians" => unary(f64::to_radians),
+        "degrees" => unary(f64::to_degrees),
+        "atan2" | "pow" if numbers.len() == 2 => Ok(Value::Number(if name == "atan2" {
+            numbers[0].atan2(numbers[1])
+        } else {
+            numbers[0].powf(numbers[1])
+        })),
+        "min" | "max" if !numbers.is_empty() => {
+            let pick = if name == "min" { f64::min } else { f64::max };
+            Ok(Value::Number(
+                numbers[1..].iter().fold(numbers[0], |a, b| pick(a, *b)),
+            ))
+        }
+        "atan2" | "pow" | "min" | "max" => Err(format!("wrong number of arguments for {}()", name)),
+        _ => Err(format!("unknown function {}", name)),
+    }
+}
+
+type Lookup<'a> = &'a dyn Fn(&str) -> Option<Value>;
+
+/// Parsed expression, evaluated lazily for `and`, `or` and `if`
+#[derive(Debug)]
+enum Expr {
+    Literal(Value),
+    Name(String),
+    Call(String, Vec<Expr>),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    /// `then if condition else otherwise`
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+const COMPARISONS: [&str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+/// Recursive descent parser of python-like expressions
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+    /// Consume the operator if it is one of `ops`
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(&Token::Op(op)) if ops.contains(&op) => {
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+    fn eat_name(&mut self, name: &str) -> bool {
+        let found = match self.peek() {
+            Some(Token::Name(n)) => n == name,
+            _ => false,
+        };
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+    fn expression(&mut self) -> Result<Expr, String> {
+        let value = self.or()?;
+        if self.eat_name("if") {
+            let condition = self.or()?;
+            if !self.eat_name("else") {
+                return Err("else is required".to_owned());
+            }
+            let other = self.expression()?;
+            return Ok(Expr::Conditional(
+                Box::new(value),
+                Box::new(condition),
+                Box::new(other),
+            ));
+        }
+        Ok(value)
+    }
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat_name("or") {
+            expr = Expr::Binary("or", Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat_name("and") {
+            expr = Expr::Binary("and", Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat_name("not") {
+            Ok(Expr::Unary("not", Box::new(self.not()?)))
+        } else {
+            self.comparison()
+        }
+    }
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.sum()?;
+        match self.eat_op(&COMPARISONS) {
+            Some(op) => Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.sum()?))),
+            None => Ok(lhs),
+        }
+    }
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.term()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.term()?));
+        }
+        Ok(expr)
+    }
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.eat_op(&["*", "/", "//", "%"]) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.eat_op(&["-", "+"]) {
+            Some(op) => Ok(Expr::Unary(op, Box::new(self.unary()?))),
+            None => self.power(),
+        }
+    }
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.eat_op(&["**"]).is_some() {
+            return Ok(Expr::Binary("**", Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| "unexpected end".to_owned())?;
+        self.position += 1;
+        match token {
+            Token::Number(number) => Ok(Expr::Literal(Value::Number(number))),
+            Token::Str(s) => Ok(Expr::Literal(Value::Str(s))),
+            Token::Op("(") => {
+                let expr = self.expression()?;
+                if self.eat_op(&[")"]).is_none() {
+                    return Err("missing )".to_owned());
+                }
+                Ok(expr)
+            }
+            Token::Name(name) => {
+                let name = name.trim_start_matches("math.").to_owned();
+                if self.eat_op(&["("]).is_none() {
+                    return Ok(Expr::Name(name));
+                }
+                let mut args = Vec::new();
+                if self.eat_op(&[")"]).is_none() {
+                    loop {
+                        args.push(self.expression()?);
+                        match self.eat_op(&[")", ","]) {
+                            Some(")") => break,
+                            Some(_) => {}
+                            None => return Err("missing )".to_owned()),
+                        }
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Token::Op(op) => Err(format!("unexpected {}", op)),
+        }
+    }
+}
+
+fn binary(op: &str, lhs: Value, rhs: Value) -> Result<Value, String> {
+    use std::cmp::Ordering;
+    if COMPARISONS.contains(&op) {
+        let ordering = match (&lhs, &rhs) {
+            (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+            (Value::Str(_), _) | (_, Value::Str(_)) => None,
+            _ => lhs.number()?.partial_cmp(&rhs.number()?),
+        };
+        let result = match (op, ordering) {
+            ("==", ordering) => ordering == Some(Ordering::Equal),
+            ("!=", ordering) => ordering != Some(Ordering::Equal),
+            (_, None) => return Err(format!("can not compare {} and {}", lhs, rhs)),
+            ("<=", Some(o)) => o != Ordering::Greater,
+            (">=", Some(o)) => o != Ordering::Less,
+            ("<", Some(o)) => o == Ordering::Less,
+            (_, Some(o)) => o == Ordering::Greater,
+        };
+        return Ok(Value::Bool(result));
+    }
+    if let (Value::Str(a), Value::Str(b), "+") = (&lhs, &rhs, op) {
+        return Ok(Value::Str(format!("{}{}", a, b)));
+    }
+    let (a, b) = (lhs.number()?, rhs.number()?);
+    if b == 0.0 && ["/", "//", "%"].contains(&op) {
+        return Err("division by zero".to_owned());
+    }
+    Ok(Value::Number(match op {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" => a / b,
+        "//" => (a / b).floor(),
+        // python modulo takes the sign of the divisor
+        "%" => a - b * (a / b).floor(),
+        _ => a.powf(b),
+    }))
+}
+
+fn eval(expr: &Expr, lookup: Lookup) -> Result<Value, String> {
+    match *expr {
+        Expr::Literal(ref value) => Ok(value.clone()),
+        Expr::Name(ref name) => {
+            if let Some(value) = lookup(name) {
+                return Ok(value);
+            }
+            match name.as_str() {
+                "pi" => Ok(Value::Number(consts::PI)),
+                "e" => Ok(Value::Number(consts::E)),
+                "True" => Ok(Value::Bool(true)),
+                "False" => Ok(Value::Bool(false)),
+                _ => Err(format!("{} is not defined", name)),
+            }
+        }
+        Expr::Call(ref name, ref args) => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, lookup))
+                .collect::<Result<Vec<_>, _>>()?;
+            call_function(name, &args)
+        }
+        Expr::Unary("not", ref operand) => Ok(Value::Bool(!eval(operand, lookup)?.is_truthy())),
+        Expr::Unary(op, ref operand) => {
+            let number = eval(operand, lookup)?.number()?;
+            Ok(Value::Number(if op == "-" { -number } else { number }))
+        }
+        Expr::Binary(op, ref lhs, ref rhs) if op == "and" || op == "or" => {
+            let lhs = eval(lhs, lookup)?;
+            if lhs.is_truthy() == (op == "or") {
+                Ok(lhs)
+            } else {
+                eval(rhs, lookup)
+            }
+        }
+        Expr::Binary(op, ref lhs, ref rhs) => binary(op, eval(lhs, lookup)?, eval(rhs, lookup)?),
+        Expr::Conditional(ref then, ref condition, ref otherwise) => {
+            if eval(condition, lookup)?.is_truthy() {
+                eval(then, lookup)
+            } else {
+                eval(otherwise, lookup)
+            }
+        }
+    }
+}
+
+fn evaluate(expression: &str, lookup: Lookup) -> Result<Value, XacroError> {
+    let error = |message| XacroError::ExpressionError {
+        expression: expression.to_owned(),
+        message,
+    };
+    let mut parser = Parser {
+        tokens: tokenize(expression).map_err(error)?,
+        position: 0,
+    };
+    let expr = parser.expression().map_err(error)?;
+    if parser.position != parser.tokens.len() {
+        return Err(error("unexpected trailing tokens".to_owned()));
+    }
+    eval(&expr, lookup).map_err(error)
+}
+
+/// Parameter of macro
+enum Param {
+    /// `name`, `name:=default`
+    Value {
+        name: String,
+        default: Option<String>,
+    },
+    /// `*name`, the child element of the call
+    Block(String),
+    /// `**name`, the children of the child element of the call
+    Blocks(String),
+}
+
+struct Macro {
+    name: String,
+    params: Vec<Param>,
+    body: Vec<Xml>,
+}
+
+#[derive(Default)]
+struct Scope {
+    properties: HashMap<String, String>,
+    blocks: HashMap<String, Vec<Xml>>,
+    /// Block params of the macro, expanded in the scope of the caller
+    arguments: HashMap<String, Vec<Xml>>,
+    macros: HashMap<String, Rc<Macro>>,
+}
+
+fn is_xacro(element: &Element) -> bool {
+    match element.ns {
+        Some(ref ns) => XACRO_NAMESPACES.contains(&ns.as_str()),
+        None => false,
+    }
+}
+
+fn parse_xml(text: &str) -> Result<Element, XacroError> {
+    text.parse().map_err(|e| XacroError::ParseError {
+        error: format!("{:?}", e),
+    })
+}
+
+fn required_attribute<'a>(element: &'a Element, name: &str) -> Result<&'a str, XacroError> {
+    element
+        .get_attribute(name, None)
+        .ok_or_else(|| XacroError::UnsupportedError {
+            element: format!("xacro:{}", element.name),
+            message: format!("{} is required", name),
+        })
+}
+
+/// Byte index of the `}` which closes `${` at the start of `text`
+///
+/// The nested braces are counted, and the braces in the quoted strings are ignored.
+fn find_expression_end(text: &str) -> Option<usize> {
+    let mut level = 0;
+    let mut quote = None;
+    for (index, c) in text.char_indices().skip(1) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '{' => level += 1,
+                '}' => {
+                    level -= 1;
+                    if level == 0 {
+                        return Some(index);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+fn child_elements(element: &Element) -> impl Iterator<Item = &Element> {
+    element.children.iter().filter_map(|child| match *child {
+        Xml::ElementNode(ref e) => Some(e),
+        _ => None,
+    })
+}
+
+/// Expansion of one document
+struct State<'a> {
+    expander: &'a Expander,
+    args: HashMap<String, String>,
+    scopes: Vec<Scope>,
+    /// Directories of the current files, for relative includes
+    dirs: Vec<PathBuf>,
+    depth: usize,
+}
+
+impl<'a> State<'a> {
+    fn property(&self, name: &str) -> Option<&String> {
+        self.scopes
+            .iter()
+            .rev()
+            .filter_map(|scope| scope.properties.get(name))
+            .next()
+    }
+
+    /// Substitute `$(...)` and `${...}`, `$$` escapes them
+    fn substitute(&self, text: &str) -> Result<String, XacroError> {
+        let mut output = String::new();
+        let mut rest = text;
+        while let Some(index) = rest.find('$') {
+            output.push_str(&rest[..index]);
+            rest = &rest[index..];
+            if rest.starts_with("$${") || rest.starts_with("$$(") {
+                output.push_str(&rest[1..3]);
+                rest = &rest[3..];
+            } else if rest.starts_with("${") {
+                let end = find_expression_end(rest).ok_or_else(|| XacroError::ExpressionError {
+                    expression: rest.to_owned(),
+                    message: "missing }".to_owned(),
+                })?;
+                let expression = self.substitute(&rest[2..end])?;
+                let lookup = |name: &str| self.property(name).map(|v| Value::from_text(v));
+                output.push_str(&evaluate(&expression, &lookup)?.to_string());
+                rest = &rest[end + 1..];
+            } else if rest.starts_with("$(") {
+                let mut level = 0;
+                let end = rest
+                    .char_indices()
+                    .find(|&(_, c)| {
+                        match c {
+                            '(' => level += 1,
+                            ')' => level -= 1,
+                            _ => {}
+                        }
+                        level == 0 && c == ')'
+                    })
+                    .ok_or_else(|| XacroError::ExpressionError {
+                        expression: rest.to_owned(),
+                        message: "missing )".to_owned(),
+                    })?
+                    .0;
+                output.push_str(&self.command(&rest[2..end])?);
+                rest = &rest[end + 1..];
+            } else {
+                output.push('$');
+                rest = &rest[1..];
+            }
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Evaluate `$(command args)`
+    fn command(&self, command: &str) -> Result<String, XacroError> {
+        let command = command.trim();
+        let (name, args) = match command.find(char::is_whitespace) {
+            Some(index) => (&command[..index], command[index..].trim()),
+            None => (command, ""),
+        };
+        let mut words = args.split_whitespace();
+        let arg = words.next().unwrap_or("");
+        let undefined = |kind: &str| XacroError::UndefinedError {
+            kind: kind.to_owned(),
+            name: arg.to_owned(),
+        };
+        match name {
+            "arg" => self.args.get(arg).cloned().ok_or_else(|| undefined("arg")),
+            "find" => self
+                .expander
+                .resolver
+                .resolve_path(&format!("package://{}", arg))
+                .map(|path| path.to_string_lossy().into_owned())
+                .ok_or_else(|| undefined("package")),
+            "env" => env::var(arg).map_err(|_| undefined("environment variable")),
+            "optenv" => Ok(env::var(arg).unwrap_or_else(|_| words.collect::<Vec<_>>().join(" "))),
+            "eval" => {
+                let lookup = |name: &str| self.property(name).map(|v| Value::from_text(v));
+                Ok(evaluate(args, &lookup)?.to_string())
+            }
+            "dirname" => Ok(self.current_dir().to_string_lossy().into_owned()),
+            _ => Err(XacroError::ExpressionError {
+                expression: command.to_owned(),
+                message: format!("unsupported command {}", name),
+            }),
+        }
+    }
+
+    fn current_dir(&self) -> &Path {
+        self.dirs.last().map_or(Path::new("."), |dir| dir.as_path())
+    }
+
+    fn enter(&mut self, element: &Element) -> Result<(), XacroError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(XacroError::UnsupportedError {
+                element: format!("xacro:{}", element.name),
+                message: "too deep recursion".to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Expand the element which is not xacro
+    fn expand_element(&mut self, element: &Element) -> Result<Element, XacroError> {
+        let mut attributes = Vec::new();
+        for ((name, ns), value) in &element.attributes {
+            let is_xacro_namespace = ns.as_ref().map(String::as_str) == Some(XMLNS_NAMESPACE)
+                && XACRO_NAMESPACES.contains(&value.as_str());
+            if !is_xacro_namespace {
+                attributes.push((name.clone(), ns.clone(), self.substitute(value)?));
+            }
+        }
+        let mut expanded = Element::new(element.name.clone(), element.ns.clone(), attributes);
+        self.expand_children(&element.children, &mut expanded.children)?;
+        Ok(expanded)
+    }
+
+    fn expand_children(
+        &mut self,
+        children: &[Xml],
+        output: &mut Vec<Xml>,
+    ) -> Result<(), XacroError> {
+        for child in children {
+            match *child {
+                Xml::ElementNode(ref element) if is_xacro(element) => {
+                    self.expand_xacro(element, output)?
+                }
+                Xml::ElementNode(ref element) => {
+                    output.push(Xml::ElementNode(self.expand_element(element)?))
+                }
+                Xml::CharacterNode(ref text) => {
+                    if !text.trim().is_empty() {
+                        output.push(Xml::CharacterNode(self.substitute(text)?));
+                    }
+                }
+                Xml::CDATANode(_) | Xml::CommentNode(_) => output.push(child.clone()),
+                Xml::PINode(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn expand_xacro(&mut self, element: &Element, output: &mut Vec<Xml>) -> Result<(), XacroError> {
+        match element.name.as_str() {
+            "property" => {
+                let name = self.substitute(required_attribute(element, "name")?)?;
+                let index = match element.get_attribute("scope", None) {
+                    Some("global") => 0,
+                    Some("parent") if self.scopes.len() > 1 => self.scopes.len() - 2,
+                    _ => self.scopes.len() - 1,
+                };
+                if let Some(value) = element.get_attribute("value", None) {
+                    let value = self.substitute(value)?;
+                    self.scopes[index].properties.insert(name, value);
+                } else if let Some(default) = element.get_attribute("default", None) {
+                    if self.property(&name).is_none() {
+                        let value = self.substitute(default)?;
+                        self.scopes[index].properties.insert(name, value);
+                    }
+                } else {
+                    self.scopes[index]
+                        .blocks
+                        .insert(name, element.children.clone());
+                }
+            }
+            "arg" => {
+                let name = required_attribute(element, "name")?;
+                if !self.args.contains_key(name) {
+                    if let Some(default) = element.get_attribute("default", None) {
+                        let value = self.substitute(default)?;
+                        self.args.insert(name.to_owned(), value);
+                    }
+                }
+            }
+            "macro" => {
+                let name = required_attribute(element, "name")?.to_owned();
+                let params = element
+                    .get_attribute("params", None)
+                    .unwrap_or("")
+                    .split_whitespace()
+                    .map(|param| {
+                        let name = param.trim_start_matches('*');
+                        match param.len() - name.len() {
+                            0 => {
+                                let mut parts = param.splitn(2, ":=");
+                                Param::Value {
+                                    name: parts.next().unwrap_or("").to_owned(),
+                                    default: parts.next().map(str::to_owned),
+                                }
+                            }
+                            1 => Param::Block(name.to_owned()),
+                            _ => Param::Blocks(name.to_owned()),
+                        }
+                    })
+                    .collect();
+                let scope = self.scopes.last_mut().unwrap();
+                scope.macros.insert(
+                    name.clone(),
+                    Rc::new(Macro {
+                        name,
+                        params,
+                        body: element.children.clone(),
+                    }),
+                );
+            }
+            "include" => {
+                let filename = self.substitute(required_attribute(element, "filename")?)?;
+                let path = if filename.starts_with("package://") {
+                    self.expander.resolver.resolve_path(&filename)
+                } else {
+                    Some(self.current_dir().join(&filename))
+                };
+                let text = path
+                    .as_ref()
+                    .ok_or_else(|| "not found".to_owned())
+                    .and_then(|path| fs::read_to_string(path).map_err(|e| e.to_string()))
+                    .map_err(|error| XacroError::IncludeError {
+                        filename: filename.clone(),
+                        error,
+                    })?;
+                let root = parse_xml(&text)?;
+                let path = path.unwrap();
+                self.enter(element)?;
+                self.dirs.push(
+                    path.parent()
+                        .unwrap_or_else(|| Path::new("."))
+                        .to_path_buf(),
+                );
+                self.expand_children(&root.children, output)?;
+                self.dirs.pop();
+                self.depth -= 1;
+            }
+            "if" | "unless" => {
+                let value = self.substitute(required_attribute(element, "value")?)?;
+                let condition = match value.trim() {
+                    "true" | "True" | "1" => true,
+                    "false" | "False" | "0" => false,
+                    other => match other.parse::<f64>() {
+                        Ok(number) => number != 0.0,
+                        Err(_) => {
+                            return Err(XacroError::ExpressionError {
+                                expression: value.clone(),
+                                message: "not a boolean".to_owned(),
+                            })
+                        }
+                    },
+                };
+                if condition == (element.name == "if") {
+                    self.expand_children(&element.children, output)?;
+                }
+            }
+            "insert_block" => {
+                let name = self.substitute(required_attribute(element, "name")?)?;
+                let (block, is_expanded) = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .filter_map(|scope| match scope.arguments.get(&name) {
+                        Some(block) => Some((block.clone(), true)),
+                        None => scope.blocks.get(&name).map(|block| (block.clone(), false)),
+                    })
+                    .next()
+                    .ok_or_else(|| XacroError::UndefinedError {
+                        kind: "block".to_owned(),
+                        name,
+                    })?;
+                if is_expanded {
+                    output.extend(block);
+                } else {
+                    self.enter(element)?;
+                    self.expand_children(&block, output)?;
+                    self.depth -= 1;
+                }
+            }
+            "call" => {
+                let name = self.substitute(required_attribute(element, "macro")?)?;
+                self.call_macro(&name, element, output)?;
+            }
+            name => self.call_macro(name, element, output)?,
+        }
+        Ok(())
+    }
+
+    fn call_macro(
+        &mut self,
+        name: &str,
+        element: &Element,
+        output: &mut Vec<Xml>,
+    ) -> Result<(), XacroError> {
+        let xacro_macro = self
+            .scopes
+            .iter()
+            .rev()
+            .filter_map(|scope| scope.macros.get(name))
+            .next()
+            .cloned()
+            .ok_or_else(|| XacroError::UndefinedError {
+                kind: "macro".to_owned(),
+                name: name.to_owned(),
+            })?;
+        let argument_error = |message: String| XacroError::MacroArgumentError {
+            macro_name: xacro_macro.name.clone(),
+            message,
+        };
+        for (attribute, _) in element.attributes.keys() {
+            let is_param = xacro_macro.params.iter().any(|param| match *param {
+                Param::Value { ref name, .. } => name == attribute,
+                _ => false,
+            });
+            if !(is_param || element.name == "call" && attribute == "macro") {
+                return Err(argument_error(format!("unknown parameter {}", attribute)));
+            }
+        }
+        let mut scope = Scope::default();
+        let mut blocks = child_elements(element);
+        for param in &xacro_macro.params {
+            match *param {
+                Param::Value {
+                    ref name,
+                    ref default,
+                } => {
+                    let value = match (element.get_attribute(name, None), default) {
+                        (Some(value), _) => self.substitute(value)?,
+                        // ^ takes the property of the outer scope
+                        (None, Some(default)) if default.starts_with('^') => {
+                            match (self.property(name), default.find('|')) {
+                                (Some(value), _) => value.clone(),
+                                (None, Some(index)) => self.substitute(&default[index + 1..])?,
+                                (None, None) => {
+                                    return Err(argument_error(format!(
+                                        "{} is not defined in the outer scope",
+                                        name
+                                    )))
+                                }
+                            }
+                        }
+                        (None, Some(default)) => self.substitute(default)?,
+                        (None, None) => {
+                            return Err(argument_error(format!("{} is required", name)))
+                        }
+                    };
+                    scope.properties.insert(name.clone(), value);
+                }
+                Param::Block(ref name) | Param::Blocks(ref name) => {
+                    let block = blocks
+                        .next()
+                        .ok_or_else(|| argument_error(format!("block {} is required", name)))?;
+                    let contents = match *param {
+                        Param::Block(_) => vec![Xml::ElementNode(block.clone())],
+                        _ => block.children.clone(),
+                    };
+                    // the block may insert the block of the caller with the same name
+                    let mut expanded = Vec::new();
+                    self.expand_children(&contents, &mut expanded)?;
+                    scope.arguments.insert(name.clone(), expanded);
+                }
+            }
+        }
+        self.enter(element)?;
+        self.scopes.push(scope);
+        self.expand_children(&xacro_macro.body, output)?;
+        self.scopes.pop();
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Namespaces declared by the written elements
+#[derive(Debug, Clone, Default)]
+struct Namespaces {
+    default: Option<String>,
+    /// Prefixes of the namespaces
+    prefixes: HashMap<String, String>,
+}
+
+impl Namespaces {
+    /// Add the namespaces declared in the attributes of `element`
+    fn declare(&mut self, element: &Element) {
+        for ((name, ns), value) in &element.attributes {
+            match *ns {
+                Some(ref ns) if ns == XMLNS_NAMESPACE => {
+                    self.prefixes.insert(value.clone(), name.clone());
+                }
+                None if name == "xmlns" && value.is_empty() => self.default = None,
+                None if name == "xmlns" => self.default = Some(value.clone()),
+                _ => {}
+            }
+        }
+    }
+    /// `prefix:` of the namespace, the new prefix is declared in `declarations`
+    ///
+    /// The elements in the default namespace have no prefix.
+    fn prefix(
+        &mut self,
+        ns: &Option<String>,
+        is_element: bool,
+        declarations: &mut Vec<String>,
+    ) -> String {
+        let ns = match *ns {
+            Some(ref ns) => ns,
+            None => {
+                if is_element && self.default.is_some() {
+                    declarations.push(" xmlns=\"\"".to_owned());
+                    self.default = None;
+                }
+                return "".to_owned();
+            }
+        };
+        if ns == XMLNS_NAMESPACE {
+            return "xmlns:".to_owned();
+        }
+        if ns == XML_NAMESPACE {
+            return "xml:".to_owned();
+        }
+        if is_element && self.default.as_ref() == Some(ns) {
+            return "".to_owned();
+        }
+        if !self.prefixes.contains_key(ns) {
+            // declared in the included file
+            let prefix = format!("ns{}", self.prefixes.len());
+            declarations.push(format!(" xmlns:{}=\"{}\"", prefix, escape(ns)));
+            self.prefixes.insert(ns.clone(), prefix);
+        }
+        format!("{}:", self.prefixes[ns])
+    }
+}
+
+/// Write XML with indentation, the attributes are sorted by name
+#[allow(clippy::match_like_matches_macro)]
+fn write_xml(output: &mut String, xml: &Xml, level: usize, namespaces: &Namespaces) {
+    let indent = "  ".repeat(level);
+    match *xml {
+        Xml::ElementNode(ref element) => {
+            let mut namespaces = namespaces.clone();
+            namespaces.declare(element);
+            let mut declarations = Vec::new();
+            let name = format!(
+                "{}{}",
+                namespaces.prefix(&element.ns, true, &mut declarations),
+                element.name
+            );
+            output.push_str(&format!("{}<{}", indent, name));
+            let mut attributes = element.attributes.iter().collect::<Vec<_>>();
+            attributes.sort_by(|a, b| (a.0).0.cmp(&(b.0).0));
+            for ((name, ns), value) in attributes {
+                let prefix = namespaces.prefix(ns, false, &mut declarations);
+                output.push_str(&format!(" {}{}=\"{}\"", prefix, name, escape(value)));
+            }
+            output.push_str(&declarations.concat());
+            let is_text = element.children.iter().all(|child| match *child {
+                Xml::CharacterNode(_) | Xml::CDATANode(_) => true,
+                _ => false,
+            });
+            if element.children.is_empty() {
+                output.push_str("/>\n");
+            } else if is_text {
+                output.push('>');
+                for child in &element.children {
+                    match *child {
+                        Xml::CharacterNode(ref text) => output.push_str(&escape(text.trim())),
+                        ref cdata => output.push_str(&cdata.to_string()),
+                    }
+                }
+                output.push_str(&format!("</{}>\n", name));
+            } else {
+                output.push_str(">\n");
+                for child in &element.children {
+                    write_xml(output, child, level + 1, &namespaces);
+                }
+                output.push_str(&format!("{}</{}>\n", indent, name));
+            }
+        }
+        Xml::CharacterNode(ref text) => {
+            output.push_str(&format!("{}{}\n", indent, escape(text.trim())))
+        }
+        ref other => output.push_str(&format!("{}{}\n", indent, other)),
+    }
+}
+
+/// Expander of xacro
+///
+/// The args which are not set by `arg()` take the default values in the xacro.
+#[derive(Debug, Clone, Default)]
+pub struct Expander {
+    args: HashMap<String, String>,
+    resolver: ResourceResolver,
+}
+
+impl Expander {
+    /// Create expander without args, `$(find pkg)` fails
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set the value of `$(arg name)`
+    pub fn arg(mut self, name: &str, value: &str) -> Self {
+        self.args.insert(name.to_owned(), value.to_owned());
+        self
+    }
+    /// Set the resolver for `$(find pkg)` and `package://` includes
+    ///
+    /// The base dir of the resolver is used for the relative includes of `expand_str()`.
+    pub fn resolver(mut self, resolver: ResourceResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+    fn expand(&self, text: &str, dir: PathBuf) -> Result<String, XacroError> {
+        let root = parse_xml(text)?;
+        let mut state = State {
+            expander: self,
+            args: self.args.clone(),
+            scopes: vec![Scope::default()],
+            dirs: vec![dir],
+            depth: 0,
+        };
+        let expanded = state.expand_element(&root)?;
+        let mut output = "<?xml version=\"1.0\"?>\n".to_owned();
+        write_xml(
+            &mut output,
+            &Xml::ElementNode(expanded),
+            0,
+            &Namespaces::default(),
+        );
+        Ok(output)
+    }
+    /// Expand xacro string into URDF string
+    pub fn expand_str(&self, xacro: &str) -> Result<String, XacroError> {
+        let dir = self
+            .resolver
+            .get_base_dir()
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+        self.expand(xacro, dir)
+    }
+    /// Expand xacro file into URDF string
+    ///
+    /// The relative includes are resolved from the directory of the file.
+    pub fn expand_file<P: AsRef<Path>>(&self, path: P) -> Result<String, XacroError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| XacroError::ParseError {
+            error: format!("{}: {}", path.display(), e),
+        })?;
+        let dir = path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+        self.expand(&text, dir)
+    }
+}
+
+#[test]
+fn test_evaluate() {
+    let lookup = |name: &str| match name {
+        "length" => Some(Value::from_text("0.5")),
+        "name" => Some(Value::from_text("arm")),
+        "flag" => Some(Value::from_text("True")),
+        _ => None,
+    };
+    let eval = |expression| evaluate(expression, &lookup).unwrap().to_string();
+    assert_eq!(eval("1 + 2 * 3"), "7");
+    assert_eq!(eval("(1 + 2) * 3"), "9");
+    assert_eq!(eval("-2 ** 2"), "-4");
+    assert_eq!(eval("2 ** -1"), "0.5");
+    assert_eq!(eval("7 // 2 + 7 % 2 + -7 % 3"), "6");
+    assert_eq!(eval("length * 2"), "1");
+    assert_eq!(eval("1.5e-1 * 2"), "0.3");
+    assert_eq!(eval("pi / 2"), format!("{}", consts::FRAC_PI_2));
+    assert_eq!(eval("math.cos(0) + max(1, 3, 2) + atan2(0, 1)"), "4");
+    assert_eq!(eval("radians(180) == pi"), "True");
+    assert_eq!(eval("name + '_link'"), "arm_link");
+    assert_eq!(eval("name == \"arm\" and not length > 1"), "True");
+    assert_eq!(eval("flag or undefined_is_not_evaluated"), "True");
+    assert_eq!(eval("1 if length < 0 else 2"), "2");
+    assert!(evaluate("1 / 0", &lookup).is_err());
+    assert!(evaluate("unknown + 1", &lookup).is_err());
+    assert!(evaluate("1 +", &lookup).is_err());
+    assert!(evaluate("(1 + 2", &lookup).is_err());
+    assert!(evaluate("name * 2", &lookup).is_err());
+}
+
+#[test]
+fn test_expand() {
+    let dir = env::temp_dir().join("k_test_xacro");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("my_robot/urdf")).unwrap();
+    fs::write(
+        dir.join("my_robot/urdf/common.xacro"),
+        r#"<robot xmlns:xacro="http://www.ros.org/wiki/xacro">
+        <xacro:property name="radius" value="0.1"/>
+        <xacro:macro name="cylinder_link" params="name length:=1 *origin **material">
+          <link name="${name}">
+            <visual>
+              <xacro:insert_block name="origin"/>
+              <geometry><cylinder radius="${radius}" length="${length}"/></geometry>
+              <material name="${name}_material"><xacro:insert_block name="material"/></material>
+            </visual>
+          </link>
+        </xacro:macro>
+        </robot>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("my_robot/urdf/robot.xacro"),
+        r#"<?xml version="1.0"?>
+        <robot name="robot" xmlns:xacro="http://ros.org/wiki/xacro">
+          <xacro:include filename="common.xacro"/>
+          <xacro:arg name="use_gripper" default="false"/>
+          <xacro:arg name="prefix" default="left_"/>
+          <xacro:property name="prefix" value="$(arg prefix)"/>
+          <xacro:property name="origin_block">
+            <origin xyz="0 0 ${radius * 5}"/>
+          </xacro:property>
+          <!-- links -->
+          <xacro:cylinder_link name="${prefix}base">
+            <origin xyz="0 0 0"/>
+            <material><color rgba="1 0 0 1"/></material>
+          </xacro:cylinder_link>
+          <xacro:call macro="cylinder_link" name="${prefix}arm" length="${radius * 2}">
+            <xacro:insert_block name="origin_block"/>
+            <material/>
+          </xacro:call>
+          <xacro:if value="$(arg use_gripper)">
+            <link name="${prefix}gripper"/>
+          </xacro:if>
+          <xacro:unless value="${prefix == 'left_'}">
+            <link name="not_left"/>
+          </xacro:unless>
+          <joint name="${prefix}joint" type="fixed">
+            <parent link="${prefix}base"/>
+            <child link="${prefix}arm"/>
+          </joint>
+          <gazebo><mu1>$${not_expanded}</mu1></gazebo>
+        </robot>"#,
+    )
+    .unwrap();
+    let expander = Expander::new()
+        .arg("use_gripper", "true")
+        .resolver(ResourceResolver::new().search_path(&dir));
+    let urdf = expander
+        .expand_file(dir.join("my_robot/urdf/robot.xacro"))
+        .unwrap();
+    let robot = parse_xml(&urdf).unwrap();
+    assert!(robot.attributes.keys().all(|(name, _)| name == "name"));
+    let link_names = robot
+        .get_children("link", None)
+        .map(|link| link.get_attribute("name", None).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(link_names, vec!["left_base", "left_arm", "left_gripper"]);
+    let arm_visual = robot
+        .get_children("link", None)
+        .nth(1)
+        .unwrap()
+        .get_child("visual", None)
+        .unwrap();
+    assert_eq!(
+        arm_visual
+            .get_child("origin", None)
+            .unwrap()
+            .get_attribute("xyz", None),
+        Some("0 0 0.5")
+    );
+    let cylinder = arm_visual
+        .get_child("geometry", None)
+        .unwrap()
+        .get_child("cylinder", None)
+        .unwrap();
+    assert_eq!(cylinder.get_attribute("length", None), Some("0.2"));
+    assert_eq!(cylinder.get_attribute("radius", None), Some("0.1"));
+    let base_material = robot
+        .get_child("link", None)
+        .unwrap()
+        .get_child("visual", None)
+        .unwrap()
+        .get_child("material", None)
+        .unwrap();
+    assert_eq!(
+        base_material
+            .get_child("color", None)
+            .unwrap()
+            .get_attribute("rgba", None),
+        Some("1 0 0 1")
+    );
+    assert!(urdf.contains("<!-- links -->"));
+    assert!(urdf.contains("<mu1>${not_expanded}</mu1>"));
+
+    // include by package:// and $(find)
+    let urdf = expander
+        .expand_str(
+            r#"<robot name="r" xmlns:xacro="http://www.ros.org/wiki/xacro">
+            <xacro:include filename="package://my_robot/urdf/common.xacro"/>
+            <xacro:include filename="$(find my_robot)/urdf/common.xacro"/>
+            <xacro:property name="length" value="3"/>
+            <xacro:cylinder_link name="a" length="${length}"><origin/><material/></xacro:cylinder_link>
+            </robot>"#,
+        )
+        .unwrap();
+    assert!(urdf.contains("length=\"3\""));
+
+    let expand = |xacro: &str| {
+        Expander::new().expand_str(&format!(
+            r#"<robot name="r" xmlns:xacro="http://www.ros.org/wiki/xacro">
+            <xacro:macro name="m" params="a b:=^|2"><link name="${{a}}_${{b}}"/></xacro:macro>
+            {}</robot>"#,
+            xacro
+        ))
+    };
+    assert!(expand(r#"<xacro:m a="x"/>"#)
+        .unwrap()
+        .contains("<link name=\"x_2\"/>"));
+    // ^ takes the property of the outer scope
+    assert!(
+        expand(r#"<xacro:property name="b" value="3"/><xacro:m a="x"/>"#)
+            .unwrap()
+            .contains("<link name=\"x_3\"/>")
+    );
+    match expand(r#"<xacro:m/>"#) {
+        Err(XacroError::MacroArgumentError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<xacro:m a="x" c="y"/>"#) {
+        Err(XacroError::MacroArgumentError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<xacro:undefined_macro/>"#) {
+        Err(XacroError::UndefinedError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<link name="$(arg undefined)"/>"#) {
+        Err(XacroError::UndefinedError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<xacro:if value="maybe"/>"#) {
+        Err(XacroError::ExpressionError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<xacro:include filename="not_found.xacro"/>"#) {
+        Err(XacroError::IncludeError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<xacro:macro name="loop"><xacro:loop/></xacro:macro><xacro:loop/>"#) {
+        Err(XacroError::UnsupportedError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_substitute() {
+    let expand = |text: &str| {
+        Expander::new()
+            .expand_str(&format!(
+                r#"<robot xmlns:xacro="http://www.ros.org/wiki/xacro">
+                <xacro:property name="name" value="arm"/>
+                <link name="{}"/></robot>"#,
+                text
+            ))
+            .map(|urdf| {
+                parse_xml(&urdf)
+                    .unwrap()
+                    .get_child("link", None)
+                    .unwrap()
+                    .get_attribute("name", None)
+                    .unwrap()
+                    .to_owned()
+            })
+    };
+    // braces in the strings and nested substitutions
+    assert_eq!(expand("${'}' + name + '{'}").unwrap(), "}arm{");
+    assert_eq!(expand("${'$' + '{' + name}").unwrap(), "${arm");
+    assert_eq!(expand("${1 + ${2 * 3}}").unwrap(), "7");
+    assert_eq!(expand("${name}_${name}").unwrap(), "arm_arm");
+    // numbers
+    assert_eq!(expand("${.5 * 2 + 1e1}").unwrap(), "11");
+    assert_eq!(expand("${2.5E-1 * 4}").unwrap(), "1");
+    // commands
+    assert_eq!(expand("$( eval  1 + 2 )").unwrap(), "3");
+    assert_eq!(expand("$(eval name + '_link')").unwrap(), "arm_link");
+    assert_eq!(expand("$(eval max(1, (2 + 3)))").unwrap(), "5");
+    assert_eq!(
+        expand("$(optenv K_TEST_XACRO_UNDEFINED default  value)").unwrap(),
+        "default value"
+    );
+    assert_eq!(
+        expand("$${name} $$(arg x) $5").unwrap(),
+        "${name} $(arg x) $5"
+    );
+    for text in &["${1 + 2", "${'}", "$(eval 1", "$(evaluate 1)", "$(eval)"] {
+        match expand(text) {
+            Err(XacroError::ExpressionError { .. }) => {}
+            other => panic!("{}: {:?}", text, other),
+        }
+    }
+}
+
+#[test]
+fn test_expand_macros() {
+    let expand = |xacro: &str| {
+        Expander::new()
+            .expand_str(&format!(
+                r#"<robot name="r" xmlns:xacro="http://www.ros.org/wiki/xacro">
+                <xacro:macro name="box_link" params="name size:=${{0.1*2}} *origin **contents">
+                  <link name="${{name}}">
+                    <visual>
+                      <xacro:insert_block name="origin"/>
+                      <geometry><box size="${{size}} ${{size}} ${{size}}"/></geometry>
+                    </visual>
+                    <xacro:insert_block name="contents"/>
+                  </link>
+                </xacro:macro>
+                <xacro:macro name="two_links" params="prefix *origin">
+                  <xacro:box_link name="${{prefix}}_a" size="1">
+                    <xacro:insert_block name="origin"/>
+                    <contents/>
+                  </xacro:box_link>
+                  <xacro:box_link name="${{prefix}}_b">
+                    <origin xyz="1 0 0"/>
+                    <contents><inertial/></contents>
+                  </xacro:box_link>
+                </xacro:macro>
+                {}</robot>"#,
+                xacro
+            ))
+            .map(|urdf| parse_xml(&urdf).unwrap())
+    };
+    let robot = expand(
+        r#"<xacro:box_link name="base"><origin xyz="0 0 1"/><contents><collision/><inertial/></contents></xacro:box_link>
+        <xacro:two_links prefix="arm"><origin xyz="0 1 0"/></xacro:two_links>"#,
+    )
+    .unwrap();
+    let links = robot.get_children("link", None).collect::<Vec<_>>();
+    let names = links
+        .iter()
+        .map(|link| link.get_attribute("name", None).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["base", "arm_a", "arm_b"]);
+    let visual = |link: &Element| link.get_child("visual", None).unwrap().clone();
+    let size = |link: &Element| {
+        visual(link)
+            .get_child("geometry", None)
+            .unwrap()
+            .get_child("box", None)
+            .unwrap()
+            .get_attribute("size", None)
+            .unwrap()
+            .to_owned()
+    };
+    let origin = |link: &Element| {
+        visual(link)
+            .get_child("origin", None)
+            .unwrap()
+            .get_attribute("xyz", None)
+            .unwrap()
+            .to_owned()
+    };
+    // the default is evaluated
+    assert_eq!(size(links[0]), "0.2 0.2 0.2");
+    assert_eq!(size(links[1]), "1 1 1");
+    assert_eq!(size(links[2]), "0.2 0.2 0.2");
+    // *block is the element, forwarded through the outer macro
+    assert_eq!(origin(links[0]), "0 0 1");
+    assert_eq!(origin(links[1]), "0 1 0");
+    assert_eq!(origin(links[2]), "1 0 0");
+    // **block is the children
+    assert!(links[0].get_child("collision", None).is_some());
+    assert!(links[0].get_child("inertial", None).is_some());
+    assert!(links[0].get_child("contents", None).is_none());
+    assert_eq!(child_elements(links[1]).count(), 1);
+    assert!(links[2].get_child("inertial", None).is_some());
+    // the params are not visible after the call
+    match expand(r#"<xacro:box_link name="a"><origin/><c/></xacro:box_link><link name="${size}"/>"#)
+    {
+        Err(XacroError::ExpressionError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<xacro:box_link name="a"><origin/></xacro:box_link>"#) {
+        Err(XacroError::MacroArgumentError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_expand_conditions() {
+    let expand = |xacro: &str| {
+        Expander::new()
+            .arg("enabled", "true")
+            .expand_str(&format!(
+                r#"<robot name="r" xmlns:xacro="http://www.ros.org/wiki/xacro">
+                <xacro:property name="count" value="2"/>
+                {}</robot>"#,
+                xacro
+            ))
+            .map(|urdf| {
+                parse_xml(&urdf)
+                    .unwrap()
+                    .get_children("link", None)
+                    .map(|link| link.get_attribute("name", None).unwrap().to_owned())
+                    .collect::<Vec<_>>()
+            })
+    };
+    let names = expand(
+        r#"<xacro:if value="true"><link name="a"/></xacro:if>
+        <xacro:if value="False"><link name="b"/></xacro:if>
+        <xacro:if value="${count > 1}"><link name="c"/></xacro:if>
+        <xacro:if value="${count - 2}"><link name="d"/></xacro:if>
+        <xacro:if value="$(arg enabled)">
+          <xacro:unless value="${count == 2}"><link name="e"/></xacro:unless>
+          <xacro:unless value="0"><link name="f"/></xacro:unless>
+        </xacro:if>
+        <xacro:unless value="1"><link name="g"/></xacro:unless>"#,
+    )
+    .unwrap();
+    assert_eq!(names, vec!["a", "c", "f"]);
+    match expand(r#"<xacro:unless value="${'yes'}"/>"#) {
+        Err(XacroError::ExpressionError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match expand(r#"<xacro:if><link name="a"/></xacro:if>"#) {
+        Err(XacroError::UnsupportedError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_expand_include() {
+    let dir = env::temp_dir().join("k_test_xacro_include");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("parts/sub")).unwrap();
+    fs::write(
+        dir.join("parts/arm.xacro"),
+        r#"<robot xmlns:xacro="http://www.ros.org/wiki/xacro">
+        <xacro:include filename="sub/sizes.xacro"/>
+        <link name="arm_${length}"/>
+        <xacro:macro name="hand"><link name="hand_$(dirname)"/></xacro:macro>
+        </robot>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("parts/sub/sizes.xacro"),
+        r#"<robot xmlns:xacro="http://www.ros.org/wiki/xacro">
+        <xacro:property name="length" value="3"/>
+        </robot>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("self.xacro"),
+        r#"<robot xmlns:xacro="http://www.ros.org/wiki/xacro">
+        <xacro:include filename="self.xacro"/>
+        </robot>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("robot.xacro"),
+        r#"<robot name="r" xmlns:xacro="http://www.ros.org/wiki/xacro">
+        <xacro:include filename="parts/arm.xacro"/>
+        <xacro:hand/>
+        <link name="base_${length * 2}"/>
+        </robot>"#,
+    )
+    .unwrap();
+    let urdf = Expander::new()
+        .expand_file(dir.join("robot.xacro"))
+        .unwrap();
+    let robot = parse_xml(&urdf).unwrap();
+    let names = robot
+        .get_children("link", None)
+        .map(|link| link.get_attribute("name", None).unwrap().to_owned())
+        .collect::<Vec<_>>();
+    // relative to the including file, the properties and the macros are visible after it
+    assert_eq!(
+        names,
+        vec![
+            "arm_3".to_owned(),
+            format!("hand_{}", dir.display()),
+            "base_6".to_owned()
+        ]
+    );
+    match Expander::new().expand_file(dir.join("self.xacro")) {
+        Err(XacroError::UnsupportedError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match Expander::new().expand_file(dir.join("parts/sub/not_found.xacro")) {
+        Err(XacroError::ParseError { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_expand_namespaces() {
+    let dir = env::temp_dir().join("k_test_xacro_namespaces");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("sensor.xacro"),
+        r#"<robot xmlns:xacro="http://www.ros.org/wiki/xacro" xmlns:s="http://example.com/sensor">
+        <s:camera s:rate="${10 * 3}"/>
+        </robot>"#,
+    )
+    .unwrap();
+    let urdf = Expander::new()
+        .resolver(ResourceResolver::new().base_dir(&dir))
+        .expand_str(
+            r#"<robot name="r" xmlns:xacro="http://www.ros.org/wiki/xacro"
+              xmlns:gazebo="http://example.com/gazebo">
+            <gazebo:plugin gazebo:name="${'p' + '1'}" xml:lang="en"><gazebo:child/></gazebo:plugin>
+            <xacro:include filename="sensor.xacro"/>
+            </robot>"#,
+        )
+        .unwrap();
+    assert!(!urdf.contains("wiki/xacro"));
+    let robot = parse_xml(&urdf).unwrap();
+    let plugin = robot
+        .get_child("plugin", Some("http://example.com/gazebo"))
+        .unwrap();
+    assert_eq!(
+        plugin.get_attribute("name", Some("http://example.com/gazebo")),
+        Some("p1")
+    );
+    assert!(plugin
+        .get_child("child", Some("http://example.com/gazebo"))
+        .is_some());
+    assert!(urdf.contains("<gazebo:plugin xml:lang=\"en\" gazebo:name=\"p1\">"));
+    // the prefix declared only in the included file
+    let camera = robot
+        .get_child("camera", Some("http://example.com/sensor"))
+        .unwrap();
+    assert_eq!(
+        camera.get_attribute("rate", Some("http://example.com/sensor")),
+        Some("30")
+    );
+}
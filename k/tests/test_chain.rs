@@ -1,9 +1,165 @@
+#[macro_use]
 extern crate k;
 extern crate nalgebra as na;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use na::{Isometry3, Translation3, UnitQuaternion, Vector3};
+
+    fn create_mixed_chain() -> k::Chain<f64> {
+        let l0 = k::JointBuilder::new()
+            .name("yaw")
+            .joint_type(k::JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .translation(Translation3::new(0.1, 0.0, 0.2))
+            .into_node();
+        let l1 = k::JointBuilder::new()
+            .name("slide")
+            .joint_type(k::JointType::Linear {
+                axis: Vector3::x_axis(),
+            })
+            .translation(Translation3::new(0.0, 0.3, 0.1))
+            .rotation(UnitQuaternion::from_euler_angles(0.3, 0.0, 0.2))
+            .into_node();
+        let l2 = k::JointBuilder::new()
+            .name("pitch")
+            .joint_type(k::JointType::Rotational {
+                axis: Vector3::y_axis(),
+            })
+            .translation(Translation3::new(0.2, 0.0, -0.4))
+            .into_node();
+        let l3 = k::JointBuilder::new()
+            .name("tip")
+            .translation(Translation3::new(0.0, 0.1, 0.3))
+            .into_node();
+        connect![l0 => l1 => l2 => l3];
+        k::Chain::from_root(l0)
+    }
+
+    fn set_state(chain: &k::Chain<f64>, q: &[f64], dq: &[f64], ddq: &[f64], t: f64) {
+        let positions = (0..q.len())
+            .map(|i| q[i] + dq[i] * t + 0.5 * ddq[i] * t * t)
+            .collect::<Vec<_>>();
+        let velocities = (0..q.len())
+            .map(|i| dq[i] + ddq[i] * t)
+            .collect::<Vec<_>>();
+        chain.set_joint_positions(&positions).unwrap();
+        chain.set_joint_velocities(&velocities).unwrap();
+        chain.set_joint_accelerations(ddq).unwrap();
+    }
+
+    fn rotation_diff(a: &Isometry3<f64>, b: &Isometry3<f64>) -> Vector3<f64> {
+        (a.rotation * b.rotation.inverse()).scaled_axis()
+    }
+
+    fn create_multi_dof_chain() -> k::Chain<f64> {
+        let l0 = k::JointBuilder::new()
+            .name("free")
+            .joint_type(k::JointType::Floating)
+            .translation(Translation3::new(0.1, 0.0, 0.2))
+            .into_node();
+        let l1 = k::JointBuilder::new()
+            .name("plane")
+            .joint_type(k::JointType::Planar {
+                normal: na::Unit::new_normalize(Vector3::new(0.2, 1.0, 0.3)),
+            })
+            .translation(Translation3::new(0.0, 0.3, 0.1))
+            .rotation(UnitQuaternion::from_euler_angles(0.3, 0.0, 0.2))
+            .into_node();
+        let l2 = k::JointBuilder::new()
+            .name("ball")
+            .joint_type(k::JointType::Spherical)
+            .translation(Translation3::new(0.2, 0.0, -0.4))
+            .into_node();
+        let l3 = k::JointBuilder::new()
+            .name("tip")
+            .translation(Translation3::new(0.0, 0.1, 0.3))
+            .into_node();
+        connect![l0 => l1 => l2 => l3];
+        k::Chain::from_root(l0)
+    }
+
+    fn check_by_finite_difference(chain: &k::Chain<f64>, q: &[f64], dq: &[f64], ddq: &[f64]) {
+        let h = 1e-4;
+
+        set_state(chain, q, dq, ddq, -h);
+        let trans_m = chain.update_transforms();
+        let vel_m = chain.update_velocities();
+        set_state(chain, q, dq, ddq, h);
+        let trans_p = chain.update_transforms();
+        let vel_p = chain.update_velocities();
+        set_state(chain, q, dq, ddq, 0.0);
+        let trans_0 = chain.update_transforms();
+        let acc = chain.update_accelerations();
+        let vel = chain
+            .iter()
+            .map(|node| node.world_velocity().unwrap())
+            .collect::<Vec<_>>();
+
+        for i in 0..trans_0.len() {
+            let v_fd = (trans_p[i].translation.vector - trans_m[i].translation.vector) / (2.0 * h);
+            let w_fd = rotation_diff(&trans_p[i], &trans_m[i]) / (2.0 * h);
+            let a_fd = (trans_p[i].translation.vector - trans_0[i].translation.vector * 2.0
+                + trans_m[i].translation.vector)
+                / (h * h);
+            let alpha_fd = (vel_p[i].rotation - vel_m[i].rotation) / (2.0 * h);
+            assert!((vel[i].translation - v_fd).norm() < 1e-6, "v of {}", i);
+            assert!((vel[i].rotation - w_fd).norm() < 1e-6, "w of {}", i);
+            assert!((acc[i].translation - a_fd).norm() < 1e-4, "dv of {}", i);
+            assert!((acc[i].rotation - alpha_fd).norm() < 1e-6, "dw of {}", i);
+        }
+    }
+
+    #[test]
+    pub fn test_velocities_and_accelerations_by_finite_difference() {
+        let chain = create_mixed_chain();
+        let q = [0.3, 0.1, -0.7];
+        let dq = [0.8, -0.5, 1.2];
+        let ddq = [-0.4, 0.6, 0.9];
+        check_by_finite_difference(&chain, &q, &dq, &ddq);
+    }
+
+    #[test]
+    pub fn test_multi_dof_joints() {
+        let chain = create_multi_dof_chain();
+        assert_eq!(chain.dof(), 12);
+        let q = [
+            0.1, -0.2, 0.3, 0.4, -0.1, 0.2, 0.3, 0.1, -0.7, 0.5, 0.2, -0.3,
+        ];
+        let dq = [
+            0.8, -0.5, 1.2, 0.3, 0.2, -0.4, 0.5, -0.3, 0.6, 0.2, -0.7, 0.4,
+        ];
+        let ddq = [
+            -0.4, 0.6, 0.9, 0.1, -0.3, 0.2, 0.4, 0.5, -0.2, 0.3, 0.1, -0.6,
+        ];
+        check_by_finite_difference(&chain, &q, &dq, &ddq);
+
+        // jacobian columns are the derivatives of the end transform
+        let arm = k::SerialChain::from_end(chain.find("tip").unwrap());
+        arm.set_joint_positions(&q).unwrap();
+        let end = arm.end_transform();
+        let jacobi = k::jacobian(&arm);
+        let h = 1e-6;
+        for i in 0..q.len() {
+            let mut moved = q.to_vec();
+            moved[i] += h;
+            arm.set_joint_positions(&moved).unwrap();
+            let end_moved = arm.end_transform();
+            let v = (end_moved.translation.vector - end.translation.vector) / h;
+            let w = rotation_diff(&end_moved, &end) / h;
+            for r in 0..3 {
+                assert!((jacobi[(r, i)] - v[r]).abs() < 1e-4, "v of column {}", i);
+                assert!(
+                    (jacobi[(r + 3, i)] - w[r]).abs() < 1e-4,
+                    "w of column {}",
+                    i
+                );
+            }
+        }
+        assert!(arm.set_joint_positions(&q[..11]).is_err());
+    }
 
     #[test]
     pub fn test_tree() {
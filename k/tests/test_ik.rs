@@ -152,4 +152,168 @@ mod tests {
             assert!((init - end).abs() < 0.002);
         }
     }
+
+    #[test]
+    pub fn ik_fk6_with_tcp_offset() {
+        let mut arm = create_joint_with_link_array6();
+        let angles = vec![0.8, 0.2, 0.0, -1.2, 0.0, 0.1];
+        arm.set_joint_positions(&angles).unwrap();
+        let wrist_pose = arm.end_transform();
+        arm.set_tcp_offset(na::Isometry3::translation(0.0, 0.0, -0.1));
+        let tcp_pose = arm.end_transform();
+        assert!((wrist_pose.translation.vector - tcp_pose.translation.vector).norm() > 0.09);
+        let solver = k::JacobianIKSolver::new(0.001, 0.001, 0.8, 100);
+        arm.set_joint_positions(&[0.4, 0.1, 0.1, -1.0, 0.1, 0.1])
+            .unwrap();
+        solver.solve(&arm, &tcp_pose).unwrap();
+        for (init, end) in angles.iter().zip(arm.joint_positions().iter()) {
+            assert!((init - end).abs() < 0.002);
+        }
+        // swap the tool, the same wrist pose is reached by the other TCP
+        arm.set_tcp_offset(na::Isometry3::translation(0.0, 0.0, -0.2));
+        arm.set_joint_positions(&[0.4, 0.1, 0.1, -1.0, 0.1, 0.1])
+            .unwrap();
+        solver
+            .solve(&arm, &(wrist_pose * arm.tcp_offset()))
+            .unwrap();
+        for (init, end) in angles.iter().zip(arm.joint_positions().iter()) {
+            assert!((init - end).abs() < 0.002);
+        }
+    }
+
+    #[test]
+    pub fn cartesian_line_and_arc() {
+        let arm = create_joint_with_link_array6();
+        arm.set_joint_positions(&[0.4, 0.1, 0.1, -1.0, 0.1, 0.1])
+            .unwrap();
+        let start = arm.end_transform();
+        let planner = k::cartesian::CartesianPlanner::new();
+        let solver = k::JacobianIKSolver::new(0.0001, 0.001, 0.8, 100);
+
+        let mut target = start;
+        target.translation.vector.x += 0.1;
+        target.translation.vector.z -= 0.05;
+        let path = planner.plan_line(&solver, &arm, &target).unwrap();
+        assert!(path.len() > 10);
+        let poses = planner.line_poses(&start, &target).unwrap();
+        assert_eq!(path.len(), poses.len());
+        for (positions, pose) in path.iter().zip(poses.iter()) {
+            arm.set_joint_positions(positions).unwrap();
+            let diff = arm.end_transform().translation.vector - pose.translation.vector;
+            assert!(diff.norm() < 0.001);
+        }
+
+        arm.set_joint_positions(&path[0]).unwrap();
+        let center = start.translation.vector + Vector3::new(0.05, 0.0, 0.0);
+        let path = planner
+            .plan_arc(&solver, &arm, &center, &Vector3::y_axis(), 0.5)
+            .unwrap();
+        let poses = planner
+            .arc_poses(&start, &center, &Vector3::y_axis(), 0.5)
+            .unwrap();
+        assert_eq!(path.len(), poses.len());
+        for (i, (positions, pose)) in path.iter().zip(poses.iter()).enumerate() {
+            arm.set_joint_positions(positions).unwrap();
+            let end = arm.end_transform();
+            // on the circle in the plane perpendicular to the axis
+            let radial = end.translation.vector - center;
+            assert!((radial.norm() - 0.05).abs() < 0.001);
+            assert!(radial.y.abs() < 0.001);
+            assert!((end.translation.vector - pose.translation.vector).norm() < 0.001);
+            // the rotation follows the arc as a rigid body
+            let angle = 0.5 * i as f64 / (poses.len() - 1) as f64;
+            let expected =
+                na::UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle) * start.rotation;
+            assert!(end.rotation.angle_to(&expected) < 0.01);
+        }
+        let last = arm.end_transform().translation.vector - center;
+        let first = start.translation.vector - center;
+        assert!((first.angle(&last) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    pub fn cartesian_rejects_non_positive_steps() {
+        let arm = create_joint_with_link_array6();
+        let solver = k::JacobianIKSolver::new(0.0001, 0.001, 0.8, 100);
+        let start = arm.end_transform();
+        let mut target = start;
+        target.translation.vector.x += 0.1;
+        let mut planner = k::cartesian::CartesianPlanner::new();
+        planner.step_length = 0.0;
+        match planner.plan_line(&solver, &arm, &target) {
+            Err(k::CartesianPathError::InvalidArgumentsError { .. }) => {}
+            _ => panic!("must fail"),
+        }
+        planner.step_length = 0.01;
+        planner.step_angle = -0.05;
+        assert!(planner
+            .arc_poses(&start, &Vector3::zeros(), &Vector3::z_axis(), 0.5)
+            .is_err());
+        planner.step_angle = 0.05;
+        assert!(planner.line_poses(&start, &target).unwrap().len() > 10);
+    }
+
+    #[test]
+    pub fn cartesian_errors_name_waypoint() {
+        let arm = create_joint_with_link_array6();
+        arm.set_joint_positions(&[0.4, 0.1, 0.1, -1.0, 0.1, 0.1])
+            .unwrap();
+        let solver = k::JacobianIKSolver::new(0.0001, 0.001, 0.8, 100);
+        let mut planner = k::cartesian::CartesianPlanner::new();
+        let mut target = arm.end_transform();
+        // out of reach
+        target.translation.vector.x += 2.0;
+        match planner.plan_line(&solver, &arm, &target) {
+            Err(k::CartesianPathError::IKFailedError { index, .. }) => assert!(index > 0),
+            _ => panic!("must fail"),
+        }
+        planner.max_joint_step = 0.0001;
+        target.translation.vector.x -= 1.9;
+        match planner.plan_line(&solver, &arm, &target) {
+            Err(k::CartesianPathError::DiscontinuityError { index, .. }) => assert_eq!(index, 1),
+            _ => panic!("must fail"),
+        }
+    }
+
+    #[test]
+    pub fn ik_continuous_joint_does_not_wind_up() {
+        let l0 = k::JointBuilder::new()
+            .joint_type(k::JointType::Continuous {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let l1 = k::JointBuilder::new()
+            .translation(Translation3::new(0.5, 0.0, 0.0))
+            .joint_type(k::JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let l2 = k::JointBuilder::new()
+            .translation(Translation3::new(0.5, 0.0, 0.0))
+            .into_node();
+        connect![l0 => l1 => l2];
+        let arm = k::SerialChain::<f64>::from_end(&l2);
+        arm.set_joint_positions(&[3.0, 0.5]).unwrap();
+        let solver = k::JacobianIKSolver::new(0.0001, 0.001, 0.5, 100);
+        let mut planner = k::cartesian::CartesianPlanner::new();
+        planner.constraints = k::Constraints {
+            position_z: false,
+            rotation_x: false,
+            rotation_y: false,
+            rotation_z: false,
+            ..Default::default()
+        };
+        // rotate around the base across +-pi
+        let path = planner
+            .plan_arc(&solver, &arm, &Vector3::zeros(), &Vector3::z_axis(), 0.6)
+            .unwrap();
+        let last = path.last().unwrap();
+        for positions in &path {
+            assert!(positions[0] > -::std::f64::consts::PI);
+            assert!(positions[0] <= ::std::f64::consts::PI);
+        }
+        assert!(last[0] < 0.0);
+        let end = l0.joint().position_difference(3.0, last[0]);
+        assert!((end - 0.6).abs() < 0.01, "{}", end);
+    }
 }
@@ -0,0 +1,218 @@
+extern crate k;
+
+#[cfg(test)]
+mod tests {
+    use k::topp::*;
+    use k::trajectory::*;
+
+    fn waypoints() -> Vec<Vec<f64>> {
+        vec![
+            vec![0.0, 0.5, -1.0],
+            vec![0.8, 0.2, -0.5],
+            vec![1.2, 0.6, -0.5],
+            vec![0.4, 0.6, 0.3],
+        ]
+    }
+
+    fn check_derivatives(trajectory: &Trajectory<f64>) {
+        let h = 1e-6;
+        let mut t = trajectory.start_time() + h;
+        while t < trajectory.end_time() - h {
+            let p = trajectory.sample(t);
+            let p_p = trajectory.sample(t + h);
+            let p_m = trajectory.sample(t - h);
+            for i in 0..trajectory.dof() {
+                let v = (p_p.position[i] - p_m.position[i]) / (2.0 * h);
+                assert!((p.velocity[i] - v).abs() < 1e-5, "velocity at {}", t);
+            }
+            t += 0.01;
+        }
+    }
+
+    #[test]
+    fn test_passes_waypoints() {
+        let times = [0.0, 1.0, 2.5, 3.0];
+        for profile in &[Profile::Cubic, Profile::Quintic, Profile::Trapezoidal] {
+            let trajectory = Trajectory::new(&waypoints(), &times, *profile).unwrap();
+            for (time, waypoint) in times.iter().zip(waypoints().iter()) {
+                let point = trajectory.sample(*time);
+                for (position, expected) in point.position.iter().zip(waypoint.iter()) {
+                    assert!((position - expected).abs() < 1e-10);
+                }
+            }
+            let start = trajectory.sample(-1.0);
+            let end = trajectory.sample(10.0);
+            for i in 0..3 {
+                assert_eq!(start.velocity[i], 0.0);
+                assert!(end.velocity[i].abs() < 1e-10);
+            }
+            check_derivatives(&trajectory);
+        }
+    }
+
+    #[test]
+    fn test_quintic_acceleration_is_continuous() {
+        let trajectory =
+            Trajectory::new(&waypoints(), &[0.0, 1.0, 2.5, 3.0], Profile::Quintic).unwrap();
+        for t in &[1.0, 2.5] {
+            let before = trajectory.sample(t - 1e-7);
+            let after = trajectory.sample(t + 1e-7);
+            for i in 0..3 {
+                assert!((before.velocity[i] - after.velocity[i]).abs() < 1e-5);
+                assert!((before.acceleration[i] - after.acceleration[i]).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_limits() {
+        let velocity_limits = [1.0, 0.5, 2.0];
+        let acceleration_limits = [2.0, 1.0, 3.0];
+        for profile in &[Profile::Cubic, Profile::Quintic, Profile::Trapezoidal] {
+            let trajectory = Trajectory::with_limits(
+                &waypoints(),
+                &velocity_limits,
+                &acceleration_limits,
+                *profile,
+            )
+            .unwrap();
+            trajectory
+                .validate(&velocity_limits, &acceleration_limits)
+                .unwrap();
+            check_derivatives(&trajectory);
+        }
+    }
+
+    #[test]
+    fn test_continuous_takes_short_way() {
+        use std::f64::consts::PI;
+        let waypoints = vec![vec![3.0, 3.0], vec![-3.0, -3.0]];
+        for profile in &[Profile::Cubic, Profile::Quintic, Profile::Trapezoidal] {
+            let trajectory =
+                Trajectory::new_continuous(&waypoints, &[0.0, 1.0], *profile, &[true, false])
+                    .unwrap();
+            let middle = trajectory.sample(0.5);
+            assert!((middle.position[0] - PI).abs() < 1e-10);
+            assert!(middle.position[1].abs() < 1e-10);
+            let end = trajectory.sample(1.0);
+            assert!((end.position[0] - (2.0 * PI - 3.0)).abs() < 1e-10);
+            for (_, point) in trajectory.sample_all(0.01).unwrap() {
+                assert!(point.position[0] >= 3.0 - 1e-10);
+                assert!(point.position[0] <= 2.0 * PI - 3.0 + 1e-10);
+            }
+        }
+        let trajectory = Trajectory::with_limits_continuous(
+            &waypoints,
+            &[1.0, 1.0],
+            &[2.0, 2.0],
+            Profile::Trapezoidal,
+            &[true, true],
+        )
+        .unwrap();
+        assert!(
+            (trajectory.sample(trajectory.end_time()).position[1] - (2.0 * PI - 3.0)).abs() < 1e-10
+        );
+        // only 2pi - 6 rad to move with 1 rad/s
+        assert!(trajectory.duration() < 1.0);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(Trajectory::new(&waypoints(), &[0.0, 1.0, 1.0, 2.0], Profile::Cubic).is_err());
+        assert!(Trajectory::new(&waypoints(), &[0.0, 1.0], Profile::Cubic).is_err());
+        assert!(Trajectory::<f64>::new(&[vec![0.0]], &[0.0], Profile::Cubic).is_err());
+        let trajectory =
+            Trajectory::new(&waypoints(), &[0.0, 0.1, 0.2, 0.3], Profile::Trapezoidal).unwrap();
+        match trajectory.validate(&[1.0, 1.0, 1.0], &[1000.0, 1000.0, 1000.0]) {
+            Err(k::TrajectoryError::VelocityLimitError { joint_index, .. }) => {
+                assert_eq!(joint_index, 0)
+            }
+            _ => panic!("velocity limit must be violated"),
+        }
+    }
+
+    #[test]
+    fn test_sample_all() {
+        let trajectory =
+            Trajectory::new(&waypoints(), &[0.0, 1.0, 2.5, 3.0], Profile::Cubic).unwrap();
+        let samples = trajectory.sample_all(0.25).unwrap();
+        assert_eq!(samples.len(), 13);
+        for (i, &(t, ref point)) in samples.iter().enumerate() {
+            assert_eq!(t, 0.25 * i as f64);
+            assert_eq!(point.position, trajectory.sample(t).position);
+        }
+        // the end is included even if dt does not divide the duration
+        let samples = trajectory.sample_all(0.4).unwrap();
+        assert_eq!(samples.len(), 9);
+        assert_eq!(samples[8].0, 3.0);
+        for dt in &[0.0, -0.1, f64::NAN, 1e-20] {
+            match trajectory.sample_all(*dt) {
+                Err(k::TrajectoryError::InvalidArgumentsError { .. }) => {}
+                _ => panic!("dt = {} must be rejected", dt),
+            }
+        }
+        // dt below the resolution of f32 time
+        let trajectory =
+            Trajectory::<f32>::new(&[vec![0.0], vec![1.0]], &[1000.0, 1001.0], Profile::Cubic)
+                .unwrap();
+        assert!(trajectory.sample_all(1e-5).is_err());
+        assert_eq!(trajectory.sample_all(0.5).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_time_optimal_respects_limits() {
+        let path = (0..=100)
+            .map(|i| {
+                let a = i as f64 / 100.0 * ::std::f64::consts::PI;
+                vec![a.cos(), a.sin(), 0.5 * a]
+            })
+            .collect::<Vec<_>>();
+        let mut parameterizer = TimeParameterizer::new(3);
+        parameterizer.velocity_limits = vec![Some(1.0), Some(0.8), None];
+        parameterizer.acceleration_limits = vec![Some(2.0), Some(2.0), Some(0.5)];
+        let timing = parameterizer.parameterize(&path).unwrap();
+        assert_eq!(timing.times.len(), path.len());
+        assert!(timing.times.windows(2).all(|t| t[1] > t[0]));
+        let mut active = 0;
+        for (vel, acc) in timing.velocities.iter().zip(timing.accelerations.iter()) {
+            let mut ratio = 0.0f64;
+            for i in 0..3 {
+                if let Some(limit) = parameterizer.velocity_limits[i] {
+                    assert!(vel[i].abs() <= limit + 1e-6);
+                    ratio = ratio.max(vel[i].abs() / limit);
+                }
+                let limit = parameterizer.acceleration_limits[i].unwrap();
+                assert!(acc[i].abs() <= limit + 1e-6);
+                ratio = ratio.max(acc[i].abs() / limit);
+            }
+            if ratio > 0.99 {
+                active += 1;
+            }
+        }
+        // time optimal means a limit is active almost everywhere
+        assert!(active > 95, "active = {}", active);
+        let trajectory = timing.to_trajectory(Profile::Cubic).unwrap();
+        assert_eq!(trajectory.duration(), timing.duration());
+    }
+
+    #[test]
+    fn test_time_optimal_errors() {
+        let path = (0..=10).map(|i| vec![i as f64 / 10.0]).collect::<Vec<_>>();
+        let mut parameterizer = TimeParameterizer::new(1);
+        parameterizer.effort_limits = vec![Some(10.0)];
+        // it can not hold the weight against the gravity
+        match parameterizer
+            .parameterize_with_dynamics(&path, |_, _, acc| vec![2.0 * (acc[0] + 9.8)])
+        {
+            Err(k::TrajectoryError::PathInfeasibleError { .. }) => {}
+            _ => panic!("effort limit must be violated"),
+        }
+        // without dynamics effort limits are ignored
+        assert!(parameterizer.parameterize(&path).is_ok());
+        let duplicated = vec![vec![0.0], vec![0.0], vec![1.0]];
+        assert!(parameterizer.parameterize(&duplicated).is_err());
+        assert!(parameterizer
+            .parameterize(&[vec![0.0, 1.0], vec![1.0, 1.0]])
+            .is_err());
+    }
+}